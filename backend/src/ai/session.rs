@@ -85,6 +85,11 @@ pub struct SessionHandle {
 }
 
 impl SessionHandle {
+    /// The runbook this session belongs to.
+    pub async fn runbook_id(&self) -> Uuid {
+        self.kind.read().await.runbook_id()
+    }
+
     /// Change the model of the session.
     pub async fn change_model(&self, model: ModelSelection) -> Result<(), AISessionError> {
         let mut config = self.config.write().await;
@@ -53,12 +53,19 @@ pub async fn ai_create_generator_session(
     runbook_id: Uuid,
     model: Option<ModelSelection>,
     block_infos: Vec<BlockInfo>,
-    current_document: serde_json::Value,
+    mut current_document: serde_json::Value,
     insert_after: Uuid,
     desktop_username: String,
     charge_target: ChargeTarget,
     hub_endpoint: String,
 ) -> Result<Uuid, String> {
+    // The document is kept in the session's system prompt, edits included, so mask the
+    // runbook's secrets before it gets there
+    state
+        .redactor(runbook_id)
+        .await
+        .redact_json(&mut current_document);
+
     let ai_manager = state.ai_manager().await;
     let handle = ai_manager
         .create_generator_session(
@@ -158,6 +165,13 @@ pub async fn ai_send_message(
         .await
         .ok_or_else(|| format!("Session {} not found", session_id))?;
 
+    // The message may quote output or variables, so mask the runbook's secrets
+    let message = state
+        .redactor(handle.runbook_id().await)
+        .await
+        .redact(&message)
+        .into_owned();
+
     handle
         .send_user_message(message)
         .await
@@ -179,6 +193,13 @@ pub async fn ai_send_tool_result(
         .await
         .ok_or_else(|| format!("Session {} not found", session_id))?;
 
+    // Tool results carry block output and variables, so mask the runbook's secrets
+    let result = state
+        .redactor(handle.runbook_id().await)
+        .await
+        .redact(&result)
+        .into_owned();
+
     handle
         .send_tool_result(tool_call_id, success, result)
         .await
@@ -226,6 +247,12 @@ pub async fn ai_send_edit_request(
         .await
         .ok_or_else(|| format!("Session {} not found", session_id))?;
 
+    let edit_prompt = state
+        .redactor(handle.runbook_id().await)
+        .await
+        .redact(&edit_prompt)
+        .into_owned();
+
     handle
         .send_edit_request(edit_prompt, tool_call_id)
         .await
@@ -8,18 +8,22 @@ use atuin_desktop_runtime::execution::ExecutionHandle;
 use atuin_desktop_runtime::execution::ExecutionResult;
 use atuin_desktop_runtime::pty::PtyStoreHandle;
 use atuin_desktop_runtime::ssh::SshPoolHandle;
+use atuin_desktop_runtime::workflow::WorkflowEvent;
 use serde_json::Value;
 use tauri::Manager;
 use tauri::{ipc::Channel, AppHandle, Runtime, State};
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 use uuid::Uuid;
 
 use crate::blocks::sqlite_context_storage::SqliteContextStorage;
 use crate::commands::events::ChannelEventBus;
+use crate::commands::workflow::runner::BLOCK_PAUSED;
 use crate::kv;
+use crate::secret_cache::SecretCache;
 use crate::state::AtuinState;
 use atuin_desktop_runtime::client::LocalValueProvider;
 use atuin_desktop_runtime::client::MessageChannel;
+use atuin_desktop_runtime::client::SecretProvider;
 use atuin_desktop_runtime::client::{ClientPromptResult, DocumentBridgeMessage};
 use atuin_desktop_runtime::context::ResolvedContext;
 use atuin_desktop_runtime::document::DocumentHandle;
@@ -82,6 +86,33 @@ impl<R: Runtime> LocalValueProvider for KvBlockLocalValueProvider<R> {
     }
 }
 
+/// Keychain service that runbook secrets are stored under, with the secret name as the user
+const RUNBOOK_SECRETS_SERVICE: &str = "sh.atuin.runbooks.secrets";
+
+/// Looks up secrets for `{{ secret("name") }}` templates in the secret cache
+struct SecretCacheProvider {
+    secret_cache: Arc<SecretCache>,
+}
+
+impl SecretProvider for SecretCacheProvider {
+    fn get_secret(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let secret_cache = self.secret_cache.clone();
+        let name = name.to_string();
+        let lookup = async move { secret_cache.get(RUNBOOK_SECRETS_SERVICE, &name).await };
+
+        // Templates render synchronously, often on a runtime worker thread
+        let secret = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => tokio::task::block_in_place(|| handle.block_on(lookup)),
+            Err(_) => tauri::async_runtime::block_on(lookup),
+        }?;
+
+        Ok(secret)
+    }
+}
+
 /// Runbook content loader that uses the workspace manager to load runbook content,
 /// with fallback to Atuin Hub for remote runbooks.
 #[derive(Clone)]
@@ -217,19 +248,7 @@ pub async fn execute_block(
     let pty_store = state.pty_store();
     let ssh_pool = state.ssh_pool();
 
-    let mut workspace_context = HashMap::new();
-    let workspace_root = if let Some(workspace_manager) = state.workspaces.lock().await.as_ref() {
-        workspace_manager
-            .workspace_root(&runbook_id)
-            .map(|path| path.to_string_lossy().to_string())
-            .unwrap_or_default()
-    } else {
-        String::new()
-    };
-    workspace_context.insert("root".to_string(), workspace_root.to_string());
-
-    let mut extra_template_context = HashMap::new();
-    extra_template_context.insert("workspace".to_string(), workspace_context);
+    let extra_template_context = workspace_template_context(&state, &runbook_id).await;
 
     let execution_handle_result = execute_single_block(
         runbook_id.clone(),
@@ -351,6 +370,13 @@ pub async fn open_document<R: Runtime>(
         workspace_root,
     );
 
+    document_handle
+        .set_secret_provider(Arc::new(SecretCacheProvider {
+            secret_cache: state.secret_cache(),
+        }))
+        .await
+        .map_err(|e| format!("Failed to set secret provider: {}", e))?;
+
     document_handle
         .put_document(document)
         .await
@@ -526,6 +552,7 @@ pub async fn start_serial_execution<R: Runtime>(
     state: State<'_, AtuinState>,
     document_id: String,
     from_block: Option<String>,
+    max_concurrency: Option<usize>,
 ) -> Result<(), String> {
     let mut serial_executions = state.serial_executions.write().await;
     if serial_executions.contains_key(&document_id) {
@@ -566,17 +593,7 @@ pub async fn start_serial_execution<R: Runtime>(
         all_block_ids
     };
 
-    let mut workspace_context = HashMap::new();
-    let workspace_root = if let Some(workspace_manager) = state.workspaces.lock().await.as_ref() {
-        workspace_manager
-            .workspace_root(&document_id)
-            .map(|path| path.to_string_lossy().to_string())
-            .unwrap_or_default()
-    } else {
-        String::new()
-    };
-
-    workspace_context.insert("root".to_string(), workspace_root.to_string());
+    let extra_template_context = workspace_template_context(&state, &document_id).await;
 
     let (tx, mut rx) = oneshot::channel();
     serial_executions.insert(document_id.clone(), tx);
@@ -590,12 +607,82 @@ pub async fn start_serial_execution<R: Runtime>(
         })
         .await
         .map_err(|e| format!("Failed to emit serial execution started event: {}", e))?;
+
+    // Independent blocks can run at once; a workflow works out which ones those are
+    if let Some(max_concurrency) = max_concurrency.filter(|&n| n > 1) {
+        let blocks = document
+            .blocks()
+            .await
+            .map_err(|e| format!("Failed to get blocks from document {document_id}: {}", e))?
+            .into_iter()
+            .filter(|block| block_ids.contains(&block.id()))
+            .collect::<Vec<_>>();
+
+        let executor = state.executor();
+        // Subscribe before starting, so no event for the run's blocks is missed
+        let mut events = state.event_sender().subscribe();
+        executor
+            .run_parallel_workflow(document_uuid, blocks, HashMap::new(), max_concurrency)
+            .await;
+
+        tokio::spawn(async move {
+            let mut cancelled = false;
+            let mut paused = false;
+            let mut error = None;
+
+            loop {
+                tokio::select! {
+                    _ = &mut rx, if !cancelled => {
+                        log::debug!("Parallel execution cancelled for document {document_id}");
+                        cancelled = true;
+                        executor.stop_workflow(document_uuid).await;
+                    }
+                    event = events.recv() => match event {
+                        Ok(WorkflowEvent::BlockFailed { id, error: block_error })
+                            if block_ids.contains(&id) =>
+                        {
+                            if block_error == BLOCK_PAUSED {
+                                paused = true;
+                            } else {
+                                error.get_or_insert(block_error);
+                            }
+                        }
+                        Ok(WorkflowEvent::WorkflowFinished { id }) if id == document_uuid => break,
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            log::warn!("Parallel execution of {document_id} missed {missed} events");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+
+            let exit_type = if cancelled {
+                ExecutionResult::Cancelled
+            } else if paused {
+                ExecutionResult::Paused
+            } else if error.is_some() {
+                ExecutionResult::Failure
+            } else {
+                ExecutionResult::Success
+            };
+
+            finish_serial_execution(
+                &app,
+                &document,
+                &document_id,
+                exit_type,
+                error.unwrap_or_else(|| "Serial execution failed".to_string()),
+            )
+            .await;
+        });
+
+        return Ok(());
+    }
+
     tokio::spawn(async move {
         let mut exit_type: ExecutionResult = ExecutionResult::Success;
 
-        let mut extra_template_context = HashMap::new();
-        extra_template_context.insert("workspace".to_string(), workspace_context);
-
         log::trace!("Starting serial execution for document {document_id}; blocks: {block_ids:?}");
         'outer: for block_id in &block_ids {
             log::trace!("Executing block {block_id} in document {document_id}");
@@ -685,54 +772,69 @@ pub async fn start_serial_execution<R: Runtime>(
             }
         }
 
-        match exit_type {
-            ExecutionResult::Success => {
-                let _ = document
-                    .event_bus()
-                    .emit(GCEvent::SerialExecutionCompleted {
-                        runbook_id: document_uuid,
-                    })
-                    .await
-                    .map_err(|e| format!("Failed to emit serial execution completed event: {}", e));
-            }
-            ExecutionResult::Failure => {
-                let _ = document
-                    .event_bus()
-                    .emit(GCEvent::SerialExecutionFailed {
-                        runbook_id: document_uuid,
-                        error: "Serial execution failed".to_string(),
-                    })
-                    .await
-                    .map_err(|e| format!("Failed to emit serial execution failed event: {}", e));
-            }
-            ExecutionResult::Cancelled => {
-                let _ = document
-                    .event_bus()
-                    .emit(GCEvent::SerialExecutionCancelled {
-                        runbook_id: document_uuid,
-                    })
-                    .await
-                    .map_err(|e| format!("Failed to emit serial execution cancelled event: {}", e));
-            }
-            ExecutionResult::Paused => {
-                // The SerialExecutionPaused event is already emitted by the pause block
-                // itself, so we don't emit any additional event here. This ensures we
-                // don't get both a "paused" and "completed" notification.
-                log::debug!("Serial execution paused for document {document_id}");
-            }
-        };
-
         log::trace!("Serial execution for document {document_id} completed; blocks: {block_ids:?}");
-        let state = app.state::<AtuinState>();
-        let mut serial_executions = state.serial_executions.write().await;
-        serial_executions.remove(&document_id);
-
-        log::debug!("Serial execution for document {document_id} completed");
+        finish_serial_execution(
+            &app,
+            &document,
+            &document_id,
+            exit_type,
+            "Serial execution failed".to_string(),
+        )
+        .await;
     });
 
     Ok(())
 }
 
+/// Announce how a serial execution ended, and allow the runbook to be run again
+async fn finish_serial_execution<R: Runtime>(
+    app: &AppHandle<R>,
+    document: &DocumentHandle,
+    document_id: &str,
+    exit_type: ExecutionResult,
+    error: String,
+) {
+    let Ok(runbook_id) = Uuid::parse_str(document_id) else {
+        return;
+    };
+
+    match exit_type {
+        ExecutionResult::Success => {
+            let _ = document
+                .event_bus()
+                .emit(GCEvent::SerialExecutionCompleted { runbook_id })
+                .await
+                .map_err(|e| format!("Failed to emit serial execution completed event: {}", e));
+        }
+        ExecutionResult::Failure => {
+            let _ = document
+                .event_bus()
+                .emit(GCEvent::SerialExecutionFailed { runbook_id, error })
+                .await
+                .map_err(|e| format!("Failed to emit serial execution failed event: {}", e));
+        }
+        ExecutionResult::Cancelled => {
+            let _ = document
+                .event_bus()
+                .emit(GCEvent::SerialExecutionCancelled { runbook_id })
+                .await
+                .map_err(|e| format!("Failed to emit serial execution cancelled event: {}", e));
+        }
+        ExecutionResult::Paused => {
+            // The SerialExecutionPaused event is already emitted by the pause block
+            // itself, so we don't emit any additional event here. This ensures we
+            // don't get both a "paused" and "completed" notification.
+            log::debug!("Serial execution paused for document {document_id}");
+        }
+    };
+
+    let state = app.state::<AtuinState>();
+    let mut serial_executions = state.serial_executions.write().await;
+    serial_executions.remove(document_id);
+
+    log::debug!("Serial execution for document {document_id} completed");
+}
+
 #[tauri::command]
 pub async fn stop_serial_execution(
     state: State<'_, AtuinState>,
@@ -777,7 +879,25 @@ pub async fn get_runbook_content(
     Ok(content)
 }
 
-async fn execute_single_block(
+/// The `workspace` template values for blocks of a runbook, such as `workspace.root`
+pub(crate) async fn workspace_template_context(
+    state: &AtuinState,
+    document_id: &str,
+) -> HashMap<String, HashMap<String, String>> {
+    let workspace_root = if let Some(workspace_manager) = state.workspaces.lock().await.as_ref() {
+        workspace_manager
+            .workspace_root(document_id)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    let workspace_context = HashMap::from([("root".to_string(), workspace_root)]);
+    HashMap::from([("workspace".to_string(), workspace_context)])
+}
+
+pub(crate) async fn execute_single_block(
     document_id: String,
     document: &Arc<DocumentHandle>,
     block_id: Uuid,
@@ -1,9 +1,15 @@
 use serde::{Deserialize, Serialize};
 use tauri::Emitter;
+use uuid::Uuid;
 
 use atuin_desktop_runtime::blocks::Block;
+use atuin_desktop_runtime::exec_log::{self, ExecLogEntry, ExecLogQuery, ExecRecord, ExecTrigger};
+use atuin_desktop_runtime::execution::ExecutionResult;
 use atuin_desktop_runtime::workflow::WorkflowEvent;
 
+use crate::commands::blocks::workspace_template_context;
+use crate::state::AtuinState;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct ExecLogCompletedEvent {
     pub block_id: String,
@@ -13,6 +19,7 @@ struct ExecLogCompletedEvent {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn log_execution<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
     state: tauri::State<'_, crate::state::AtuinState>,
@@ -20,17 +27,52 @@ pub async fn log_execution<R: tauri::Runtime>(
     start_time: u64,
     end_time: u64,
     output: String,
+    runbook_id: Option<Uuid>,
+    exit_code: Option<i32>,
+    result: Option<ExecutionResult>,
+    trigger: Option<ExecTrigger>,
 ) -> Result<(), String> {
     log::debug!("Logging execution for block: {block:?}");
+    let output = match runbook_id {
+        Some(runbook_id) => state
+            .redactor(runbook_id)
+            .await
+            .redact(&output)
+            .into_owned(),
+        None => state.global_redactor().await.redact(&output).into_owned(),
+    };
+    let fingerprint = match runbook_id {
+        Some(runbook_id) => block_fingerprint(&state, runbook_id, &block).await,
+        None => None,
+    };
+    let result = result.unwrap_or(ExecutionResult::Success);
+    let record = ExecRecord {
+        block_id: block.id(),
+        runbook_id,
+        start_time,
+        end_time,
+        output: output.clone(),
+        exit_code,
+        result,
+        fingerprint,
+        trigger: trigger.unwrap_or(ExecTrigger::User),
+    };
     state
         .exec_log()
-        .log_execution(block.clone(), start_time, end_time, output.clone())
+        .log_record(record)
         .await
         .map_err(|e| e.to_string())?;
 
     let event_sender = state.event_sender();
+    let event = match result {
+        ExecutionResult::Success => WorkflowEvent::BlockFinished { id: block.id() },
+        _ => WorkflowEvent::BlockFailed {
+            id: block.id(),
+            error: format!("Block finished with result {result:?}"),
+        },
+    };
     event_sender
-        .send(WorkflowEvent::BlockFinished { id: block.id() })
+        .send(event)
         .expect("Failed to send stop block event");
 
     app.emit(
@@ -44,3 +86,29 @@ pub async fn log_execution<R: tauri::Runtime>(
     )
     .map_err(|e| e.to_string())
 }
+
+/// Fingerprint of the inputs a block resolves to in its open runbook, as `atuin-run` records it
+async fn block_fingerprint(state: &AtuinState, runbook_id: Uuid, block: &Block) -> Option<String> {
+    let document_id = runbook_id.to_string();
+    let document = state.documents.read().await.get(&document_id).cloned()?;
+    let extra_template_context = workspace_template_context(state, &document_id).await;
+    let context = document
+        .create_execution_context(block.id(), None, None, Some(extra_template_context))
+        .await
+        .inspect_err(|e| log::debug!("Not fingerprinting block {}: {e}", block.id()))
+        .ok()?;
+
+    Some(exec_log::fingerprint(block, &context.context_resolver))
+}
+
+#[tauri::command]
+pub async fn exec_log_query(
+    state: tauri::State<'_, crate::state::AtuinState>,
+    query: ExecLogQuery,
+) -> Result<Vec<ExecLogEntry>, String> {
+    state
+        .exec_log()
+        .query(query)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use tauri::State;
+
+use crate::state::AtuinState;
 
 /// Information about an SSH key found in ~/.ssh
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +93,19 @@ pub async fn list_ssh_keys() -> Result<Vec<SshKeyInfo>, String> {
     Ok(keys)
 }
 
+/// Close the tunnel opened by an SSH tunnel block
+#[tauri::command]
+pub async fn close_ssh_tunnel(
+    state: State<'_, AtuinState>,
+    block_id: String,
+) -> Result<(), String> {
+    state
+        .ssh_pool()
+        .close_tunnel(&block_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Detect the key type from the file content
 fn detect_key_type(path: &PathBuf) -> Option<String> {
     let content = fs::read_to_string(path).ok()?;
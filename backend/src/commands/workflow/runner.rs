@@ -0,0 +1,129 @@
+//! Runs the blocks workflows ask for
+//!
+//! Workflows only decide when a block should run; they send `RunBlock` and wait for a
+//! `BlockFinished` or `BlockFailed` event. This task runs each requested block through the
+//! open document it belongs to and reports back when it's done.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use atuin_desktop_runtime::execution::{ExecutionHandle, ExecutionResult};
+use atuin_desktop_runtime::workflow::{WorkflowCommand, WorkflowEvent};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
+
+use crate::commands::blocks::{execute_single_block, workspace_template_context};
+use crate::state::AtuinState;
+
+/// Error reported for a block that paused, so a run can tell a pause from a failure
+pub(crate) const BLOCK_PAUSED: &str = "Paused";
+
+/// Start consuming workflow commands for the lifetime of the app
+pub(crate) fn spawn_block_runner<R: Runtime>(
+    app: AppHandle<R>,
+    mut commands: broadcast::Receiver<WorkflowCommand>,
+    events: broadcast::Sender<WorkflowEvent>,
+) {
+    // Blocks started by a workflow that are still running, so they can be stopped
+    let running: Arc<Mutex<HashMap<Uuid, ExecutionHandle>>> = Default::default();
+
+    tokio::spawn(async move {
+        loop {
+            let command = match commands.recv().await {
+                Ok(command) => command,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Workflow block runner missed {missed} commands");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            match command {
+                WorkflowCommand::RunBlock { id } => {
+                    let app = app.clone();
+                    let events = events.clone();
+                    let running = running.clone();
+                    tokio::spawn(async move {
+                        let event = match run_block(&app, id, &running).await {
+                            Ok(()) => WorkflowEvent::BlockFinished { id },
+                            Err(error) => WorkflowEvent::BlockFailed { id, error },
+                        };
+                        let _ = events.send(event);
+                    });
+                }
+                WorkflowCommand::StopBlock { id } => {
+                    if let Some(handle) = running.lock().await.remove(&id) {
+                        log::debug!("Stopping workflow block {id}");
+                        handle.cancellation_token.cancel();
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Run a block in whichever open document contains it, and wait for it to finish
+async fn run_block<R: Runtime>(
+    app: &AppHandle<R>,
+    block_id: Uuid,
+    running: &Mutex<HashMap<Uuid, ExecutionHandle>>,
+) -> Result<(), String> {
+    let state = app.state::<AtuinState>();
+
+    let mut found = None;
+    for (document_id, document) in state.documents.read().await.iter() {
+        if document.get_block(block_id).await.is_some() {
+            found = Some((document_id.clone(), document.clone()));
+            break;
+        }
+    }
+    let Some((document_id, document)) = found else {
+        return Err(format!("Block {block_id} is not in an open runbook"));
+    };
+
+    let extra_template_context = workspace_template_context(&state, &document_id).await;
+    let handle = execute_single_block(
+        document_id.clone(),
+        &document,
+        block_id,
+        state.ssh_pool(),
+        state.pty_store(),
+        extra_template_context,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Blocks without an execution handle are done as soon as they return
+    let Some(handle) = handle else {
+        return Ok(());
+    };
+
+    let mut finished = handle.finished_channel();
+    state
+        .block_executions
+        .write()
+        .await
+        .insert(handle.id, handle.clone());
+    running.lock().await.insert(block_id, handle.clone());
+
+    let result = loop {
+        if let Some(result) = *finished.borrow_and_update() {
+            break Some(result);
+        }
+        if finished.changed().await.is_err() {
+            break None;
+        }
+    };
+
+    running.lock().await.remove(&block_id);
+    state.block_executions.write().await.remove(&handle.id);
+
+    match result {
+        Some(ExecutionResult::Success) => Ok(()),
+        Some(ExecutionResult::Failure) => Err(format!("Block {block_id} failed")),
+        Some(ExecutionResult::Cancelled) => Err(format!("Block {block_id} was cancelled")),
+        Some(ExecutionResult::Paused) => Err(BLOCK_PAUSED.to_string()),
+        None => Err(format!("Block {block_id} stopped without a result")),
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::HashMap;
+
 use tauri::State;
 use uuid::Uuid;
 
 use crate::state::AtuinState;
 use atuin_desktop_runtime::blocks::Block;
-use atuin_desktop_runtime::workflow::WorkflowEvent;
+use atuin_desktop_runtime::workflow::{DependencySpec, WorkflowEvent};
+
+/// Blocks run at once by a parallel workflow, unless the caller asks otherwise
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
 
 #[tauri::command]
 pub async fn workflow_block_start_event(
@@ -18,6 +23,20 @@ pub async fn workflow_block_start_event(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn workflow_block_failed_event(
+    state: State<'_, AtuinState>,
+    block: Uuid,
+    error: String,
+) -> Result<(), String> {
+    let event_sender = state.event_sender();
+    event_sender
+        .send(WorkflowEvent::BlockFailed { id: block, error })
+        .expect("Failed to send failed block event");
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn workflow_serial(
     state: State<'_, AtuinState>,
@@ -30,6 +49,27 @@ pub async fn workflow_serial(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn workflow_parallel(
+    state: State<'_, AtuinState>,
+    id: Uuid,
+    workflow: Vec<Block>,
+    dependencies: Option<HashMap<Uuid, DependencySpec>>,
+    max_concurrency: Option<usize>,
+) -> Result<(), String> {
+    state
+        .executor()
+        .run_parallel_workflow(
+            id,
+            workflow,
+            dependencies.unwrap_or_default(),
+            max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY),
+        )
+        .await;
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn workflow_stop(state: State<'_, AtuinState>, id: Uuid) -> Result<(), String> {
     state.executor().stop_workflow(id).await;
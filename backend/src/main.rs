@@ -542,10 +542,13 @@ fn main() {
             main_window::save_window_info,
             main_window::show_window,
             commands::exec_log::log_execution,
+            commands::exec_log::exec_log_query,
             commands::dependency::can_run,
             commands::pty_store::runbook_kill_all_ptys,
             commands::workflow::serial::workflow_serial,
+            commands::workflow::serial::workflow_parallel,
             commands::workflow::serial::workflow_block_start_event,
+            commands::workflow::serial::workflow_block_failed_event,
             commands::workflow::serial::workflow_stop,
             commands::stats::command_stats,
             commands::template::set_template_var,
@@ -567,6 +570,7 @@ fn main() {
             commands::blocks::get_runbook_content,
             commands::events::subscribe_to_events,
             commands::ssh::list_ssh_keys,
+            commands::ssh::close_ssh_tunnel,
             commands::updates::check_for_updates,
             commands::workspaces::copy_welcome_workspace,
             commands::workspaces::reset_workspaces,
@@ -107,6 +107,11 @@ impl SecretCache {
 
         Ok(())
     }
+
+    /// Every secret currently held in the cache
+    pub async fn cached_values(&self) -> Vec<String> {
+        self.inner.read().await.cache.values().cloned().collect()
+    }
 }
 
 impl Drop for SecretCache {
@@ -11,15 +11,17 @@ use uuid::Uuid;
 
 use crate::{
     ai::{manager::AISessionManager, storage::AISessionStorage},
+    commands::workflow::runner::spawn_block_runner,
     secret_cache::{KeychainSecretStorage, KvDbSecretStorage, SecretCache},
 };
 use crate::{
     shared_state::SharedStateHandle, sqlite::DbInstances, workspaces::manager::WorkspaceManager,
 };
 use atuin_desktop_runtime::{
+    context::{is_sensitive_name, Redactor},
     document::DocumentHandle,
     events::GCEvent,
-    exec_log::ExecLogHandle,
+    exec_log::{ExecLogHandle, RetentionPolicy},
     execution::ExecutionHandle,
     pty::PtyStoreHandle,
     ssh::SshPoolHandle,
@@ -112,7 +114,7 @@ impl AtuinState {
             secret_cache: Mutex::new(None),
         }
     }
-    pub async fn init<R: Runtime>(&self, _app: &AppHandle<R>) -> Result<()> {
+    pub async fn init<R: Runtime>(&self, app: &AppHandle<R>) -> Result<()> {
         let path = if let Some(ref prefix) = self.dev_prefix {
             self.app_path.join(format!("{prefix}_exec_log.db"))
         } else {
@@ -129,6 +131,12 @@ impl AtuinState {
 
         // For some reason we cannot spawn the exec log task before the state is managed. Annoying.
         let exec_log = ExecLogHandle::new(path).expect("Failed to boot exec log");
+        let pruned_log = exec_log.clone();
+        tokio::spawn(async move {
+            if let Err(e) = pruned_log.prune(RetentionPolicy::default()).await {
+                log::warn!("Failed to prune exec log: {e}");
+            }
+        });
         self.exec_log.lock().unwrap().replace(exec_log);
 
         let pty_store = PtyStoreHandle::new();
@@ -149,13 +157,14 @@ impl AtuinState {
         // This is a BROADCAST channel, not a normal mpsc!
         // TODO: handle broadcast channel lag
         //
-        // Used by the executor for sending workflow events,
-        // but not currently used in the application after the 0.2.0 runtime update.
+        // Used by the executor for sending workflow events, such as when running the blocks
+        // of a runbook in parallel.
+        // Commands are broadcast too, so several workflows can run at once.
         let (event_sender, mut _event_receiver) = tokio::sync::broadcast::channel(24);
-        let (cmd_sender, mut _cmd_receiver) = mpsc::channel(8);
+        let (cmd_sender, cmd_receiver) = tokio::sync::broadcast::channel(24);
 
-        // NOTE: The executor is currently not doing anything post runtime update.
-        // It may still do so in the future for dependency-based workflows.
+        // Workflows decide when blocks run; the block runner runs them in their documents
+        spawn_block_runner(app.clone(), cmd_receiver, event_sender.clone());
         let executor = ExecutorHandle::new(event_sender.clone(), cmd_sender);
 
         self.executor.lock().unwrap().replace(executor);
@@ -276,4 +285,38 @@ impl AtuinState {
             panic!("AI manager not found");
         }
     }
+
+    /// Masks the sensitive values of an open runbook; runbooks that aren't open mask nothing
+    pub async fn redactor(&self, runbook_id: Uuid) -> Redactor {
+        let document = self
+            .documents
+            .read()
+            .await
+            .get(&runbook_id.to_string())
+            .cloned();
+
+        match document {
+            Some(document) => document
+                .get_context_resolver()
+                .await
+                .map(|resolver| resolver.redactor())
+                .unwrap_or_default(),
+            None => Redactor::default(),
+        }
+    }
+
+    /// Masks the sensitive values that don't belong to a runbook: the cached secrets, and the
+    /// app's environment variables with sensitive names
+    pub async fn global_redactor(&self) -> Redactor {
+        let secret_cache = self.secret_cache.lock().unwrap().clone();
+        let secrets = match secret_cache {
+            Some(secret_cache) => secret_cache.cached_values().await,
+            None => Vec::new(),
+        };
+        let env_values = std::env::vars()
+            .filter(|(name, _)| is_sensitive_name(name))
+            .map(|(_, value)| value);
+
+        Redactor::new(secrets.into_iter().chain(env_values))
+    }
 }
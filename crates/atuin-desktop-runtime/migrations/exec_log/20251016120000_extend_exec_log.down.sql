@@ -0,0 +1,9 @@
+drop index exec_log_end_time;
+drop index exec_log_runbook_end_time;
+drop index exec_log_block_end_time;
+
+alter table exec_log drop column triggered_by;
+alter table exec_log drop column fingerprint;
+alter table exec_log drop column result;
+alter table exec_log drop column exit_code;
+alter table exec_log drop column runbook_id;
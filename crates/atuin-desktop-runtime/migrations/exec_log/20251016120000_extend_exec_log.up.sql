@@ -0,0 +1,13 @@
+-- Record how each execution went and where it came from, so the log can be browsed
+-- rather than only used to check dependencies.
+-- Rows logged before this migration have NULL for all of these; they were only ever
+-- logged for completed executions.
+alter table exec_log add column runbook_id text;
+alter table exec_log add column exit_code integer;
+alter table exec_log add column result text;
+alter table exec_log add column fingerprint text;
+alter table exec_log add column triggered_by text;
+
+create index exec_log_block_end_time on exec_log(block_id, end_time);
+create index exec_log_runbook_end_time on exec_log(runbook_id, end_time);
+create index exec_log_end_time on exec_log(end_time);
@@ -0,0 +1,469 @@
+//! Assert block implementation
+//!
+//! The Assert block checks one or more MiniJinja expressions against the resolved
+//! context and the outputs of earlier blocks, such as
+//! `{{ doc.named.health.output.status == 200 }}`. The block fails if any expression is
+//! false, showing the values that were compared, so runbooks can double as smoke tests.
+
+use async_trait::async_trait;
+use minijinja::Value;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::blocks::{Block, BlockBehavior, FromDocument};
+use crate::context::{BlockExecutionOutput, ContextResolver};
+use crate::execution::{ExecutionContext, ExecutionHandle, StreamingBlockOutput};
+
+/// Operators whose operands are shown when a comparison fails, longest first
+const COMPARISON_OPERATORS: &[&str] = &["==", "!=", ">=", "<=", ">", "<"];
+
+/// A block that fails unless every one of its expressions is true
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct Assert {
+    #[builder(setter(into))]
+    pub id: Uuid,
+
+    #[builder(default, setter(into))]
+    pub name: String,
+
+    /// Expressions to check, one per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    #[builder(default, setter(into))]
+    pub assertions: String,
+}
+
+impl FromDocument for Assert {
+    fn from_document(block_data: &serde_json::Value) -> Result<Self, String> {
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or("Invalid or missing id")?;
+
+        let props = block_data
+            .get("props")
+            .and_then(|p| p.as_object())
+            .ok_or("Invalid or missing props")?;
+
+        let name = props
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let assertions = props
+            .get("assertions")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        Ok(Assert::builder()
+            .id(id)
+            .name(name)
+            .assertions(assertions)
+            .build())
+    }
+}
+
+impl Assert {
+    /// The expressions to check, without comments, blank lines or `{{ }}` wrappers
+    pub fn expressions(&self) -> Vec<&str> {
+        self.assertions
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.strip_prefix("{{")
+                    .and_then(|line| line.strip_suffix("}}"))
+                    .unwrap_or(line)
+                    .trim()
+            })
+            .collect()
+    }
+}
+
+/// The outcome of checking a single expression
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AssertionResult {
+    pub expression: String,
+    pub passed: bool,
+    /// Why the expression failed, including the values that were compared
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AssertExecutionOutput {
+    pub results: Vec<AssertionResult>,
+}
+
+impl AssertExecutionOutput {
+    pub fn failed(&self) -> impl Iterator<Item = &AssertionResult> {
+        self.results.iter().filter(|result| !result.passed)
+    }
+}
+
+impl BlockExecutionOutput for AssertExecutionOutput {
+    fn get_template_value(&self, key: &str) -> Option<minijinja::Value> {
+        match key {
+            "passed" => Some(minijinja::Value::from(self.failed().next().is_none())),
+            "failed" => Some(minijinja::Value::from(self.failed().count())),
+            "results" => Some(minijinja::Value::from_serialize(&self.results)),
+            _ => None,
+        }
+    }
+
+    fn enumerate_template_keys(&self) -> minijinja::value::Enumerator {
+        minijinja::value::Enumerator::Str(&["passed", "failed", "results"])
+    }
+}
+
+/// Evaluate an expression and explain why it failed, if it did
+fn check(resolver: &ContextResolver, expression: &str) -> AssertionResult {
+    let message = match resolver.evaluate_expression(expression) {
+        Ok(value) if value.is_true() => None,
+        Ok(value) => Some(explain_failure(resolver, expression, &value)),
+        Err(e) => Some(format!("assertion error: {expression}\n  {e}")),
+    };
+
+    AssertionResult {
+        expression: expression.to_string(),
+        passed: message.is_none(),
+        message,
+    }
+}
+
+fn explain_failure(resolver: &ContextResolver, expression: &str, value: &Value) -> String {
+    let mut message = format!("assertion failed: {expression}");
+
+    let operands = split_comparison(expression).and_then(|(left, operator, right)| {
+        let left = resolver.evaluate_expression(left).ok()?;
+        let right = resolver.evaluate_expression(right).ok()?;
+        Some((left, operator, right))
+    });
+
+    match operands {
+        Some((left, "==", right)) if is_multiline(&left) && is_multiline(&right) => {
+            message.push_str("\n  diff (- left, + right):");
+            for line in diff_lines(left.as_str().unwrap(), right.as_str().unwrap()) {
+                message.push_str("\n    ");
+                message.push_str(&line);
+            }
+        }
+        Some((left, _, right)) => {
+            message.push_str(&format!("\n   left: {}", describe(&left)));
+            message.push_str(&format!("\n  right: {}", describe(&right)));
+        }
+        None => message.push_str(&format!("\n  evaluated to: {}", describe(value))),
+    }
+
+    message
+}
+
+/// Split an expression at its top-level comparison, if it is a single comparison
+///
+/// Operators inside brackets or strings are skipped, and expressions combining several
+/// conditions with `and`/`or` are left whole, as their operands wouldn't explain much.
+fn split_comparison(expression: &str) -> Option<(&str, &'static str, &str)> {
+    let bytes = expression.as_bytes();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        match quote {
+            Some(_) if byte == b'\\' => index += 1,
+            Some(open) if byte == open => quote = None,
+            Some(_) => {}
+            None => match byte {
+                b'"' | b'\'' => quote = Some(byte),
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+                _ if depth == 0 => {
+                    let operator = COMPARISON_OPERATORS
+                        .iter()
+                        .find(|operator| bytes[index..].starts_with(operator.as_bytes()));
+                    if let Some(operator) = operator {
+                        let left = expression[..index].trim();
+                        let right = expression[index + operator.len()..].trim();
+                        let combined = |side: &str| side.contains(" and ") || side.contains(" or ");
+                        if combined(left) || combined(right) {
+                            return None;
+                        }
+                        return Some((left, operator, right));
+                    }
+                }
+                _ => {}
+            },
+        }
+        index += 1;
+    }
+
+    None
+}
+
+fn is_multiline(value: &Value) -> bool {
+    value.as_str().is_some_and(|text| text.contains('\n'))
+}
+
+/// Show a value as JSON, so `"200"` and `200` can be told apart
+fn describe(value: &Value) -> String {
+    if value.is_undefined() {
+        return "undefined".to_string();
+    }
+    serde_json::to_string(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// A line diff of two strings, with removed lines as `- ` and added lines as `+ `
+fn diff_lines(left: &str, right: &str) -> Vec<String> {
+    let left = left.lines().collect::<Vec<_>>();
+    let right = right.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of the remaining lines, from each position
+    let mut common = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < left.len() && j < right.len() {
+        if left[i] == right[j] {
+            lines.push(format!("  {}", left[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(format!("- {}", left[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", right[j]));
+            j += 1;
+        }
+    }
+    lines.extend(left[i..].iter().map(|line| format!("- {line}")));
+    lines.extend(right[j..].iter().map(|line| format!("+ {line}")));
+    lines
+}
+
+#[async_trait]
+impl BlockBehavior for Assert {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn into_block(self) -> Block {
+        Block::Assert(self)
+    }
+
+    async fn execute(
+        self,
+        context: ExecutionContext,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::trace!("Executing Assert block {id}", id = self.id);
+
+        let _ = context.block_started().await;
+
+        let expressions = self.expressions();
+        if expressions.is_empty() {
+            let _ = context
+                .block_failed("Assert block has no expressions to check".to_string())
+                .await;
+            return Ok(Some(context.handle()));
+        }
+
+        let output = AssertExecutionOutput {
+            results: expressions
+                .into_iter()
+                .map(|expression| check(&context.context_resolver, expression))
+                .collect(),
+        };
+
+        for result in &output.results {
+            let line = match &result.message {
+                None => StreamingBlockOutput::builder()
+                    .block_id(self.id)
+                    .stdout(format!("ok: {}\n", result.expression))
+                    .build(),
+                Some(message) => StreamingBlockOutput::builder()
+                    .block_id(self.id)
+                    .stderr(format!("{message}\n"))
+                    .build(),
+            };
+            let _ = context.send_output(line).await;
+        }
+
+        let _ = context
+            .send_output(
+                StreamingBlockOutput::builder()
+                    .block_id(self.id)
+                    .object(serde_json::to_value(&output)?)
+                    .build(),
+            )
+            .await;
+
+        let failures = output
+            .failed()
+            .filter_map(|result| result.message.clone())
+            .collect::<Vec<_>>();
+        let total = output.results.len();
+        let _ = context.set_block_output(output).await;
+
+        if failures.is_empty() {
+            let _ = context.block_finished(Some(0), true).await;
+        } else {
+            // Failure messages include evaluated values, which may be sensitive
+            let message = format!(
+                "{} of {total} assertions failed\n{}",
+                failures.len(),
+                failures.join("\n")
+            );
+            let message = context
+                .context_resolver
+                .redactor()
+                .redact(&message)
+                .into_owned();
+            let _ = context.block_failed(message).await;
+        }
+
+        Ok(Some(context.handle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn resolver() -> ContextResolver {
+        let mut vars = HashMap::new();
+        vars.insert("status".to_string(), "503".to_string());
+        vars.insert("body".to_string(), "ok\nready\nhealthy".to_string());
+        ContextResolver::with_vars(vars)
+    }
+
+    #[test]
+    fn test_assert_from_document() {
+        let id = Uuid::new_v4();
+        let json_data = serde_json::json!({
+            "id": id.to_string(),
+            "props": {
+                "name": "Smoke test",
+                "assertions": "# health check\n{{ var.status == '200' }}\n\n  var.ready  \n"
+            },
+            "type": "assert"
+        });
+
+        let assert = Assert::from_document(&json_data).unwrap();
+        assert_eq!(assert.id, id);
+        assert_eq!(assert.name, "Smoke test");
+        assert_eq!(
+            assert.expressions(),
+            vec!["var.status == '200'", "var.ready"]
+        );
+    }
+
+    #[test]
+    fn test_assert_from_document_missing_id() {
+        let json_data = serde_json::json!({
+            "props": {},
+            "type": "assert"
+        });
+
+        assert!(Assert::from_document(&json_data).is_err());
+    }
+
+    #[test]
+    fn test_passing_assertion() {
+        let result = check(&resolver(), "var.status | int >= 500");
+        assert!(result.passed);
+        assert_eq!(result.message, None);
+    }
+
+    #[test]
+    fn test_failed_comparison_shows_operands() {
+        let result = check(&resolver(), "var.status == 200");
+        assert!(!result.passed);
+        assert_eq!(
+            result.message.unwrap(),
+            "assertion failed: var.status == 200\n   left: \"503\"\n  right: 200"
+        );
+    }
+
+    #[test]
+    fn test_failed_expression_shows_value() {
+        let result = check(&resolver(), "var.status in ['200', '204']");
+        assert_eq!(
+            result.message.unwrap(),
+            "assertion failed: var.status in ['200', '204']\n  evaluated to: false"
+        );
+
+        let result = check(&resolver(), "var.status == '200' or var.status == '204'");
+        assert_eq!(
+            result.message.unwrap(),
+            "assertion failed: var.status == '200' or var.status == '204'\n  evaluated to: false"
+        );
+    }
+
+    #[test]
+    fn test_failed_multiline_comparison_shows_diff() {
+        let result = check(&resolver(), "var.body == 'ok\\nstarting\\nhealthy'");
+        assert_eq!(
+            result.message.unwrap(),
+            "assertion failed: var.body == 'ok\\nstarting\\nhealthy'\n  diff (- left, + right):\n      ok\n    - ready\n    + starting\n      healthy"
+        );
+    }
+
+    #[test]
+    fn test_invalid_expression_fails() {
+        let result = check(&resolver(), "var.missing.field == 1");
+        assert!(!result.passed);
+        assert!(result
+            .message
+            .unwrap()
+            .starts_with("assertion error: var.missing.field == 1"));
+    }
+
+    #[test]
+    fn test_split_comparison() {
+        assert_eq!(
+            split_comparison("a.b == 'x == y'"),
+            Some(("a.b", "==", "'x == y'"))
+        );
+        assert_eq!(
+            split_comparison("x | default(1) >= (y > 2)"),
+            Some(("x | default(1)", ">=", "(y > 2)"))
+        );
+        assert_eq!(split_comparison("x is defined"), None);
+    }
+
+    #[test]
+    fn test_output_template_values() {
+        let output = AssertExecutionOutput {
+            results: vec![check(&resolver(), "true"), check(&resolver(), "false")],
+        };
+
+        assert_eq!(
+            output.get_template_value("passed"),
+            Some(minijinja::Value::from(false))
+        );
+        assert_eq!(
+            output.get_template_value("failed"),
+            Some(minijinja::Value::from(1))
+        );
+    }
+}
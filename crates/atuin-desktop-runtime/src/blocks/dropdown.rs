@@ -121,15 +121,12 @@ pub struct Dropdown {
 }
 
 impl Dropdown {
-    async fn resolve_options(
-        &self,
-        context: &ExecutionContext,
-    ) -> Result<Vec<DropdownOption>, Box<dyn std::error::Error + Send + Sync>> {
+    fn options_source(&self) -> &str {
         let all_three_options_blank = self.fixed_options.is_empty()
             && self.variable_options.is_empty()
             && self.command_options.is_empty();
 
-        let options_source = if all_three_options_blank {
+        if all_three_options_blank {
             &self.options
         } else {
             match self.options_type {
@@ -137,13 +134,36 @@ impl Dropdown {
                 DropdownOptionType::Variable => &self.variable_options,
                 DropdownOptionType::Command => &self.command_options,
             }
-        };
+        }
+    }
 
-        let delimiter = if self.delimiter.is_empty() {
+    fn delimiter(&self) -> &str {
+        if self.delimiter.is_empty() {
             ":"
         } else {
             &self.delimiter
-        };
+        }
+    }
+
+    /// Get the options of a fixed dropdown without executing it
+    ///
+    /// Returns `None` for variable and command dropdowns, as their options are only known at runtime.
+    pub fn fixed_option_list(&self) -> Option<Result<Vec<DropdownOption>, String>> {
+        match self.options_type {
+            DropdownOptionType::Fixed => Some(DropdownOption::vec_from_str_with_delimiter(
+                self.options_source(),
+                self.delimiter(),
+            )),
+            _ => None,
+        }
+    }
+
+    async fn resolve_options(
+        &self,
+        context: &ExecutionContext,
+    ) -> Result<Vec<DropdownOption>, Box<dyn std::error::Error + Send + Sync>> {
+        let options_source = self.options_source();
+        let delimiter = self.delimiter();
 
         let options = match self.options_type {
             DropdownOptionType::Fixed => {
@@ -0,0 +1,976 @@
+//! For Each block implementation
+//!
+//! The For Each block runs the blocks nested under it once for every item of a list, such as
+//! the hosts in a typed var, the rows of a SQL query or the lines a script printed. Its
+//! children are scoped to it: they are not part of the document's linear order, and every
+//! iteration runs them in a document of their own, with `item` and `loop` set, so variables
+//! they set don't leak into other iterations or the rest of the runbook.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use minijinja::value::ValueKind;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinSet;
+use ts_rs::TS;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::blocks::{Block, BlockBehavior, FromDocument};
+use crate::client::{DocumentBridgeMessage, MessageChannel};
+use crate::context::{BlockExecutionOutput, BlockState, Redactor};
+use crate::document::DocumentHandle;
+use crate::events::MemoryEventBus;
+use crate::execution::{ExecutionContext, ExecutionHandle, ExecutionResult, StreamingBlockOutput};
+use crate::templates::OutputWrapper;
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Longest item label shown in progress, before it is cut short
+const MAX_LABEL_LENGTH: usize = 60;
+
+/// How the iterations of a For Each block are run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum LoopMode {
+    /// One item at a time, in order
+    #[default]
+    Serial,
+    /// Several items at once, up to the block's concurrency
+    Parallel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct Foreach {
+    #[builder(setter(into))]
+    pub id: Uuid,
+
+    #[builder(setter(into))]
+    pub name: String,
+
+    /// Expression for the list to loop over, such as `var.hosts`
+    #[builder(setter(into))]
+    pub items: String,
+
+    #[builder(default)]
+    pub mode: LoopMode,
+
+    /// How many iterations run at once in parallel mode
+    #[builder(default = DEFAULT_CONCURRENCY)]
+    pub concurrency: usize,
+
+    /// Whether the first failed iteration stops the ones that haven't finished
+    #[builder(default = true)]
+    pub fail_fast: bool,
+
+    /// The nested blocks run for every item, as they are in the document
+    #[builder(default)]
+    pub children: Vec<serde_json::Value>,
+}
+
+impl FromDocument for Foreach {
+    fn from_document(block_data: &serde_json::Value) -> Result<Self, String> {
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or("Invalid or missing id")?;
+
+        let props = block_data
+            .get("props")
+            .and_then(|p| p.as_object())
+            .ok_or("Invalid or missing props")?;
+
+        let mode = match props.get("mode").and_then(|v| v.as_str()) {
+            Some("parallel") => LoopMode::Parallel,
+            _ => LoopMode::Serial,
+        };
+
+        // Numbers come from text inputs in the editor, so accept them as strings too
+        let concurrency = props
+            .get("concurrency")
+            .and_then(|v| match v {
+                serde_json::Value::Number(n) => n.as_u64().map(|n| n as usize),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            })
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_CONCURRENCY);
+
+        let fail_fast = props
+            .get("failFast")
+            .and_then(|v| match v {
+                serde_json::Value::Bool(b) => Some(*b),
+                serde_json::Value::String(s) => s.parse().ok(),
+                _ => None,
+            })
+            .unwrap_or(true);
+
+        let children = block_data
+            .get("children")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(Foreach::builder()
+            .id(id)
+            .name(
+                props
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            )
+            .items(
+                props
+                    .get("items")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            )
+            .mode(mode)
+            .concurrency(concurrency)
+            .fail_fast(fail_fast)
+            .children(children)
+            .build())
+    }
+}
+
+/// State of one iteration of a For Each block
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub enum IterationStatus {
+    Pending,
+    Running,
+    Success,
+    Failed,
+    Cancelled,
+    /// Never started, as the loop was stopped first
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct IterationState {
+    /// The item, as text
+    pub label: String,
+    pub status: IterationStatus,
+    pub error: Option<String>,
+}
+
+/// Progress of a For Each block, one entry per item
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ForeachState {
+    pub iterations: Vec<IterationState>,
+}
+
+impl BlockState for ForeachState {}
+
+/// Output of one iteration
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IterationOutput {
+    pub item: serde_json::Value,
+    pub status: IterationStatus,
+    pub error: Option<String>,
+    /// Outputs of the iteration's named blocks, by name
+    #[serde(skip)]
+    pub outputs: BTreeMap<String, Arc<dyn BlockExecutionOutput>>,
+}
+
+impl IterationOutput {
+    fn template_value(&self, index: usize) -> minijinja::Value {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|(name, output)| {
+                let output = OutputWrapper::new(output.clone(), Redactor::default());
+                (name.clone(), minijinja::Value::from_object(output))
+            })
+            .collect::<minijinja::Value>();
+
+        [
+            ("item", minijinja::Value::from_serialize(&self.item)),
+            ("index", minijinja::Value::from(index + 1)),
+            (
+                "success",
+                minijinja::Value::from(self.status == IterationStatus::Success),
+            ),
+            ("error", minijinja::Value::from(self.error.clone())),
+            ("outputs", outputs),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+/// Output of a For Each block: what happened to every item, in order
+///
+/// In templates, `output.iterations[0].outputs.deploy.stdout` reads the output of the block
+/// named "deploy" in the first iteration.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeachOutput {
+    pub iterations: Vec<IterationOutput>,
+}
+
+impl BlockExecutionOutput for ForeachOutput {
+    fn get_template_value(&self, key: &str) -> Option<minijinja::Value> {
+        match key {
+            "iterations" => Some(
+                self.iterations
+                    .iter()
+                    .enumerate()
+                    .map(|(index, iteration)| iteration.template_value(index))
+                    .collect(),
+            ),
+            "items" => Some(
+                self.iterations
+                    .iter()
+                    .map(|iteration| minijinja::Value::from_serialize(&iteration.item))
+                    .collect(),
+            ),
+            "failed" => Some(
+                self.iterations
+                    .iter()
+                    .filter(|iteration| iteration.status == IterationStatus::Failed)
+                    .map(|iteration| minijinja::Value::from_serialize(&iteration.item))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn enumerate_template_keys(&self) -> minijinja::value::Enumerator {
+        minijinja::value::Enumerator::Str(&["iterations", "items", "failed"])
+    }
+}
+
+/// Turn the value of the `items` expression into the list to loop over
+///
+/// Lists are used as they are; text, like a script's stdout, is split into its non-empty lines.
+fn list_items(value: &minijinja::Value) -> Result<Vec<serde_json::Value>, String> {
+    if let Some(text) = value.as_str() {
+        return Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::Value::String(line.to_string()))
+            .collect());
+    }
+
+    match value.kind() {
+        ValueKind::Seq | ValueKind::Iterable => {
+            let items = value.try_iter().map_err(|e| e.to_string())?;
+            items
+                .map(|item| serde_json::to_value(&item).map_err(|e| e.to_string()))
+                .collect()
+        }
+        ValueKind::Undefined | ValueKind::None => Err("Items are empty or undefined".to_string()),
+        kind => Err(format!("Expected a list of items, got {kind}")),
+    }
+}
+
+/// How an item is shown in progress and messages
+fn item_label(item: &serde_json::Value) -> String {
+    let label = match item {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+
+    if label.chars().count() > MAX_LABEL_LENGTH {
+        let short = label.chars().take(MAX_LABEL_LENGTH - 1).collect::<String>();
+        format!("{short}…")
+    } else {
+        label
+    }
+}
+
+/// The `loop` value templates see in an iteration, named after Jinja's loop variable
+fn loop_value(index: usize, length: usize) -> minijinja::Value {
+    [
+        ("index", minijinja::Value::from(index + 1)),
+        ("index0", minijinja::Value::from(index)),
+        ("length", minijinja::Value::from(length)),
+        ("first", minijinja::Value::from(index == 0)),
+        ("last", minijinja::Value::from(index + 1 == length)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Stands in for the bridge when the loop's context has no output channel
+struct NoOpChannel;
+
+#[async_trait]
+impl MessageChannel<DocumentBridgeMessage> for NoOpChannel {
+    async fn send(
+        &self,
+        _message: DocumentBridgeMessage,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+/// Output channel for one iteration of a parallel loop
+///
+/// Parallel iterations run the same blocks at the same time, so their output can't go to the
+/// nested blocks without mixing. Their text output goes to the loop block instead, each line
+/// labelled with the item, and their lifecycle events are left out. Everything else, such as
+/// prompts, goes to the loop's channel as it is.
+struct IterationChannel {
+    inner: Arc<dyn MessageChannel<DocumentBridgeMessage>>,
+    block_id: Uuid,
+    prefix: String,
+    /// Whether the next text from each nested block and stream starts a new line
+    line_starts: Mutex<HashMap<(Uuid, bool), bool>>,
+}
+
+impl IterationChannel {
+    fn new(
+        inner: Arc<dyn MessageChannel<DocumentBridgeMessage>>,
+        block_id: Uuid,
+        label: &str,
+    ) -> Self {
+        Self {
+            inner,
+            block_id,
+            prefix: format!("[{label}] "),
+            line_starts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn label(&self, block_id: Uuid, stderr: bool, text: Option<String>) -> Option<String> {
+        let text = text.filter(|text| !text.is_empty())?;
+        let mut line_starts = self.line_starts.lock().unwrap();
+        let at_line_start = line_starts.entry((block_id, stderr)).or_insert(true);
+        Some(label_lines(&text, &self.prefix, at_line_start))
+    }
+}
+
+#[async_trait]
+impl MessageChannel<DocumentBridgeMessage> for IterationChannel {
+    async fn send(
+        &self,
+        message: DocumentBridgeMessage,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let DocumentBridgeMessage::BlockOutput { block_id, output } = message else {
+            return self.inner.send(message).await;
+        };
+
+        let stdout = self.label(block_id, false, output.stdout);
+        let stderr = self.label(block_id, true, output.stderr);
+        if stdout.is_none() && stderr.is_none() {
+            return Ok(());
+        }
+
+        self.inner
+            .send(
+                StreamingBlockOutput::builder()
+                    .block_id(self.block_id)
+                    .stdout_opt(stdout)
+                    .stderr_opt(stderr)
+                    .build()
+                    .into(),
+            )
+            .await
+    }
+}
+
+/// Put `prefix` at the start of every line of `text`
+///
+/// `at_line_start` says whether `text` carries on a line from the previous chunk, and is
+/// updated for the next one.
+fn label_lines(text: &str, prefix: &str, at_line_start: &mut bool) -> String {
+    let mut labelled = String::with_capacity(text.len() + prefix.len());
+    for line in text.split_inclusive('\n') {
+        if *at_line_start {
+            labelled.push_str(prefix);
+        }
+        labelled.push_str(line);
+        *at_line_start = line.ends_with('\n');
+    }
+    labelled
+}
+
+/// Outcome of one iteration
+struct IterationRun {
+    status: IterationStatus,
+    error: Option<String>,
+    outputs: BTreeMap<String, Arc<dyn BlockExecutionOutput>>,
+}
+
+impl IterationRun {
+    fn stopped(status: IterationStatus) -> Self {
+        Self {
+            status,
+            error: None,
+            outputs: BTreeMap::new(),
+        }
+    }
+}
+
+/// Everything an iteration needs, shared between iterations
+struct LoopTask {
+    block_id: Uuid,
+    children: Vec<serde_json::Value>,
+    length: usize,
+    mode: LoopMode,
+    context: ExecutionContext,
+}
+
+impl Foreach {
+    /// Run every iteration and report the block's outcome
+    async fn run(self, items: Vec<serde_json::Value>, context: ExecutionContext) {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let stop_tx = Arc::new(stop_tx);
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let cancel_watcher = context.cancellation_receiver().map(|cancel_rx| {
+            let stop_tx = stop_tx.clone();
+            let cancelled = cancelled.clone();
+            tokio::spawn(async move {
+                if cancel_rx.await.is_ok() {
+                    cancelled.store(true, Ordering::SeqCst);
+                    stop_tx.send_replace(true);
+                }
+            })
+        });
+
+        let task = Arc::new(LoopTask {
+            block_id: self.id,
+            children: self.children.clone(),
+            length: items.len(),
+            mode: self.mode,
+            context: context.clone(),
+        });
+
+        let mut runs = Vec::with_capacity(items.len());
+        match self.mode {
+            LoopMode::Serial => {
+                for (index, item) in items.iter().enumerate() {
+                    let run = run_iteration(&task, index, item, stop_rx.clone()).await;
+                    if run.status == IterationStatus::Failed && self.fail_fast {
+                        stop_tx.send_replace(true);
+                    }
+                    runs.push(run);
+                }
+            }
+            LoopMode::Parallel => {
+                let semaphore = Arc::new(Semaphore::new(self.concurrency.max(1)));
+                let mut running = JoinSet::new();
+                for (index, item) in items.iter().cloned().enumerate() {
+                    let task = task.clone();
+                    let semaphore = semaphore.clone();
+                    let stop_rx = stop_rx.clone();
+                    running.spawn(async move {
+                        let Ok(_permit) = semaphore.acquire_owned().await else {
+                            return (index, IterationRun::stopped(IterationStatus::Skipped));
+                        };
+                        (index, run_iteration(&task, index, &item, stop_rx).await)
+                    });
+                }
+
+                let mut finished = BTreeMap::new();
+                while let Some(joined) = running.join_next().await {
+                    let Ok((index, run)) = joined else {
+                        continue;
+                    };
+                    if run.status == IterationStatus::Failed && self.fail_fast {
+                        stop_tx.send_replace(true);
+                    }
+                    finished.insert(index, run);
+                }
+                runs = (0..items.len())
+                    .map(|index| {
+                        finished
+                            .remove(&index)
+                            .unwrap_or_else(|| IterationRun::stopped(IterationStatus::Cancelled))
+                    })
+                    .collect();
+            }
+        }
+
+        if let Some(cancel_watcher) = cancel_watcher {
+            cancel_watcher.abort();
+        }
+
+        let failed = items
+            .iter()
+            .zip(&runs)
+            .filter(|(_, run)| run.status == IterationStatus::Failed)
+            .map(|(item, _)| item_label(item))
+            .collect::<Vec<_>>();
+        let failure = (!failed.is_empty()).then(|| {
+            format!(
+                "Failed for {} of {} items: {}",
+                failed.len(),
+                items.len(),
+                failed.join(", ")
+            )
+        });
+
+        let output = ForeachOutput {
+            iterations: items
+                .into_iter()
+                .zip(runs)
+                .map(|(item, run)| IterationOutput {
+                    item,
+                    status: run.status,
+                    error: run.error,
+                    outputs: run.outputs,
+                })
+                .collect(),
+        };
+        let _ = context.set_block_output(output).await;
+
+        if cancelled.load(Ordering::SeqCst) {
+            let _ = context.block_cancelled().await;
+        } else if let Some(failure) = failure {
+            let _ = context.block_failed(failure).await;
+        } else {
+            let _ = context.block_finished(None, true).await;
+        }
+    }
+}
+
+/// Run the children once for the item at `index`, reporting progress as it goes
+async fn run_iteration(
+    task: &LoopTask,
+    index: usize,
+    item: &serde_json::Value,
+    stop_rx: watch::Receiver<bool>,
+) -> IterationRun {
+    if *stop_rx.borrow() {
+        set_iteration_status(task, index, IterationStatus::Skipped, None).await;
+        return IterationRun::stopped(IterationStatus::Skipped);
+    }
+
+    set_iteration_status(task, index, IterationStatus::Running, None).await;
+
+    let run = run_children(task, index, item, stop_rx)
+        .await
+        .unwrap_or_else(|error| IterationRun {
+            status: IterationStatus::Failed,
+            error: Some(error),
+            outputs: BTreeMap::new(),
+        });
+
+    let label = item_label(item);
+    let line = match (&run.status, &run.error) {
+        (IterationStatus::Failed, Some(error)) => format!("[{label}] failed: {error}"),
+        (IterationStatus::Success, _) => format!("[{label}] done"),
+        (IterationStatus::Cancelled, _) => format!("[{label}] cancelled"),
+        _ => format!("[{label}] failed"),
+    };
+    let _ = task
+        .context
+        .send_output(
+            StreamingBlockOutput::builder()
+                .block_id(task.block_id)
+                .stdout(format!("{line}\n"))
+                .build(),
+        )
+        .await;
+
+    set_iteration_status(task, index, run.status, run.error.clone()).await;
+    run
+}
+
+/// Run the children in a document of their own, scoped to the item
+///
+/// Errors are problems setting the iteration up rather than failures of its blocks.
+async fn run_children(
+    task: &LoopTask,
+    index: usize,
+    item: &serde_json::Value,
+    mut stop_rx: watch::Receiver<bool>,
+) -> Result<IterationRun, String> {
+    let context = &task.context;
+
+    let mut resolver = (*context.context_resolver).clone();
+    resolver.add_extra_template_value("item".to_string(), minijinja::Value::from_serialize(item));
+    resolver.add_extra_template_value("loop".to_string(), loop_value(index, task.length));
+
+    let mut output_channel = context
+        .output_channel()
+        .unwrap_or_else(|| Arc::new(NoOpChannel));
+    if task.mode == LoopMode::Parallel {
+        output_channel = Arc::new(IterationChannel::new(
+            output_channel,
+            task.block_id,
+            &item_label(item),
+        ));
+    }
+
+    let document = DocumentHandle::new(
+        context.runbook_id.to_string(),
+        Arc::new(MemoryEventBus::new()),
+        output_channel,
+        context.block_local_value_provider(),
+        None, // context_storage: iterations don't persist context
+        context.runbook_loader().cloned(),
+        None, // workspace_root: inherited through the enclosing context
+    );
+
+    document
+        .set_enclosing_context(Arc::new(resolver))
+        .await
+        .map_err(|e| format!("Failed to set loop context: {e}"))?;
+    document
+        .put_document(task.children.clone())
+        .await
+        .map_err(|e| format!("Failed to load nested blocks: {e}"))?;
+    let blocks = document
+        .blocks()
+        .await
+        .map_err(|e| format!("Failed to load nested blocks: {e}"))?;
+
+    let mut run = IterationRun {
+        status: IterationStatus::Success,
+        error: None,
+        outputs: BTreeMap::new(),
+    };
+    for block in &blocks {
+        if *stop_rx.borrow() {
+            run.status = IterationStatus::Cancelled;
+            break;
+        }
+
+        let block_context = document
+            .create_execution_context(block.id(), None, None, None)
+            .await
+            .map_err(|e| format!("Failed to create execution context: {e}"))?
+            .configure_for_nested_block(context);
+
+        let handle = block
+            .clone()
+            .execute(block_context)
+            .await
+            .map_err(|e| format!("Block '{}' failed: {e}", block.name()))?;
+
+        // Passive blocks like vars have no handle and are done already
+        let result = match handle {
+            Some(handle) => {
+                tokio::select! {
+                    result = handle.wait_for_completion() => result,
+                    _ = wait_for_stop(&mut stop_rx) => {
+                        handle.cancellation_token.cancel();
+                        handle.wait_for_completion().await
+                    }
+                }
+            }
+            None => ExecutionResult::Success,
+        };
+
+        match result {
+            ExecutionResult::Success => {}
+            ExecutionResult::Failure => {
+                run.status = IterationStatus::Failed;
+                run.error = Some(format!("Block '{}' failed", block.name()));
+                break;
+            }
+            ExecutionResult::Cancelled => {
+                run.status = IterationStatus::Cancelled;
+                break;
+            }
+            ExecutionResult::Paused => {
+                run.status = IterationStatus::Failed;
+                run.error = Some("Pause blocks are not supported in loops".to_string());
+                break;
+            }
+        }
+    }
+
+    for block in &blocks {
+        let name = block.name();
+        if name.is_empty() {
+            continue;
+        }
+        if let Ok(Some(output)) = document.get_block_execution_output_dyn(block.id()).await {
+            run.outputs.insert(name, output);
+        }
+    }
+
+    Ok(run)
+}
+
+/// Resolve once the loop is stopped, by cancellation or a failed iteration
+async fn wait_for_stop(stop_rx: &mut watch::Receiver<bool>) {
+    let _ = stop_rx.wait_for(|stop| *stop).await;
+}
+
+async fn set_iteration_status(
+    task: &LoopTask,
+    index: usize,
+    status: IterationStatus,
+    error: Option<String>,
+) {
+    let _ = task
+        .context
+        .update_block_state::<ForeachState, _>(task.block_id, move |state| {
+            if let Some(iteration) = state.iterations.get_mut(index) {
+                iteration.status = status;
+                iteration.error = error;
+            }
+        })
+        .await;
+}
+
+#[async_trait]
+impl BlockBehavior for Foreach {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn into_block(self) -> Block {
+        Block::Foreach(self)
+    }
+
+    fn create_state(&self) -> Option<Box<dyn BlockState>> {
+        Some(Box::new(ForeachState::default()))
+    }
+
+    async fn execute(
+        self,
+        context: ExecutionContext,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::trace!("Executing For Each block {id}", id = self.id);
+
+        let items = if self.items.trim().is_empty() {
+            Err("No items to loop over".to_string())
+        } else {
+            context
+                .context_resolver
+                .evaluate_expression(&self.items)
+                .map_err(|e| format!("Invalid items: {e}"))
+                .and_then(|value| list_items(&value))
+        };
+
+        let items = match items {
+            Ok(_) if self.children.is_empty() => {
+                Err("Nothing to run: indent blocks under this one".to_string())
+            }
+            items => items,
+        };
+
+        let items = match items {
+            Ok(items) => items,
+            Err(e) => {
+                let _ = context.block_failed(e).await;
+                return Ok(Some(context.handle()));
+            }
+        };
+
+        let _ = context.block_started().await;
+        let iterations = items
+            .iter()
+            .map(|item| IterationState {
+                label: item_label(item),
+                status: IterationStatus::Pending,
+                error: None,
+            })
+            .collect::<Vec<_>>();
+        let _ = context
+            .update_block_state::<ForeachState, _>(self.id, move |state| {
+                state.iterations = iterations;
+            })
+            .await;
+
+        let handle = context.handle();
+        tokio::spawn(self.run(items, context));
+
+        Ok(Some(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::flatten_document;
+
+    fn foreach_block(children: Vec<serde_json::Value>) -> serde_json::Value {
+        serde_json::json!({
+            "id": "6f0c4f2e-8b4f-4b8e-9d6a-1a2b3c4d5e6f",
+            "type": "foreach",
+            "props": {
+                "name": "each host",
+                "items": "{{ var.hosts }}",
+                "mode": "parallel",
+                "concurrency": "2",
+                "failFast": false
+            },
+            "children": children
+        })
+    }
+
+    fn script_block(id: Uuid, name: &str, code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "type": "script",
+            "props": {
+                "name": name,
+                "code": code,
+                "interpreter": "bash",
+                "outputVariable": "",
+                "outputVisible": true
+            },
+            "children": []
+        })
+    }
+
+    #[test]
+    fn test_from_document() {
+        let child = script_block(Uuid::new_v4(), "greet", "echo {{ item }}");
+        let foreach = Foreach::from_document(&foreach_block(vec![child.clone()])).unwrap();
+
+        assert_eq!(foreach.name, "each host");
+        assert_eq!(foreach.items, "{{ var.hosts }}");
+        assert_eq!(foreach.mode, LoopMode::Parallel);
+        assert_eq!(foreach.concurrency, 2);
+        assert!(!foreach.fail_fast);
+        assert_eq!(foreach.children, vec![child]);
+    }
+
+    #[test]
+    fn test_from_document_defaults() {
+        let block = serde_json::json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "foreach",
+            "props": {},
+        });
+        let foreach = Foreach::from_document(&block).unwrap();
+
+        assert_eq!(foreach.mode, LoopMode::Serial);
+        assert_eq!(foreach.concurrency, DEFAULT_CONCURRENCY);
+        assert!(foreach.fail_fast);
+        assert!(foreach.children.is_empty());
+    }
+
+    #[test]
+    fn test_list_items() {
+        let rows = minijinja::Value::from_serialize(serde_json::json!([
+            {"name": "alice"},
+            {"name": "bob"}
+        ]));
+        assert_eq!(
+            list_items(&rows).unwrap(),
+            vec![
+                serde_json::json!({"name": "alice"}),
+                serde_json::json!({"name": "bob"})
+            ]
+        );
+
+        let stdout = minijinja::Value::from("web-1\n\n  web-2  \n");
+        assert_eq!(
+            list_items(&stdout).unwrap(),
+            vec![serde_json::json!("web-1"), serde_json::json!("web-2")]
+        );
+
+        assert!(list_items(&minijinja::Value::from(())).is_err());
+        assert!(list_items(&minijinja::Value::from(42)).is_err());
+    }
+
+    #[test]
+    fn test_item_label() {
+        assert_eq!(item_label(&serde_json::json!("web-1")), "web-1");
+        assert_eq!(item_label(&serde_json::json!({"id": 1})), r#"{"id":1}"#);
+
+        let long = item_label(&serde_json::json!("x".repeat(100)));
+        assert_eq!(long.chars().count(), MAX_LABEL_LENGTH);
+        assert!(long.ends_with('…'));
+    }
+
+    #[test]
+    fn test_label_lines() {
+        let mut at_line_start = true;
+        assert_eq!(
+            label_lines("one\ntwo\nthr", "[a] ", &mut at_line_start),
+            "[a] one\n[a] two\n[a] thr"
+        );
+        assert!(!at_line_start);
+        assert_eq!(
+            label_lines("ee\nfour\n", "[a] ", &mut at_line_start),
+            "ee\n[a] four\n"
+        );
+        assert!(at_line_start);
+    }
+
+    #[test]
+    fn test_children_are_not_flattened() {
+        let child = script_block(Uuid::new_v4(), "greet", "echo {{ item }}");
+        let document = vec![foreach_block(vec![child])];
+
+        let flattened = flatten_document(&document);
+        assert_eq!(flattened.len(), 1);
+        assert_eq!(flattened[0]["type"], "foreach");
+    }
+
+    #[tokio::test]
+    async fn test_runs_children_once_per_item() {
+        let runbook_id = Uuid::new_v4();
+        let foreach_id = Uuid::new_v4();
+        let child_id = Uuid::new_v4();
+
+        let mut foreach = foreach_block(vec![script_block(
+            child_id,
+            "greet",
+            "echo \"{{ loop.index }}/{{ loop.length }} {{ item }}\"",
+        )]);
+        foreach["id"] = serde_json::json!(foreach_id.to_string());
+        foreach["props"]["items"] = serde_json::json!("{{ ['web-1', 'web-2', 'web-3'] }}");
+
+        let document = DocumentHandle::new(
+            runbook_id.to_string(),
+            Arc::new(MemoryEventBus::new()),
+            Arc::new(NoOpChannel),
+            None,
+            None,
+            None,
+            None,
+        );
+        document.put_document(vec![foreach]).await.unwrap();
+
+        let blocks = document.blocks().await.unwrap();
+        assert_eq!(
+            blocks.len(),
+            1,
+            "children are run by the loop, not the document"
+        );
+
+        let context = document
+            .create_execution_context(foreach_id, None, None, None)
+            .await
+            .unwrap();
+        let handle = blocks[0].clone().execute(context).await.unwrap().unwrap();
+        assert_eq!(handle.wait_for_completion().await, ExecutionResult::Success);
+
+        let output = document
+            .get_block_execution_output_dyn(foreach_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let output = output.downcast_ref::<ForeachOutput>().unwrap();
+        assert_eq!(output.iterations.len(), 3);
+
+        let stdout = output
+            .iterations
+            .iter()
+            .map(|iteration| {
+                let stdout = iteration.outputs["greet"]
+                    .get_template_value("stdout")
+                    .unwrap();
+                stdout.as_str().unwrap().trim().to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(stdout, vec!["1/3 web-1", "2/3 web-2", "3/3 web-3"]);
+    }
+}
@@ -225,18 +225,9 @@ impl BlockBehavior for Http {
         let response = response.unwrap();
         let was_success = response.status_success;
 
-        let body_json = serde_json::from_str(&response.body).ok();
-        let output = HttpExecutionOutput {
-            status: response.status,
-            status_text: response.status_text.clone(),
-            status_success: response.status_success,
-            headers: response.headers.clone(),
-            duration_seconds: response.duration,
-            body: response.body.clone(),
-            body_json,
-        };
-
-        let _ = context.set_block_output(output).await;
+        let _ = context
+            .set_block_output(HttpExecutionOutput::from(response.clone()))
+            .await;
 
         let _ = context
             .send_output(
@@ -254,6 +245,14 @@ impl BlockBehavior for Http {
 }
 
 impl Http {
+    /// Send the request without reporting anything, for blocks that poll an endpoint
+    pub(crate) async fn check(
+        &self,
+        context: &ExecutionContext,
+    ) -> Result<HttpExecutionOutput, HttpError> {
+        Ok(self.clone().make_http_request(context).await?.into())
+    }
+
     async fn make_http_request(
         self,
         context: &ExecutionContext,
@@ -313,6 +312,20 @@ struct HttpResponse {
     pub body: String,
 }
 
+impl From<HttpResponse> for HttpExecutionOutput {
+    fn from(response: HttpResponse) -> Self {
+        Self {
+            body_json: serde_json::from_str(&response.body).ok(),
+            status: response.status,
+            status_text: response.status_text,
+            status_success: response.status_success,
+            headers: response.headers,
+            duration_seconds: response.duration,
+            body: response.body,
+        }
+    }
+}
+
 impl From<HttpVerb> for Method {
     fn from(verb: HttpVerb) -> Self {
         match verb {
@@ -16,6 +16,15 @@ pub struct LocalVar {
 
     #[builder(setter(into))]
     pub name: String,
+
+    /// Obscured values are typed into a password field, and masked in output
+    #[serde(default = "default_obscured")]
+    #[builder(default = true)]
+    pub obscured: bool,
+}
+
+fn default_obscured() -> bool {
+    true
 }
 
 impl FromDocument for LocalVar {
@@ -37,7 +46,17 @@ impl FromDocument for LocalVar {
             .ok_or("Missing name")?
             .to_string();
 
-        Ok(LocalVar::builder().id(id).name(name).build())
+        // Blocks created before the toggle existed are obscured
+        let obscured = props
+            .get("obscured")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        Ok(LocalVar::builder()
+            .id(id)
+            .name(name)
+            .obscured(obscured)
+            .build())
     }
 }
 
@@ -83,7 +102,11 @@ impl BlockBehavior for LocalVar {
         // Resolve value
         let mut context = BlockContext::new();
         let resolved_value = resolver.resolve_template(&local_value)?;
-        context.insert(DocumentVar::new(resolved_name, resolved_value, local_value));
+        context.insert(if self.obscured {
+            DocumentVar::new_sensitive(resolved_name, resolved_value, local_value)
+        } else {
+            DocumentVar::new(resolved_name, resolved_value, local_value)
+        });
         Ok(Some(context))
     }
 }
@@ -158,4 +181,33 @@ mod tests {
             assert_eq!(context.variables.get(name), Some(&"test_value".to_string()));
         }
     }
+
+    #[tokio::test]
+    async fn test_obscured_local_var_is_sensitive() {
+        let block_data = serde_json::json!({
+            "id": Uuid::new_v4().to_string(),
+            "props": { "name": "password" },
+        });
+        let local_var = LocalVar::from_document(&block_data).unwrap();
+        assert!(local_var.obscured);
+
+        let context = local_var
+            .passive_context(&ContextResolver::new(), Some(&local_value_provider()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(context.get::<DocumentVar>().unwrap().sensitive);
+
+        let visible = LocalVar::builder()
+            .id(Uuid::new_v4())
+            .name("user")
+            .obscured(false)
+            .build();
+        let context = visible
+            .passive_context(&ContextResolver::new(), Some(&local_value_provider()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!context.get::<DocumentVar>().unwrap().sensitive);
+    }
 }
@@ -7,11 +7,13 @@
 //! Each block type implements the [`BlockBehavior`] trait which defines how blocks
 //! provide context and execute their operations.
 
+pub(crate) mod assert;
 pub(crate) mod clickhouse;
 pub(crate) mod directory;
 pub(crate) mod dropdown;
 pub(crate) mod editor;
 pub(crate) mod environment;
+pub(crate) mod foreach;
 pub(crate) mod host;
 pub(crate) mod http;
 pub(crate) mod kubernetes;
@@ -24,24 +26,30 @@ pub(crate) mod postgres;
 pub(crate) mod prometheus;
 pub(crate) mod query_block;
 pub(crate) mod script;
+pub(crate) mod sftp;
 pub(crate) mod sql_block;
 pub(crate) mod sqlite;
 pub(crate) mod ssh_connect;
+pub(crate) mod ssh_tunnel;
 
 pub(crate) mod sub_runbook;
 pub(crate) mod terminal;
 pub(crate) mod var;
 pub(crate) mod var_display;
+pub(crate) mod wait_until;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub use dropdown::DropdownOption;
+pub use foreach::LoopMode;
 pub use query_block::{BlockExecutionError, QueryBlockBehavior, QueryBlockError};
 pub use sql_block::{
     SqlBlockBehavior, SqlBlockError, SqlBlockExecutionResult, SqlBlockOutput, SqlQueryResult,
     SqlStatementResult,
 };
+pub use wait_until::{WaitBackoff, WaitCheck};
 
 use crate::{
     client::LocalValueProvider,
@@ -69,6 +77,12 @@ pub const KNOWN_UNSUPPORTED_BLOCKS: &[&str] = &[
     "video",
 ];
 
+/// Block types whose children belong to them rather than to the document
+///
+/// Their children are left out of the document's linear order; the block runs them itself,
+/// each time in a scope of its own.
+pub const SCOPED_CONTAINER_BLOCKS: &[&str] = &["foreach"];
+
 /// Trait for parsing block data from a document JSON representation
 pub trait FromDocument: Sized {
     /// Parse block data from a JSON value
@@ -154,6 +168,8 @@ pub enum Block {
     Directory(directory::Directory),
     LocalDirectory(local_directory::LocalDirectory),
     SshConnect(ssh_connect::SshConnect),
+    SshTunnel(ssh_tunnel::SshTunnel),
+    Sftp(sftp::Sftp),
     Host(host::Host),
     VarDisplay(var_display::VarDisplay),
     MarkdownRender(markdown_render::MarkdownRender),
@@ -161,6 +177,9 @@ pub enum Block {
     Dropdown(dropdown::Dropdown),
     Pause(pause::Pause),
     SubRunbook(sub_runbook::SubRunbook),
+    Assert(assert::Assert),
+    WaitUntil(wait_until::WaitUntil),
+    Foreach(foreach::Foreach),
 }
 
 impl Block {
@@ -183,6 +202,8 @@ impl Block {
             Block::Directory(directory) => directory.id,
             Block::LocalDirectory(local_directory) => local_directory.id,
             Block::SshConnect(ssh_connect) => ssh_connect.id,
+            Block::SshTunnel(ssh_tunnel) => ssh_tunnel.id,
+            Block::Sftp(sftp) => sftp.id,
             Block::Host(host) => host.id,
             Block::VarDisplay(var_display) => var_display.id,
             Block::MarkdownRender(markdown_render) => markdown_render.id,
@@ -190,6 +211,9 @@ impl Block {
             Block::Dropdown(dropdown) => dropdown.id,
             Block::Pause(pause) => pause.id,
             Block::SubRunbook(sub_runbook) => sub_runbook.id,
+            Block::Assert(assert) => assert.id,
+            Block::WaitUntil(wait_until) => wait_until.id,
+            Block::Foreach(foreach) => foreach.id,
         }
     }
 
@@ -226,6 +250,8 @@ impl Block {
             Block::Mysql(mysql) => mysql.name.clone(),
             Block::Kubernetes(kubernetes) => kubernetes.name.clone(),
             Block::Dropdown(dropdown) => dropdown.name.clone(),
+            Block::SshTunnel(ssh_tunnel) => ssh_tunnel.name.clone(),
+            Block::Sftp(sftp) => sftp.name.clone(),
 
             Block::Editor(_) => "".to_string(),
             Block::LocalVar(_) => "".to_string(),
@@ -239,6 +265,9 @@ impl Block {
             Block::MarkdownRender(_) => "".to_string(),
             Block::Pause(_) => "".to_string(),
             Block::SubRunbook(sub_runbook) => sub_runbook.name.clone(),
+            Block::Assert(assert) => assert.name.clone(),
+            Block::WaitUntil(wait_until) => wait_until.name.clone(),
+            Block::Foreach(foreach) => foreach.name.clone(),
         }
     }
 
@@ -291,6 +320,10 @@ impl Block {
             "ssh-connect" => Ok(Block::SshConnect(ssh_connect::SshConnect::from_document(
                 block_data,
             )?)),
+            "ssh-tunnel" => Ok(Block::SshTunnel(ssh_tunnel::SshTunnel::from_document(
+                block_data,
+            )?)),
+            "sftp" => Ok(Block::Sftp(sftp::Sftp::from_document(block_data)?)),
             "host-select" => Ok(Block::Host(host::Host::from_document(block_data)?)),
             "var_display" => Ok(Block::VarDisplay(var_display::VarDisplay::from_document(
                 block_data,
@@ -306,6 +339,11 @@ impl Block {
             "sub-runbook" => Ok(Block::SubRunbook(sub_runbook::SubRunbook::from_document(
                 block_data,
             )?)),
+            "assert" => Ok(Block::Assert(assert::Assert::from_document(block_data)?)),
+            "wait-until" => Ok(Block::WaitUntil(wait_until::WaitUntil::from_document(
+                block_data,
+            )?)),
+            "foreach" => Ok(Block::Foreach(foreach::Foreach::from_document(block_data)?)),
             _ => Err(format!("Unknown block type: {}", block_type)),
         }
     }
@@ -353,6 +391,15 @@ impl Block {
                     .passive_context(resolver, block_local_value_provider)
                     .await
             }
+            Block::SshTunnel(ssh_tunnel) => {
+                ssh_tunnel
+                    .passive_context(resolver, block_local_value_provider)
+                    .await
+            }
+            Block::Sftp(sftp) => {
+                sftp.passive_context(resolver, block_local_value_provider)
+                    .await
+            }
             Block::Host(host) => {
                 host.passive_context(resolver, block_local_value_provider)
                     .await
@@ -431,6 +478,21 @@ impl Block {
                     .passive_context(resolver, block_local_value_provider)
                     .await
             }
+            Block::Assert(assert) => {
+                assert
+                    .passive_context(resolver, block_local_value_provider)
+                    .await
+            }
+            Block::WaitUntil(wait_until) => {
+                wait_until
+                    .passive_context(resolver, block_local_value_provider)
+                    .await
+            }
+            Block::Foreach(foreach) => {
+                foreach
+                    .passive_context(resolver, block_local_value_provider)
+                    .await
+            }
         }
     }
 
@@ -452,6 +514,8 @@ impl Block {
             Block::Directory(directory) => directory.create_state(),
             Block::LocalDirectory(local_directory) => local_directory.create_state(),
             Block::SshConnect(ssh_connect) => ssh_connect.create_state(),
+            Block::SshTunnel(ssh_tunnel) => ssh_tunnel.create_state(),
+            Block::Sftp(sftp) => sftp.create_state(),
             Block::Host(host) => host.create_state(),
             Block::VarDisplay(var_display) => var_display.create_state(),
             Block::MarkdownRender(markdown_render) => markdown_render.create_state(),
@@ -459,6 +523,9 @@ impl Block {
             Block::Dropdown(dropdown) => dropdown.create_state(),
             Block::Pause(pause) => pause.create_state(),
             Block::SubRunbook(sub_runbook) => sub_runbook.create_state(),
+            Block::Assert(assert) => assert.create_state(),
+            Block::WaitUntil(wait_until) => wait_until.create_state(),
+            Block::Foreach(foreach) => foreach.create_state(),
         }
     }
 
@@ -489,6 +556,8 @@ impl Block {
             Block::Directory(directory) => directory.execute(context).await,
             Block::LocalDirectory(local_directory) => local_directory.execute(context).await,
             Block::SshConnect(ssh_connect) => ssh_connect.execute(context).await,
+            Block::SshTunnel(ssh_tunnel) => ssh_tunnel.execute(context).await,
+            Block::Sftp(sftp) => sftp.execute(context).await,
             Block::Host(host) => host.execute(context).await,
             Block::VarDisplay(var_display) => var_display.execute(context).await,
             Block::MarkdownRender(markdown_render) => markdown_render.execute(context).await,
@@ -496,6 +565,9 @@ impl Block {
             Block::Dropdown(dropdown) => dropdown.execute(context).await,
             Block::Pause(pause) => pause.execute(context).await,
             Block::SubRunbook(sub_runbook) => sub_runbook.execute(context).await,
+            Block::Assert(assert) => assert.execute(context).await,
+            Block::WaitUntil(wait_until) => wait_until.execute(context).await,
+            Block::Foreach(foreach) => foreach.execute(context).await,
         }
     }
 }
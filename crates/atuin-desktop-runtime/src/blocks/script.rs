@@ -17,10 +17,16 @@ use crate::events::GCEvent;
 use crate::execution::{
     CancellationToken, ExecutionContext, ExecutionHandle, ExecutionStatus, StreamingBlockOutput,
 };
-use crate::ssh::{build_env_exports, OutputLine as SessionOutputLine, SshWarning};
+use crate::ssh::{
+    build_env_exports, HostKeyError, OutputLine as SessionOutputLine, SshPrompt, SshWarning,
+};
 
 use super::FromDocument;
 
+mod fan_out;
+
+pub use fan_out::{FanOut, FanOutUpdate, HostSource, HostStatus, ScriptFanOutOutput};
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct Script {
@@ -41,6 +47,11 @@ pub struct Script {
 
     #[builder(default = true)]
     pub output_visible: bool,
+
+    /// Run on each of several SSH hosts instead of the current host
+    #[builder(default)]
+    #[serde(default)]
+    pub fan_out: Option<FanOut>,
 }
 
 impl FromDocument for Script {
@@ -93,6 +104,7 @@ impl FromDocument for Script {
                     .and_then(|v| v.as_bool())
                     .unwrap_or(true),
             )
+            .fan_out(FanOut::from_props(props))
             .build();
 
         Ok(script)
@@ -225,6 +237,11 @@ impl BlockBehavior for Script {
 
         let context_clone = context.clone();
         tokio::spawn(async move {
+            if let Some(fan_out) = self.fan_out.clone() {
+                fan_out::execute(&self, &fan_out, var_name, context).await;
+                return;
+            }
+
             let (exit_code, captured_lines, vars) = self
                 .run_script(context.clone(), context.cancellation_token())
                 .await;
@@ -249,7 +266,12 @@ impl BlockBehavior for Script {
                         let _ = context
                             .update_active_context(self.id, move |ctx| {
                                 for (key, value) in vars.into_iter() {
-                                    ctx.add_var(key, value, "(script variable output)".to_string());
+                                    let (key, value) = fs_var::typed_var(key, value);
+                                    ctx.add_typed_var(
+                                        key,
+                                        value,
+                                        "(script variable output)".to_string(),
+                                    );
                                 }
                             })
                             .await;
@@ -322,7 +344,7 @@ impl BlockBehavior for Script {
 }
 
 impl Script {
-    fn parse_ssh_host(ssh_host: &str) -> (Option<String>, String) {
+    pub(crate) fn parse_ssh_host(ssh_host: &str) -> (Option<String>, String) {
         if let Some(at_pos) = ssh_host.find('@') {
             let username = ssh_host[..at_pos].to_string();
             let host_part = ssh_host[at_pos + 1..].to_string();
@@ -332,6 +354,11 @@ impl Script {
         }
     }
 
+    /// Exit code of a remote command; without an exit status (e.g. killed by a signal) it failed
+    pub(crate) fn remote_exit_code(exit_status: Option<u32>) -> i32 {
+        exit_status.map(|status| status as i32).unwrap_or(-1)
+    }
+
     /// Determine the correct flag for passing code to the interpreter
     fn get_interpreter_flag(interpreter: &str) -> Option<&'static str> {
         let interpreter = Self::get_program_name(interpreter);
@@ -680,14 +707,23 @@ impl Script {
         };
 
         let uses_output_vars = code.contains("ATUIN_OUTPUT_VARS");
+        let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
 
         let remote_temp_path: Option<String> = if uses_output_vars {
             match ssh_pool
-                .create_temp_file(&hostname, username.as_deref(), "atuin-desktop-vars")
+                .create_temp_file(
+                    &hostname,
+                    username.as_deref(),
+                    "atuin-desktop-vars",
+                    Some(ssh_prompt.clone()),
+                )
                 .await
             {
                 Ok(path) => Some(path),
                 Err(e) => {
+                    if let Some(event) = HostKeyError::mismatch_event(&e) {
+                        let _ = context.emit_gc_event(event).await;
+                    }
                     let error_msg = format!("Failed to create remote temp file: {}", e);
                     let _ = context.block_failed(error_msg.clone()).await;
                     return (Err(error_msg.into()), Vec::new(), None);
@@ -712,7 +748,7 @@ impl Script {
 
         let channel_id = self.id.to_string();
         let (output_sender, mut output_receiver) = mpsc::channel::<SessionOutputLine>(100);
-        let (result_tx, result_rx) = oneshot::channel::<()>();
+        let (result_tx, result_rx) = oneshot::channel::<Option<u32>>();
         let (warnings_tx, warnings_rx) = oneshot::channel::<Vec<SshWarning>>();
 
         let captured_output = Arc::new(RwLock::new(Vec::new()));
@@ -743,6 +779,7 @@ impl Script {
                 result_tx,
                 ssh_config,
                 Some(warnings_tx),
+                Some(ssh_prompt),
             ) => {
                 result
             }
@@ -758,6 +795,9 @@ impl Script {
         };
 
         if let Err(e) = exec_result {
+            if let Some(event) = HostKeyError::mismatch_event(&e) {
+                let _ = context.emit_gc_event(event).await;
+            }
             let error_msg = format!("Failed to start SSH execution: {}", e);
             let _ = context.block_failed(error_msg.to_string()).await;
             if let Some(ref path) = remote_temp_path {
@@ -862,8 +902,8 @@ impl Script {
                 }
                 return (Err("SSH script execution cancelled".into()), captured, None);
             }
-            _ = result_rx => {
-                0
+            status = result_rx => {
+                Self::remote_exit_code(status.ok().flatten())
             }
         };
 
@@ -0,0 +1,741 @@
+// Running a script block on many SSH hosts at once.
+//
+// Each host gets its own channel on the SSH pool; a semaphore bounds how many run at the same
+// time, and a watch channel stops the rest on cancellation or (with fail-fast) the first failure.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot, watch, Semaphore};
+use tokio::task::JoinSet;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use super::{OutputLine, Script, ScriptExecutionOutput};
+use crate::context::{BlockExecutionOutput, BlockVars, ContextResolver, DocumentSshConfig};
+use crate::execution::{ExecutionContext, StreamingBlockOutput};
+use crate::ssh::{
+    build_env_exports, HostKeyError, OutputLine as SessionOutputLine, SshPoolHandle, SshPrompt,
+};
+
+const DEFAULT_CONCURRENCY: usize = 10;
+
+/// Where a fan-out script gets its list of hosts from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum HostSource {
+    /// A template variable holding hosts separated by whitespace or commas, or a JSON array
+    Variable(String),
+    /// A file with one host per line; `#` comments and `[group]` headers are ignored
+    Inventory(String),
+    /// Concrete `Host` entries in ~/.ssh/config matching a glob, such as `web-*`
+    SshConfig(String),
+}
+
+/// Settings for running a script on several hosts
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOut {
+    pub hosts: HostSource,
+    /// Maximum number of hosts running at the same time
+    pub concurrency: usize,
+    /// Stop all hosts after the first failure, rather than letting the others finish
+    pub fail_fast: bool,
+}
+
+impl FanOut {
+    /// Read fan-out settings from Script block props; `None` runs on the current host as usual
+    pub(super) fn from_props(props: &serde_json::Map<String, serde_json::Value>) -> Option<Self> {
+        let hosts = props
+            .get("hosts")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|hosts| !hosts.is_empty())?
+            .to_string();
+
+        let hosts = match props.get("hostsSource").and_then(|v| v.as_str())? {
+            "variable" => HostSource::Variable(hosts),
+            "inventory" => HostSource::Inventory(hosts),
+            "sshConfig" => HostSource::SshConfig(hosts),
+            _ => return None,
+        };
+
+        let concurrency = props
+            .get("concurrency")
+            .and_then(|v| match v {
+                serde_json::Value::Number(n) => n.as_u64().map(|n| n as usize),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            })
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_CONCURRENCY);
+
+        let fail_fast = props
+            .get("failFast")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        Some(Self {
+            hosts,
+            concurrency,
+            fail_fast,
+        })
+    }
+}
+
+/// State of one host in a fan-out run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub enum HostStatus {
+    Running,
+    Success,
+    Failed,
+    Cancelled,
+    /// Never started, as the run was stopped first
+    Skipped,
+}
+
+/// Progress of one host, sent as the `object` of the block's streamed output
+///
+/// Output lines are also sent as plain stdout/stderr, prefixed with `[host]`.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct FanOutUpdate {
+    pub host: String,
+    pub status: HostStatus,
+    /// Set once the host has finished
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    /// A line of output, if this update carries one
+    pub line: Option<OutputLine>,
+}
+
+/// Output of a fan-out run: the output of each host, by host
+///
+/// In templates, `output["web-1"].stdout` reads like the output of a single-host script.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ScriptFanOutOutput {
+    pub hosts: BTreeMap<String, ScriptExecutionOutput>,
+}
+
+impl BlockExecutionOutput for ScriptFanOutOutput {
+    fn get_template_value(&self, key: &str) -> Option<minijinja::Value> {
+        let output = self.hosts.get(key)?;
+        let values = ["exit_code", "stdout", "stderr", "combined"]
+            .into_iter()
+            .filter_map(|key| Some((key, output.get_template_value(key)?)))
+            .collect::<BTreeMap<_, _>>();
+        Some(minijinja::Value::from_serialize(values))
+    }
+
+    fn enumerate_template_keys(&self) -> minijinja::value::Enumerator {
+        minijinja::value::Enumerator::Values(
+            self.hosts.keys().map(minijinja::Value::from).collect(),
+        )
+    }
+}
+
+/// Outcome of running the script on one host
+struct HostRun {
+    status: HostStatus,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    output: Vec<OutputLine>,
+}
+
+impl HostRun {
+    fn stopped(status: HostStatus, output: Vec<OutputLine>) -> Self {
+        Self {
+            status,
+            exit_code: None,
+            error: None,
+            output,
+        }
+    }
+
+    fn failed(error: String, output: Vec<OutputLine>) -> Self {
+        Self {
+            status: HostStatus::Failed,
+            exit_code: None,
+            error: Some(error),
+            output,
+        }
+    }
+}
+
+/// Everything a host task needs, shared between hosts
+struct HostTask {
+    block_id: Uuid,
+    interpreter: String,
+    code: String,
+    ssh_pool: SshPoolHandle,
+    ssh_config: Option<DocumentSshConfig>,
+    context: ExecutionContext,
+}
+
+/// Run `script` on every host from `fan_out`, handling the block's whole lifecycle
+pub(super) async fn execute(
+    script: &Script,
+    fan_out: &FanOut,
+    var_name: Option<String>,
+    context: ExecutionContext,
+) {
+    let _ = context.block_started().await;
+
+    let Some(ssh_pool) = context.ssh_pool() else {
+        let _ = context
+            .block_failed("SSH pool not available in execution context".to_string())
+            .await;
+        return;
+    };
+
+    let hosts = match resolve_hosts(&fan_out.hosts, &context.context_resolver) {
+        Ok(hosts) => hosts,
+        Err(e) => {
+            let _ = context.block_failed(e).await;
+            return;
+        }
+    };
+
+    let code = context
+        .context_resolver
+        .resolve_template(&script.code)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Templating error in script {id}: {e}", id = script.id);
+            script.code.clone()
+        });
+    let code = format!(
+        "{}{code}",
+        build_env_exports(context.context_resolver.env_vars())
+    );
+
+    // Identity settings from an SSH block still apply, but not its host
+    let ssh_config = context
+        .context_resolver
+        .ssh_config()
+        .cloned()
+        .map(|config| DocumentSshConfig {
+            user_host: String::new(),
+            user: None,
+            hostname: None,
+            port: None,
+            ..config
+        });
+
+    let task = Arc::new(HostTask {
+        block_id: script.id,
+        interpreter: script.interpreter.clone(),
+        code,
+        ssh_pool,
+        ssh_config,
+        context: context.clone(),
+    });
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let stop_tx = Arc::new(stop_tx);
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let cancel_watcher = context.cancellation_receiver().map(|cancel_rx| {
+        let stop_tx = stop_tx.clone();
+        let cancelled = cancelled.clone();
+        tokio::spawn(async move {
+            if cancel_rx.await.is_ok() {
+                cancelled.store(true, Ordering::SeqCst);
+                stop_tx.send_replace(true);
+            }
+        })
+    });
+
+    let semaphore = Arc::new(Semaphore::new(fan_out.concurrency.max(1)));
+    let mut running = JoinSet::new();
+    for host in hosts.iter().cloned() {
+        let task = task.clone();
+        let semaphore = semaphore.clone();
+        let stop_rx = stop_rx.clone();
+        running.spawn(async move {
+            let run = run_host(&task, &host, semaphore, stop_rx).await;
+            (host, run)
+        });
+    }
+
+    let mut runs = BTreeMap::new();
+    while let Some(joined) = running.join_next().await {
+        let Ok((host, run)) = joined else {
+            continue;
+        };
+
+        if run.status == HostStatus::Failed && fan_out.fail_fast {
+            stop_tx.send_replace(true);
+        }
+
+        send_update(
+            &context,
+            script.id,
+            FanOutUpdate {
+                host: host.clone(),
+                status: run.status,
+                exit_code: run.exit_code,
+                error: run.error.clone(),
+                line: None,
+            },
+        )
+        .await;
+        runs.insert(host, run);
+    }
+
+    if let Some(cancel_watcher) = cancel_watcher {
+        cancel_watcher.abort();
+    }
+
+    let failed = runs
+        .iter()
+        .filter(|(_, run)| run.status == HostStatus::Failed)
+        .map(|(host, _)| host.as_str())
+        .collect::<Vec<_>>();
+    let failure = (!failed.is_empty()).then(|| {
+        format!(
+            "Failed on {} of {} hosts: {}",
+            failed.len(),
+            hosts.len(),
+            failed.join(", ")
+        )
+    });
+
+    let output = ScriptFanOutOutput {
+        hosts: runs
+            .into_iter()
+            .map(|(host, run)| {
+                let output = ScriptExecutionOutput {
+                    exit_code: run.exit_code,
+                    output: run.output,
+                };
+                (host, output)
+            })
+            .collect(),
+    };
+
+    if cancelled.load(Ordering::SeqCst) {
+        let _ = context.set_block_output(output).await;
+        let _ = context.block_cancelled().await;
+        return;
+    }
+
+    if let Some(failure) = failure {
+        let _ = context.set_block_output(output).await;
+        let _ = context.block_failed(failure).await;
+        return;
+    }
+
+    // As for a single host, the context must be updated before the block finishes
+    if let Some(var_name) = var_name {
+        let stdout_by_host = output
+            .hosts
+            .iter()
+            .map(|(host, output)| (host.clone(), output.stdout().unwrap_or_default()))
+            .collect::<BTreeMap<_, _>>();
+        let value = serde_json::to_value(&stdout_by_host).unwrap_or_default();
+
+        let _ = context
+            .update_active_context(script.id, move |ctx| {
+                ctx.add_typed_var(var_name, value, "(script output)".to_string());
+            })
+            .await;
+    }
+
+    let _ = context.set_block_output(output).await;
+    let _ = context.block_finished(Some(0), true).await;
+}
+
+/// Run the script on `host` once a slot is free, unless the run is stopped first
+async fn run_host(
+    task: &HostTask,
+    host: &str,
+    semaphore: Arc<Semaphore>,
+    mut stop_rx: watch::Receiver<bool>,
+) -> HostRun {
+    let _permit = tokio::select! {
+        permit = semaphore.acquire_owned() => match permit {
+            Ok(permit) => permit,
+            Err(_) => return HostRun::stopped(HostStatus::Skipped, Vec::new()),
+        },
+        Ok(_) = stop_rx.wait_for(|stop| *stop) => {
+            return HostRun::stopped(HostStatus::Skipped, Vec::new());
+        }
+    };
+
+    // Another host may have failed while this one waited
+    if *stop_rx.borrow() {
+        return HostRun::stopped(HostStatus::Skipped, Vec::new());
+    }
+
+    send_update(
+        &task.context,
+        task.block_id,
+        FanOutUpdate {
+            host: host.to_string(),
+            status: HostStatus::Running,
+            exit_code: None,
+            error: None,
+            line: None,
+        },
+    )
+    .await;
+
+    let (username, hostname) = Script::parse_ssh_host(host);
+    let channel_id = format!("{}:{host}", task.block_id);
+    let (output_sender, mut output_receiver) = mpsc::channel::<SessionOutputLine>(100);
+    let (result_tx, mut result_rx) = oneshot::channel::<Option<u32>>();
+    let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(task.context.clone());
+
+    let started = tokio::select! {
+        result = task.ssh_pool.exec_with_config(
+            &hostname,
+            username.as_deref(),
+            &task.interpreter,
+            &task.code,
+            &channel_id,
+            output_sender,
+            result_tx,
+            task.ssh_config.clone(),
+            None,
+            Some(ssh_prompt),
+        ) => result,
+        Ok(_) = stop_rx.wait_for(|stop| *stop) => {
+            let _ = task.ssh_pool.exec_cancel(&channel_id).await;
+            return HostRun::stopped(HostStatus::Cancelled, Vec::new());
+        }
+    };
+
+    if let Err(e) = started {
+        if let Some(event) = HostKeyError::mismatch_event(&e) {
+            let _ = task.context.emit_gc_event(event).await;
+        }
+        return HostRun::failed(format!("Failed to start SSH execution: {e}"), Vec::new());
+    }
+
+    let mut output = Vec::new();
+    let exit_status = loop {
+        tokio::select! {
+            line = output_receiver.recv() => {
+                let Some(line) = line else {
+                    // The session drops its sender once the command has finished
+                    break (&mut result_rx).await.ok().flatten();
+                };
+
+                let mut text = line.inner().to_string();
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                let line = if line.is_stdout() {
+                    OutputLine::stdout(text)
+                } else {
+                    OutputLine::stderr(text)
+                };
+
+                send_line(task, host, &line).await;
+                output.push(line);
+            }
+            Ok(_) = stop_rx.wait_for(|stop| *stop) => {
+                let _ = task.ssh_pool.exec_cancel(&channel_id).await;
+                return HostRun::stopped(HostStatus::Cancelled, output);
+            }
+        }
+    };
+
+    let exit_code = Script::remote_exit_code(exit_status);
+    if exit_code == 0 {
+        HostRun {
+            status: HostStatus::Success,
+            exit_code: Some(exit_code),
+            error: None,
+            output,
+        }
+    } else {
+        HostRun {
+            status: HostStatus::Failed,
+            exit_code: Some(exit_code),
+            error: Some(format!("Script exited with code {exit_code}")),
+            output,
+        }
+    }
+}
+
+async fn send_update(context: &ExecutionContext, block_id: Uuid, update: FanOutUpdate) {
+    let Ok(object) = serde_json::to_value(&update) else {
+        return;
+    };
+
+    let _ = context
+        .send_output(
+            StreamingBlockOutput::builder()
+                .block_id(block_id)
+                .object(object)
+                .build(),
+        )
+        .await;
+}
+
+/// Stream a line from `host`, both prefixed on stdout/stderr and as a per-host update
+async fn send_line(task: &HostTask, host: &str, line: &OutputLine) {
+    let prefixed = format!("[{host}] {}", line.text);
+    let update = FanOutUpdate {
+        host: host.to_string(),
+        status: HostStatus::Running,
+        exit_code: None,
+        error: None,
+        line: Some(line.clone()),
+    };
+
+    let builder = StreamingBlockOutput::builder().block_id(task.block_id);
+    let output = match serde_json::to_value(&update) {
+        Ok(object) if line.is_stdout => builder.stdout(prefixed).object(object).build(),
+        Ok(object) => builder.stderr(prefixed).object(object).build(),
+        Err(_) if line.is_stdout => builder.stdout(prefixed).build(),
+        Err(_) => builder.stderr(prefixed).build(),
+    };
+
+    let _ = task.context.send_output(output).await;
+}
+
+/// Expand a host source into a list of hosts, without duplicates
+pub(crate) fn resolve_hosts(
+    source: &HostSource,
+    resolver: &ContextResolver,
+) -> Result<Vec<String>, String> {
+    let hosts = match source {
+        HostSource::Variable(name) => {
+            let name = name.trim();
+            let value = resolver
+                .get_var(name)
+                .ok_or_else(|| format!("Variable '{name}' is not set"))?;
+            parse_host_list(&value)?
+        }
+        HostSource::Inventory(path) => {
+            let path = resolver
+                .resolve_template(path)
+                .map_err(|e| format!("Failed to render inventory path: {e}"))?;
+            let path = PathBuf::from(shellexpand::tilde(path.trim()).as_ref());
+            let path = if path.is_relative() {
+                PathBuf::from(resolver.cwd()).join(path)
+            } else {
+                path
+            };
+
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read inventory {}: {e}", path.display()))?;
+            parse_inventory(&content)
+        }
+        HostSource::SshConfig(pattern) => {
+            let path = dirs::home_dir()
+                .map(|home| home.join(".ssh").join("config"))
+                .ok_or("Could not find the home directory")?;
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            ssh_config_hosts(&content, pattern.trim())?
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let hosts = hosts
+        .into_iter()
+        .filter(|host| seen.insert(host.clone()))
+        .collect::<Vec<_>>();
+
+    if hosts.is_empty() {
+        return Err("No hosts to run on".to_string());
+    }
+
+    Ok(hosts)
+}
+
+/// Parse hosts from a variable: a JSON array, or names separated by whitespace or commas
+fn parse_host_list(value: &str) -> Result<Vec<String>, String> {
+    let value = value.trim();
+    if value.starts_with('[') {
+        let hosts: Vec<String> = serde_json::from_str(value)
+            .map_err(|e| format!("Host list is not a JSON array of strings: {e}"))?;
+        return Ok(hosts
+            .into_iter()
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .collect());
+    }
+
+    Ok(value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parse an inventory file: the first word of each line is a host
+///
+/// Blank lines, `#` comments and `[group]` headers are skipped, so simple Ansible INI
+/// inventories work as-is.
+fn parse_inventory(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('['))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Find the concrete hosts named on `Host` lines of an ssh config that match `pattern`
+///
+/// Entries that are themselves patterns (`*`, `?` or negated) are not hosts and are skipped.
+fn ssh_config_hosts(content: &str, pattern: &str) -> Result<Vec<String>, String> {
+    let pattern = glob::Pattern::new(pattern)
+        .map_err(|e| format!("Invalid host pattern '{pattern}': {e}"))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (keyword, rest) = line.split_once(|c: char| c.is_whitespace() || c == '=')?;
+            keyword.eq_ignore_ascii_case("host").then_some(rest)
+        })
+        .flat_map(|hosts| hosts.split_whitespace())
+        .map(|host| host.trim_matches('"'))
+        .filter(|host| !host.contains(['*', '?', '!']))
+        .filter(|host| pattern.matches(host))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_props() {
+        let props = serde_json::json!({
+            "hostsSource": "inventory",
+            "hosts": " ~/hosts.txt ",
+            "concurrency": "4",
+            "failFast": false,
+        });
+        let fan_out = FanOut::from_props(props.as_object().unwrap()).unwrap();
+        assert_eq!(
+            fan_out,
+            FanOut {
+                hosts: HostSource::Inventory("~/hosts.txt".to_string()),
+                concurrency: 4,
+                fail_fast: false,
+            }
+        );
+
+        let props = serde_json::json!({ "hostsSource": "sshConfig", "hosts": "web-*" });
+        let fan_out = FanOut::from_props(props.as_object().unwrap()).unwrap();
+        assert_eq!(fan_out.hosts, HostSource::SshConfig("web-*".to_string()));
+        assert_eq!(fan_out.concurrency, DEFAULT_CONCURRENCY);
+        assert!(fan_out.fail_fast);
+
+        for props in [
+            serde_json::json!({}),
+            serde_json::json!({ "hostsSource": "", "hosts": "web-*" }),
+            serde_json::json!({ "hostsSource": "variable", "hosts": "" }),
+        ] {
+            assert_eq!(FanOut::from_props(props.as_object().unwrap()), None);
+        }
+    }
+
+    #[test]
+    fn test_resolve_hosts_from_variable() {
+        let resolver = ContextResolver::with_vars(HashMap::from([
+            (
+                "plain".to_string(),
+                "web1, web2\ndeploy@db1 web1".to_string(),
+            ),
+            (
+                "json".to_string(),
+                r#"["a.example.com", " b "]"#.to_string(),
+            ),
+            ("empty".to_string(), "  ".to_string()),
+        ]));
+
+        assert_eq!(
+            resolve_hosts(&HostSource::Variable("plain".to_string()), &resolver).unwrap(),
+            vec!["web1", "web2", "deploy@db1"]
+        );
+        assert_eq!(
+            resolve_hosts(&HostSource::Variable("json".to_string()), &resolver).unwrap(),
+            vec!["a.example.com", "b"]
+        );
+        assert!(resolve_hosts(&HostSource::Variable("empty".to_string()), &resolver).is_err());
+        assert!(resolve_hosts(&HostSource::Variable("missing".to_string()), &resolver).is_err());
+    }
+
+    #[test]
+    fn test_parse_inventory() {
+        let content = "\
+# production
+[web]
+web1.example.com ansible_user=deploy
+web2.example.com   # canary
+
+[db]
+admin@db1.example.com
+";
+        assert_eq!(
+            parse_inventory(content),
+            vec![
+                "web1.example.com",
+                "web2.example.com",
+                "admin@db1.example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ssh_config_hosts() {
+        let content = "\
+Host *
+    ServerAliveInterval 30
+
+Host web-1 web-2 web-*.staging
+    User deploy
+
+host=web-3
+    HostName 10.0.0.3
+
+Host db-1 !web-4
+";
+        assert_eq!(
+            ssh_config_hosts(content, "web-*").unwrap(),
+            vec!["web-1", "web-2", "web-3"]
+        );
+        assert_eq!(ssh_config_hosts(content, "db-?").unwrap(), vec!["db-1"]);
+        assert!(ssh_config_hosts(content, "[").is_err());
+    }
+
+    #[test]
+    fn test_fan_out_output_template_values() {
+        let output = ScriptFanOutOutput {
+            hosts: BTreeMap::from([(
+                "web-1".to_string(),
+                ScriptExecutionOutput {
+                    exit_code: Some(0),
+                    output: vec![OutputLine::stdout("ok\n".to_string())],
+                },
+            )]),
+        };
+
+        let web = output.get_template_value("web-1").unwrap();
+        assert_eq!(
+            web.get_attr("exit_code").unwrap(),
+            minijinja::Value::from(0)
+        );
+        assert_eq!(web.get_attr("stdout").unwrap().as_str(), Some("ok\n"));
+        assert!(output.get_template_value("web-2").is_none());
+    }
+}
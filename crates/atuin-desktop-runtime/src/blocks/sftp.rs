@@ -0,0 +1,419 @@
+//! SFTP block implementation
+//!
+//! Copies a file between the local machine and the current SSH host over the
+//! pooled connection, so transfers share the connection and auth settings used
+//! by Terminal and Script blocks. Uploads can send either a local file or a
+//! rendered template; downloads land in the current directory by default.
+//! File modes are kept unless the block sets one explicitly.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::blocks::{Block, BlockBehavior, FromDocument};
+use crate::execution::{ExecutionContext, ExecutionHandle, StreamingBlockOutput};
+use crate::ssh::{HostKeyError, SftpTransfer, SshPrompt, TransferProgress, UploadSource};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SftpDirection {
+    #[default]
+    Upload,
+    Download,
+}
+
+/// Where uploaded content comes from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SftpSource {
+    /// The file at `local_path`
+    #[default]
+    File,
+    /// The block's `content`, rendered as a template
+    Template,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct Sftp {
+    #[builder(setter(into))]
+    pub id: Uuid,
+
+    #[builder(default, setter(into))]
+    pub name: String,
+
+    #[builder(default)]
+    pub direction: SftpDirection,
+
+    #[builder(default)]
+    pub source: SftpSource,
+
+    /// Local file to upload, or where to save a download (template)
+    /// Relative paths are resolved against the current directory
+    #[builder(default, setter(into))]
+    pub local_path: String,
+
+    /// Path on the SSH host (template)
+    #[builder(setter(into))]
+    pub remote_path: String,
+
+    /// Content to upload when `source` is `Template`
+    #[builder(default, setter(into))]
+    pub content: String,
+
+    /// Mode for the copied file; the source file's mode is kept when unset
+    #[builder(default, setter(strip_option))]
+    pub mode: Option<u32>,
+}
+
+/// Parse an octal mode such as `644` or `0755`
+fn parse_mode(mode: &str) -> Result<Option<u32>, String> {
+    let mode = mode.trim();
+    if mode.is_empty() {
+        return Ok(None);
+    }
+
+    match u32::from_str_radix(mode.trim_start_matches("0o"), 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(Some(mode)),
+        _ => Err(format!(
+            "Invalid file mode: {mode} (expected octal, e.g. 0644)"
+        )),
+    }
+}
+
+impl FromDocument for Sftp {
+    fn from_document(block_data: &serde_json::Value) -> Result<Self, String> {
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or("Invalid or missing id")?;
+
+        let props = block_data
+            .get("props")
+            .and_then(|p| p.as_object())
+            .ok_or("Invalid or missing props")?;
+
+        let get_str = |key: &str| {
+            props
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+
+        let direction = match get_str("direction").as_str() {
+            "" | "upload" => SftpDirection::Upload,
+            "download" => SftpDirection::Download,
+            other => return Err(format!("Invalid SFTP direction: {other}")),
+        };
+
+        let source = match get_str("source").as_str() {
+            "" | "file" => SftpSource::File,
+            "template" => SftpSource::Template,
+            other => return Err(format!("Invalid SFTP source: {other}")),
+        };
+
+        let name = props
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("SFTP")
+            .to_string();
+
+        Ok(Sftp {
+            id,
+            name,
+            direction,
+            source,
+            local_path: get_str("localPath"),
+            remote_path: get_str("remotePath"),
+            content: get_str("content"),
+            mode: parse_mode(&get_str("mode"))?,
+        })
+    }
+}
+
+impl Sftp {
+    /// Resolve the local side of the transfer to an absolute path
+    fn local_path(local_path: &str, remote_path: &str, cwd: &str) -> Result<PathBuf, String> {
+        let path = if local_path.trim().is_empty() {
+            // Downloads default to the remote file's name in the current directory
+            Path::new(remote_path)
+                .file_name()
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("Cannot pick a local name for {remote_path}"))?
+        } else {
+            PathBuf::from(shellexpand::tilde(local_path.trim()).as_ref())
+        };
+
+        Ok(if path.is_absolute() {
+            path
+        } else {
+            Path::new(cwd).join(path)
+        })
+    }
+
+    fn transfer(&self, context: &ExecutionContext) -> Result<SftpTransfer, String> {
+        let resolver = &context.context_resolver;
+        let remote_path = resolver
+            .resolve_template(&self.remote_path)
+            .map_err(|e| e.to_string())?
+            .trim()
+            .to_string();
+        if remote_path.is_empty() {
+            return Err("Remote path is empty".to_string());
+        }
+
+        let local_path = resolver
+            .resolve_template(&self.local_path)
+            .map_err(|e| e.to_string())?;
+
+        Ok(match (self.direction, self.source) {
+            (SftpDirection::Upload, SftpSource::File) => {
+                if local_path.trim().is_empty() {
+                    return Err("Local path is empty".to_string());
+                }
+                SftpTransfer::Upload {
+                    source: UploadSource::File(Self::local_path(
+                        &local_path,
+                        &remote_path,
+                        resolver.cwd(),
+                    )?),
+                    remote_path,
+                    mode: self.mode,
+                }
+            }
+            (SftpDirection::Upload, SftpSource::Template) => {
+                let content = resolver
+                    .resolve_template(&self.content)
+                    .map_err(|e| e.to_string())?;
+                SftpTransfer::Upload {
+                    source: UploadSource::Contents(content.into_bytes()),
+                    remote_path,
+                    mode: self.mode,
+                }
+            }
+            (SftpDirection::Download, _) => SftpTransfer::Download {
+                local_path: Self::local_path(&local_path, &remote_path, resolver.cwd())?,
+                remote_path,
+                mode: self.mode,
+            },
+        })
+    }
+
+    async fn fail(
+        &self,
+        context: &ExecutionContext,
+        message: String,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = context.block_failed(message.clone()).await;
+        Err(message.into())
+    }
+}
+
+#[async_trait]
+impl BlockBehavior for Sftp {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn into_block(self) -> Block {
+        Block::Sftp(self)
+    }
+
+    async fn execute(
+        self,
+        context: ExecutionContext,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::trace!("Executing SFTP block {id}", id = self.id);
+
+        let _ = context.block_started().await;
+
+        let Some(ssh_host) = context.context_resolver.ssh_host().cloned() else {
+            return self
+                .fail(
+                    &context,
+                    "SFTP transfers need an SSH host; add an SSH Connect block above this one"
+                        .to_string(),
+                )
+                .await;
+        };
+        let ssh_config = context.context_resolver.ssh_config().cloned();
+
+        let Some(ssh_pool) = context.ssh_pool() else {
+            return self
+                .fail(
+                    &context,
+                    "SSH pool not available in execution context".to_string(),
+                )
+                .await;
+        };
+
+        let transfer = match self.transfer(&context) {
+            Ok(transfer) => transfer,
+            Err(e) => return self.fail(&context, e).await,
+        };
+
+        let Some(mut cancel_rx) = context.cancellation_receiver() else {
+            return self
+                .fail(&context, "Cancellation receiver already taken".to_string())
+                .await;
+        };
+
+        let (username, hostname) = match ssh_host.split_once('@') {
+            Some((username, hostname)) => (Some(username.to_string()), hostname.to_string()),
+            None => (None, ssh_host.clone()),
+        };
+
+        let summary = match &transfer {
+            SftpTransfer::Upload { remote_path, .. } => format!("to {ssh_host}:{remote_path}"),
+            SftpTransfer::Download {
+                remote_path,
+                local_path,
+                ..
+            } => format!("from {ssh_host}:{remote_path} to {}", local_path.display()),
+        };
+
+        let context_clone = context.clone();
+        tokio::spawn(async move {
+            let block_id = self.id;
+            let channel = block_id.to_string();
+            let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
+
+            // Forward progress reports to the client as they arrive
+            let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<TransferProgress>();
+            let progress_context = context.clone();
+            let progress_task = tokio::spawn(async move {
+                while let Some(progress) = progress_rx.recv().await {
+                    let _ = progress_context
+                        .send_output(
+                            StreamingBlockOutput::builder()
+                                .block_id(block_id)
+                                .object(serde_json::to_value(progress).unwrap_or_default())
+                                .build(),
+                        )
+                        .await;
+                }
+            });
+
+            let result = tokio::select! {
+                result = ssh_pool.transfer(
+                    &hostname,
+                    username.as_deref(),
+                    &channel,
+                    transfer,
+                    ssh_config,
+                    Some(ssh_prompt),
+                    progress_tx,
+                ) => Some(result),
+                _ = &mut cancel_rx => None,
+            };
+
+            if result.is_none() {
+                let _ = ssh_pool.close_transfer(&channel).await;
+            }
+
+            // Let the final progress report through before reporting the outcome
+            let _ = progress_task.await;
+
+            match result {
+                None => {
+                    tracing::debug!("SFTP block {block_id} cancelled");
+                    let _ = context.block_cancelled().await;
+                }
+                Some(Ok(bytes)) => {
+                    let verb = match self.direction {
+                        SftpDirection::Upload => "Uploaded",
+                        SftpDirection::Download => "Downloaded",
+                    };
+                    let _ = context
+                        .send_output(
+                            StreamingBlockOutput::builder()
+                                .block_id(block_id)
+                                .stdout(format!("{verb} {bytes} bytes {summary}"))
+                                .build(),
+                        )
+                        .await;
+                    let _ = context.block_finished(Some(0), true).await;
+                }
+                Some(Err(e)) => {
+                    if let Some(event) = HostKeyError::mismatch_event(&e) {
+                        let _ = context.emit_gc_event(event).await;
+                    }
+                    let _ = context
+                        .block_failed(format!("SFTP transfer failed: {e}"))
+                        .await;
+                }
+            }
+        });
+
+        Ok(Some(context_clone.handle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_document() {
+        let id = Uuid::new_v4();
+        let sftp = Sftp::from_document(&json!({
+            "id": id.to_string(),
+            "type": "sftp",
+            "props": {
+                "direction": "download",
+                "localPath": "logs/app.log",
+                "remotePath": "/var/log/{{ var.app }}.log",
+                "mode": "0640"
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(sftp.id, id);
+        assert_eq!(sftp.direction, SftpDirection::Download);
+        assert_eq!(sftp.source, SftpSource::File);
+        assert_eq!(sftp.remote_path, "/var/log/{{ var.app }}.log");
+        assert_eq!(sftp.mode, Some(0o640));
+
+        let result = Sftp::from_document(&json!({
+            "id": id.to_string(),
+            "type": "sftp",
+            "props": { "remotePath": "/tmp/x", "mode": "rwx" }
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode(""), Ok(None));
+        assert_eq!(parse_mode("644"), Ok(Some(0o644)));
+        assert_eq!(parse_mode("0755"), Ok(Some(0o755)));
+        assert_eq!(parse_mode("0o600"), Ok(Some(0o600)));
+        assert!(parse_mode("888").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(
+            Sftp::local_path("", "/etc/nginx/nginx.conf", "/work").unwrap(),
+            PathBuf::from("/work/nginx.conf")
+        );
+        assert_eq!(
+            Sftp::local_path("out/app.tar", "/tmp/app.tar", "/work").unwrap(),
+            PathBuf::from("/work/out/app.tar")
+        );
+        assert_eq!(
+            Sftp::local_path("/abs/file", "/tmp/file", "/work").unwrap(),
+            PathBuf::from("/abs/file")
+        );
+        assert!(Sftp::local_path("", "/", "/work").is_err());
+    }
+}
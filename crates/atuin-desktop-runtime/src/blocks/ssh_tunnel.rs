@@ -0,0 +1,318 @@
+//! SSH tunnel block implementation
+//!
+//! Forwards a local port to a host reachable from the current SSH host, like
+//! `ssh -L`. The local port is published as a template variable so that later
+//! blocks (Postgres, MySQL, ClickHouse, HTTP...) can connect through the tunnel.
+//! The tunnel stays open after the block finishes, until the block is cancelled,
+//! re-run, or the document is closed; the port variable is cleared when it closes.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::blocks::{Block, BlockBehavior, FromDocument};
+use crate::execution::{ExecutionContext, ExecutionHandle, StreamingBlockOutput};
+use crate::ssh::{HostKeyError, SshPrompt};
+
+/// Variable the local port is stored in when the block doesn't name one
+const DEFAULT_OUTPUT_VARIABLE: &str = "tunnel_port";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct SshTunnel {
+    #[builder(setter(into))]
+    pub id: Uuid,
+
+    #[builder(default, setter(into))]
+    pub name: String,
+
+    /// Host to forward to, resolved by the SSH host (template)
+    #[builder(setter(into))]
+    pub remote_host: String,
+
+    pub remote_port: u16,
+
+    /// Local port to listen on; 0 picks a free port
+    #[builder(default)]
+    pub local_port: u16,
+
+    /// Variable that receives the local port
+    #[builder(default = DEFAULT_OUTPUT_VARIABLE.to_string(), setter(into))]
+    pub output_variable: String,
+}
+
+/// Parse a port prop, where a missing or empty value is 0
+///
+/// `accepted` describes the values the prop takes, for the error message.
+fn parse_port(
+    props: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    accepted: &str,
+) -> Result<u16, String> {
+    let port = match props.get(key) {
+        None | Some(serde_json::Value::Null) => return Ok(0),
+        Some(serde_json::Value::String(s)) if s.trim().is_empty() => return Ok(0),
+        Some(serde_json::Value::String(s)) => s.trim().parse::<u64>().ok(),
+        Some(v) => v.as_u64(),
+    };
+
+    match port {
+        Some(p) if p <= 65535 => Ok(p as u16),
+        Some(p) => Err(format!("Invalid {key}: {p} (must be {accepted})")),
+        None => Err(format!("Invalid {key}: expected a number")),
+    }
+}
+
+impl FromDocument for SshTunnel {
+    fn from_document(block_data: &serde_json::Value) -> Result<Self, String> {
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or("Invalid or missing id")?;
+
+        let props = block_data
+            .get("props")
+            .and_then(|p| p.as_object())
+            .ok_or("Invalid or missing props")?;
+
+        let name = props
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("SSH Tunnel")
+            .to_string();
+
+        let remote_host = props
+            .get("remoteHost")
+            .and_then(|v| v.as_str())
+            .unwrap_or("localhost")
+            .to_string();
+
+        // New blocks have no remote port yet, so a missing one is only reported when run
+        let remote_port = parse_port(props, "remotePort", "1-65535")?;
+        let local_port = parse_port(props, "localPort", "0-65535; 0 picks a free local port")?;
+
+        let output_variable = props
+            .get("outputVariable")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(DEFAULT_OUTPUT_VARIABLE)
+            .to_string();
+
+        Ok(SshTunnel::builder()
+            .id(id)
+            .name(name)
+            .remote_host(remote_host)
+            .remote_port(remote_port)
+            .local_port(local_port)
+            .output_variable(output_variable)
+            .build())
+    }
+}
+
+impl SshTunnel {
+    async fn fail(
+        &self,
+        context: &ExecutionContext,
+        message: String,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = context.block_failed(message.clone()).await;
+        Err(message.into())
+    }
+}
+
+#[async_trait]
+impl BlockBehavior for SshTunnel {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn into_block(self) -> Block {
+        Block::SshTunnel(self)
+    }
+
+    async fn execute(
+        self,
+        context: ExecutionContext,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::trace!("Executing SSH tunnel block {id}", id = self.id);
+
+        let _ = context.block_started().await;
+
+        let Some(ssh_host) = context.context_resolver.ssh_host().cloned() else {
+            return self
+                .fail(
+                    &context,
+                    "SSH tunnels need an SSH host; add an SSH Connect block above this one"
+                        .to_string(),
+                )
+                .await;
+        };
+        let ssh_config = context.context_resolver.ssh_config().cloned();
+
+        let Some(ssh_pool) = context.ssh_pool() else {
+            return self
+                .fail(
+                    &context,
+                    "SSH pool not available in execution context".to_string(),
+                )
+                .await;
+        };
+
+        let remote_host = match context.context_resolver.resolve_template(&self.remote_host) {
+            Ok(host) if !host.trim().is_empty() => host.trim().to_string(),
+            Ok(_) => {
+                return self
+                    .fail(&context, "Remote host is empty".to_string())
+                    .await
+            }
+            Err(e) => return self.fail(&context, e.to_string()).await,
+        };
+
+        if self.remote_port == 0 {
+            return self
+                .fail(&context, "Remote port must be set (1-65535)".to_string())
+                .await;
+        }
+
+        let (username, hostname) = match ssh_host.split_once('@') {
+            Some((username, hostname)) => (Some(username.to_string()), hostname.to_string()),
+            None => (None, ssh_host.clone()),
+        };
+
+        // Take the receiver before reporting success, so a cancel can't slip past us
+        let Some(cancel_rx) = context.cancellation_receiver() else {
+            return self
+                .fail(&context, "Cancellation receiver already taken".to_string())
+                .await;
+        };
+
+        let channel = self.id.to_string();
+        let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
+        let addr = match ssh_pool
+            .open_tunnel(
+                &hostname,
+                username.as_deref(),
+                &channel,
+                self.local_port,
+                &remote_host,
+                self.remote_port,
+                ssh_config,
+                Some(ssh_prompt),
+            )
+            .await
+        {
+            Ok(addr) => addr,
+            Err(e) => {
+                if let Some(event) = HostKeyError::mismatch_event(&e) {
+                    let _ = context.emit_gc_event(event).await;
+                }
+                return self
+                    .fail(&context, format!("Failed to open SSH tunnel: {e}"))
+                    .await;
+            }
+        };
+
+        let port = addr.port().to_string();
+        let var_name = self.output_variable.clone();
+        let _ = context
+            .update_active_context(self.id, move |ctx| {
+                ctx.add_var(var_name, port, "(ssh tunnel)".to_string());
+            })
+            .await;
+
+        let _ = context
+            .send_output(
+                StreamingBlockOutput::builder()
+                    .block_id(self.id)
+                    .stdout(format!(
+                        "Forwarding {addr} to {remote_host}:{} via {ssh_host}",
+                        self.remote_port
+                    ))
+                    .object(serde_json::json!({
+                        "localAddress": addr.to_string(),
+                        "localPort": addr.port(),
+                    }))
+                    .build(),
+            )
+            .await;
+
+        // The tunnel outlives the block's execution, so report success now and let
+        // workflows carry on to the blocks that use it
+        let _ = context.block_finished(Some(0), true).await;
+
+        let context_clone = context.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancel_rx => {
+                    tracing::debug!("SSH tunnel block {id} cancelled", id = self.id);
+                    let _ = ssh_pool.close_tunnel(&channel).await;
+                    let _ = context_clone.block_cancelled().await;
+                }
+                _ = context_clone.document_closed() => {
+                    tracing::debug!("Document closed, closing SSH tunnel {id}", id = self.id);
+                    let _ = ssh_pool.close_tunnel(&channel).await;
+                }
+            }
+
+            // Later blocks shouldn't be handed the port of a closed tunnel
+            let _ = context_clone.clear_active_context(self.id).await;
+        });
+
+        Ok(Some(context.handle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_document() {
+        let id = Uuid::new_v4();
+        let tunnel = SshTunnel::from_document(&json!({
+            "id": id.to_string(),
+            "type": "ssh-tunnel",
+            "props": {
+                "name": "Database",
+                "remoteHost": "{{ var.db_host }}",
+                "remotePort": 5432,
+                "localPort": "15432",
+                "outputVariable": "db_port"
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(tunnel.id, id);
+        assert_eq!(tunnel.name, "Database");
+        assert_eq!(tunnel.remote_host, "{{ var.db_host }}");
+        assert_eq!(tunnel.remote_port, 5432);
+        assert_eq!(tunnel.local_port, 15432);
+        assert_eq!(tunnel.output_variable, "db_port");
+    }
+
+    #[test]
+    fn test_from_document_defaults() {
+        let tunnel = SshTunnel::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "ssh-tunnel",
+            "props": { "remotePort": 3306, "localPort": "" }
+        }))
+        .unwrap();
+
+        assert_eq!(tunnel.remote_host, "localhost");
+        assert_eq!(tunnel.local_port, 0);
+        assert_eq!(tunnel.output_variable, DEFAULT_OUTPUT_VARIABLE);
+
+        assert!(SshTunnel::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "ssh-tunnel",
+            "props": { "remotePort": 70000 }
+        }))
+        .is_err());
+    }
+}
@@ -550,7 +550,7 @@ impl BlockBehavior for SubRunbook {
 
                 // Collect items to export
                 let mut new_env_vars: Vec<(String, String)> = Vec::new();
-                let mut new_vars: Vec<(String, String, String)> = Vec::new();
+                let mut new_vars: Vec<(String, serde_json::Value, String)> = Vec::new();
                 let mut new_cwd: Option<String> = None;
 
                 // Export environment variables
@@ -583,8 +583,8 @@ impl BlockBehavior for SubRunbook {
 
                 // Export template variables
                 if export_vars {
-                    let child_vars = final_resolver.vars();
-                    let parent_vars = context.context_resolver.vars();
+                    let child_vars = final_resolver.typed_vars();
+                    let parent_vars = context.context_resolver.typed_vars();
 
                     tracing::debug!(
                         "export_vars: child has {} vars, parent has {} vars",
@@ -594,9 +594,7 @@ impl BlockBehavior for SubRunbook {
 
                     new_vars = child_vars
                         .iter()
-                        .filter(|(k, v)| {
-                            parent_vars.get(*k).map(|pv| pv.as_str()) != Some(v.as_str())
-                        })
+                        .filter(|(k, v)| parent_vars.get(*k) != Some(*v))
                         .map(|(k, v)| (k.clone(), v.clone(), "(sub-runbook export)".to_string()))
                         .collect();
 
@@ -632,7 +630,7 @@ impl BlockBehavior for SubRunbook {
                                 ctx.add_env(name, value);
                             }
                             for (name, value, source) in new_vars {
-                                ctx.add_var(name, value, source);
+                                ctx.add_typed_var(name, value, source);
                             }
                             if let Some(cwd) = new_cwd {
                                 ctx.set_cwd(cwd);
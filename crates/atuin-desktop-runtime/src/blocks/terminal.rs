@@ -14,7 +14,7 @@ use crate::execution::{
     CancellationToken, ExecutionContext, ExecutionHandle, ExecutionStatus, StreamingBlockOutput,
 };
 use crate::pty::{Pty, PtyLike};
-use crate::ssh::{build_env_exports, SshPty, SshWarning};
+use crate::ssh::{build_env_exports, HostKeyError, SshPrompt, SshPty, SshWarning};
 
 /// Output structure for Terminal blocks that implements BlockExecutionOutput
 /// for template access to terminal output.
@@ -243,10 +243,24 @@ impl Terminal {
                     .clone()
                     .ok_or("SSH pool not available in execution context")?;
 
-                let remote_path = ssh_pool
-                    .create_temp_file(&hostname, username.as_deref(), "atuin-desktop-vars")
+                let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
+                let remote_path = match ssh_pool
+                    .create_temp_file(
+                        &hostname,
+                        username.as_deref(),
+                        "atuin-desktop-vars",
+                        Some(ssh_prompt),
+                    )
                     .await
-                    .map_err(|e| format!("Failed to create remote temp file: {}", e))?;
+                {
+                    Ok(path) => path,
+                    Err(e) => {
+                        if let Some(event) = HostKeyError::mismatch_event(&e) {
+                            let _ = context.emit_gc_event(event).await;
+                        }
+                        return Err(format!("Failed to create remote temp file: {}", e).into());
+                    }
+                };
 
                 remote_var_path = Some(remote_path);
             } else {
@@ -281,6 +295,7 @@ impl Terminal {
             let initial_cols = self.cols;
             let initial_rows = self.rows;
             let ssh_config_clone = ssh_config.clone();
+            let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
             let ssh_result = tokio::select! {
                 result = ssh_pool_clone.open_pty_with_config(
                     &hostname_clone,
@@ -290,8 +305,9 @@ impl Terminal {
                     initial_cols,
                     initial_rows,
                     ssh_config_clone,
+                    Some(ssh_prompt),
                 ) => {
-                    result.map_err(|e| format!("Failed to open SSH PTY: {}", e))
+                    result
                 }
                 _ = &mut cancel_rx => {
                     let _ = ssh_pool_clone.close_pty(&pty_id_str).await;
@@ -303,7 +319,15 @@ impl Terminal {
                 }
             };
 
-            let (pty_tx, resize_tx, warnings) = ssh_result?;
+            let (pty_tx, resize_tx, warnings) = match ssh_result {
+                Ok(result) => result,
+                Err(e) => {
+                    if let Some(event) = HostKeyError::mismatch_event(&e) {
+                        let _ = context.emit_gc_event(event).await;
+                    }
+                    return Err(format!("Failed to open SSH PTY: {}", e).into());
+                }
+            };
 
             // Emit events for any authentication warnings
             for warning in warnings {
@@ -354,9 +378,11 @@ impl Terminal {
             let context_clone = context.clone();
             let block_id = self.id;
             let output_accumulator_clone = output_accumulator.clone();
+            let mut redactor = context.context_resolver.redactor().stream();
             tokio::spawn(async move {
                 while let Some(output) = output_receiver.recv().await {
-                    let bytes = output.as_bytes().to_vec();
+                    // Mask secrets before they're stored or shown, even if split across chunks
+                    let bytes = redactor.push(output.as_bytes());
 
                     // Accumulate output
                     output_accumulator_clone
@@ -374,6 +400,22 @@ impl Terminal {
                         .await;
                 }
 
+                let remaining = redactor.finish();
+                if !remaining.is_empty() {
+                    output_accumulator_clone
+                        .write()
+                        .await
+                        .extend_from_slice(&remaining);
+                    let _ = context_clone
+                        .send_output(
+                            StreamingBlockOutput::builder()
+                                .block_id(block_id)
+                                .binary(remaining)
+                                .build(),
+                        )
+                        .await;
+                }
+
                 cancellation_token_clone.cancel();
             });
 
@@ -415,6 +457,7 @@ impl Terminal {
 
             let cancellation_token_clone = cancellation_token_clone.clone();
             let output_accumulator_clone = output_accumulator.clone();
+            let mut redactor = context.context_resolver.redactor().stream();
             tokio::spawn(async move {
                 loop {
                     // Use blocking read in a blocking task
@@ -433,12 +476,32 @@ impl Terminal {
                     match read_result {
                         Ok(Ok((0, _))) => {
                             // EOF - PTY terminated naturally
+                            let remaining = redactor.finish();
+                            if !remaining.is_empty() {
+                                output_accumulator_clone
+                                    .write()
+                                    .await
+                                    .extend_from_slice(&remaining);
+                                let _ = context_clone
+                                    .send_output(
+                                        StreamingBlockOutput::builder()
+                                            .block_id(block_id)
+                                            .binary(remaining)
+                                            .build(),
+                                    )
+                                    .await;
+                            }
                             let _ = context_clone.block_finished(Some(0), true).await;
                             cancellation_token_clone.cancel();
                             break;
                         }
                         Ok(Ok((n, buf))) => {
-                            let bytes = buf[..n].to_vec();
+                            // Mask secrets before they're stored or shown, even if split
+                            // across reads
+                            let bytes = redactor.push(&buf[..n]);
+                            if bytes.is_empty() {
+                                continue;
+                            }
 
                             // Accumulate output
                             output_accumulator_clone
@@ -543,7 +606,12 @@ impl Terminal {
                     let _ = context
                         .update_active_context(block_id, move |ctx| {
                             for (key, value) in vars.into_iter() {
-                                ctx.add_var(key, value, "(terminal variable output)".to_string());
+                                let (key, value) = crate::context::fs_var::typed_var(key, value);
+                                ctx.add_typed_var(
+                                    key,
+                                    value,
+                                    "(terminal variable output)".to_string(),
+                                );
                             }
                         })
                         .await;
@@ -570,7 +638,9 @@ impl Terminal {
                         let _ = context
                             .update_active_context(block_id, move |ctx| {
                                 for (key, value) in vars.into_iter() {
-                                    ctx.add_var(
+                                    let (key, value) =
+                                        crate::context::fs_var::typed_var(key, value);
+                                    ctx.add_typed_var(
                                         key,
                                         value,
                                         "(terminal variable output)".to_string(),
@@ -0,0 +1,799 @@
+//! Wait Until block implementation
+//!
+//! The Wait Until block re-runs a check until a condition is true or a timeout passes,
+//! replacing hand-written polling loops like "wait for the rollout to be healthy". The check
+//! is an HTTP request, a SQL query, a Prometheus query or a command, and the condition is a
+//! MiniJinja expression over the check's output, which has the same fields as the output of
+//! the equivalent block. Commands run where a script block would: on the SSH host set above
+//! the block, or locally.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    process::Command,
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use ts_rs::TS;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::blocks::{
+    clickhouse::Clickhouse, http::Http, mysql::Mysql, postgres::Postgres, prometheus::Prometheus,
+    script::OutputLine, script::Script, script::ScriptExecutionOutput, sqlite::SQLite, Block,
+    BlockBehavior, FromDocument, QueryBlockBehavior,
+};
+use crate::context::{BlockExecutionOutput, BlockState, ContextResolver, Redactor};
+use crate::execution::{ExecutionContext, ExecutionHandle, StreamingBlockOutput};
+use crate::ssh::{
+    build_env_exports, HostKeyError, OutputLine as SessionOutputLine, SshPoolHandle, SshPrompt,
+};
+use crate::templates::{template_environment, OutputWrapper};
+
+/// What a Wait Until block checks on every attempt
+///
+/// Each variant is parsed from the block's props as the equivalent block would be, so the
+/// props have the same names: `url` and `verb` for HTTP, `uri` and `query` for SQL, and so on.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "check", rename_all = "camelCase")]
+pub enum WaitCheck {
+    Http(Http),
+    Postgres(Postgres),
+    Mysql(Mysql),
+    #[serde(rename = "sqlite")]
+    SQLite(SQLite),
+    Clickhouse(Clickhouse),
+    Prometheus(Prometheus),
+    Command {
+        interpreter: String,
+        code: String,
+    },
+}
+
+impl WaitCheck {
+    fn from_document(block_data: &serde_json::Value, check: &str) -> Result<Self, String> {
+        match check {
+            "http" => Ok(WaitCheck::Http(Http::from_document(block_data)?)),
+            "postgres" => Ok(WaitCheck::Postgres(Postgres::from_document(block_data)?)),
+            "mysql" => Ok(WaitCheck::Mysql(Mysql::from_document(block_data)?)),
+            "sqlite" => Ok(WaitCheck::SQLite(SQLite::from_document(block_data)?)),
+            "clickhouse" => Ok(WaitCheck::Clickhouse(Clickhouse::from_document(
+                block_data,
+            )?)),
+            "prometheus" => Ok(WaitCheck::Prometheus(Prometheus::from_document(
+                block_data,
+            )?)),
+            "command" => {
+                let props = block_data.get("props");
+                let prop = |name: &str, default: &str| {
+                    props
+                        .and_then(|p| p.get(name))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(default)
+                        .to_string()
+                };
+                Ok(WaitCheck::Command {
+                    interpreter: prop("interpreter", "bash"),
+                    code: prop("code", ""),
+                })
+            }
+            _ => Err(format!("Unknown check type: {check}")),
+        }
+    }
+}
+
+/// How the interval between attempts changes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WaitBackoff {
+    /// Wait the same interval every time
+    #[default]
+    Constant,
+    /// Double the interval after every attempt, up to the maximum interval
+    Exponential,
+}
+
+/// A block that repeats a check until its condition holds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct WaitUntil {
+    #[builder(setter(into))]
+    pub id: Uuid,
+
+    #[builder(default, setter(into))]
+    pub name: String,
+
+    pub check: WaitCheck,
+
+    /// MiniJinja expression over `output`, the result of the check
+    ///
+    /// If empty, the check itself has to succeed: a 2xx response, a query without errors,
+    /// or a command that exits with 0.
+    #[builder(default, setter(into))]
+    pub condition: String,
+
+    #[builder(default = 5)]
+    pub interval_seconds: u64,
+
+    #[builder(default = 300)]
+    pub timeout_seconds: u64,
+
+    #[builder(default)]
+    pub backoff: WaitBackoff,
+
+    #[builder(default = 60)]
+    pub max_interval_seconds: u64,
+}
+
+impl FromDocument for WaitUntil {
+    fn from_document(block_data: &serde_json::Value) -> Result<Self, String> {
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .ok_or("Invalid or missing id")?;
+
+        let props = block_data
+            .get("props")
+            .and_then(|p| p.as_object())
+            .ok_or("Invalid or missing props")?;
+
+        let check = props
+            .get("check")
+            .and_then(|v| v.as_str())
+            .unwrap_or("http");
+
+        // Numbers come from text inputs in the editor, so accept them as strings too
+        let seconds = |name: &str, default: u64| {
+            props
+                .get(name)
+                .and_then(|v| {
+                    v.as_u64()
+                        .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+                })
+                .unwrap_or(default)
+        };
+
+        let backoff = match props.get("backoff").and_then(|v| v.as_str()) {
+            Some("exponential") => WaitBackoff::Exponential,
+            _ => WaitBackoff::Constant,
+        };
+
+        Ok(WaitUntil::builder()
+            .id(id)
+            .name(
+                props
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            )
+            .check(WaitCheck::from_document(block_data, check)?)
+            .condition(
+                props
+                    .get("condition")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            )
+            .interval_seconds(seconds("intervalSeconds", 5))
+            .timeout_seconds(seconds("timeoutSeconds", 300))
+            .backoff(backoff)
+            .max_interval_seconds(seconds("maxIntervalSeconds", 60))
+            .build())
+    }
+}
+
+/// Progress of a Wait Until block, updated after every attempt
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct WaitUntilState {
+    pub attempts: u32,
+    pub elapsed_seconds: f64,
+    /// When the next attempt will be made, if the block is still waiting
+    pub next_attempt_in_seconds: Option<f64>,
+    /// Why the last attempt didn't satisfy the condition
+    pub last_result: Option<String>,
+    pub satisfied: bool,
+}
+
+impl BlockState for WaitUntilState {}
+
+/// Output of a Wait Until block, once its condition has held
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WaitUntilOutput {
+    pub attempts: u32,
+    pub elapsed_seconds: f64,
+    /// Output of the check that satisfied the condition
+    #[serde(skip)]
+    pub check: Arc<dyn BlockExecutionOutput>,
+}
+
+impl BlockExecutionOutput for WaitUntilOutput {
+    fn get_template_value(&self, key: &str) -> Option<minijinja::Value> {
+        match key {
+            "attempts" => Some(minijinja::Value::from(self.attempts)),
+            "elapsed_seconds" => Some(minijinja::Value::from(self.elapsed_seconds)),
+            "check" => Some(minijinja::Value::from_object(OutputWrapper::new(
+                self.check.clone(),
+                Redactor::default(),
+            ))),
+            _ => None,
+        }
+    }
+
+    fn enumerate_template_keys(&self) -> minijinja::value::Enumerator {
+        minijinja::value::Enumerator::Str(&["attempts", "elapsed_seconds", "check"])
+    }
+}
+
+/// The result of one attempt
+struct CheckResult {
+    output: Arc<dyn BlockExecutionOutput>,
+    /// Whether the check itself succeeded, used when there is no condition
+    succeeded: bool,
+}
+
+impl WaitUntil {
+    /// The interval to wait after one of `current`
+    fn next_interval(&self, current: Duration) -> Duration {
+        match self.backoff {
+            WaitBackoff::Constant => current,
+            WaitBackoff::Exponential => {
+                let max = Duration::from_secs(self.max_interval_seconds.max(self.interval_seconds));
+                (current * 2).min(max)
+            }
+        }
+    }
+
+    /// Whether a check's result satisfies the condition
+    fn is_satisfied(
+        &self,
+        resolver: &ContextResolver,
+        result: &CheckResult,
+    ) -> Result<bool, minijinja::Error> {
+        if self.condition.trim().is_empty() {
+            return Ok(result.succeeded);
+        }
+
+        let mut resolver = resolver.clone();
+        resolver.add_extra_template_context(
+            "output".to_string(),
+            OutputWrapper::new(result.output.clone(), Redactor::default()),
+        );
+        Ok(resolver.evaluate_expression(&self.condition)?.is_true())
+    }
+
+    async fn run_check(&self, context: &ExecutionContext) -> Result<CheckResult, String> {
+        match &self.check {
+            WaitCheck::Http(http) => {
+                let output = http.check(context).await.map_err(|e| e.to_string())?;
+                Ok(CheckResult {
+                    succeeded: output.status_success,
+                    output: Arc::new(output),
+                })
+            }
+            WaitCheck::Postgres(block) => run_query(block, context).await,
+            WaitCheck::Mysql(block) => run_query(block, context).await,
+            WaitCheck::SQLite(block) => run_query(block, context).await,
+            WaitCheck::Clickhouse(block) => run_query(block, context).await,
+            WaitCheck::Prometheus(block) => run_query(block, context).await,
+            WaitCheck::Command { interpreter, code } => {
+                let command = context
+                    .context_resolver
+                    .resolve_template(code)
+                    .map_err(|e| e.to_string())?;
+
+                let output = match context.context_resolver.ssh_host() {
+                    Some(ssh_host) => {
+                        run_remote_command(interpreter, &command, ssh_host, context).await?
+                    }
+                    None => run_local_command(interpreter, &command, context).await?,
+                };
+                Ok(CheckResult {
+                    succeeded: output.exit_code == Some(0),
+                    output: Arc::new(output),
+                })
+            }
+        }
+    }
+
+    async fn report(&self, context: &ExecutionContext, message: String) {
+        let _ = context
+            .send_output(
+                StreamingBlockOutput::builder()
+                    .block_id(self.id)
+                    .stdout(format!("{message}\n"))
+                    .build(),
+            )
+            .await;
+    }
+
+    /// Check until the condition holds, returning the satisfying output
+    async fn wait(&self, context: &ExecutionContext) -> Result<WaitUntilOutput, WaitError> {
+        let start = Instant::now();
+        let deadline = start + Duration::from_secs(self.timeout_seconds);
+        let mut interval = Duration::from_secs(self.interval_seconds.max(1));
+        let mut attempts = 0;
+
+        let cancellation_receiver = context.cancellation_receiver();
+        let cancelled = async move {
+            match cancellation_receiver {
+                Some(receiver) => {
+                    let _ = receiver.await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        };
+        tokio::pin!(cancelled);
+
+        loop {
+            attempts += 1;
+
+            let result = tokio::select! {
+                _ = &mut cancelled => return Err(WaitError::Cancelled),
+                _ = tokio::time::sleep_until(deadline) => Err("Check did not finish".to_string()),
+                result = self.run_check(context) => result,
+            };
+
+            let outcome = match result {
+                Ok(result) => match self.is_satisfied(&context.context_resolver, &result) {
+                    Ok(true) => Ok(result.output),
+                    Ok(false) if self.condition.trim().is_empty() => {
+                        Err("check did not succeed".to_string())
+                    }
+                    Ok(false) => Err("condition is false".to_string()),
+                    Err(e) => Err(format!("condition could not be evaluated: {e}")),
+                },
+                Err(e) => Err(e),
+            };
+
+            let elapsed = start.elapsed().as_secs_f64();
+            let now = Instant::now();
+            let next_attempt = match &outcome {
+                Err(_) if now + interval < deadline => Some(interval),
+                _ => None,
+            };
+
+            let state_update = WaitUntilState {
+                attempts,
+                elapsed_seconds: elapsed,
+                next_attempt_in_seconds: next_attempt.map(|next| next.as_secs_f64()),
+                last_result: outcome.as_ref().err().cloned(),
+                satisfied: outcome.is_ok(),
+            };
+            let _ = context
+                .update_block_state::<WaitUntilState, _>(self.id, move |state| {
+                    *state = state_update;
+                })
+                .await;
+
+            let reason = match outcome {
+                Ok(check) => {
+                    self.report(
+                        context,
+                        format!("Attempt {attempts}: condition met after {elapsed:.1}s"),
+                    )
+                    .await;
+                    return Ok(WaitUntilOutput {
+                        attempts,
+                        elapsed_seconds: elapsed,
+                        check,
+                    });
+                }
+                Err(reason) => reason,
+            };
+
+            let Some(next_attempt) = next_attempt else {
+                return Err(WaitError::TimedOut {
+                    attempts,
+                    timeout: self.timeout_seconds,
+                    reason,
+                });
+            };
+
+            self.report(
+                context,
+                format!(
+                    "Attempt {attempts}: {reason}; retrying in {}s",
+                    next_attempt.as_secs_f64()
+                ),
+            )
+            .await;
+
+            tokio::select! {
+                _ = &mut cancelled => return Err(WaitError::Cancelled),
+                _ = tokio::time::sleep(next_attempt) => {}
+            }
+            interval = self.next_interval(interval);
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum WaitError {
+    #[error("Timed out after {timeout}s and {attempts} attempt(s); last attempt: {reason}")]
+    TimedOut {
+        attempts: u32,
+        timeout: u64,
+        reason: String,
+    },
+
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+async fn run_local_command(
+    interpreter: &str,
+    command: &str,
+    context: &ExecutionContext,
+) -> Result<ScriptExecutionOutput, String> {
+    let output = Command::new(interpreter)
+        .current_dir(context.context_resolver.cwd())
+        .envs(context.context_resolver.env_vars().clone())
+        .arg("-c")
+        .arg(command)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {interpreter}: {e}"))?;
+
+    Ok(ScriptExecutionOutput {
+        exit_code: output.status.code(),
+        output: vec![
+            OutputLine::stdout(String::from_utf8_lossy(&output.stdout).to_string()),
+            OutputLine::stderr(String::from_utf8_lossy(&output.stderr).to_string()),
+        ],
+    })
+}
+
+/// Run a command on the SSH host, through the pool, as a script block under the host would
+async fn run_remote_command(
+    interpreter: &str,
+    command: &str,
+    ssh_host: &str,
+    context: &ExecutionContext,
+) -> Result<ScriptExecutionOutput, String> {
+    let ssh_pool = context
+        .ssh_pool
+        .clone()
+        .ok_or("SSH pool not available in execution context")?;
+    let (username, hostname) = Script::parse_ssh_host(ssh_host);
+    let code = format!(
+        "{}{command}",
+        build_env_exports(context.context_resolver.env_vars())
+    );
+
+    let mut remote = RemoteCommand {
+        ssh_pool: ssh_pool.clone(),
+        channel: Uuid::new_v4().to_string(),
+        finished: false,
+    };
+    let (output_sender, mut output_receiver) = mpsc::channel::<SessionOutputLine>(100);
+    let (result_tx, result_rx) = oneshot::channel::<Option<u32>>();
+    let ssh_prompt: Arc<dyn SshPrompt> = Arc::new(context.clone());
+
+    let started = ssh_pool
+        .exec_with_config(
+            &hostname,
+            username.as_deref(),
+            interpreter,
+            &code,
+            &remote.channel,
+            output_sender,
+            result_tx,
+            context.context_resolver.ssh_config().cloned(),
+            None,
+            Some(ssh_prompt),
+        )
+        .await;
+    if let Err(e) = started {
+        remote.finished = true;
+        if let Some(event) = HostKeyError::mismatch_event(&e) {
+            let _ = context.emit_gc_event(event).await;
+        }
+        return Err(format!("Failed to run the command on {ssh_host}: {e}"));
+    }
+
+    let mut output = Vec::new();
+    // The session drops its sender once the command has finished
+    while let Some(line) = output_receiver.recv().await {
+        let mut text = line.inner().to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        output.push(if line.is_stdout() {
+            OutputLine::stdout(text)
+        } else {
+            OutputLine::stderr(text)
+        });
+    }
+    let exit_status = result_rx.await.ok().flatten();
+    remote.finished = true;
+
+    Ok(ScriptExecutionOutput {
+        exit_code: Some(Script::remote_exit_code(exit_status)),
+        output,
+    })
+}
+
+/// A command running on an SSH host, cancelled if the check stops waiting for it, such as
+/// when the attempt times out or the block is cancelled
+struct RemoteCommand {
+    ssh_pool: SshPoolHandle,
+    channel: String,
+    finished: bool,
+}
+
+impl Drop for RemoteCommand {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let ssh_pool = self.ssh_pool.clone();
+        let channel = std::mem::take(&mut self.channel);
+        tokio::spawn(async move {
+            let _ = ssh_pool.exec_cancel(&channel).await;
+        });
+    }
+}
+
+/// Run a query block's query once, without the lifecycle events of executing the block
+async fn run_query<B: QueryBlockBehavior>(
+    block: &B,
+    context: &ExecutionContext,
+) -> Result<CheckResult, String> {
+    let query = block.resolve_query(context).map_err(|e| e.to_string())?;
+    let connection_string = block
+        .resolve_connection_string(context)
+        .map_err(|e| e.to_string())?;
+
+    let connection = block
+        .connect(connection_string)
+        .await
+        .map_err(|e| e.to_string())?;
+    let results = block.execute_query(&connection, &query, context).await;
+    let _ = block.disconnect(&connection).await;
+    let results = results.map_err(|e| e.to_string())?;
+
+    let output = block
+        .create_output(&results)
+        .ok_or("Query produced no output")?;
+    Ok(CheckResult {
+        output: Arc::from(output),
+        succeeded: true,
+    })
+}
+
+#[async_trait]
+impl BlockBehavior for WaitUntil {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn into_block(self) -> Block {
+        Block::WaitUntil(self)
+    }
+
+    fn create_state(&self) -> Option<Box<dyn BlockState>> {
+        Some(Box::new(WaitUntilState::default()))
+    }
+
+    async fn execute(
+        self,
+        context: ExecutionContext,
+    ) -> Result<Option<ExecutionHandle>, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::trace!("Executing Wait Until block {id}", id = self.id);
+
+        // A bad condition would fail every attempt, so report it before waiting
+        let condition = self.condition.trim();
+        let invalid = (!condition.is_empty())
+            .then(|| {
+                template_environment()
+                    .compile_expression(condition.trim_start_matches("{{").trim_end_matches("}}"))
+                    .err()
+            })
+            .flatten();
+        if let Some(e) = invalid {
+            let _ = context
+                .block_failed(format!("Invalid condition: {e}"))
+                .await;
+            return Ok(Some(context.handle()));
+        }
+
+        let _ = context.block_started().await;
+        let _ = context
+            .update_block_state::<WaitUntilState, _>(self.id, |state| {
+                *state = WaitUntilState::default();
+            })
+            .await;
+
+        let handle = context.handle();
+        tokio::spawn(async move {
+            match self.wait(&context).await {
+                Ok(output) => {
+                    let _ = context.set_block_output(output).await;
+                    let _ = context.block_finished(None, true).await;
+                }
+                Err(WaitError::Cancelled) => {
+                    let _ = context.block_cancelled().await;
+                }
+                Err(e) => {
+                    let _ = context.block_failed(e.to_string()).await;
+                }
+            }
+        });
+
+        Ok(Some(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::blocks::http::HttpExecutionOutput;
+    use crate::document::actor::DocumentCommand;
+    use crate::document::DocumentHandle;
+    use crate::events::MemoryEventBus;
+    use tokio::sync::mpsc;
+
+    fn wait_until(props: serde_json::Value) -> Result<WaitUntil, String> {
+        WaitUntil::from_document(&serde_json::json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "wait-until",
+            "props": props,
+        }))
+    }
+
+    fn http_result(status: u16, body: &str) -> CheckResult {
+        CheckResult {
+            succeeded: (200..300).contains(&status),
+            output: Arc::new(HttpExecutionOutput {
+                status,
+                status_text: String::new(),
+                status_success: (200..300).contains(&status),
+                headers: HashMap::new(),
+                duration_seconds: 0.1,
+                body: body.to_string(),
+                body_json: serde_json::from_str(body).ok(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_from_document() {
+        let block = wait_until(serde_json::json!({
+            "name": "Rollout healthy",
+            "check": "http",
+            "url": "https://example.com/health",
+            "condition": "output.status == 200",
+            "intervalSeconds": "10",
+            "timeoutSeconds": 600,
+            "backoff": "exponential"
+        }))
+        .unwrap();
+
+        assert_eq!(block.name, "Rollout healthy");
+        assert!(
+            matches!(&block.check, WaitCheck::Http(http) if http.url == "https://example.com/health")
+        );
+        assert_eq!(block.interval_seconds, 10);
+        assert_eq!(block.timeout_seconds, 600);
+        assert_eq!(block.backoff, WaitBackoff::Exponential);
+        assert_eq!(block.max_interval_seconds, 60);
+    }
+
+    #[test]
+    fn test_from_document_command_and_query_checks() {
+        let block = wait_until(serde_json::json!({
+            "check": "command",
+            "code": "kubectl rollout status deploy/api"
+        }))
+        .unwrap();
+        assert_eq!(
+            block.check,
+            WaitCheck::Command {
+                interpreter: "bash".to_string(),
+                code: "kubectl rollout status deploy/api".to_string()
+            }
+        );
+        assert_eq!(block.interval_seconds, 5);
+        assert_eq!(block.backoff, WaitBackoff::Constant);
+
+        let block = wait_until(serde_json::json!({
+            "check": "postgres",
+            "uri": "postgres://localhost/app",
+            "query": "select count(*) as pending from jobs"
+        }))
+        .unwrap();
+        assert!(
+            matches!(&block.check, WaitCheck::Postgres(pg) if pg.uri == "postgres://localhost/app")
+        );
+
+        assert!(wait_until(serde_json::json!({ "check": "carrier-pigeon" })).is_err());
+    }
+
+    #[test]
+    fn test_next_interval() {
+        let mut block = wait_until(serde_json::json!({
+            "intervalSeconds": 5,
+            "maxIntervalSeconds": 15
+        }))
+        .unwrap();
+        assert_eq!(
+            block.next_interval(Duration::from_secs(5)),
+            Duration::from_secs(5)
+        );
+
+        block.backoff = WaitBackoff::Exponential;
+        assert_eq!(
+            block.next_interval(Duration::from_secs(5)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            block.next_interval(Duration::from_secs(10)),
+            Duration::from_secs(15)
+        );
+    }
+
+    #[test]
+    fn test_is_satisfied() {
+        let resolver = ContextResolver::new();
+
+        let block = wait_until(serde_json::json!({})).unwrap();
+        assert!(block
+            .is_satisfied(&resolver, &http_result(204, ""))
+            .unwrap());
+        assert!(!block
+            .is_satisfied(&resolver, &http_result(503, ""))
+            .unwrap());
+
+        let block = wait_until(serde_json::json!({
+            "condition": "{{ output.body_json.replicas.ready == output.body_json.replicas.desired }}"
+        }))
+        .unwrap();
+        let ready = http_result(200, r#"{"replicas": {"ready": 3, "desired": 3}}"#);
+        let rolling = http_result(200, r#"{"replicas": {"ready": 1, "desired": 3}}"#);
+        assert!(block.is_satisfied(&resolver, &ready).unwrap());
+        assert!(!block.is_satisfied(&resolver, &rolling).unwrap());
+        assert!(block
+            .is_satisfied(&resolver, &http_result(502, "bad gateway"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_check() {
+        let block = wait_until(serde_json::json!({
+            "check": "command",
+            "code": "echo ready; exit 3"
+        }))
+        .unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel::<DocumentCommand>();
+        let document_handle = DocumentHandle::from_raw(
+            "test-runbook".to_string(),
+            tx,
+            Arc::new(MemoryEventBus::new()),
+        );
+        let context = ExecutionContext::builder()
+            .block_id(block.id)
+            .runbook_id(Uuid::new_v4())
+            .document_handle(document_handle)
+            .context_resolver(Arc::new(ContextResolver::new()))
+            .handle(ExecutionHandle::new(block.id))
+            .build();
+
+        let result = block.run_check(&context).await.unwrap();
+        assert!(!result.succeeded);
+        assert_eq!(
+            result.output.get_template_value("stdout"),
+            Some(minijinja::Value::from_serialize(Some("ready\n")))
+        );
+    }
+}
@@ -84,10 +84,10 @@ pub enum PromptOptionColor {
 /// A button option in a client prompt dialog
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 pub struct PromptOption {
-    label: String,
-    value: String,
-    variant: Option<PromptOptionVariant>,
-    color: Option<PromptOptionColor>,
+    pub label: String,
+    pub value: String,
+    pub variant: Option<PromptOptionVariant>,
+    pub color: Option<PromptOptionColor>,
 }
 
 impl PromptOption {
@@ -138,6 +138,8 @@ pub enum PromptIcon {
 #[ts(export)]
 pub enum PromptInput {
     String,
+    /// A single line that is hidden as it is typed, such as a password or OTP
+    Secret,
     Text,
     Dropdown(Vec<(String, String)>),
 }
@@ -148,11 +150,11 @@ pub enum PromptInput {
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct ClientPrompt {
-    title: String,
-    prompt: String,
-    icon: Option<PromptIcon>,
-    input: Option<PromptInput>,
-    options: Vec<PromptOption>,
+    pub title: String,
+    pub prompt: String,
+    pub icon: Option<PromptIcon>,
+    pub input: Option<PromptInput>,
+    pub options: Vec<PromptOption>,
 }
 
 impl ClientPrompt {
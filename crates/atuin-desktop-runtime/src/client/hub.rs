@@ -113,6 +113,12 @@ impl HubClient {
         }
     }
 
+    /// Point this client at a different API base URL (e.g. a self-hosted hub or a test server)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
     /// Fetch a runbook by ID
     pub async fn get_runbook_by_id(&self, id: &str) -> Result<HubRunbook, HubError> {
         let url = format!(
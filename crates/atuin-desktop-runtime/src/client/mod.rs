@@ -6,6 +6,7 @@
 //! - Message channels for sending execution output and events
 //! - Client prompts for user interaction during block execution
 //! - Local value providers for accessing client-side data
+//! - Secret providers for looking up secrets used in templates
 //! - Runbook content loaders for loading sub-runbooks
 
 mod bridge;
@@ -13,6 +14,7 @@ pub mod hub;
 pub(crate) mod local;
 mod message_channel;
 mod runbook_loader;
+pub(crate) mod secrets;
 
 pub use bridge::{
     ClientPrompt, ClientPromptResult, DocumentBridgeMessage, PromptIcon, PromptInput, PromptOption,
@@ -25,6 +27,7 @@ pub use hub::{
 pub use local::LocalValueProvider;
 pub use message_channel::MessageChannel;
 pub use runbook_loader::{LoadedRunbook, RunbookContentLoader, RunbookLoadError, SubRunbookRef};
+pub use secrets::SecretProvider;
 
 #[cfg(test)]
 pub use runbook_loader::MemoryRunbookContentLoader;
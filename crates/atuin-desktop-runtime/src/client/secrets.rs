@@ -0,0 +1,50 @@
+#[cfg(test)]
+use std::collections::HashMap;
+
+/// Trait for looking up secrets referenced from templates, as `{{ secret("name") }}`
+///
+/// Templates are rendered synchronously, so lookups block the caller. Values are cached by
+/// the runtime after the first lookup, so each secret is fetched at most once per document.
+pub trait SecretProvider: Send + Sync {
+    /// Get the value of a secret
+    ///
+    /// # Returns
+    /// The value if found, or None if there is no secret with this name
+    fn get_secret(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+#[cfg(test)]
+pub(crate) struct MemorySecretProvider {
+    values: HashMap<String, String>,
+    lookups: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl MemorySecretProvider {
+    pub fn new(values: Vec<(String, String)>) -> Self {
+        Self {
+            values: values.into_iter().collect(),
+            lookups: Default::default(),
+        }
+    }
+
+    /// Number of times a secret has been looked up
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+impl SecretProvider for MemorySecretProvider {
+    fn get_secret(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.lookups
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(self.values.get(name).cloned())
+    }
+}
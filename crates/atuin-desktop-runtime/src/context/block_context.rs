@@ -84,6 +84,7 @@ impl BlockContext {
 /// This trait is implemented for `BlockContext` and can be used to add variables to a block context.
 pub trait BlockVars {
     fn add_var(&mut self, var_name: String, var_value: String, var_source: String);
+    fn add_typed_var(&mut self, var_name: String, var_value: serde_json::Value, var_source: String);
     fn add_env(&mut self, env_name: String, env_value: String);
     fn set_cwd(&mut self, cwd: String);
     fn set_ssh_host(&mut self, ssh_host: Option<String>);
@@ -91,11 +92,20 @@ pub trait BlockVars {
 
 impl BlockVars for BlockContext {
     fn add_var(&mut self, var_name: String, var_value: String, var_source: String) {
+        self.add_typed_var(var_name, serde_json::Value::String(var_value), var_source);
+    }
+
+    fn add_typed_var(
+        &mut self,
+        var_name: String,
+        var_value: serde_json::Value,
+        var_source: String,
+    ) {
         if let Some(vars) = self.get_mut::<DocumentVars>() {
-            vars.push(DocumentVar::new(var_name, var_value, var_source));
+            vars.push(DocumentVar::new_typed(var_name, var_value, var_source));
         } else {
             let mut vars = DocumentVars::new();
-            vars.push(DocumentVar::new(var_name, var_value, var_source));
+            vars.push(DocumentVar::new_typed(var_name, var_value, var_source));
             self.insert(vars);
         }
     }
@@ -254,19 +264,47 @@ dyn_clone::clone_trait_object!(BlockContextItem);
 impl_downcast!(BlockContextItem);
 
 /// Variables defined by template variable blocks
+///
+/// Values are JSON, so lists and objects reach templates intact. Most variables are strings;
+/// other values are only turned into text where a string is needed, such as in a shell.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DocumentVar {
     pub name: String,
-    pub value: String,
+    pub value: serde_json::Value,
     pub source: String,
+    /// Sensitive values are masked wherever they appear in output
+    #[serde(default)]
+    pub sensitive: bool,
 }
 
 impl DocumentVar {
     pub fn new(name: String, value: String, source: String) -> Self {
+        Self::new_typed(name, serde_json::Value::String(value), source)
+    }
+
+    /// A variable holding any JSON value, such as a list or an object
+    pub fn new_typed(name: String, value: serde_json::Value, source: String) -> Self {
         Self {
             name,
             value,
             source,
+            sensitive: false,
+        }
+    }
+
+    /// A variable whose value must not be shown, such as a password
+    pub fn new_sensitive(name: String, value: String, source: String) -> Self {
+        Self {
+            sensitive: true,
+            ..Self::new(name, value, source)
+        }
+    }
+
+    /// The value as text: strings as they are, anything else as JSON
+    pub fn value_string(&self) -> String {
+        match &self.value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
         }
     }
 }
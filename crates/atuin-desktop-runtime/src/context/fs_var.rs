@@ -37,6 +37,8 @@ pub fn setup() -> Result<FsVarHandle, Box<dyn std::error::Error + Send + Sync>>
 /// EOF
 /// ```
 ///
+/// Names ending in `:json` declare a JSON value; see [`typed_var`].
+///
 /// Returns a HashMap of the parsed variables.
 pub fn parse_vars(content: &str) -> HashMap<String, String> {
     let mut vars = HashMap::new();
@@ -99,6 +101,27 @@ pub fn parse_vars(content: &str) -> HashMap<String, String> {
     vars
 }
 
+/// Suffix on a variable name declaring a JSON value, as in `hosts:json=["web-1", "web-2"]`
+const JSON_SUFFIX: &str = ":json";
+
+/// Split a parsed variable into its name and value
+///
+/// Values of names ending in `:json` are parsed as JSON, so lists, numbers and objects reach
+/// templates intact. If they aren't valid JSON they're kept as strings.
+pub fn typed_var(name: String, value: String) -> (String, serde_json::Value) {
+    let Some(name) = name.strip_suffix(JSON_SUFFIX) else {
+        return (name, serde_json::Value::String(value));
+    };
+
+    match serde_json::from_str(&value) {
+        Ok(parsed) => (name.to_string(), parsed),
+        Err(e) => {
+            tracing::warn!("Variable {name} is declared as JSON, but isn't valid JSON: {e}");
+            (name.to_string(), serde_json::Value::String(value))
+        }
+    }
+}
+
 /// Reads the variables from the temporary file and returns them as a HashMap.
 /// The file supports two formats:
 /// - Simple: `key=value` (one per line)
@@ -376,4 +399,22 @@ mod tests {
         );
         assert_eq!(vars.get("op"), Some(&"test 5<<10".to_string()));
     }
+
+    #[test]
+    fn test_typed_vars() {
+        let content =
+            "count:json=3\nhosts:json<<EOF\n[\"web-1\",\n \"web-2\"]\nEOF\nplain=[1]\nbad:json={\n";
+        let vars = parse_vars(content)
+            .into_iter()
+            .map(|(name, value)| typed_var(name, value))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(vars.get("count"), Some(&serde_json::json!(3)));
+        assert_eq!(
+            vars.get("hosts"),
+            Some(&serde_json::json!(["web-1", "web-2"]))
+        );
+        assert_eq!(vars.get("plain"), Some(&serde_json::json!("[1]")));
+        assert_eq!(vars.get("bad"), Some(&serde_json::json!("{")));
+    }
 }
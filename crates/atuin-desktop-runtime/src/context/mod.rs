@@ -14,7 +14,9 @@
 
 mod block_context;
 pub mod fs_var;
+mod redaction;
 mod resolution;
+mod secrets;
 mod storage;
 
 pub use block_context::BlockState;
@@ -24,7 +26,9 @@ pub use block_context::{
     DocumentSshHost, DocumentVar, DocumentVars, SshCertificateConfig, SshIdentityKeyConfig,
 };
 
+pub use redaction::{is_sensitive_name, Redactor, StreamRedactor, REDACTED};
 pub use resolution::{ContextResolver, ResolvedContext};
+pub use secrets::Secrets;
 pub use storage::BlockContextStorage;
 pub use typetag::serde as typetag_serde;
 
@@ -159,10 +163,7 @@ mod tests {
 
         let resolver = ContextResolver::from_blocks(&[block_with_context]);
 
-        assert_eq!(
-            resolver.get_var("TEST_VAR"),
-            Some(&"test_value".to_string())
-        );
+        assert_eq!(resolver.get_var("TEST_VAR"), Some("test_value".to_string()));
     }
 
     #[tokio::test]
@@ -205,7 +206,7 @@ mod tests {
 
         let resolver = ContextResolver::from_blocks(&blocks);
 
-        assert_eq!(resolver.get_var("VAR1"), Some(&"value1".to_string()));
+        assert_eq!(resolver.get_var("VAR1"), Some("value1".to_string()));
         assert_eq!(
             resolver.env_vars().get("PATH"),
             Some(&"/usr/bin".to_string())
@@ -250,7 +251,7 @@ mod tests {
 
         assert_eq!(
             resolver.get_var("SHARED_VAR"),
-            Some(&"second_value".to_string())
+            Some("second_value".to_string())
         );
     }
 
@@ -345,7 +346,7 @@ mod tests {
 
         resolver.push_block(&block_with_context);
 
-        assert_eq!(resolver.get_var("NEW_VAR"), Some(&"new_value".to_string()));
+        assert_eq!(resolver.get_var("NEW_VAR"), Some("new_value".to_string()));
     }
 
     #[tokio::test]
@@ -378,11 +379,8 @@ mod tests {
 
         resolver.push_block(&block_with_context);
 
-        assert_eq!(
-            resolver.get_var("PASSIVE_VAR"),
-            Some(&"passive".to_string())
-        );
-        assert_eq!(resolver.get_var("ACTIVE_VAR"), Some(&"active".to_string()));
+        assert_eq!(resolver.get_var("PASSIVE_VAR"), Some("passive".to_string()));
+        assert_eq!(resolver.get_var("ACTIVE_VAR"), Some("active".to_string()));
     }
 
     #[test]
@@ -736,8 +734,8 @@ mod tests {
 
         let resolver = ContextResolver::from_blocks(&[block_with_context]);
 
-        assert_eq!(resolver.get_var("VAR1"), Some(&"value1".to_string()));
-        assert_eq!(resolver.get_var("VAR2"), Some(&"value2".to_string()));
+        assert_eq!(resolver.get_var("VAR1"), Some("value1".to_string()));
+        assert_eq!(resolver.get_var("VAR2"), Some("value2".to_string()));
     }
 
     #[tokio::test]
@@ -785,12 +783,12 @@ mod tests {
 
         let resolver = ContextResolver::from_blocks(&blocks);
 
-        assert_eq!(resolver.get_var("BASE"), Some(&"hello".to_string()));
+        assert_eq!(resolver.get_var("BASE"), Some("hello".to_string()));
         assert_eq!(
             resolver.get_var("GREETING"),
-            Some(&"hello world".to_string())
+            Some("hello world".to_string())
         );
-        assert_eq!(resolver.get_var("OTHER"), Some(&"static value".to_string()));
+        assert_eq!(resolver.get_var("OTHER"), Some("static value".to_string()));
     }
 
     #[tokio::test]
@@ -826,14 +824,8 @@ mod tests {
 
         let resolver = ContextResolver::from_blocks(&[block_with_context]);
 
-        assert_eq!(
-            resolver.get_var("ACTIVE_VAR1"),
-            Some(&"active1".to_string())
-        );
-        assert_eq!(
-            resolver.get_var("ACTIVE_VAR2"),
-            Some(&"active2".to_string())
-        );
+        assert_eq!(resolver.get_var("ACTIVE_VAR1"), Some("active1".to_string()));
+        assert_eq!(resolver.get_var("ACTIVE_VAR2"), Some("active2".to_string()));
     }
 
     #[test]
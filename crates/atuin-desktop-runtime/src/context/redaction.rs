@@ -0,0 +1,312 @@
+use std::{borrow::Cow, fmt, sync::Arc};
+
+use super::Secrets;
+
+/// Shown in place of a sensitive value
+pub const REDACTED: &str = "***";
+
+/// Values shorter than this are never masked, as hiding every `a` or `42` would make output
+/// unreadable without protecting anything
+const MIN_SENSITIVE_LEN: usize = 3;
+
+/// Environment variables with any of these in their name hold sensitive values
+const SENSITIVE_NAME_PATTERNS: &[&str] = &[
+    "PASSWORD",
+    "PASSWD",
+    "SECRET",
+    "TOKEN",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    "CREDENTIAL",
+];
+
+/// Whether an environment variable name looks like it holds a sensitive value
+pub fn is_sensitive_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SENSITIVE_NAME_PATTERNS
+        .iter()
+        .any(|pattern| name.contains(pattern))
+}
+
+/// Masks sensitive values as [`REDACTED`] before text leaves the runtime
+///
+/// Secrets are read from the shared cache on every call, so a secret first used after the
+/// redactor was created is still masked.
+#[derive(Clone, Default)]
+pub struct Redactor {
+    values: Arc<Vec<String>>,
+    secrets: Option<Secrets>,
+}
+
+impl Redactor {
+    pub fn new(values: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            values: Arc::new(values.into_iter().collect()),
+            secrets: None,
+        }
+    }
+
+    pub(crate) fn with_secrets(mut self, secrets: Option<Secrets>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Values to mask, longest first so a value containing another is masked whole
+    fn values(&self) -> Vec<String> {
+        let mut values = self
+            .values
+            .iter()
+            .cloned()
+            .chain(
+                self.secrets
+                    .as_ref()
+                    .map(Secrets::sensitive_values)
+                    .unwrap_or_default(),
+            )
+            .filter(|value| value.len() >= MIN_SENSITIVE_LEN)
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        values.dedup();
+        values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values().is_empty()
+    }
+
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for value in self.values() {
+            if text.contains(value.as_str()) {
+                text = Cow::Owned(text.replace(value.as_str(), REDACTED));
+            }
+        }
+        text
+    }
+
+    /// Redact a string in place, leaving it untouched if it has nothing to mask
+    pub fn redact_in_place(&self, text: &mut String) {
+        let redacted = match self.redact(text) {
+            Cow::Owned(redacted) => Some(redacted),
+            Cow::Borrowed(_) => None,
+        };
+        if let Some(redacted) = redacted {
+            *text = redacted;
+        }
+    }
+
+    pub fn redact_bytes<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        redact_bytes_with(&self.values(), bytes)
+    }
+
+    /// Redact every string in a JSON value, leaving object keys alone
+    pub fn redact_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => self.redact_in_place(text),
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.redact_json(item));
+            }
+            serde_json::Value::Object(map) => {
+                map.values_mut().for_each(|item| self.redact_json(item));
+            }
+            _ => {}
+        }
+    }
+
+    /// Redact a value handed to templates, such as a field of a block's output
+    ///
+    /// Sequences and maps are only rebuilt when they actually contain a sensitive value.
+    pub fn redact_template_value(&self, value: minijinja::Value) -> minijinja::Value {
+        if let Some(text) = value.as_str() {
+            let mut text = text.to_string();
+            self.redact_in_place(&mut text);
+            return minijinja::Value::from(text);
+        }
+
+        if !matches!(
+            value.kind(),
+            minijinja::value::ValueKind::Seq | minijinja::value::ValueKind::Map
+        ) {
+            return value;
+        }
+
+        let Ok(original) = serde_json::to_value(&value) else {
+            return value;
+        };
+        let mut redacted = original.clone();
+        self.redact_json(&mut redacted);
+        if redacted == original {
+            value
+        } else {
+            minijinja::Value::from_serialize(&redacted)
+        }
+    }
+
+    /// A redactor for a byte stream, where a value may be split across chunks
+    pub fn stream(&self) -> StreamRedactor {
+        StreamRedactor {
+            redactor: self.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the values themselves
+        f.debug_struct("Redactor")
+            .field("values", &self.values.len())
+            .field("secrets", &self.secrets)
+            .finish()
+    }
+}
+
+/// Redacts a stream of output chunks, such as the bytes read from a terminal
+///
+/// Bytes at the end of a chunk that could be the start of a sensitive value are held back
+/// until the next chunk shows whether they are.
+#[derive(Debug)]
+pub struct StreamRedactor {
+    redactor: Redactor,
+    pending: Vec<u8>,
+}
+
+impl StreamRedactor {
+    /// Redact the next chunk, returning the bytes that are safe to emit now
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+
+        let values = self.redactor.values();
+        let mut ready = redact_bytes_with(&values, &self.pending).into_owned();
+        let hold = values
+            .iter()
+            .map(|value| partial_match_len(&ready, value.as_bytes()))
+            .max()
+            .unwrap_or(0);
+
+        self.pending = ready.split_off(ready.len() - hold);
+        ready
+    }
+
+    /// Emit whatever is still held back, once the stream has ended
+    pub fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+fn redact_bytes_with<'a>(values: &[String], bytes: &'a [u8]) -> Cow<'a, [u8]> {
+    let mut bytes = Cow::Borrowed(bytes);
+    for value in values {
+        if let Some(replaced) = replace_bytes(&bytes, value.as_bytes()) {
+            bytes = Cow::Owned(replaced);
+        }
+    }
+    bytes
+}
+
+/// Replace every occurrence of `needle` with [`REDACTED`], or None if there are none
+fn replace_bytes(haystack: &[u8], needle: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut rest = haystack;
+    let mut found = false;
+
+    while let Some(index) = rest.windows(needle.len()).position(|w| w == needle) {
+        found = true;
+        result.extend_from_slice(&rest[..index]);
+        result.extend_from_slice(REDACTED.as_bytes());
+        rest = &rest[index + needle.len()..];
+    }
+
+    if !found {
+        return None;
+    }
+    result.extend_from_slice(rest);
+    Some(result)
+}
+
+/// Length of the longest end of `haystack` that is the start of `needle`, without being all of it
+fn partial_match_len(haystack: &[u8], needle: &[u8]) -> usize {
+    let longest = needle.len().saturating_sub(1).min(haystack.len());
+    (1..=longest)
+        .rev()
+        .find(|&len| haystack.ends_with(&needle[..len]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::secrets::MemorySecretProvider;
+
+    #[test]
+    fn test_redact_text() {
+        let redactor = Redactor::new(vec!["hunter2".to_string(), "hunter2-extra".to_string()]);
+
+        assert_eq!(redactor.redact("password is hunter2"), "password is ***");
+        assert_eq!(redactor.redact("token hunter2-extra!"), "token ***!");
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_short_values_are_not_masked() {
+        let redactor = Redactor::new(vec!["ab".to_string()]);
+        assert!(redactor.is_empty());
+        assert_eq!(redactor.redact("abc"), "abc");
+    }
+
+    #[test]
+    fn test_redact_json() {
+        let redactor = Redactor::new(vec!["s3cret".to_string()]);
+        let mut value = serde_json::json!({
+            "s3cret": ["x s3cret y", 1],
+            "nested": { "stdout": "s3cret" },
+        });
+
+        redactor.redact_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "s3cret": ["x *** y", 1],
+                "nested": { "stdout": "***" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_redacts_secrets_used_later() {
+        let secrets = Secrets::new(Arc::new(MemorySecretProvider::new(vec![(
+            "token".to_string(),
+            "abc123".to_string(),
+        )])));
+        let redactor = Redactor::default().with_secrets(Some(secrets.clone()));
+        assert_eq!(redactor.redact("abc123"), "abc123");
+
+        secrets.get("token").unwrap();
+        assert_eq!(redactor.redact("abc123"), "***");
+    }
+
+    #[test]
+    fn test_stream_redactor_across_chunks() {
+        let redactor = Redactor::new(vec!["hunter2".to_string()]);
+        let mut stream = redactor.stream();
+
+        let mut output = stream.push(b"pass: hun");
+        assert_eq!(output, b"pass: ");
+        output.extend(stream.push(b"ter2\r\nhu"));
+        output.extend(stream.push(b"h"));
+        output.extend(stream.finish());
+
+        assert_eq!(output, b"pass: ***\r\nhuh");
+    }
+
+    #[test]
+    fn test_sensitive_names() {
+        assert!(is_sensitive_name("GITHUB_TOKEN"));
+        assert!(is_sensitive_name("db_password"));
+        assert!(is_sensitive_name("AWS_SECRET_ACCESS_KEY"));
+        assert!(!is_sensitive_name("HOME"));
+        assert!(!is_sensitive_name("AUTHOR"));
+    }
+}
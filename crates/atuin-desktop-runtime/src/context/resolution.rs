@@ -3,7 +3,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use minijinja::{value::Object, Environment, Value};
+use minijinja::{value::Object, Value};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
@@ -11,9 +11,10 @@ use crate::{
     blocks::BlockBehavior,
     client::LocalValueProvider,
     context::{
-        DocumentBlock, DocumentCwd, DocumentEnvVar, DocumentEnvVars, DocumentSshConfig,
-        DocumentSshHost, DocumentVar, DocumentVars,
+        is_sensitive_name, DocumentBlock, DocumentCwd, DocumentEnvVar, DocumentEnvVars,
+        DocumentSshConfig, DocumentSshHost, DocumentVar, DocumentVars, Redactor, Secrets,
     },
+    templates::{template_environment, SECRETS_KEY},
 };
 
 /// A struct representing the resolved context of a block.
@@ -69,6 +70,8 @@ pub struct ContextResolver {
     /// Full SSH configuration from SSH Connect block (includes identity key, overrides, etc.)
     ssh_config: Option<DocumentSshConfig>,
     extra_template_context: HashMap<String, Value>,
+    /// Secrets for the `secret()` template function; templates can't use secrets without them
+    secrets: Option<Secrets>,
 }
 
 impl ContextResolver {
@@ -81,16 +84,31 @@ impl ContextResolver {
             ssh_host: None,
             ssh_config: None,
             extra_template_context: HashMap::new(),
+            secrets: None,
         }
     }
 
+    /// Make secrets available to templates via `{{ secret("name") }}`
+    pub fn set_secrets(&mut self, secrets: Secrets) {
+        self.secrets = Some(secrets);
+    }
+
     pub fn add_extra_template_context(
         &mut self,
         namespace: String,
         context: impl Object + 'static,
     ) {
-        self.extra_template_context
-            .insert(namespace, Value::from_object(context));
+        self.add_extra_template_value(namespace, Value::from_object(context));
+    }
+
+    /// Make a value available to templates under `name`, such as `item` in a loop
+    pub fn add_extra_template_value(&mut self, name: String, value: Value) {
+        self.extra_template_context.insert(name, value);
+    }
+
+    /// A value added with [`Self::add_extra_template_context`] or [`Self::add_extra_template_value`]
+    pub(crate) fn extra_template_value(&self, name: &str) -> Option<&Value> {
+        self.extra_template_context.get(name)
     }
 
     /// Build a resolver from blocks (typically all blocks above the current one)
@@ -122,6 +140,19 @@ impl ContextResolver {
         }
     }
 
+    /// Set a variable that is not defined by any block (e.g. one supplied by the caller)
+    ///
+    /// Blocks pushed afterwards can still override it.
+    pub fn set_var(&mut self, name: String, value: String) {
+        self.vars
+            .insert(name.clone(), DocumentVar::new(name, value.clone(), value));
+    }
+
+    /// Set an environment variable that is not defined by any block
+    pub fn set_env_var(&mut self, name: String, value: String) {
+        self.env_vars.insert(name, value);
+    }
+
     /// Test-only constructor to create a resolver with specific vars
     #[cfg(test)]
     pub fn with_vars(vars: HashMap<String, String>) -> Self {
@@ -138,6 +169,7 @@ impl ContextResolver {
             ssh_host: None,
             ssh_config: None,
             extra_template_context: HashMap::new(),
+            secrets: None,
         }
     }
 
@@ -150,11 +182,8 @@ impl ContextResolver {
         for ctx in [passive_context, active_context] {
             // Process variables first as they can be used in templates
             if let Some(var) = ctx.get::<DocumentVar>() {
-                if let Ok(resolved_value) = self.resolve_template(&var.value) {
-                    self.vars.insert(
-                        var.name.clone(),
-                        DocumentVar::new(var.name.clone(), resolved_value, var.source.clone()),
-                    );
+                if let Ok(resolved) = self.resolve_var(var) {
+                    self.vars.insert(var.name.clone(), resolved);
                 } else {
                     tracing::warn!("Failed to resolve template for variable {}", var.name);
                 }
@@ -163,11 +192,8 @@ impl ContextResolver {
             // Process multiple variables from DocumentVars container
             if let Some(vars) = ctx.get::<DocumentVars>() {
                 for var in vars.iter() {
-                    if let Ok(resolved_value) = self.resolve_template(&var.value) {
-                        self.vars.insert(
-                            var.name.clone(),
-                            DocumentVar::new(var.name.clone(), resolved_value, var.source.clone()),
-                        );
+                    if let Ok(resolved) = self.resolve_var(var) {
+                        self.vars.insert(var.name.clone(), resolved);
                     } else {
                         tracing::warn!("Failed to resolve template for variable {}", var.name);
                     }
@@ -249,6 +275,21 @@ impl ContextResolver {
         }
     }
 
+    /// Resolve a variable's value; only string values are templates, typed values are data
+    fn resolve_var(&self, var: &DocumentVar) -> Result<DocumentVar, minijinja::Error> {
+        let value = match &var.value {
+            serde_json::Value::String(template) => {
+                serde_json::Value::String(self.resolve_template(template)?)
+            }
+            value => value.clone(),
+        };
+
+        Ok(DocumentVar {
+            value,
+            ..var.clone()
+        })
+    }
+
     /// Resolve a template string using minijinja
     pub fn resolve_template(&self, template: &str) -> Result<String, minijinja::Error> {
         // If the string doesn't contain template markers, return it as-is
@@ -256,20 +297,28 @@ impl ContextResolver {
             return Ok(template.to_string());
         }
 
-        // Create a minijinja environment
-        let mut env = Environment::new();
-        env.set_trim_blocks(true);
-
-        // Add custom filter for shell escaping
-        env.add_filter("shellquote", |value: String| -> String {
-            // Use POSIX shell single-quote escaping:
-            // wrap in single quotes and escape any single quotes as '\''
-            format!("'{}'", value.replace('\'', "'\\''"))
-        });
+        // Render the template with the shared environment and its filters
+        template_environment().render_str(template, self.template_context())
+    }
 
-        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+    /// Evaluate a single expression, such as `var.count > 3`, to a value
+    ///
+    /// The expression may be wrapped in `{{ }}`, as it would be in a template.
+    pub fn evaluate_expression(&self, expression: &str) -> Result<Value, minijinja::Error> {
+        let expression = expression.trim();
+        let expression = expression
+            .strip_prefix("{{")
+            .and_then(|e| e.strip_suffix("}}"))
+            .unwrap_or(expression)
+            .trim();
+
+        template_environment()
+            .compile_expression(expression)?
+            .eval(self.template_context())
+    }
 
-        // Build the context object for template rendering
+    /// The values templates and expressions are rendered with
+    fn template_context(&self) -> HashMap<&str, Value> {
         let mut context: HashMap<&str, Value> = HashMap::new();
 
         // Add any extra template context
@@ -284,23 +333,40 @@ impl ContextResolver {
             Value::from_object(
                 self.vars
                     .iter()
-                    .map(|(k, v)| (k.clone(), Value::from(v.value.clone())))
+                    .map(|(k, v)| (k.clone(), Value::from_serialize(&v.value)))
                     .collect::<HashMap<String, Value>>(),
             ),
         );
         context.insert("env", Value::from_object(self.env_vars.clone()));
 
-        // Render the template
-        env.render_str(template, context)
+        // Secrets are read by the `secret()` function, not exposed directly
+        if let Some(secrets) = &self.secrets {
+            context.insert(SECRETS_KEY, Value::from_object(secrets.clone()));
+        }
+
+        context
     }
 
-    /// Get a variable value
-    pub fn get_var(&self, name: &str) -> Option<&String> {
+    /// Get a variable value as text
+    pub fn get_var(&self, name: &str) -> Option<String> {
+        self.vars.get(name).map(DocumentVar::value_string)
+    }
+
+    /// Get a variable value as it was set, which may be a list, object or number
+    pub fn get_typed_var(&self, name: &str) -> Option<&serde_json::Value> {
         self.vars.get(name).map(|v| &v.value)
     }
 
-    /// Get all variables
+    /// Get all variables as text
     pub fn vars(&self) -> HashMap<String, String> {
+        self.vars
+            .iter()
+            .map(|(k, v)| (k.clone(), v.value_string()))
+            .collect()
+    }
+
+    /// Get all variables as they were set
+    pub fn typed_vars(&self) -> HashMap<String, serde_json::Value> {
         self.vars
             .iter()
             .map(|(k, v)| (k.clone(), v.value.clone()))
@@ -334,6 +400,38 @@ impl ContextResolver {
     pub fn ssh_config(&self) -> Option<&DocumentSshConfig> {
         self.ssh_config.as_ref()
     }
+
+    /// Values that must not be shown: secrets templates have used so far, sensitive
+    /// variables, and environment variables with names like `API_TOKEN`
+    pub fn sensitive_values(&self) -> Vec<String> {
+        self.secrets
+            .as_ref()
+            .map(Secrets::sensitive_values)
+            .unwrap_or_default()
+            .into_iter()
+            .chain(self.sensitive_context_values())
+            .collect()
+    }
+
+    /// A redactor that masks this context's sensitive values, including secrets used later
+    pub fn redactor(&self) -> Redactor {
+        Redactor::new(self.sensitive_context_values()).with_secrets(self.secrets.clone())
+    }
+
+    fn sensitive_context_values(&self) -> Vec<String> {
+        let vars = self
+            .vars
+            .values()
+            .filter(|var| var.sensitive)
+            .map(DocumentVar::value_string);
+        let env_vars = self
+            .env_vars
+            .iter()
+            .filter(|(name, _)| is_sensitive_name(name))
+            .map(|(_, value)| value.clone());
+
+        vars.chain(env_vars).collect()
+    }
 }
 
 fn default_cwd() -> String {
@@ -412,6 +510,7 @@ impl ContextResolver {
             ssh_host: parent.ssh_host.clone(),
             ssh_config: parent.ssh_config.clone(),
             extra_template_context: parent.extra_template_context.clone(),
+            secrets: parent.secrets.clone(),
         }
     }
 }
@@ -481,17 +580,13 @@ impl ContextResolverBuilder {
 
     pub fn build(self) -> ContextResolver {
         ContextResolver {
-            vars: self
-                .vars
-                .unwrap_or_default()
-                .into_iter()
-                .map(|(k, v)| (k.clone(), DocumentVar::new(k, v.value, v.source)))
-                .collect(),
+            vars: self.vars.unwrap_or_default(),
             cwd: self.cwd.unwrap_or_default(),
             env_vars: self.env_vars.unwrap_or_default(),
             ssh_host: self.ssh_host,
             ssh_config: None,
             extra_template_context: self.extra_template_context.unwrap_or_default(),
+            secrets: None,
         }
     }
 }
@@ -847,6 +942,33 @@ mod tests {
         assert_eq!(resolver.get_var("key").unwrap(), "active");
     }
 
+    #[test]
+    fn test_seeded_values_are_inherited_and_overridable() {
+        let mut parent = ContextResolver::new();
+        parent.set_var("env_name".to_string(), "staging".to_string());
+        parent.set_var("region".to_string(), "eu-west-1".to_string());
+        parent.set_env_var("DEPLOY_TOKEN".to_string(), "abc".to_string());
+
+        let mut passive_context = BlockContext::new();
+        passive_context.insert(DocumentVar::new(
+            "region".to_string(),
+            "us-east-1".to_string(),
+            "us-east-1".to_string(),
+        ));
+        let block = create_block_with_context(passive_context, None);
+
+        let resolver = ContextResolver::from_blocks_with_parent(&[block], &parent);
+        assert_eq!(resolver.get_var("env_name").unwrap(), "staging");
+        assert_eq!(resolver.get_var("region").unwrap(), "us-east-1");
+        assert_eq!(resolver.env_vars().get("DEPLOY_TOKEN").unwrap(), "abc");
+        assert_eq!(
+            resolver
+                .resolve_template("{{ var.env_name }}-{{ env.DEPLOY_TOKEN }}")
+                .unwrap(),
+            "staging-abc"
+        );
+    }
+
     #[test]
     fn test_resolved_context_from_resolver() {
         let resolver = ContextResolverBuilder::new()
@@ -1057,4 +1179,134 @@ mod tests {
         resolver.push_block(&block);
         assert_eq!(resolver.ssh_host(), None);
     }
+
+    #[test]
+    fn test_secret_function_fetches_lazily_and_caches() {
+        use crate::client::secrets::MemorySecretProvider;
+        use std::sync::Arc;
+
+        let provider = Arc::new(MemorySecretProvider::new(vec![(
+            "db-password".to_string(),
+            "hunter2".to_string(),
+        )]));
+        let mut resolver = ContextResolver::new();
+        resolver.set_secrets(Secrets::new(provider.clone()));
+
+        assert_eq!(
+            resolver.resolve_template("no secrets here").unwrap(),
+            "no secrets here"
+        );
+        assert_eq!(provider.lookups(), 0);
+        assert!(resolver.sensitive_values().is_empty());
+
+        assert_eq!(
+            resolver
+                .resolve_template(r#"psql -W {{ secret("db-password") }}"#)
+                .unwrap(),
+            "psql -W hunter2"
+        );
+        // Clones (e.g. for sub-runbooks) share the cache
+        let child = ContextResolver::from_parent(&resolver);
+        assert_eq!(
+            child
+                .resolve_template(r#"{{ secret("db-password") }}"#)
+                .unwrap(),
+            "hunter2"
+        );
+        assert_eq!(provider.lookups(), 1);
+        assert_eq!(resolver.sensitive_values(), vec!["hunter2".to_string()]);
+
+        let err = resolver
+            .resolve_template(r#"{{ secret("missing") }}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("secret 'missing' does not exist"));
+    }
+
+    #[test]
+    fn test_secret_function_without_provider() {
+        let resolver = ContextResolver::new();
+        let err = resolver
+            .resolve_template(r#"{{ secret("token") }}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("secrets are not available"));
+    }
+
+    #[test]
+    fn test_redactor_masks_sensitive_vars_and_env_vars() {
+        let mut resolver = ContextResolver::new();
+
+        let mut passive_context = BlockContext::new();
+        passive_context.insert(DocumentVar::new_sensitive(
+            "db_password".to_string(),
+            "hunter2".to_string(),
+            "hunter2".to_string(),
+        ));
+        passive_context.insert(DocumentEnvVar(
+            "GITHUB_TOKEN".to_string(),
+            "ghp_abcdef".to_string(),
+        ));
+        resolver.push_block(&create_block_with_context(passive_context, None));
+
+        let mut passive_context = BlockContext::new();
+        passive_context.insert(DocumentVar::new(
+            "user".to_string(),
+            "admin".to_string(),
+            "admin".to_string(),
+        ));
+        resolver.push_block(&create_block_with_context(passive_context, None));
+
+        assert_eq!(
+            resolver.redactor().redact("admin:hunter2 with ghp_abcdef"),
+            "admin:*** with ***"
+        );
+    }
+
+    #[test]
+    fn test_typed_vars_are_native_in_templates() {
+        let mut resolver = ContextResolver::new();
+
+        let mut passive_context = BlockContext::new();
+        passive_context.insert(DocumentVar::new_typed(
+            "hosts".to_string(),
+            serde_json::json!(["web-1", "web-2"]),
+            "test".to_string(),
+        ));
+        resolver.push_block(&create_block_with_context(passive_context, None));
+
+        assert_eq!(
+            resolver
+                .resolve_template("{{ var.hosts | length }} {{ var.hosts[1] }}")
+                .unwrap(),
+            "2 web-2"
+        );
+        assert_eq!(
+            resolver.get_typed_var("hosts"),
+            Some(&serde_json::json!(["web-1", "web-2"]))
+        );
+        assert_eq!(resolver.get_var("hosts").unwrap(), r#"["web-1","web-2"]"#);
+    }
+
+    #[test]
+    fn test_evaluate_expression() {
+        let mut vars = HashMap::new();
+        vars.insert("count".to_string(), "5".to_string());
+        let resolver = ContextResolver::with_vars(vars);
+
+        assert!(resolver
+            .evaluate_expression("var.count | int > 3")
+            .unwrap()
+            .is_true());
+        assert!(!resolver
+            .evaluate_expression("{{ var.count == '4' }}")
+            .unwrap()
+            .is_true());
+        assert_eq!(
+            resolver
+                .evaluate_expression("[1, 2] | length")
+                .unwrap()
+                .as_usize(),
+            Some(2)
+        );
+        assert!(resolver.evaluate_expression("var.missing.field").is_err());
+    }
 }
@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use minijinja::{value::Object, Error, ErrorKind};
+
+use crate::client::SecretProvider;
+
+/// Secrets available to templates through the `secret()` function
+///
+/// Each secret is fetched from the provider the first time a template uses it, then cached.
+/// Clones share the cache, so a secret is fetched once for a whole document. Every value
+/// fetched is sensitive, and can be listed with [`Secrets::sensitive_values`] to hide it
+/// from output.
+#[derive(Clone)]
+pub struct Secrets {
+    provider: Arc<dyn SecretProvider>,
+    cache: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Secrets {
+    pub fn new(provider: Arc<dyn SecretProvider>) -> Self {
+        Self {
+            provider,
+            cache: Default::default(),
+        }
+    }
+
+    /// Get a secret, fetching it from the provider if it hasn't been used yet
+    pub fn get(&self, name: &str) -> Result<String, Error> {
+        if let Some(value) = self.cache.lock().unwrap().get(name) {
+            return Ok(value.clone());
+        }
+
+        let value = self
+            .provider
+            .get_secret(name)
+            .map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("failed to look up secret '{name}': {e}"),
+                )
+            })?
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("secret '{name}' does not exist"),
+                )
+            })?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Values of every secret fetched so far
+    pub fn sensitive_values(&self) -> Vec<String> {
+        self.cache.lock().unwrap().values().cloned().collect()
+    }
+}
+
+// Passed to templates in the render context, so `secret()` can find it
+impl Object for Secrets {}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the values themselves
+        let names = self
+            .cache
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        f.debug_struct("Secrets").field("cached", &names).finish()
+    }
+}
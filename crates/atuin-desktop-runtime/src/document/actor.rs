@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use crate::blocks::Block;
 use crate::client::{
-    DocumentBridgeMessage, LocalValueProvider, MessageChannel, RunbookContentLoader,
+    DocumentBridgeMessage, LocalValueProvider, MessageChannel, RunbookContentLoader, SecretProvider,
 };
 use crate::context::{
     BlockContext, BlockContextStorage, BlockExecutionOutput, BlockState, BlockStateUpdater,
@@ -24,6 +24,9 @@ pub enum DocumentError {
     #[error("Block not found: {0}")]
     BlockNotFound(Uuid),
 
+    #[error("Block {0} only runs as part of the block it is nested in")]
+    NestedBlock(Uuid),
+
     #[error("Failed to send command to document actor")]
     ActorSendError,
 
@@ -53,6 +56,12 @@ pub enum DocumentError {
 
     #[error("Failed to downcast block execution output")]
     ExecutionOutputDowncastError,
+
+    #[error("The prompt was cancelled before it was answered")]
+    PromptCancelled,
+
+    #[error("The prompt timed out before it was answered")]
+    PromptTimedOut,
 }
 
 impl<T> From<mpsc::error::SendError<T>> for DocumentError {
@@ -173,6 +182,14 @@ pub(crate) enum DocumentCommand {
     /// Set parent context for sub-runbook execution
     SetParentContext {
         parent: Arc<ContextResolver>,
+        /// Whether the document's blocks are nested in the parent block, like a loop's children
+        enclosing: bool,
+        reply: Reply<()>,
+    },
+
+    /// Set the provider for secrets used in templates
+    SetSecretProvider {
+        provider: Arc<dyn SecretProvider>,
         reply: Reply<()>,
     },
 
@@ -488,6 +505,21 @@ impl DocumentHandle {
         }
     }
 
+    /// Get a block's execution output without knowing its type, such as to expose it to templates
+    pub async fn get_block_execution_output_dyn(
+        &self,
+        block_id: Uuid,
+    ) -> Result<Option<Arc<dyn BlockExecutionOutput>>, DocumentError> {
+        let (tx, rx) = oneshot::channel();
+        self.command_tx
+            .send(DocumentCommand::GetBlockExecutionOutput {
+                block_id,
+                reply: tx,
+            })
+            .map_err(|_| DocumentError::ActorSendError)?;
+        rx.await.map_err(|_| DocumentError::ActorSendError)?
+    }
+
     /// Get a block's execution output as a serialized value
     ///
     /// If all you want to do with the output is serialize it, this saves a clone
@@ -563,6 +595,11 @@ impl DocumentHandle {
         Ok(())
     }
 
+    /// Wait until the document actor has shut down
+    pub async fn closed(&self) {
+        self.command_tx.closed().await
+    }
+
     /// Reset the document state
     pub async fn reset_state(&self) -> Result<(), DocumentError> {
         let (tx, rx) = oneshot::channel();
@@ -580,7 +617,44 @@ impl DocumentHandle {
     ) -> Result<(), DocumentError> {
         let (tx, rx) = oneshot::channel();
         self.command_tx
-            .send(DocumentCommand::SetParentContext { parent, reply: tx })
+            .send(DocumentCommand::SetParentContext {
+                parent,
+                enclosing: false,
+                reply: tx,
+            })
+            .map_err(|_| DocumentError::ActorSendError)?;
+        rx.await.map_err(|_| DocumentError::ActorSendError)?
+    }
+
+    /// Set the context of the block this document's blocks are nested in, such as a loop
+    /// Like `set_parent_context`, but blocks can also refer to the parent document's named blocks
+    pub async fn set_enclosing_context(
+        &self,
+        parent: Arc<ContextResolver>,
+    ) -> Result<(), DocumentError> {
+        let (tx, rx) = oneshot::channel();
+        self.command_tx
+            .send(DocumentCommand::SetParentContext {
+                parent,
+                enclosing: true,
+                reply: tx,
+            })
+            .map_err(|_| DocumentError::ActorSendError)?;
+        rx.await.map_err(|_| DocumentError::ActorSendError)?
+    }
+
+    /// Set the provider for secrets used in templates, as `{{ secret("name") }}`
+    /// Secrets are fetched the first time a template uses them, then cached by the document
+    pub async fn set_secret_provider(
+        &self,
+        provider: Arc<dyn SecretProvider>,
+    ) -> Result<(), DocumentError> {
+        let (tx, rx) = oneshot::channel();
+        self.command_tx
+            .send(DocumentCommand::SetSecretProvider {
+                provider,
+                reply: tx,
+            })
             .map_err(|_| DocumentError::ActorSendError)?;
         rx.await.map_err(|_| DocumentError::ActorSendError)?
     }
@@ -767,8 +841,20 @@ impl DocumentActor {
                     let result = self.handle_reset_state().await;
                     let _ = reply.send(result);
                 }
-                DocumentCommand::SetParentContext { parent, reply } => {
-                    self.document.set_parent_context(parent);
+                DocumentCommand::SetParentContext {
+                    parent,
+                    enclosing,
+                    reply,
+                } => {
+                    if enclosing {
+                        self.document.set_enclosing_context(parent);
+                    } else {
+                        self.document.set_parent_context(parent);
+                    }
+                    let _ = reply.send(Ok(()));
+                }
+                DocumentCommand::SetSecretProvider { provider, reply } => {
+                    self.document.set_secret_provider(provider);
                     let _ = reply.send(Ok(()));
                 }
                 DocumentCommand::GetContextResolver { reply } => {
@@ -1006,9 +1092,15 @@ impl DocumentActor {
             output
                 .erased_serialize(&mut erased)
                 .map_err(|e| DocumentError::ExecutionOutputSerializationError(e.to_string()))?;
-            Ok(Some(serde_json::from_slice(&buf).map_err(|e| {
-                DocumentError::ExecutionOutputSerializationError(e.to_string())
-            })?))
+            let mut value: Value = serde_json::from_slice(&buf)
+                .map_err(|e| DocumentError::ExecutionOutputSerializationError(e.to_string()))?;
+
+            // Outputs are read by other blocks, the exec log and AI, so hide secrets first
+            self.document
+                .get_context_resolver()
+                .redactor()
+                .redact_json(&mut value);
+            Ok(Some(value))
         } else {
             Ok(None)
         }
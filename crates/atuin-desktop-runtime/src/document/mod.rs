@@ -22,11 +22,14 @@ use uuid::Uuid;
 use crate::templates::DocumentTemplateState;
 
 use crate::{
-    blocks::{Block, KNOWN_UNSUPPORTED_BLOCKS},
-    client::{DocumentBridgeMessage, LocalValueProvider, MessageChannel, RunbookContentLoader},
+    blocks::{Block, KNOWN_UNSUPPORTED_BLOCKS, SCOPED_CONTAINER_BLOCKS},
+    client::{
+        DocumentBridgeMessage, LocalValueProvider, MessageChannel, RunbookContentLoader,
+        SecretProvider,
+    },
     context::{
-        BlockContext, BlockContextStorage, BlockState, ContextResolver, DocumentBlock,
-        ResolvedContext,
+        BlockContext, BlockContextStorage, BlockState, ContextResolver, DocumentBlock, Redactor,
+        ResolvedContext, Secrets,
     },
     events::{EventBus, GCEvent},
     execution::{ExecutionContext, ExecutionHandle},
@@ -49,9 +52,15 @@ pub(crate) struct Document {
     /// Parent context resolver for sub-runbooks. When set, this document inherits
     /// vars, env_vars, cwd, and ssh_host from the parent.
     pub(crate) parent_context: Option<Arc<ContextResolver>>,
+    /// Whether `doc.named` also finds the named blocks of the parent's document, for documents
+    /// made of the children of a block such as a loop
+    pub(crate) inherit_named_blocks: bool,
     /// The workspace root path, if this document belongs to an offline workspace.
     /// Used for template resolution (e.g., `{{ workspace.root }}`).
     pub(crate) workspace_root: Option<String>,
+    /// Secrets for the `secret()` template function, shared by every resolver built for
+    /// this document so each secret is fetched once
+    pub(crate) secrets: Option<Secrets>,
     /// Tracks the last ResolvedContext sent to the frontend for each block.
     /// Used to avoid sending redundant BlockContextUpdate messages when the
     /// resolved context hasn't actually changed.
@@ -78,7 +87,9 @@ impl Document {
             context_storage,
             runbook_loader,
             parent_context: None,
+            inherit_named_blocks: false,
             workspace_root,
+            secrets: None,
             last_sent_contexts: HashMap::new(),
         };
         doc.put_document(document).await?;
@@ -287,7 +298,7 @@ impl Document {
         for node in nodes {
             out.push(node.clone());
 
-            if let Some(children) = node.get("children").and_then(|v| v.as_array()) {
+            if let Some(children) = unscoped_children(node) {
                 Self::flatten_recursive(children, out)?;
             }
         }
@@ -305,6 +316,24 @@ impl Document {
         self.blocks.iter().position(|block| &block.id() == block_id)
     }
 
+    /// The outermost scoped container block, such as a loop, that a block is nested in
+    pub fn enclosing_container(&self, block_id: &Uuid) -> Option<Uuid> {
+        let block_id = block_id.to_string();
+        flatten_document(&self.raw)
+            .iter()
+            .filter(|node| {
+                let block_type = node.get("type").and_then(|t| t.as_str());
+                block_type.is_some_and(|t| SCOPED_CONTAINER_BLOCKS.contains(&t))
+            })
+            .find(|node| {
+                node.get("children")
+                    .and_then(|c| c.as_array())
+                    .is_some_and(|children| contains_block(children, &block_id))
+            })
+            .and_then(|node| node.get("id").and_then(|id| id.as_str()))
+            .and_then(|id| Uuid::parse_str(id).ok())
+    }
+
     /// Get a block's context
     pub fn get_block(&self, block_id: &Uuid) -> Option<&DocumentBlock> {
         let index = self.get_block_index(block_id)?;
@@ -328,10 +357,25 @@ impl Document {
     /// Set the parent context for this document (used for sub-runbooks)
     pub fn set_parent_context(&mut self, parent: Arc<ContextResolver>) {
         self.parent_context = Some(parent);
+        self.inherit_named_blocks = false;
     }
 
-    /// Get the current context resolver (includes all blocks and parent context)
-    pub fn get_context_resolver(&self) -> ContextResolver {
+    /// Set the context of the block this document's blocks are nested in, such as a loop
+    ///
+    /// Like [`Self::set_parent_context`], but blocks can also refer to the named blocks of the
+    /// enclosing document.
+    pub fn set_enclosing_context(&mut self, parent: Arc<ContextResolver>) {
+        self.parent_context = Some(parent);
+        self.inherit_named_blocks = true;
+    }
+
+    /// Set the provider for secrets used in templates, discarding any cached secrets
+    pub fn set_secret_provider(&mut self, provider: Arc<dyn SecretProvider>) {
+        self.secrets = Some(Secrets::new(provider));
+    }
+
+    /// A resolver with the parent context, workspace and secrets, but no blocks yet
+    fn base_resolver(&self) -> ContextResolver {
         let mut resolver = match &self.parent_context {
             Some(parent) => ContextResolver::from_parent(parent),
             None => ContextResolver::new(),
@@ -343,6 +387,17 @@ impl Document {
             resolver.add_extra_template_context("workspace".to_string(), workspace_context);
         }
 
+        // Sub-runbooks without a provider of their own keep using their parent's secrets
+        if let Some(secrets) = &self.secrets {
+            resolver.set_secrets(secrets.clone());
+        }
+
+        resolver
+    }
+
+    /// Get the current context resolver (includes all blocks and parent context)
+    pub fn get_context_resolver(&self) -> ContextResolver {
+        let mut resolver = self.base_resolver();
         resolver.push_blocks(&self.blocks);
         resolver
     }
@@ -359,9 +414,13 @@ impl Document {
         extra_template_context: Option<HashMap<String, HashMap<String, String>>>,
     ) -> Result<ExecutionContext, DocumentError> {
         // Verify block exists
-        let _block = self
-            .get_block(block_id)
-            .ok_or(DocumentError::BlockNotFound(*block_id))?;
+        let _block = self.get_block(block_id).ok_or_else(|| {
+            if self.enclosing_container(block_id).is_some() {
+                DocumentError::NestedBlock(*block_id)
+            } else {
+                DocumentError::BlockNotFound(*block_id)
+            }
+        })?;
 
         // Find the block's position in the document
         let position = self
@@ -370,17 +429,7 @@ impl Document {
 
         // Build context resolver - add extra context BEFORE processing blocks
         // so that templates like {{ workspace.root }} can resolve during block processing
-        let mut context_resolver = match &self.parent_context {
-            Some(parent) => ContextResolver::from_parent(parent),
-            None => ContextResolver::new(),
-        };
-
-        // Add workspace template context first (before blocks are processed)
-        if let Some(ref workspace_root) = self.workspace_root {
-            let mut workspace_context = HashMap::new();
-            workspace_context.insert("root".to_string(), workspace_root.clone());
-            context_resolver.add_extra_template_context("workspace".to_string(), workspace_context);
-        }
+        let mut context_resolver = self.base_resolver();
 
         // Add any extra template context passed by caller
         if let Some(extra_template_context) = extra_template_context {
@@ -397,17 +446,8 @@ impl Document {
         // Now process blocks - templates will resolve against the context we just set up
         context_resolver.push_blocks(&self.blocks[..position]);
 
-        let block_outputs = self
-            .blocks
-            .iter()
-            .map(|block| (block.id().to_string(), block.execution_output().clone()))
-            .collect::<HashMap<_, _>>();
-
-        let document_template_context = DocumentTemplateState::new(
-            flatten_document(&self.raw).as_slice(),
-            Some(&block_id.to_string()),
-            block_outputs,
-        );
+        let document_template_context =
+            self.document_template_state(block_id, context_resolver.redactor());
 
         if let Some(document_template_context) = document_template_context {
             context_resolver
@@ -437,22 +477,49 @@ impl Document {
             .build())
     }
 
+    /// The `doc` templates see when rendered for a block
+    fn document_template_state(
+        &self,
+        block_id: &Uuid,
+        redactor: Redactor,
+    ) -> Option<DocumentTemplateState> {
+        let block_outputs = self
+            .blocks
+            .iter()
+            .map(|block| (block.id().to_string(), block.execution_output()))
+            .collect::<HashMap<_, _>>();
+
+        let mut state = DocumentTemplateState::new(
+            flatten_document(&self.raw).as_slice(),
+            Some(&block_id.to_string()),
+            block_outputs,
+            redactor,
+        )?;
+
+        let enclosing = self
+            .parent_context
+            .as_ref()
+            .filter(|_| self.inherit_named_blocks)
+            .and_then(|parent| parent.extra_template_value("doc"))
+            .and_then(|doc| doc.downcast_object_ref::<DocumentTemplateState>());
+        if let Some(enclosing) = enclosing {
+            state.inherit_named(enclosing);
+        }
+
+        Some(state)
+    }
+
+    /// Get the context a block sees. Blocks nested in a loop see the context of the loop
     pub fn get_resolved_context(&self, block_id: &Uuid) -> Result<ResolvedContext, DocumentError> {
         let position = self
             .get_block_index(block_id)
+            .or_else(|| {
+                self.enclosing_container(block_id)
+                    .and_then(|container| self.get_block_index(&container))
+            })
             .ok_or(DocumentError::BlockNotFound(*block_id))?;
 
-        let mut resolver = match &self.parent_context {
-            Some(parent) => ContextResolver::from_parent(parent),
-            None => ContextResolver::new(),
-        };
-
-        if let Some(ref workspace_root) = self.workspace_root {
-            let mut workspace_context = HashMap::new();
-            workspace_context.insert("root".to_string(), workspace_root.clone());
-            resolver.add_extra_template_context("workspace".to_string(), workspace_context);
-        }
-
+        let mut resolver = self.base_resolver();
         resolver.push_blocks(&self.blocks[..position]);
         Ok(ResolvedContext::from_resolver(&resolver))
     }
@@ -494,17 +561,7 @@ impl Document {
 
         // Build context resolver - add extra context BEFORE processing blocks
         // so that templates like {{ workspace.root }} can resolve during block processing
-        let mut context_resolver = match &self.parent_context {
-            Some(parent) => ContextResolver::from_parent(parent),
-            None => ContextResolver::new(),
-        };
-
-        // Add workspace template context first (before blocks are processed)
-        if let Some(ref workspace_root) = self.workspace_root {
-            let mut workspace_context = HashMap::new();
-            workspace_context.insert("root".to_string(), workspace_root.clone());
-            context_resolver.add_extra_template_context("workspace".to_string(), workspace_context);
-        }
+        let mut context_resolver = self.base_resolver();
 
         // Now process blocks[..start] with workspace context available
         context_resolver.push_blocks(&self.blocks[..start]);
@@ -513,17 +570,8 @@ impl Document {
             let block_id = self.blocks[i].id();
 
             // Build DocumentTemplateState so blocks can access doc.named[name].output etc.
-            let block_outputs = self
-                .blocks
-                .iter()
-                .map(|block| (block.id().to_string(), block.execution_output()))
-                .collect::<HashMap<_, _>>();
-
-            let document_template_context = DocumentTemplateState::new(
-                flatten_document(&self.raw).as_slice(),
-                Some(&block_id.to_string()),
-                block_outputs,
-            );
+            let document_template_context =
+                self.document_template_state(&block_id, context_resolver.redactor());
 
             if let Some(document_template_context) = document_template_context {
                 context_resolver
@@ -663,11 +711,14 @@ impl Document {
 
 /// Flatten a document to include nested blocks (like those in ToggleHeading children)
 /// This creates a linear execution order regardless of UI nesting structure
+///
+/// Children of [scoped container blocks](SCOPED_CONTAINER_BLOCKS) are left out, as they only
+/// run as part of their parent.
 pub fn flatten_document(doc: &[serde_json::Value]) -> Vec<serde_json::Value> {
     let mut flattened = Vec::with_capacity(doc.len());
     for block in doc {
         flattened.push(block.clone());
-        if let Some(children) = block.get("children").and_then(|c| c.as_array()) {
+        if let Some(children) = unscoped_children(block) {
             if !children.is_empty() {
                 flattened.extend(flatten_document(children));
             }
@@ -675,3 +726,27 @@ pub fn flatten_document(doc: &[serde_json::Value]) -> Vec<serde_json::Value> {
     }
     flattened
 }
+
+/// Whether a block with the given ID is among `nodes` or their children
+fn contains_block(nodes: &[serde_json::Value], block_id: &str) -> bool {
+    nodes.iter().any(|node| {
+        node.get("id").and_then(|id| id.as_str()) == Some(block_id)
+            || node
+                .get("children")
+                .and_then(|c| c.as_array())
+                .is_some_and(|children| contains_block(children, block_id))
+    })
+}
+
+/// A block's children, unless they are scoped to the block
+fn unscoped_children(block: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
+    let block_type = block
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    if SCOPED_CONTAINER_BLOCKS.contains(&block_type) {
+        return None;
+    }
+
+    block.get("children").and_then(|c| c.as_array())
+}
@@ -96,6 +96,15 @@ pub enum GCEvent {
         valid_from: String,
     },
 
+    /// The server presented a different host key than the one in known_hosts
+    /// The connection was refused and the block failed
+    SshHostKeyMismatch {
+        host: String,
+        fingerprint: String,
+        known_hosts_path: String,
+        line: usize,
+    },
+
     /// Runbook execution started
     RunbookStarted { runbook_id: Uuid },
 
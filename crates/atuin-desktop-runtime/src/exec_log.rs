@@ -1,21 +1,32 @@
 //! Execution logging for block executions
 //!
 //! This module provides a SQLite-based logging system for tracking block execution
-//! history, including timestamps and outputs.
+//! history, including timestamps, outputs, exit codes and what triggered each run.
 
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 use sqlx::{
-    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
-    Row, SqlitePool,
+    sqlite::{
+        SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous,
+    },
+    QueryBuilder, Row, Sqlite, SqlitePool,
 };
-use std::{fs, path::PathBuf, str::FromStr, time::Duration};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr, time::Duration};
 use tokio::sync::{mpsc, oneshot};
 use tracing::debug;
+use ts_rs::TS;
+use typed_builder::TypedBuilder;
 use uuid::Uuid;
 
 use eyre::Result;
 
 use super::blocks::Block;
+use super::context::ContextResolver;
+use super::execution::ExecutionResult;
+
+/// Executions that count as a block having run, for dependency checks. Rows logged before
+/// results were recorded have no result, and were only ever logged on completion.
+const SUCCESSFUL: &str = "(exec_log.result IS NULL OR exec_log.result = 'success')";
 
 /// Database record for a block tracked in the execution log
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,18 +37,160 @@ pub struct ExecLogBlock {
     pub uuid: Uuid,
 }
 
+/// Who or what started a block execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub enum ExecTrigger {
+    /// Run by hand from the app
+    User,
+    /// Run as part of a workflow
+    Workflow { id: Uuid },
+    /// Run first because another block depends on it
+    Dependency { dependent: Uuid },
+    /// Run by `atuin-run`
+    Cli,
+}
+
+/// A block execution to be logged
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct ExecRecord {
+    pub block_id: Uuid,
+    #[builder(default, setter(strip_option))]
+    pub runbook_id: Option<Uuid>,
+    /// Start time, in nanoseconds since the epoch
+    pub start_time: u64,
+    /// End time, in nanoseconds since the epoch
+    pub end_time: u64,
+    #[builder(default, setter(into))]
+    pub output: String,
+    #[builder(default)]
+    pub exit_code: Option<i32>,
+    #[builder(default = ExecutionResult::Success)]
+    pub result: ExecutionResult,
+    /// See [`fingerprint`]
+    #[builder(default)]
+    pub fingerprint: Option<String>,
+    #[builder(default = ExecTrigger::User)]
+    pub trigger: ExecTrigger,
+}
+
 /// Database record for a single block execution
-#[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
 pub struct ExecLogEntry {
     /// Database-assigned ID
+    #[ts(type = "number")]
     pub id: u64,
-    /// Foreign key to ExecLogBlock
-    pub block_id: u64,
-    /// Execution timestamp
-    pub timestamp: u64,
+    pub block_id: Uuid,
+    pub runbook_id: Option<Uuid>,
+    /// Start time, in nanoseconds since the epoch
+    #[ts(type = "number")]
+    pub start_time: u64,
+    /// End time, in nanoseconds since the epoch
+    #[ts(type = "number")]
+    pub end_time: u64,
     /// Execution output
     pub output: String,
+    pub exit_code: Option<i32>,
+    /// `None` for executions logged before results were recorded
+    pub result: Option<ExecutionResult>,
+    pub fingerprint: Option<String>,
+    pub trigger: Option<ExecTrigger>,
+}
+
+impl ExecLogEntry {
+    pub fn duration(&self) -> Duration {
+        Duration::from_nanos(self.end_time.saturating_sub(self.start_time))
+    }
+
+    fn from_row(row: &SqliteRow) -> Result<Self> {
+        let uuid = |column: &str| -> Result<Option<Uuid>> {
+            let value: Option<String> = row.try_get(column)?;
+            Ok(value.map(|v| Uuid::parse_str(&v)).transpose()?)
+        };
+        let result: Option<String> = row.try_get("result")?;
+        let trigger: Option<String> = row.try_get("triggered_by")?;
+
+        Ok(Self {
+            id: row.try_get::<i64, _>("id")? as u64,
+            block_id: uuid("uuid")?.unwrap_or_default(),
+            runbook_id: uuid("runbook_id")?,
+            start_time: row.try_get::<i64, _>("start_time")? as u64,
+            end_time: row.try_get::<i64, _>("end_time")? as u64,
+            output: row.try_get("output")?,
+            exit_code: row.try_get("exit_code")?,
+            result: result
+                .map(|r| serde_json::from_value(serde_json::Value::String(r)))
+                .transpose()?,
+            fingerprint: row.try_get("fingerprint")?,
+            trigger: trigger.map(|t| serde_json::from_str(&t)).transpose()?,
+        })
+    }
+}
+
+/// Filters for browsing the execution log; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+#[ts(export)]
+pub struct ExecLogQuery {
+    pub block_id: Option<Uuid>,
+    pub runbook_id: Option<Uuid>,
+    pub result: Option<ExecutionResult>,
+    /// Only executions that ended at or after this time, in nanoseconds since the epoch
+    #[ts(type = "number | null")]
+    pub since: Option<u64>,
+    /// Return at most this many executions, most recent first
+    pub limit: Option<u32>,
+}
+
+/// How much of the execution log to keep
+///
+/// The most recent successful execution of each block is always kept, so pruning never
+/// changes whether a block's dependencies are met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Remove executions that ended longer ago than this
+    pub max_age: Option<Duration>,
+    /// Keep at most this many executions of each block
+    pub max_runs_per_block: Option<u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+            max_runs_per_block: Some(1000),
+        }
+    }
+}
+
+/// Hash of the inputs a block ran with: its own definition plus the directory,
+/// environment, host and variables it resolved
+///
+/// Two executions with the same fingerprint ran the same thing in the same context.
+pub fn fingerprint(block: &Block, resolver: &ContextResolver) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(serde_json::to_string(block).unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(resolver.cwd());
+    hasher.update([0]);
+    hasher.update(resolver.ssh_host().map(String::as_str).unwrap_or_default());
+    for (key, value) in resolver.env_vars().iter().collect::<BTreeMap<_, _>>() {
+        hasher.update([0]);
+        hasher.update(format!("env.{key}={value}"));
+    }
+    for (key, value) in resolver.vars().into_iter().collect::<BTreeMap<_, _>>() {
+        hasher.update([0]);
+        hasher.update(format!("var.{key}={value}"));
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
 /// Messages for interacting with the execution log actor
@@ -56,16 +209,21 @@ pub enum ExecLogMessage {
         reply_to: oneshot::Sender<Result<ExecLogBlock>>,
     },
     LogExecution {
-        block_id: Uuid,
-        start_time: u64,
-        end_time: u64,
-        output: String,
+        record: ExecRecord,
         reply_to: oneshot::Sender<Result<()>>,
     },
     GetLastExecutionTime {
         block_id: Uuid,
         reply_to: oneshot::Sender<Result<Option<u64>>>,
     },
+    Query {
+        query: ExecLogQuery,
+        reply_to: oneshot::Sender<Result<Vec<ExecLogEntry>>>,
+    },
+    Prune {
+        policy: RetentionPolicy,
+        reply_to: oneshot::Sender<Result<u64>>,
+    },
 }
 
 /// Handle for interacting with the execution log
@@ -119,6 +277,7 @@ impl ExecLogHandle {
         receiver.await?
     }
 
+    /// Log a successful execution, started by hand
     pub async fn log_execution(
         &self,
         block: Block,
@@ -126,25 +285,76 @@ impl ExecLogHandle {
         end_time: u64,
         output: String,
     ) -> Result<()> {
+        let record = ExecRecord::builder()
+            .block_id(block.id())
+            .start_time(start_time)
+            .end_time(end_time)
+            .output(output)
+            .build();
+
+        self.log_record(record).await
+    }
+
+    pub async fn log_record(&self, record: ExecRecord) -> Result<()> {
         let (reply_to, receiver) = oneshot::channel();
-        let msg = ExecLogMessage::LogExecution {
-            block_id: block.id(),
-            start_time,
-            end_time,
-            output,
-            reply_to,
-        };
+        let msg = ExecLogMessage::LogExecution { record, reply_to };
 
         self.sender.send(msg).await?;
         receiver.await?
     }
 
+    /// When the block last finished successfully, in nanoseconds since the epoch
     pub async fn get_last_execution_time(&self, block_id: Uuid) -> Result<Option<u64>> {
         let (reply_to, receiver) = oneshot::channel();
         let msg = ExecLogMessage::GetLastExecutionTime { block_id, reply_to };
         self.sender.send(msg).await?;
         receiver.await?
     }
+
+    pub async fn query(&self, query: ExecLogQuery) -> Result<Vec<ExecLogEntry>> {
+        let (reply_to, receiver) = oneshot::channel();
+        let msg = ExecLogMessage::Query { query, reply_to };
+        self.sender.send(msg).await?;
+        receiver.await?
+    }
+
+    /// The last `limit` executions of a block, most recent first
+    pub async fn last_runs(&self, block_id: Uuid, limit: u32) -> Result<Vec<ExecLogEntry>> {
+        self.query(ExecLogQuery {
+            block_id: Some(block_id),
+            limit: Some(limit),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Failed executions that ended at or after `since` (nanoseconds since the epoch)
+    pub async fn failed_since(&self, since: u64) -> Result<Vec<ExecLogEntry>> {
+        self.query(ExecLogQuery {
+            result: Some(ExecutionResult::Failure),
+            since: Some(since),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Executions of any block in a runbook, most recent first
+    pub async fn runbook_runs(&self, runbook_id: Uuid, limit: u32) -> Result<Vec<ExecLogEntry>> {
+        self.query(ExecLogQuery {
+            runbook_id: Some(runbook_id),
+            limit: Some(limit),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Remove executions the policy doesn't keep, returning how many were removed
+    pub async fn prune(&self, policy: RetentionPolicy) -> Result<u64> {
+        let (reply_to, receiver) = oneshot::channel();
+        let msg = ExecLogMessage::Prune { policy, reply_to };
+        self.sender.send(msg).await?;
+        receiver.await?
+    }
 }
 
 pub struct ExecLog {
@@ -200,22 +410,22 @@ impl ExecLog {
                     let result = self.get_or_create_block(uuid).await;
                     let _ = reply_to.send(result);
                 }
-                ExecLogMessage::LogExecution {
-                    block_id,
-                    start_time,
-                    end_time,
-                    output,
-                    reply_to,
-                } => {
-                    let result = self
-                        .log_execution(block_id, start_time, end_time, output)
-                        .await;
+                ExecLogMessage::LogExecution { record, reply_to } => {
+                    let result = self.log_execution(record).await;
                     let _ = reply_to.send(result);
                 }
                 ExecLogMessage::GetLastExecutionTime { block_id, reply_to } => {
                     let result = self.get_last_execution_time(block_id).await;
                     let _ = reply_to.send(result);
                 }
+                ExecLogMessage::Query { query, reply_to } => {
+                    let result = self.query(query).await;
+                    let _ = reply_to.send(result);
+                }
+                ExecLogMessage::Prune { policy, reply_to } => {
+                    let result = self.prune(policy).await;
+                    let _ = reply_to.send(result);
+                }
             }
         }
     }
@@ -262,27 +472,28 @@ impl ExecLog {
         Ok(block)
     }
 
-    async fn log_execution(
-        &self,
-        block_id: Uuid,
-        start_time: u64,
-        end_time: u64,
-        output: String,
-    ) -> Result<()> {
+    async fn log_execution(&self, record: ExecRecord) -> Result<()> {
         debug!(
-            "logging execution for block {:?}, start_time: {}, end_time: {}, output: {}",
-            block_id, start_time, end_time, output
+            "logging execution for block {:?}, start_time: {}, end_time: {}, result: {:?}, exit_code: {:?}",
+            record.block_id, record.start_time, record.end_time, record.result, record.exit_code
         );
 
-        let block = self.get_or_create_block(block_id).await?;
+        let block = self.get_or_create_block(record.block_id).await?;
+        let result = serde_json::to_value(record.result)?;
 
         sqlx::query(
-            "INSERT INTO exec_log (block_id, start_time, end_time, output) VALUES (?, ?, ?, ?)",
+            "INSERT INTO exec_log (block_id, start_time, end_time, output, runbook_id, exit_code, result, fingerprint, triggered_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(block.id as i64)
-        .bind(start_time as i64)
-        .bind(end_time as i64)
-        .bind(output)
+        .bind(record.start_time as i64)
+        .bind(record.end_time as i64)
+        .bind(record.output)
+        .bind(record.runbook_id.map(|id| id.to_string()))
+        .bind(record.exit_code)
+        .bind(result.as_str())
+        .bind(record.fingerprint)
+        .bind(serde_json::to_string(&record.trigger)?)
         .execute(&self.pool)
         .await?;
 
@@ -290,7 +501,7 @@ impl ExecLog {
     }
 
     async fn get_last_execution_time(&self, block_id: Uuid) -> Result<Option<u64>> {
-        let row = sqlx::query("SELECT MAX(exec_log.end_time) as end_time FROM exec_log join blocks on exec_log.block_id = blocks.id WHERE blocks.uuid = ?")
+        let row = sqlx::query(&format!("SELECT MAX(exec_log.end_time) as end_time FROM exec_log join blocks on exec_log.block_id = blocks.id WHERE blocks.uuid = ? AND {SUCCESSFUL}"))
             .bind(block_id.to_string())
             .fetch_one(&self.pool)
             .await?;
@@ -298,4 +509,194 @@ impl ExecLog {
         let end_time: Option<i64> = row.get("end_time");
         Ok(end_time.map(|t| t as u64))
     }
+
+    async fn query(&self, query: ExecLogQuery) -> Result<Vec<ExecLogEntry>> {
+        let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT exec_log.*, blocks.uuid FROM exec_log join blocks on exec_log.block_id = blocks.id WHERE 1 = 1",
+        );
+        if let Some(block_id) = query.block_id {
+            builder.push(" AND blocks.uuid = ");
+            builder.push_bind(block_id.to_string());
+        }
+        if let Some(runbook_id) = query.runbook_id {
+            builder.push(" AND exec_log.runbook_id = ");
+            builder.push_bind(runbook_id.to_string());
+        }
+        if let Some(result) = query.result {
+            let result = serde_json::to_value(result)?;
+            builder.push(" AND exec_log.result = ");
+            builder.push_bind(result.as_str().unwrap_or_default().to_string());
+        }
+        if let Some(since) = query.since {
+            builder.push(" AND exec_log.end_time >= ");
+            builder.push_bind(since as i64);
+        }
+        builder.push(" ORDER BY exec_log.end_time DESC, exec_log.id DESC");
+        if let Some(limit) = query.limit {
+            builder.push(" LIMIT ");
+            builder.push_bind(limit as i64);
+        }
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        rows.iter().map(ExecLogEntry::from_row).collect()
+    }
+
+    async fn prune(&self, policy: RetentionPolicy) -> Result<u64> {
+        // The latest successful execution of each block, which dependency checks rely on
+        let kept = format!(
+            "SELECT id FROM exec_log e WHERE e.id = (SELECT exec_log.id FROM exec_log WHERE exec_log.block_id = e.block_id AND {SUCCESSFUL} ORDER BY exec_log.end_time DESC, exec_log.id DESC LIMIT 1)"
+        );
+        let mut removed = 0;
+
+        if let Some(max_age) = policy.max_age {
+            let cutoff =
+                time::OffsetDateTime::now_utc().unix_timestamp_nanos() - max_age.as_nanos() as i128;
+            removed += sqlx::query(&format!(
+                "DELETE FROM exec_log WHERE end_time < ? AND id NOT IN ({kept})"
+            ))
+            .bind(cutoff.max(0) as i64)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+
+        if let Some(max_runs) = policy.max_runs_per_block {
+            removed += sqlx::query(&format!(
+                "DELETE FROM exec_log WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, ROW_NUMBER() OVER (PARTITION BY block_id ORDER BY end_time DESC, id DESC) AS n
+                        FROM exec_log
+                    ) WHERE n > ?
+                ) AND id NOT IN ({kept})"
+            ))
+            .bind(max_runs as i64)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+
+        debug!("pruned {removed} executions from the exec log");
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn now() -> u64 {
+        time::OffsetDateTime::now_utc().unix_timestamp_nanos() as u64
+    }
+
+    fn record(block_id: Uuid, end_time: u64, result: ExecutionResult) -> ExecRecord {
+        ExecRecord::builder()
+            .block_id(block_id)
+            .start_time(end_time - SECOND)
+            .end_time(end_time)
+            .result(result)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_log_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = ExecLogHandle::new(dir.path().join("exec_log.db")).unwrap();
+        let block = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let runbook = Uuid::new_v4();
+        let now = now();
+
+        log.log_record(record(block, now - 30 * SECOND, ExecutionResult::Success))
+            .await
+            .unwrap();
+        let mut failed = record(block, now - 20 * SECOND, ExecutionResult::Failure);
+        failed.exit_code = Some(2);
+        failed.runbook_id = Some(runbook);
+        failed.trigger = ExecTrigger::Workflow { id: runbook };
+        log.log_record(failed).await.unwrap();
+        log.log_record(record(other, now - 10 * SECOND, ExecutionResult::Success))
+            .await
+            .unwrap();
+
+        // Failed runs don't count as the block having run
+        assert_eq!(
+            log.get_last_execution_time(block).await.unwrap(),
+            Some(now - 30 * SECOND)
+        );
+
+        let runs = log.last_runs(block, 10).await.unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].result, Some(ExecutionResult::Failure));
+        assert_eq!(runs[0].exit_code, Some(2));
+        assert_eq!(runs[0].trigger, Some(ExecTrigger::Workflow { id: runbook }));
+        assert_eq!(runs[0].duration(), Duration::from_secs(1));
+        assert_eq!(log.last_runs(block, 1).await.unwrap().len(), 1);
+
+        let failures = log.failed_since(now - 60 * SECOND).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].block_id, block);
+        assert!(log
+            .failed_since(now - 15 * SECOND)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let runbook_runs = log.runbook_runs(runbook, 10).await.unwrap();
+        assert_eq!(runbook_runs.len(), 1);
+        assert_eq!(runbook_runs[0].runbook_id, Some(runbook));
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_last_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = ExecLogHandle::new(dir.path().join("exec_log.db")).unwrap();
+        let block = Uuid::new_v4();
+        let now = now();
+        let day = 24 * 60 * 60 * SECOND;
+
+        for days_ago in [40, 30, 20] {
+            log.log_record(record(
+                block,
+                now - days_ago * day,
+                ExecutionResult::Success,
+            ))
+            .await
+            .unwrap();
+        }
+        log.log_record(record(block, now - 10 * day, ExecutionResult::Failure))
+            .await
+            .unwrap();
+
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            max_runs_per_block: None,
+        };
+        assert_eq!(log.prune(policy).await.unwrap(), 3);
+
+        let runs = log.last_runs(block, 10).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].end_time, now - 20 * day);
+
+        for seconds_ago in [3, 2, 1] {
+            log.log_record(record(
+                block,
+                now - seconds_ago * SECOND,
+                ExecutionResult::Success,
+            ))
+            .await
+            .unwrap();
+        }
+        let policy = RetentionPolicy {
+            max_age: None,
+            max_runs_per_block: Some(2),
+        };
+        assert_eq!(log.prune(policy).await.unwrap(), 2);
+        let runs = log.last_runs(block, 10).await.unwrap();
+        assert_eq!(
+            runs.iter().map(|run| run.end_time).collect::<Vec<_>>(),
+            vec![now - SECOND, now - 2 * SECOND]
+        );
+    }
 }
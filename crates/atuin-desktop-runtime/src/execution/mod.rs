@@ -8,8 +8,10 @@
 //! - [`ExecutionHandle`]: Tracks execution state and provides cancellation
 //! - [`BlockOutput`]: Represents output from block execution
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::{oneshot, watch, Mutex, RwLock};
@@ -19,15 +21,17 @@ use uuid::Uuid;
 
 use crate::client::{
     ClientPrompt, ClientPromptResult, DocumentBridgeMessage, LocalValueProvider, MessageChannel,
-    RunbookContentLoader,
+    PromptIcon, PromptInput, PromptOption, PromptOptionColor, RunbookContentLoader,
 };
-use crate::context::{BlockContext, BlockExecutionOutput, BlockState, ContextResolver};
+use crate::context::{BlockContext, BlockExecutionOutput, BlockState, ContextResolver, Redactor};
 use crate::document::{DocumentError, DocumentHandle};
 use crate::events::{EventBus, GCEvent};
 use crate::pty::PtyStoreHandle;
-use crate::ssh::SshPoolHandle;
+use crate::ssh::{KeyboardInteractiveChallenge, SshPoolHandle, SshPrompt};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
 pub enum ExecutionResult {
     Success,
     Failure,
@@ -86,13 +90,18 @@ impl ExecutionContext {
         self.handle().finished_channel()
     }
 
-    /// Send a message to the output channel
+    /// Send a message to the output channel, with sensitive values masked
     pub async fn send_output(
         &self,
         message: impl Into<DocumentBridgeMessage>,
     ) -> Result<(), DocumentError> {
         if let Some(chan) = &self.output_channel {
-            chan.send(message.into())
+            let mut message = message.into();
+            if let DocumentBridgeMessage::BlockOutput { output, .. } = &mut message {
+                output.redact(&self.context_resolver.redactor());
+            }
+
+            chan.send(message)
                 .await
                 .map_err(|_| DocumentError::OutputSendError)?;
         }
@@ -338,9 +347,27 @@ impl ExecutionContext {
         self.handle().cancellation_token.clone().take_receiver()
     }
 
+    /// Ask the client a question, waiting until it's answered or the execution is cancelled
     pub async fn prompt_client(
         &self,
         prompt: ClientPrompt,
+    ) -> Result<ClientPromptResult, DocumentError> {
+        self.prompt_client_until(prompt, None).await
+    }
+
+    /// Like [`ExecutionContext::prompt_client`], but gives up if nobody answers within `timeout`
+    pub async fn prompt_client_with_timeout(
+        &self,
+        prompt: ClientPrompt,
+        timeout: Duration,
+    ) -> Result<ClientPromptResult, DocumentError> {
+        self.prompt_client_until(prompt, Some(timeout)).await
+    }
+
+    async fn prompt_client_until(
+        &self,
+        prompt: ClientPrompt,
+        timeout: Option<Duration>,
     ) -> Result<ClientPromptResult, DocumentError> {
         let prompt_id = Uuid::new_v4();
         let (sender, receiver) = oneshot::channel();
@@ -359,9 +386,28 @@ impl ExecutionContext {
         .await
         .map_err(|_| DocumentError::OutputSendError)?;
 
-        let result = receiver.await.map_err(|_| DocumentError::EventSendError)?;
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let result = tokio::select! {
+            result = receiver => result.map_err(|_| DocumentError::EventSendError),
+            _ = self.cancellation_token().cancelled() => Err(DocumentError::PromptCancelled),
+            _ = deadline => Err(DocumentError::PromptTimedOut),
+        };
+
+        if result.is_err() {
+            self.handle()
+                .prompt_callbacks
+                .lock()
+                .await
+                .remove(&prompt_id);
+        }
 
-        Ok(result)
+        result
     }
 
     /// Check if a runbook is already in the execution stack (recursion detection)
@@ -436,6 +482,24 @@ impl ExecutionContext {
         Ok(self)
     }
 
+    /// Configure this context for a block nested in `parent`'s block, such as a loop's child
+    ///
+    /// Like `configure_for_sub_runbook`, output and events go where the parent's do and
+    /// resources are shared, but the nested block stays in the parent's runbook.
+    pub fn configure_for_nested_block(mut self, parent: &ExecutionContext) -> Self {
+        self.output_channel = parent.output_channel.clone();
+        self.gc_event_bus = parent.gc_event_bus.clone();
+        self.execution_stack = parent.execution_stack.clone();
+        self.runbook_loader = parent.runbook_loader.clone();
+
+        self.with_resources(parent.ssh_pool(), parent.pty_store())
+    }
+
+    /// Get the channel output is sent to (if available)
+    pub(crate) fn output_channel(&self) -> Option<Arc<dyn MessageChannel<DocumentBridgeMessage>>> {
+        self.output_channel.clone()
+    }
+
     /// Get the runbook content loader (if available)
     pub fn runbook_loader(&self) -> Option<&Arc<dyn RunbookContentLoader>> {
         self.runbook_loader.as_ref()
@@ -456,6 +520,11 @@ impl ExecutionContext {
         self.document_handle.block_local_value_provider()
     }
 
+    /// Wait until the document this block belongs to has shut down
+    pub async fn document_closed(&self) {
+        self.document_handle.closed().await
+    }
+
     /// Create a new context with different resource handles
     pub fn with_resources(
         mut self,
@@ -472,6 +541,89 @@ impl ExecutionContext {
     }
 }
 
+/// How long an SSH connection waits on a host key or 2FA prompt before giving up
+const SSH_PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[async_trait::async_trait]
+impl SshPrompt for ExecutionContext {
+    async fn confirm_unknown_host(&self, host: &str, key_type: &str, fingerprint: &str) -> bool {
+        let prompt = ClientPrompt::new(
+            "Unknown SSH host",
+            &format!(
+                "The authenticity of host {host} can't be established.\n\
+                 {key_type} key fingerprint is {fingerprint}.\n\
+                 Trust this host and add it to known_hosts?"
+            ),
+        )
+        .icon(PromptIcon::Warning)
+        .option(PromptOption::new("Trust", "trust").color(PromptOptionColor::Primary))
+        .option(PromptOption::new("Cancel", "cancel"));
+
+        match self
+            .prompt_client_with_timeout(prompt, SSH_PROMPT_TIMEOUT)
+            .await
+        {
+            Ok(result) => result.button == "trust",
+            Err(e) => {
+                tracing::warn!("Failed to ask about host key for {host}: {e}");
+                false
+            }
+        }
+    }
+
+    async fn answer_challenge(
+        &self,
+        challenge: &KeyboardInteractiveChallenge,
+    ) -> Option<Vec<String>> {
+        let title = if challenge.name.is_empty() {
+            "SSH authentication"
+        } else {
+            challenge.name.as_str()
+        };
+
+        let mut answers = Vec::with_capacity(challenge.prompts.len());
+        for question in &challenge.prompts {
+            let message = match challenge.instructions.trim() {
+                "" => format!("{}\n{}", challenge.host, question.prompt.trim()),
+                instructions => format!(
+                    "{}\n{instructions}\n{}",
+                    challenge.host,
+                    question.prompt.trim()
+                ),
+            };
+            let input = if question.echo {
+                PromptInput::String
+            } else {
+                PromptInput::Secret
+            };
+            let prompt = ClientPrompt::new(title, &message)
+                .icon(PromptIcon::Question)
+                .input(input)
+                .option(PromptOption::new("Submit", "submit").color(PromptOptionColor::Primary))
+                .option(PromptOption::new("Cancel", "cancel"));
+
+            match self
+                .prompt_client_with_timeout(prompt, SSH_PROMPT_TIMEOUT)
+                .await
+            {
+                Ok(result) if result.button == "submit" => {
+                    answers.push(result.value.unwrap_or_default())
+                }
+                Ok(_) => return None,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to ask for SSH authentication on {}: {e}",
+                        challenge.host
+                    );
+                    return None;
+                }
+            }
+        }
+
+        Some(answers)
+    }
+}
+
 /// Error when recursion is detected in sub-runbook execution
 #[derive(Debug, Clone)]
 pub struct SubRunbookRecursionError {
@@ -499,6 +651,7 @@ impl std::error::Error for SubRunbookRecursionError {}
 pub struct CancellationToken {
     sender: Arc<std::sync::Mutex<Option<oneshot::Sender<()>>>>,
     receiver: Arc<std::sync::Mutex<Option<oneshot::Receiver<()>>>>,
+    cancelled: Arc<watch::Sender<bool>>,
 }
 
 impl Default for CancellationToken {
@@ -507,6 +660,7 @@ impl Default for CancellationToken {
         Self {
             sender: Arc::new(std::sync::Mutex::new(Some(sender))),
             receiver: Arc::new(std::sync::Mutex::new(Some(receiver))),
+            cancelled: Arc::new(watch::channel(false).0),
         }
     }
 }
@@ -526,6 +680,15 @@ impl CancellationToken {
                 let _ = sender.send(()); // Ignore error if receiver already dropped
             }
         }
+        self.cancelled.send_replace(true);
+    }
+
+    /// Wait until the execution is cancelled
+    ///
+    /// Unlike the receiver, this can be awaited from any number of places.
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.subscribe();
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
     }
 
     /// Take the receiver end of the cancellation token
@@ -649,6 +812,27 @@ pub struct StreamingBlockOutput {
     pub object: Option<serde_json::Value>, // For structured JSON data
 }
 
+impl StreamingBlockOutput {
+    /// Mask sensitive values in every part of the output
+    pub fn redact(&mut self, redactor: &Redactor) {
+        for text in [&mut self.stdout, &mut self.stderr].into_iter().flatten() {
+            redactor.redact_in_place(text);
+        }
+        if let Some(binary) = &mut self.binary {
+            let redacted = match redactor.redact_bytes(binary) {
+                Cow::Owned(redacted) => Some(redacted),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(redacted) = redacted {
+                *binary = redacted;
+            }
+        }
+        if let Some(object) = &mut self.object {
+            redactor.redact_json(object);
+        }
+    }
+}
+
 /// Data for block finished lifecycle event
 #[derive(TS, Debug, Clone, Serialize, Deserialize)]
 #[ts(export)]
@@ -8,11 +8,15 @@
 //! cd docker/ssh-test
 //! ./setup-keys.sh
 //! docker-compose up -d
+//! ssh-keyscan -p 2222 localhost >> ~/.ssh/known_hosts
 //!
 //! # Run tests
 //! cargo test -p atuin-desktop-runtime -- --ignored --test-threads=1
 //! ```
 //!
+//! The server's host key must be in known_hosts (as above), or `StrictHostKeyChecking`
+//! set to `accept-new` for the test host in `~/.ssh/config`, as there is nobody to ask.
+//!
 //! Environment variables:
 //! - SSH_TEST_HOST: Target host (default: localhost)
 //! - SSH_TEST_PORT: Target port (default: 2222)
@@ -494,6 +498,133 @@ async fn test_pool_connection_reuse() {
     );
 }
 
+/// Test local port forwarding through a pooled connection
+///
+/// Forwards to the container's own sshd, so the tunnel should hand back its banner.
+#[tokio::test]
+#[ignore]
+async fn test_pool_tunnel() {
+    use super::SshPoolHandle;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpStream;
+
+    let pool = SshPoolHandle::new();
+    let host = format!("{}:{}", test_host(), test_port());
+    let key_path = test_keys_dir().join("id_ed25519");
+
+    pool.connect(
+        &host,
+        Some(&test_user()),
+        Some(Authentication::Key(key_path)),
+    )
+    .await
+    .expect("Connection failed");
+
+    let addr = pool
+        .open_tunnel(
+            &host,
+            Some(&test_user()),
+            "tunnel-test",
+            0,
+            "localhost",
+            22,
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to open tunnel");
+    assert_ne!(addr.port(), 0, "Tunnel should listen on an ephemeral port");
+
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .expect("Failed to connect to tunnel");
+    let mut banner = [0u8; 8];
+    stream
+        .read_exact(&mut banner)
+        .await
+        .expect("Failed to read through tunnel");
+    assert_eq!(&banner, b"SSH-2.0-");
+
+    pool.close_tunnel("tunnel-test").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert!(
+        TcpStream::connect(addr).await.is_err(),
+        "Tunnel should stop listening once closed"
+    );
+}
+
+/// Test an SFTP round trip keeps content and file mode
+#[tokio::test]
+#[ignore]
+async fn test_pool_sftp_round_trip() {
+    use super::{SftpTransfer, SshPoolHandle, UploadSource};
+    use std::os::unix::fs::PermissionsExt;
+
+    let pool = SshPoolHandle::new();
+    let host = format!("{}:{}", test_host(), test_port());
+    let key_path = test_keys_dir().join("id_ed25519");
+
+    pool.connect(
+        &host,
+        Some(&test_user()),
+        Some(Authentication::Key(key_path)),
+    )
+    .await
+    .expect("Connection failed");
+
+    let remote_path = format!("/tmp/atuin-sftp-test-{}", uuid::Uuid::new_v4());
+    let contents = b"#!/bin/sh\necho hello\n".to_vec();
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let uploaded = pool
+        .transfer(
+            &host,
+            Some(&test_user()),
+            "sftp-upload-test",
+            SftpTransfer::Upload {
+                source: UploadSource::Contents(contents.clone()),
+                remote_path: remote_path.clone(),
+                mode: Some(0o750),
+            },
+            None,
+            None,
+            progress_tx,
+        )
+        .await
+        .expect("Upload failed");
+    assert_eq!(uploaded, contents.len() as u64);
+    assert_eq!(
+        progress_rx.recv().await.map(|p| p.transferred),
+        Some(uploaded)
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let local_path = dir.path().join("downloaded");
+    let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    pool.transfer(
+        &host,
+        Some(&test_user()),
+        "sftp-download-test",
+        SftpTransfer::Download {
+            remote_path: remote_path.clone(),
+            local_path: local_path.clone(),
+            mode: None,
+        },
+        None,
+        None,
+        progress_tx,
+    )
+    .await
+    .expect("Download failed");
+
+    assert_eq!(std::fs::read(&local_path).unwrap(), contents);
+    let mode = std::fs::metadata(&local_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o750, "Download should keep the remote mode");
+
+    let session = connect_default().await.expect("Failed to connect");
+    session.delete_file(&remote_path).await.unwrap();
+}
+
 // =============================================================================
 // Edge Cases and Error Handling
 // =============================================================================
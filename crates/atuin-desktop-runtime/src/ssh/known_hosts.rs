@@ -0,0 +1,727 @@
+// Host key verification against OpenSSH known_hosts files.
+//
+// We parse the files ourselves rather than using russh's helpers, as those do not understand
+// wildcard patterns, negations or marker lines (`@cert-authority`, `@revoked`).
+//
+// russh only negotiates plain host key algorithms, so a server never presents its certificate
+// on the connection itself. When a host isn't trusted through a plain entry but an
+// `@cert-authority` line covers it, its certificates are fetched with `ssh-keyscan -c` instead.
+// Where a certificate comes from doesn't matter: it is only trusted if a listed CA signed it for
+// this host, and only for the key the server proved it holds on the real connection.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use russh::keys::ssh_key::certificate::CertType;
+use russh::keys::{Certificate, HashAlg, PublicKey};
+use sha1::Sha1;
+
+use crate::events::GCEvent;
+
+/// System-wide known hosts file, consulted after the user's files but never written to
+const GLOBAL_KNOWN_HOSTS: &str = "/etc/ssh/ssh_known_hosts";
+
+/// How long `ssh-keyscan` may take to fetch a host's certificates
+const KEYSCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to treat host keys that are missing from, or differ from, known_hosts.
+/// Mirrors the values of OpenSSH's `StrictHostKeyChecking` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrictHostKeyChecking {
+    /// Never connect to unknown hosts
+    Yes,
+    /// Ask the user before trusting an unknown host; refuse if nobody can be asked
+    #[default]
+    Ask,
+    /// Trust unknown hosts and remember their keys
+    AcceptNew,
+    /// Trust unknown hosts and remember their keys, and only warn about changed keys
+    No,
+}
+
+impl StrictHostKeyChecking {
+    /// Parse a `StrictHostKeyChecking` value from an ssh config file
+    pub fn from_config(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "yes" => Some(Self::Yes),
+            "ask" => Some(Self::Ask),
+            "accept-new" => Some(Self::AcceptNew),
+            "no" | "off" => Some(Self::No),
+            _ => None,
+        }
+    }
+}
+
+/// Why a server's host key was refused
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum HostKeyError {
+    #[error(
+        "Host key for {host} has changed (now {fingerprint}). The old key is at {}:{line}; remove it if the change is expected",
+        path.display()
+    )]
+    Changed {
+        host: String,
+        fingerprint: String,
+        path: PathBuf,
+        line: usize,
+    },
+
+    #[error("Host key for {host} ({fingerprint}) is not in known_hosts and was not trusted")]
+    Unknown { host: String, fingerprint: String },
+
+    #[error("Host key for {host} ({fingerprint}) is revoked in {}:{line}", path.display())]
+    Revoked {
+        host: String,
+        fingerprint: String,
+        path: PathBuf,
+        line: usize,
+    },
+}
+
+impl HostKeyError {
+    /// The Grand Central event to emit if an SSH connection failed because the host key changed
+    pub fn mismatch_event(error: &eyre::Report) -> Option<GCEvent> {
+        match error.downcast_ref::<Self>()? {
+            Self::Changed {
+                host,
+                fingerprint,
+                path,
+                line,
+            } => Some(GCEvent::SshHostKeyMismatch {
+                host: host.clone(),
+                fingerprint: fingerprint.clone(),
+                known_hosts_path: path.to_string_lossy().to_string(),
+                line: *line,
+            }),
+            Self::Unknown { .. } | Self::Revoked { .. } => None,
+        }
+    }
+}
+
+/// Result of looking up a host key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    /// The key is listed for this host
+    Trusted,
+    /// No key of this type is listed for this host
+    Unknown,
+    /// A different key of the same type is listed for this host
+    Changed { path: PathBuf, line: usize },
+    /// The key is listed under `@revoked`
+    Revoked { path: PathBuf, line: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    None,
+    CertAuthority,
+    Revoked,
+}
+
+#[derive(Debug)]
+enum HostPatterns {
+    /// `|1|salt|hash`, as written by `HashKnownHosts yes`
+    Hashed { salt: Vec<u8>, hash: Vec<u8> },
+    /// Comma-separated patterns, possibly negated with `!`
+    Plain(Vec<String>),
+}
+
+impl HostPatterns {
+    fn parse(field: &str) -> Option<Self> {
+        if let Some(hashed) = field.strip_prefix("|1|") {
+            let (salt, hash) = hashed.split_once('|')?;
+            let engine = base64::engine::general_purpose::STANDARD;
+            return Some(Self::Hashed {
+                salt: engine.decode(salt).ok()?,
+                hash: engine.decode(hash).ok()?,
+            });
+        }
+
+        Some(Self::Plain(
+            field.split(',').map(|p| p.to_lowercase()).collect(),
+        ))
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Hashed { salt, hash } => {
+                let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(salt) else {
+                    return false;
+                };
+                mac.update(name.as_bytes());
+                mac.verify_slice(hash).is_ok()
+            }
+            Self::Plain(patterns) => {
+                let mut matched = false;
+                for pattern in patterns {
+                    if let Some(negated) = pattern.strip_prefix('!') {
+                        if wildcard_match(negated, name) {
+                            return false;
+                        }
+                    } else if wildcard_match(pattern, name) {
+                        matched = true;
+                    }
+                }
+                matched
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    marker: Marker,
+    hosts: HostPatterns,
+    key: PublicKey,
+    path: PathBuf,
+    line: usize,
+}
+
+/// The combined contents of one or more known_hosts files
+#[derive(Debug, Default)]
+pub struct KnownHosts {
+    entries: Vec<Entry>,
+}
+
+impl KnownHosts {
+    /// Load the given files followed by the system-wide known hosts file.
+    /// Files that do not exist are skipped, as are lines that fail to parse.
+    pub fn load(paths: &[PathBuf]) -> Self {
+        let mut known_hosts = Self::default();
+
+        let global = PathBuf::from(GLOBAL_KNOWN_HOSTS);
+        for path in paths.iter().chain(std::iter::once(&global)) {
+            match std::fs::read_to_string(path) {
+                Ok(content) => known_hosts.add_file(path, &content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("Failed to read {}: {e}", path.display()),
+            }
+        }
+
+        known_hosts
+    }
+
+    fn add_file(&mut self, path: &Path, content: &str) {
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match Self::parse_line(line) {
+                Some((marker, hosts, key)) => self.entries.push(Entry {
+                    marker,
+                    hosts,
+                    key,
+                    path: path.to_path_buf(),
+                    line: index + 1,
+                }),
+                None => {
+                    tracing::debug!("Skipping unparseable line {}:{}", path.display(), index + 1)
+                }
+            }
+        }
+    }
+
+    fn parse_line(line: &str) -> Option<(Marker, HostPatterns, PublicKey)> {
+        let mut fields = line.split_whitespace().peekable();
+
+        let marker = match fields.peek() {
+            Some(&"@cert-authority") => Marker::CertAuthority,
+            Some(&"@revoked") => Marker::Revoked,
+            Some(field) if field.starts_with('@') => return None,
+            _ => Marker::None,
+        };
+        if marker != Marker::None {
+            fields.next();
+        }
+
+        let hosts = HostPatterns::parse(fields.next()?)?;
+        let key_type = fields.next()?;
+        let key_data = fields.next()?;
+        let key = PublicKey::from_openssh(&format!("{key_type} {key_data}")).ok()?;
+
+        Some((marker, hosts, key))
+    }
+
+    /// Entries that apply to `host:port`
+    fn matching(&self, host: &str, port: u16) -> Vec<&Entry> {
+        let name = host_entry_name(host, port);
+        self.entries
+            .iter()
+            .filter(|entry| entry.hosts.matches(&name))
+            .collect()
+    }
+
+    /// Look up the key a server presented for `host:port`
+    ///
+    /// Certificate authority lines never vouch for a plain host key, but they don't count as a
+    /// conflicting key either; see [`KnownHosts::check_certificate`].
+    pub fn check(&self, host: &str, port: u16, key: &PublicKey) -> HostKeyStatus {
+        let matching = self.matching(host, port);
+
+        if let Some(entry) = matching
+            .iter()
+            .find(|e| e.marker == Marker::Revoked && e.key.key_data() == key.key_data())
+        {
+            return HostKeyStatus::Revoked {
+                path: entry.path.clone(),
+                line: entry.line,
+            };
+        }
+
+        let host_keys = matching.iter().filter(|e| e.marker == Marker::None);
+
+        if host_keys
+            .clone()
+            .any(|e| e.key.key_data() == key.key_data())
+        {
+            return HostKeyStatus::Trusted;
+        }
+
+        if let Some(entry) = host_keys
+            .clone()
+            .find(|e| e.key.algorithm() == key.algorithm())
+        {
+            return HostKeyStatus::Changed {
+                path: entry.path.clone(),
+                line: entry.line,
+            };
+        }
+
+        HostKeyStatus::Unknown
+    }
+
+    /// Whether an `@cert-authority` line applies to `host:port`
+    pub fn has_cert_authority(&self, host: &str, port: u16) -> bool {
+        self.matching(host, port)
+            .iter()
+            .any(|e| e.marker == Marker::CertAuthority)
+    }
+
+    /// Check a certificate for the key a server presented for `host:port`
+    ///
+    /// Like OpenSSH, the certificate must be a host certificate for `key`, signed by a CA listed
+    /// for the host, valid now, naming `host` if it names any principals, and free of critical
+    /// options. Returns `Unknown` if it doesn't vouch for the key, and `Revoked` if its CA or
+    /// the key is revoked.
+    pub fn check_certificate(
+        &self,
+        host: &str,
+        port: u16,
+        key: &PublicKey,
+        certificate: &Certificate,
+    ) -> HostKeyStatus {
+        if certificate.public_key() != key.key_data() {
+            return HostKeyStatus::Unknown;
+        }
+
+        let matching = self.matching(host, port);
+        if let Some(entry) = matching.iter().find(|e| {
+            e.marker == Marker::Revoked
+                && (e.key.key_data() == certificate.signature_key()
+                    || e.key.key_data() == key.key_data())
+        }) {
+            return HostKeyStatus::Revoked {
+                path: entry.path.clone(),
+                line: entry.line,
+            };
+        }
+
+        let principals = certificate.valid_principals();
+        if certificate.cert_type() != CertType::Host
+            || !certificate.critical_options().is_empty()
+            || (!principals.is_empty() && !principals.iter().any(|p| p.eq_ignore_ascii_case(host)))
+        {
+            return HostKeyStatus::Unknown;
+        }
+
+        let authorities = matching
+            .iter()
+            .filter(|e| e.marker == Marker::CertAuthority)
+            .map(|e| e.key.fingerprint(HashAlg::Sha256))
+            .collect::<Vec<_>>();
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        match certificate.validate_at(now, &authorities) {
+            Ok(()) => HostKeyStatus::Trusted,
+            Err(e) => {
+                tracing::debug!(
+                    "Host certificate {} for {host} did not validate: {e}",
+                    certificate.key_id()
+                );
+                HostKeyStatus::Unknown
+            }
+        }
+    }
+}
+
+/// Fetch the certificates `host:port` offers for its host keys, using `ssh-keyscan -c`
+///
+/// Returns nothing if `ssh-keyscan` isn't installed, fails or takes too long, such as for a
+/// host that is only reachable through a jump host.
+pub async fn scan_host_certificates(host: &str, port: u16) -> Vec<Certificate> {
+    let scan = tokio::process::Command::new("ssh-keyscan")
+        .arg("-c")
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("--")
+        .arg(host)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    match tokio::time::timeout(KEYSCAN_TIMEOUT, scan).await {
+        Ok(Ok(output)) => parse_keyscan_certificates(&String::from_utf8_lossy(&output.stdout)),
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to run ssh-keyscan for {host}: {e}");
+            Vec::new()
+        }
+        Err(_) => {
+            tracing::debug!("ssh-keyscan for {host} timed out");
+            Vec::new()
+        }
+    }
+}
+
+/// Parse the `host cert-type data` lines printed by `ssh-keyscan -c`
+fn parse_keyscan_certificates(output: &str) -> Vec<Certificate> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (_host, certificate) = line.split_once(char::is_whitespace)?;
+            Certificate::from_openssh(certificate.trim()).ok()
+        })
+        .collect()
+}
+
+/// Append a host key to a known_hosts file, creating it if needed
+pub fn learn(path: &Path, host: &str, port: u16, key: &PublicKey) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let key = key
+        .to_openssh()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+
+    let needs_newline = std::fs::read(path)
+        .map(|content| !content.is_empty() && !content.ends_with(b"\n"))
+        .unwrap_or(false);
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    if needs_newline {
+        writeln!(file)?;
+    }
+    writeln!(file, "{} {key}", host_entry_name(host, port))?;
+
+    Ok(())
+}
+
+/// The SHA256 fingerprint of a key, as printed by OpenSSH
+pub fn fingerprint(key: &PublicKey) -> String {
+    key.fingerprint(HashAlg::Sha256).to_string()
+}
+
+/// The name a host is listed under in known_hosts; non-default ports use `[host]:port`
+pub fn host_entry_name(host: &str, port: u16) -> String {
+    let host = host.to_lowercase();
+    if port == 22 {
+        host
+    } else {
+        format!("[{host}]:{port}")
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and `?` any one
+/// character. Unlike glob, `[` is literal, as it appears in `[host]:port` entries.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use russh::keys::ssh_key::certificate::Builder;
+    use russh::keys::ssh_key::private::Ed25519Keypair;
+    use russh::keys::PrivateKey;
+
+    const KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFbKLa7aJxo9wiXwF4B2A17m2rEZIqIWOXUaL0s7kQsn";
+    const OTHER_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDk/56zCMnPibWo1jD70I0lvtZ0V/ku9nsptnAoIIduy";
+
+    fn key(key: &str) -> PublicKey {
+        PublicKey::from_openssh(key).unwrap()
+    }
+
+    fn known_hosts(content: &str) -> KnownHosts {
+        let mut known_hosts = KnownHosts::default();
+        known_hosts.add_file(Path::new("/tmp/known_hosts"), content);
+        known_hosts
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.com", "db.example.com"));
+        assert!(wildcard_match("db?.example.com", "db1.example.com"));
+        assert!(wildcard_match(
+            "[*.example.com]:2222",
+            "[db.example.com]:2222"
+        ));
+        assert!(!wildcard_match("*.example.com", "example.com"));
+        assert!(!wildcard_match("[db.example.com]:2222", "db.example.com"));
+    }
+
+    #[test]
+    fn test_check_plain_entries() {
+        let known_hosts = known_hosts(&format!(
+            "# comment\nexample.com,192.0.2.1 {KEY}\n[example.com]:2222 {OTHER_KEY}\n"
+        ));
+
+        assert_eq!(
+            known_hosts.check("example.com", 22, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("EXAMPLE.com", 22, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("192.0.2.1", 22, &key(OTHER_KEY)),
+            HostKeyStatus::Changed {
+                path: PathBuf::from("/tmp/known_hosts"),
+                line: 2
+            }
+        );
+        assert_eq!(
+            known_hosts.check("example.com", 2222, &key(OTHER_KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("other.com", 22, &key(KEY)),
+            HostKeyStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_check_hashed_entries() {
+        // Produced with `ssh-keygen -H` from plain `example.com` and `[example.com]:2222` lines
+        let known_hosts = known_hosts(&format!(
+            "|1|WjYpP3tL7drCeKNZaygUc/ilbVs=|WPbPYKtl0BseK+bruvkxYQqLlvM= {KEY}\n\
+             |1|O4XizESqXWEiUPRqGAZtTCS5FLU=|YYEert0UE56tiPz8M+4F0TFZo2Y= {KEY}\n"
+        ));
+
+        assert_eq!(
+            known_hosts.check("example.com", 22, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("example.com", 2222, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("example.org", 22, &key(KEY)),
+            HostKeyStatus::Unknown
+        );
+        assert!(matches!(
+            known_hosts.check("example.com", 22, &key(OTHER_KEY)),
+            HostKeyStatus::Changed { line: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_markers_and_negation() {
+        let known_hosts = known_hosts(&format!(
+            "@cert-authority *.example.com {OTHER_KEY}\n\
+             *.example.com,!bastion.example.com {KEY}\n\
+             @revoked * {OTHER_KEY}\n"
+        ));
+
+        assert_eq!(
+            known_hosts.check("db.example.com", 22, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        // A CA key alone neither trusts nor conflicts with a host key
+        assert_eq!(
+            known_hosts.check("bastion.example.com", 22, &key(KEY)),
+            HostKeyStatus::Unknown
+        );
+        assert!(matches!(
+            known_hosts.check("anything.org", 22, &key(OTHER_KEY)),
+            HostKeyStatus::Revoked { line: 3, .. }
+        ));
+    }
+
+    fn private_key(seed: u8) -> PrivateKey {
+        PrivateKey::from(Ed25519Keypair::from_seed(&[seed; 32]))
+    }
+
+    fn certificate(
+        ca: &PrivateKey,
+        host_key: &PrivateKey,
+        cert_type: CertType,
+        principal: &str,
+        valid_before: u64,
+    ) -> Certificate {
+        let mut builder = Builder::new(
+            [0u8; 16],
+            host_key.public_key().key_data().clone(),
+            0,
+            valid_before,
+        )
+        .unwrap();
+        builder.cert_type(cert_type).unwrap();
+        builder.valid_principal(principal).unwrap();
+        builder.sign(ca).unwrap()
+    }
+
+    #[test]
+    fn test_check_certificate() {
+        let ca = private_key(1);
+        let other_ca = private_key(2);
+        let host_key = private_key(3);
+        let key = host_key.public_key();
+        let known_hosts = known_hosts(&format!(
+            "@cert-authority *.example.com {}\n",
+            ca.public_key().to_openssh().unwrap()
+        ));
+        let valid = certificate(
+            &ca,
+            &host_key,
+            CertType::Host,
+            "db.example.com",
+            u64::MAX >> 2,
+        );
+
+        assert!(known_hosts.has_cert_authority("db.example.com", 22));
+        assert!(!known_hosts.has_cert_authority("example.org", 22));
+        assert_eq!(
+            known_hosts.check_certificate("db.example.com", 22, key, &valid),
+            HostKeyStatus::Trusted
+        );
+
+        // Not listed for this host, not signed by the CA, for another host, for a user,
+        // expired, or for a different key
+        assert_eq!(
+            known_hosts.check_certificate("db.example.org", 22, key, &valid),
+            HostKeyStatus::Unknown
+        );
+        let rejected = [
+            certificate(
+                &other_ca,
+                &host_key,
+                CertType::Host,
+                "db.example.com",
+                u64::MAX >> 2,
+            ),
+            certificate(
+                &ca,
+                &host_key,
+                CertType::Host,
+                "web.example.com",
+                u64::MAX >> 2,
+            ),
+            certificate(
+                &ca,
+                &host_key,
+                CertType::User,
+                "db.example.com",
+                u64::MAX >> 2,
+            ),
+            certificate(&ca, &host_key, CertType::Host, "db.example.com", 1),
+        ];
+        for certificate in &rejected {
+            assert_eq!(
+                known_hosts.check_certificate("db.example.com", 22, key, certificate),
+                HostKeyStatus::Unknown
+            );
+        }
+        assert_eq!(
+            known_hosts.check_certificate("db.example.com", 22, other_ca.public_key(), &valid),
+            HostKeyStatus::Unknown
+        );
+
+        let ca = ca.public_key().to_openssh().unwrap();
+        let revoked = known_hosts(&format!("@cert-authority * {ca}\n@revoked * {ca}\n"));
+        assert!(matches!(
+            revoked.check_certificate("db.example.com", 22, key, &valid),
+            HostKeyStatus::Revoked { line: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_keyscan_certificates() {
+        let certificate = certificate(
+            &private_key(1),
+            &private_key(3),
+            CertType::Host,
+            "example.com",
+            u64::MAX >> 2,
+        );
+        let output = format!(
+            "# example.com:22 SSH-2.0-OpenSSH_9.6\n[example.com]:2222 {}\n\nexample.com {KEY}\n",
+            certificate.to_openssh().unwrap()
+        );
+
+        assert_eq!(parse_keyscan_certificates(&output), vec![certificate]);
+    }
+
+    #[test]
+    fn test_learn_appends_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ssh").join("known_hosts");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, format!("example.com {OTHER_KEY}")).unwrap();
+
+        learn(&path, "Example.org", 2222, &key(KEY)).unwrap();
+
+        let known_hosts = KnownHosts::load(std::slice::from_ref(&path));
+        assert_eq!(
+            known_hosts.check("example.org", 2222, &key(KEY)),
+            HostKeyStatus::Trusted
+        );
+        assert_eq!(
+            known_hosts.check("example.com", 22, &key(OTHER_KEY)),
+            HostKeyStatus::Trusted
+        );
+    }
+}
@@ -6,8 +6,11 @@
 //! Features:
 //! - Connection pooling with automatic cleanup
 //! - SSH configuration file parsing
-//! - Multiple authentication methods (keys and certificates)
+//! - Multiple authentication methods (agent, keys, certificates and keyboard-interactive)
+//! - Agent forwarding when `ForwardAgent` is set in ssh config
 //! - Remote PTY support
+//! - Local port forwarding (`ssh -L`) over pooled connections
+//! - SFTP uploads and downloads that keep file modes
 //!
 //! ## Certificate Support
 //!
@@ -19,16 +22,32 @@
 //! supported due to limitations in the russh library. Users relying on agent-based
 //! certificate authentication should ensure the private key and certificate files
 //! are available on disk.
+//!
+//! ## Host Key Verification
+//!
+//! Server keys are checked against `~/.ssh/known_hosts` (or `UserKnownHostsFile`) and
+//! `/etc/ssh/ssh_known_hosts`, including hashed entries and `@revoked` lines. Hosts covered by
+//! an `@cert-authority` line are trusted if they offer a certificate signed by that CA for their
+//! key, fetched with `ssh-keyscan -c`. Unknown hosts are confirmed through an [`SshPrompt`]
+//! according to `StrictHostKeyChecking`, and changed keys fail the connection with a
+//! [`HostKeyError`] unless checking is turned off.
 
+mod known_hosts;
 mod pool;
+mod prompt;
 mod session;
+mod sftp;
 mod ssh_env;
 mod ssh_pool;
+mod tunnel;
 
 #[cfg(test)]
 mod integration_tests;
 
+pub use known_hosts::{HostKeyError, StrictHostKeyChecking};
 pub use pool::Pool;
+pub use prompt::{ChallengePrompt, KeyboardInteractiveChallenge, SshPrompt};
 pub use session::{Authentication, CommandResult, OutputLine, Session, SshConfig, SshWarning};
+pub use sftp::{SftpTransfer, TransferProgress, UploadSource};
 pub use ssh_env::build_env_exports;
 pub use ssh_pool::{SshPoolHandle, SshPty};
@@ -1,14 +1,15 @@
+use super::prompt::SshPrompt;
 use super::session::{AuthResult, Authentication, Session};
 use crate::context::DocumentSshConfig;
 use eyre::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, RwLock};
 
 // A pool of ssh connections
 // This avoids opening several ssh connections to the same host
-// Intended to be wrapped by an actor in our runtime, so we do not use
-// thread safe primitives
+// Intended to be wrapped by an actor in our runtime. Tasks that share the pool
+// connect through `Pool::connect_shared`, which keeps the lock short
 
 pub struct Pool {
     /// A map of ssh connections, host -> session
@@ -39,13 +40,14 @@ impl Pool {
         auth: Option<Authentication>,
         cancellation_rx: Option<oneshot::Receiver<()>>,
     ) -> Result<(Arc<Session>, AuthResult)> {
-        self.connect_with_config(host, username, auth, cancellation_rx, None)
+        self.connect_with_config(host, username, auth, cancellation_rx, None, None)
             .await
     }
 
     /// Connect to a host with optional block configuration overrides
     /// If the session already exists, return it (with no warnings)
     /// If the existing session is dead, remove it and create a new one
+    /// New connections to unknown hosts are confirmed through `ssh_prompt`
     pub async fn connect_with_config(
         &mut self,
         host: &str,
@@ -53,17 +55,9 @@ impl Pool {
         auth: Option<Authentication>,
         cancellation_rx: Option<oneshot::Receiver<()>>,
         ssh_config_override: Option<&DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     ) -> Result<(Arc<Session>, AuthResult)> {
-        let ssh_config = Session::resolve_ssh_config(host);
-
-        // Determine username: block override > provided > SSH config > current user
-        let username = ssh_config_override
-            .and_then(|cfg| cfg.user.as_deref())
-            .or(username)
-            .map(|u| u.to_string())
-            .or(ssh_config.username)
-            .unwrap_or_else(whoami::username);
-
+        let username = Self::resolve_username(host, username, ssh_config_override);
         let key = format!("{username}@{host}");
 
         tracing::debug!("connecting to {key}");
@@ -80,6 +74,101 @@ impl Pool {
             }
         }
 
+        let (session, auth_result) = Self::open_session(
+            host,
+            &username,
+            auth,
+            cancellation_rx,
+            ssh_config_override,
+            ssh_prompt,
+        )
+        .await?;
+
+        let session = Arc::new(session);
+        self.connections.insert(key, session.clone());
+
+        Ok((session, auth_result))
+    }
+
+    /// Connect to a host through a pool shared between tasks
+    ///
+    /// Behaves like [`Pool::connect_with_config`], but only takes the lock to look up and store
+    /// the session. The handshake and authentication run without it, so a host key prompt or a
+    /// 2FA challenge waiting on the user doesn't hold up connections to other hosts.
+    pub async fn connect_shared(
+        pool: &RwLock<Pool>,
+        host: &str,
+        username: Option<&str>,
+        auth: Option<Authentication>,
+        cancellation_rx: Option<oneshot::Receiver<()>>,
+        ssh_config_override: Option<&DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+    ) -> Result<(Arc<Session>, AuthResult)> {
+        let username = Self::resolve_username(host, username, ssh_config_override);
+        let key = format!("{username}@{host}");
+
+        tracing::debug!("connecting to {key}");
+
+        let existing = pool.read().await.get(host, &username);
+        if let Some(session) = existing {
+            tracing::debug!("found existing ssh session in pool");
+            if session.send_keepalive().await {
+                tracing::debug!("session keepalive success");
+                return Ok((session, AuthResult::default()));
+            }
+
+            tracing::debug!("Removing dead SSH connection for {key}");
+            let mut pool = pool.write().await;
+            // Another task may have replaced it with a live session in the meantime
+            if pool
+                .connections
+                .get(&key)
+                .is_some_and(|current| Arc::ptr_eq(current, &session))
+            {
+                pool.connections.remove(&key);
+            }
+        }
+
+        let (session, auth_result) = Self::open_session(
+            host,
+            &username,
+            auth,
+            cancellation_rx,
+            ssh_config_override,
+            ssh_prompt,
+        )
+        .await?;
+
+        let session = Arc::new(session);
+        pool.write().await.connections.insert(key, session.clone());
+
+        Ok((session, auth_result))
+    }
+
+    /// Determine username: block override > provided > SSH config > current user
+    fn resolve_username(
+        host: &str,
+        username: Option<&str>,
+        ssh_config_override: Option<&DocumentSshConfig>,
+    ) -> String {
+        ssh_config_override
+            .and_then(|cfg| cfg.user.as_deref())
+            .or(username)
+            .map(|u| u.to_string())
+            .or_else(|| Session::resolve_ssh_config(host).username)
+            .unwrap_or_else(whoami::username)
+    }
+
+    /// Open and authenticate a new session, giving up if `cancellation_rx` fires first
+    async fn open_session(
+        host: &str,
+        username: &str,
+        auth: Option<Authentication>,
+        cancellation_rx: Option<oneshot::Receiver<()>>,
+        ssh_config_override: Option<&DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+    ) -> Result<(Session, AuthResult)> {
+        let key = format!("{username}@{host}");
         let identity_key_config = ssh_config_override.and_then(|cfg| cfg.identity_key.as_ref());
         let certificate_config = ssh_config_override.and_then(|cfg| cfg.certificate.as_ref());
         tracing::debug!(
@@ -90,11 +179,12 @@ impl Pool {
         );
 
         let async_session = async {
-            let mut session = Session::open_with_config(host, ssh_config_override).await?;
+            let mut session =
+                Session::open_with_config(host, ssh_config_override, ssh_prompt).await?;
             let auth_result = session
                 .authenticate_with_config(
                     auth,
-                    Some(&username),
+                    Some(username),
                     identity_key_config,
                     certificate_config,
                 )
@@ -103,24 +193,17 @@ impl Pool {
         };
 
         tracing::debug!("Creating new SSH connection for {key}");
-        let (session, auth_result) = if let Some(mut cancellation_rx) = cancellation_rx {
+        if let Some(mut cancellation_rx) = cancellation_rx {
             tokio::select! {
-                result = async_session => {
-                    result?
-                }
+                result = async_session => result,
                 _ = &mut cancellation_rx => {
                     tracing::debug!("SSH connection {key} cancelled");
-                    return Err(eyre::eyre!("SSH connection cancelled"));
+                    Err(eyre::eyre!("SSH connection cancelled"))
                 }
             }
         } else {
-            async_session.await?
-        };
-
-        let session = Arc::new(session);
-        self.connections.insert(key, session.clone());
-
-        Ok((session, auth_result))
+            async_session.await
+        }
     }
 
     pub fn get(&self, host: &str, username: &str) -> Option<Arc<Session>> {
@@ -0,0 +1,41 @@
+// Questions the SSH layer needs a person to answer while connecting.
+
+use async_trait::async_trait;
+
+/// One question in a keyboard-interactive challenge, such as "Password:" or "Passcode:"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengePrompt {
+    pub prompt: String,
+    /// Whether the answer may be shown as it is typed; secrets such as OTPs are not echoed
+    pub echo: bool,
+}
+
+/// A keyboard-interactive (RFC 4256) request from the server, as used for 2FA, Duo and OTPs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardInteractiveChallenge {
+    /// The `host[:port]` being authenticated to
+    pub host: String,
+    pub name: String,
+    pub instructions: String,
+    /// May be empty, for example while the server waits on a push notification
+    pub prompts: Vec<ChallengePrompt>,
+}
+
+/// Asks the user questions that come up while connecting to a host
+#[async_trait]
+pub trait SshPrompt: Send + Sync {
+    /// Ask whether to trust a host whose key is not in known_hosts
+    ///
+    /// Returns `true` if the user chose to trust the key
+    async fn confirm_unknown_host(&self, host: &str, key_type: &str, fingerprint: &str) -> bool;
+
+    /// Ask the user to answer a keyboard-interactive challenge
+    ///
+    /// Returns one answer per prompt, in order, or `None` if the user cancelled or cannot be asked.
+    async fn answer_challenge(
+        &self,
+        _challenge: &KeyboardInteractiveChallenge,
+    ) -> Option<Vec<String>> {
+        None
+    }
+}
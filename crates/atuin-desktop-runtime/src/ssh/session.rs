@@ -17,8 +17,15 @@ use russh_config::*;
 use time::OffsetDateTime;
 
 use crate::context::{DocumentSshConfig, SshCertificateConfig, SshIdentityKeyConfig};
+use crate::ssh::known_hosts::{
+    self, HostKeyError, HostKeyStatus, KnownHosts, StrictHostKeyChecking,
+};
+use crate::ssh::prompt::{ChallengePrompt, KeyboardInteractiveChallenge, SshPrompt};
 use crate::ssh::SshPoolHandle;
 
+/// Upper bound on keyboard-interactive rounds, in case a server keeps sending challenges
+const MAX_KEYBOARD_INTERACTIVE_ROUNDS: usize = 10;
+
 /// Guard struct to ensure temp file cleanup on drop
 struct TempFileGuard {
     path: PathBuf,
@@ -42,6 +49,10 @@ pub struct CommandResult {
 pub struct Session {
     session: Handle<Client>,
     ssh_config: SshConfig,
+    /// Answers keyboard-interactive challenges during authentication
+    ssh_prompt: Option<Arc<dyn SshPrompt>>,
+    /// Set when the server accepted a key but wants another method too, e.g. key + OTP
+    partial_success: bool,
 }
 
 /// SSH connection configuration resolved from SSH config
@@ -54,6 +65,11 @@ pub struct SshConfig {
     pub proxy_command: Option<String>,
     pub proxy_jump: Option<String>,
     pub identity_agent: Option<String>,
+    /// Files consulted for host keys; newly trusted keys are written to the first
+    pub known_hosts_files: Vec<PathBuf>,
+    pub strict_host_key_checking: StrictHostKeyChecking,
+    /// Agent socket to forward to the server, from `ForwardAgent`
+    pub forward_agent: Option<String>,
 }
 
 /// Authentication methods
@@ -116,21 +132,194 @@ impl OutputLine {
 }
 
 /// SSH client implementation for russh
-pub struct Client;
+pub struct Client {
+    hostname: String,
+    port: u16,
+    known_hosts_files: Vec<PathBuf>,
+    strict_host_key_checking: StrictHostKeyChecking,
+    ssh_prompt: Option<Arc<dyn SshPrompt>>,
+    forward_agent: Option<String>,
+    /// Set when the server's key is refused, so the connect error can say why
+    rejection: Arc<std::sync::Mutex<Option<HostKeyError>>>,
+}
+
+impl Client {
+    fn new(ssh_config: &SshConfig, ssh_prompt: Option<Arc<dyn SshPrompt>>) -> Self {
+        Self {
+            hostname: ssh_config.hostname.clone(),
+            port: ssh_config.port,
+            known_hosts_files: ssh_config.known_hosts_files.clone(),
+            strict_host_key_checking: ssh_config.strict_host_key_checking,
+            ssh_prompt,
+            forward_agent: ssh_config.forward_agent.clone(),
+            rejection: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Check a server key against known_hosts, asking the user about unknown hosts
+    async fn verify_server_key(
+        &self,
+        server_public_key: &russh::keys::PublicKey,
+    ) -> Result<(), HostKeyError> {
+        let host = known_hosts::host_entry_name(&self.hostname, self.port);
+        let fingerprint = known_hosts::fingerprint(server_public_key);
+        let known = KnownHosts::load(&self.known_hosts_files);
+
+        let mut status = known.check(&self.hostname, self.port, server_public_key);
+        if matches!(
+            status,
+            HostKeyStatus::Unknown | HostKeyStatus::Changed { .. }
+        ) && known.has_cert_authority(&self.hostname, self.port)
+        {
+            status = self
+                .check_host_certificates(&known, server_public_key)
+                .await
+                .unwrap_or(status);
+        }
+
+        match status {
+            HostKeyStatus::Trusted => Ok(()),
+            HostKeyStatus::Revoked { path, line } => Err(HostKeyError::Revoked {
+                host,
+                fingerprint,
+                path,
+                line,
+            }),
+            HostKeyStatus::Changed { path, line }
+                if self.strict_host_key_checking != StrictHostKeyChecking::No =>
+            {
+                Err(HostKeyError::Changed {
+                    host,
+                    fingerprint,
+                    path,
+                    line,
+                })
+            }
+            HostKeyStatus::Changed { path, line } => {
+                tracing::warn!(
+                    "Host key for {host} has changed (old key at {}:{line}), connecting anyway as StrictHostKeyChecking is off",
+                    path.display()
+                );
+                Ok(())
+            }
+            HostKeyStatus::Unknown => {
+                let trusted = match self.strict_host_key_checking {
+                    StrictHostKeyChecking::Yes => false,
+                    StrictHostKeyChecking::AcceptNew | StrictHostKeyChecking::No => true,
+                    StrictHostKeyChecking::Ask => match &self.ssh_prompt {
+                        Some(prompt) => {
+                            prompt
+                                .confirm_unknown_host(
+                                    &host,
+                                    server_public_key.algorithm().as_str(),
+                                    &fingerprint,
+                                )
+                                .await
+                        }
+                        None => false,
+                    },
+                };
+
+                if !trusted {
+                    return Err(HostKeyError::Unknown { host, fingerprint });
+                }
+
+                if let Some(path) = self.known_hosts_files.first() {
+                    match known_hosts::learn(path, &self.hostname, self.port, server_public_key) {
+                        Ok(()) => tracing::info!(
+                            "Added host key for {host} ({fingerprint}) to {}",
+                            path.display()
+                        ),
+                        Err(e) => tracing::warn!(
+                            "Failed to add host key for {host} to {}: {e}",
+                            path.display()
+                        ),
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Look for a certificate the server offers for its key that a listed CA vouches for
+    ///
+    /// Returns `None` if no certificate settles whether the key is trusted.
+    async fn check_host_certificates(
+        &self,
+        known: &KnownHosts,
+        server_public_key: &russh::keys::PublicKey,
+    ) -> Option<HostKeyStatus> {
+        let certificates = known_hosts::scan_host_certificates(&self.hostname, self.port).await;
+        certificates
+            .iter()
+            .map(|certificate| {
+                known.check_certificate(&self.hostname, self.port, server_public_key, certificate)
+            })
+            .find(|status| *status != HostKeyStatus::Unknown)
+    }
+}
 
 impl russh::client::Handler for Client {
     type Error = russh::Error;
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &russh::keys::PublicKey,
+        server_public_key: &russh::keys::PublicKey,
     ) -> Result<bool, Self::Error> {
-        // For now, accept all server keys
-        // In production, you'd want to implement proper host key verification
-        Ok(true)
+        match self.verify_server_key(server_public_key).await {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                tracing::warn!("Refusing SSH connection: {e}");
+                *self.rejection.lock().unwrap() = Some(e);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn server_channel_open_agent_forward(
+        &mut self,
+        channel: Channel<client::Msg>,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        match self.forward_agent.clone() {
+            Some(socket) => {
+                tokio::spawn(forward_agent_channel(channel, socket));
+            }
+            None => {
+                // Dropping the channel closes it
+                tracing::warn!(
+                    "{} opened an agent channel, but ForwardAgent is off",
+                    self.hostname
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Relay an agent channel opened by the server to the local agent at `socket`
+#[cfg(unix)]
+async fn forward_agent_channel(channel: Channel<client::Msg>, socket: String) {
+    let mut agent = match tokio::net::UnixStream::connect(&socket).await {
+        Ok(agent) => agent,
+        Err(e) => {
+            tracing::warn!("Failed to connect to SSH agent at {socket} for forwarding: {e}");
+            return;
+        }
+    };
+
+    let mut stream = channel.into_stream();
+    if let Err(e) = tokio::io::copy_bidirectional(&mut stream, &mut agent).await {
+        tracing::debug!("Forwarded agent connection closed: {e}");
     }
 }
 
+#[cfg(not(unix))]
+async fn forward_agent_channel(_channel: Channel<client::Msg>, socket: String) {
+    tracing::warn!("Agent forwarding to {socket} is only supported on Unix");
+}
+
 impl Session {
     /// Execute a simple command and capture its output
     /// This opens a new channel, runs the command through a shell, and returns stdout, stderr, and exit code
@@ -268,6 +457,51 @@ impl Session {
         Ok(())
     }
 
+    /// Ask the server to forward the agent over `channel`, if `ForwardAgent` is set
+    ///
+    /// Failures are only logged, as the command can still run without the agent.
+    async fn request_agent_forward(&self, channel: &Channel<client::Msg>) {
+        if self.ssh_config.forward_agent.is_none() {
+            return;
+        }
+
+        if let Err(e) = channel.agent_forward(false).await {
+            tracing::warn!(
+                "Failed to request agent forwarding to {}: {e}",
+                self.ssh_config.hostname
+            );
+        }
+    }
+
+    /// Start an SFTP session on a new channel
+    pub async fn open_sftp(&self) -> Result<russh_sftp::client::SftpSession> {
+        let channel = self.session.channel_open_session().await?;
+        channel.request_subsystem(true, "sftp").await?;
+
+        Ok(russh_sftp::client::SftpSession::new(channel.into_stream()).await?)
+    }
+
+    /// Open a `direct-tcpip` channel to `host:port` as seen from the remote system
+    /// `originator` is the local peer that the forwarded connection came from
+    pub async fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+        originator: std::net::SocketAddr,
+    ) -> Result<ChannelStream<client::Msg>> {
+        let channel = self
+            .session
+            .channel_open_direct_tcpip(
+                host,
+                port as u32,
+                originator.ip().to_string(),
+                originator.port() as u32,
+            )
+            .await?;
+
+        Ok(channel.into_stream())
+    }
+
     /// Send a keepalive to test if the SSH connection is still active and responsive
     /// Uses a lightweight exec command that actually tests network connectivity
     pub async fn send_keepalive(&self) -> bool {
@@ -310,6 +544,82 @@ impl Session {
 
     /// Helper function to parse IdentityAgent from a specific config file path
     fn parse_identity_agent_from_path(host: &str, config_path: &std::path::Path) -> Option<String> {
+        let value = Self::parse_option_from_path(host, config_path, "identityagent")?;
+
+        // Expand ~ to home directory
+        if let Some(pref) = value.strip_prefix("~/") {
+            if let Some(home) = dirs::home_dir() {
+                return Some(home.join(pref).to_string_lossy().to_string());
+            }
+        }
+        Some(value)
+    }
+
+    /// Parse UserKnownHostsFile from SSH config, falling back to ~/.ssh/known_hosts
+    fn parse_known_hosts_files_from_path(host: &str, config_path: &Path) -> Vec<PathBuf> {
+        let Some(home) = dirs::home_dir() else {
+            return Vec::new();
+        };
+
+        match Self::parse_option_from_path(host, config_path, "userknownhostsfile") {
+            Some(value) if !value.eq_ignore_ascii_case("none") => value
+                .split_whitespace()
+                .map(|file| match file.strip_prefix("~/") {
+                    Some(pref) => home.join(pref),
+                    None => PathBuf::from(file),
+                })
+                .collect(),
+            Some(_) => Vec::new(),
+            None => vec![home.join(".ssh").join("known_hosts")],
+        }
+    }
+
+    /// Parse StrictHostKeyChecking from SSH config, defaulting to asking the user
+    fn parse_strict_host_key_checking_from_path(
+        host: &str,
+        config_path: &Path,
+    ) -> StrictHostKeyChecking {
+        Self::parse_option_from_path(host, config_path, "stricthostkeychecking")
+            .and_then(|value| StrictHostKeyChecking::from_config(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parse ForwardAgent from SSH config into the agent socket to forward, if any
+    ///
+    /// `yes` forwards the agent used for authentication (`IdentityAgent` or `SSH_AUTH_SOCK`);
+    /// any other value than `no` is a socket path or the name of a variable holding one.
+    fn parse_forward_agent_from_path(
+        host: &str,
+        config_path: &Path,
+        identity_agent: Option<&str>,
+    ) -> Option<String> {
+        let value = Self::parse_option_from_path(host, config_path, "forwardagent")?;
+
+        match value.to_lowercase().as_str() {
+            "no" => None,
+            "yes" => identity_agent
+                .map(str::to_string)
+                .or_else(|| std::env::var("SSH_AUTH_SOCK").ok()),
+            _ => {
+                if let Some(var) = value.strip_prefix('$') {
+                    return std::env::var(var).ok();
+                }
+                if let Some(pref) = value.strip_prefix("~/") {
+                    if let Some(home) = dirs::home_dir() {
+                        return Some(home.join(pref).to_string_lossy().to_string());
+                    }
+                }
+                Some(value)
+            }
+        }
+    }
+
+    /// Find the first value of a (lowercase) option in the Host sections matching `host`
+    fn parse_option_from_path(
+        host: &str,
+        config_path: &std::path::Path,
+        option: &str,
+    ) -> Option<String> {
         if !config_path.exists() {
             return None;
         }
@@ -343,18 +653,12 @@ impl Session {
                     }
                 });
             } else if current_host_matches {
-                // Parse IdentityAgent under the matching host
+                // Parse the option under the matching host
                 if let Some((key, value)) = line.split_once(' ').or_else(|| line.split_once('\t')) {
                     let key = key.trim().to_lowercase();
                     let value = value.trim().trim_matches('"');
 
-                    if key == "identityagent" {
-                        // Expand ~ to home directory
-                        if let Some(pref) = value.strip_prefix("~/") {
-                            if let Some(home) = dirs::home_dir() {
-                                return Some(home.join(pref).to_string_lossy().to_string());
-                            }
-                        }
+                    if key == option {
                         return Some(value.to_string());
                     }
                 }
@@ -400,6 +704,14 @@ impl Session {
         // Parse the input to extract user, hostname, and port
         let (input_user, hostname, input_port) = Self::parse_host_string(host);
 
+        let user_config_path = dirs::home_dir()
+            .map(|home| home.join(".ssh").join("config"))
+            .unwrap_or_default();
+        let known_hosts_files =
+            Self::parse_known_hosts_files_from_path(&hostname, &user_config_path);
+        let strict_host_key_checking =
+            Self::parse_strict_host_key_checking_from_path(&hostname, &user_config_path);
+
         let default_config = SshConfig {
             hostname: hostname.clone(),
             port: input_port.unwrap_or(22),
@@ -408,6 +720,9 @@ impl Session {
             proxy_command: None,
             proxy_jump: None,
             identity_agent: None,
+            known_hosts_files: known_hosts_files.clone(),
+            strict_host_key_checking,
+            forward_agent: Self::parse_forward_agent_from_path(&hostname, &user_config_path, None),
         };
 
         // Try to read SSH config using russh-config
@@ -456,6 +771,11 @@ impl Session {
 
                     // Parse IdentityAgent manually since russh-config doesn't support it
                     let identity_agent = Self::parse_identity_agent(&hostname);
+                    let forward_agent = Self::parse_forward_agent_from_path(
+                        &hostname,
+                        &user_config_path,
+                        identity_agent.as_deref(),
+                    );
 
                     tracing::debug!(
                         "Resolved SSH config for {host}: hostname={hostname}, port={port}, username={username:?}, identity_files={identity_files:?}, proxy_command={proxy_command:?}, proxy_jump={proxy_jump:?}"
@@ -469,6 +789,9 @@ impl Session {
                         proxy_command,
                         proxy_jump,
                         identity_agent,
+                        known_hosts_files,
+                        strict_host_key_checking,
+                        forward_agent,
                     };
                 }
                 Err(e) => {
@@ -482,11 +805,14 @@ impl Session {
     }
 
     /// Open a new SSH session to the given host, and connect
+    ///
+    /// Unknown host keys are only accepted if ssh config allows it, as there is nobody to ask.
     pub async fn open(host: &str) -> Result<Self> {
         let ssh_config = Self::resolve_ssh_config(host);
 
         let config = russh::client::Config::default();
-        let sh = Client;
+        let sh = Client::new(&ssh_config, None);
+        let rejection = sh.rejection.clone();
 
         // Parse the hostname for proxy connections
         let (_, hostname, _) = Self::parse_host_string(host);
@@ -504,34 +830,40 @@ impl Session {
             match parse_home(&hostname) {
                 Ok(parsed_config) => {
                     let stream = parsed_config.stream().await?;
-                    russh::client::connect_stream(Arc::new(config), stream, sh).await?
+                    russh::client::connect_stream(Arc::new(config), stream, sh).await
                 }
                 Err(e) => {
                     tracing::warn!("Failed to create proxy stream: {e}");
                     // Fallback to direct connection
                     let address = format!("{}:{}", ssh_config.hostname, ssh_config.port);
                     tracing::debug!("Falling back to direct connection: {address}");
-                    russh::client::connect(Arc::new(config), address.as_str(), sh).await?
+                    russh::client::connect(Arc::new(config), address.as_str(), sh).await
                 }
             }
         } else {
             // Direct connection
             let address = format!("{}:{}", ssh_config.hostname, ssh_config.port);
             tracing::debug!("Connecting directly to: {address}");
-            russh::client::connect(Arc::new(config), address.as_str(), sh).await?
+            russh::client::connect(Arc::new(config), address.as_str(), sh).await
         };
+        let session = Self::check_host_key_rejection(session, &rejection)?;
 
         Ok(Session {
             session,
             ssh_config,
+            ssh_prompt: None,
+            partial_success: false,
         })
     }
 
     /// Open a new SSH session with optional configuration overrides from block settings.
     /// Block settings take precedence over SSH config file.
+    /// If given, `ssh_prompt` is asked whether to trust hosts missing from known_hosts,
+    /// and to answer keyboard-interactive challenges.
     pub async fn open_with_config(
         host: &str,
         config_override: Option<&DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     ) -> Result<Self> {
         let mut ssh_config = Self::resolve_ssh_config(host);
 
@@ -558,7 +890,8 @@ impl Session {
         }
 
         let config = russh::client::Config::default();
-        let sh = Client;
+        let sh = Client::new(&ssh_config, ssh_prompt.clone());
+        let rejection = sh.rejection.clone();
 
         // Handle ProxyCommand and ProxyJump
         let session = if ssh_config.proxy_command.is_some() || ssh_config.proxy_jump.is_some() {
@@ -572,24 +905,39 @@ impl Session {
             match parse_home(&ssh_config.hostname) {
                 Ok(parsed_config) => {
                     let stream = parsed_config.stream().await?;
-                    russh::client::connect_stream(Arc::new(config), stream, sh).await?
+                    russh::client::connect_stream(Arc::new(config), stream, sh).await
                 }
                 Err(e) => {
                     tracing::warn!("Failed to create proxy stream: {e}");
                     let address = format!("{}:{}", ssh_config.hostname, ssh_config.port);
                     tracing::debug!("Falling back to direct connection: {address}");
-                    russh::client::connect(Arc::new(config), address.as_str(), sh).await?
+                    russh::client::connect(Arc::new(config), address.as_str(), sh).await
                 }
             }
         } else {
             let address = format!("{}:{}", ssh_config.hostname, ssh_config.port);
             tracing::debug!("Connecting directly to: {address}");
-            russh::client::connect(Arc::new(config), address.as_str(), sh).await?
+            russh::client::connect(Arc::new(config), address.as_str(), sh).await
         };
+        let session = Self::check_host_key_rejection(session, &rejection)?;
 
         Ok(Session {
             session,
             ssh_config,
+            ssh_prompt,
+            partial_success: false,
+        })
+    }
+
+    /// Surface a refused host key as a `HostKeyError`, rather than russh's generic error,
+    /// so callers can downcast it
+    fn check_host_key_rejection(
+        result: Result<Handle<Client>, russh::Error>,
+        rejection: &std::sync::Mutex<Option<HostKeyError>>,
+    ) -> Result<Handle<Client>> {
+        result.map_err(|e| match rejection.lock().unwrap().take() {
+            Some(host_key_error) => eyre::Report::new(host_key_error),
+            None => e.into(),
         })
     }
 
@@ -705,6 +1053,7 @@ impl Session {
                 remaining_methods,
                 partial_success,
             } => {
+                self.partial_success |= partial_success;
                 tracing::warn!(
                     "Server rejected key {} (remaining methods: {:?}, partial: {})",
                     key_path.display(),
@@ -772,7 +1121,12 @@ impl Session {
 
         match auth_res {
             russh::client::AuthResult::Success => Ok(()),
-            _ => Err(eyre::eyre!("Public key authentication failed")),
+            russh::client::AuthResult::Failure {
+                partial_success, ..
+            } => {
+                self.partial_success |= partial_success;
+                Err(eyre::eyre!("Public key authentication failed"))
+            }
         }
     }
 
@@ -908,6 +1262,7 @@ impl Session {
                 remaining_methods,
                 partial_success,
             } => {
+                self.partial_success |= partial_success;
                 tracing::warn!(
                     "Server rejected certificate {} (remaining methods: {:?}, partial: {})",
                     cert_source,
@@ -959,6 +1314,17 @@ impl Session {
                                 );
                                 return Ok(true);
                             }
+                            Ok(russh::client::AuthResult::Failure {
+                                partial_success: true,
+                                ..
+                            }) => {
+                                tracing::info!(
+                                    "SSH agent key #{} accepted, but the server wants another method",
+                                    i + 1
+                                );
+                                self.partial_success = true;
+                                return Ok(false);
+                            }
                             Ok(_) => {
                                 tracing::debug!("SSH agent key #{} rejected by server", i + 1);
                                 continue;
@@ -984,13 +1350,89 @@ impl Session {
         }
     }
 
+    /// Keyboard-interactive authentication, answering each challenge through the session's prompt
+    ///
+    /// Returns `Ok(false)` if the server refuses it or there is nobody to ask.
+    pub async fn keyboard_interactive_auth(&mut self, username: &str) -> Result<bool> {
+        let Some(prompt) = self.ssh_prompt.clone() else {
+            tracing::debug!("Skipping keyboard-interactive authentication, nobody to prompt");
+            return Ok(false);
+        };
+
+        tracing::info!("Attempting keyboard-interactive authentication for {username}");
+        let host = known_hosts::host_entry_name(&self.ssh_config.hostname, self.ssh_config.port);
+        let mut response = self
+            .session
+            .authenticate_keyboard_interactive_start(username, None::<String>)
+            .await?;
+
+        for _ in 0..MAX_KEYBOARD_INTERACTIVE_ROUNDS {
+            match response {
+                russh::client::KeyboardInteractiveAuthResponse::Success => return Ok(true),
+                russh::client::KeyboardInteractiveAuthResponse::Failure { .. } => {
+                    tracing::info!("Server rejected keyboard-interactive authentication");
+                    return Ok(false);
+                }
+                russh::client::KeyboardInteractiveAuthResponse::InfoRequest {
+                    name,
+                    instructions,
+                    prompts,
+                } => {
+                    let answers = if prompts.is_empty() {
+                        Vec::new()
+                    } else {
+                        let challenge = KeyboardInteractiveChallenge {
+                            host: host.clone(),
+                            name,
+                            instructions,
+                            prompts: prompts
+                                .into_iter()
+                                .map(|p| ChallengePrompt {
+                                    prompt: p.prompt,
+                                    echo: p.echo,
+                                })
+                                .collect(),
+                        };
+                        prompt.answer_challenge(&challenge).await.ok_or_else(|| {
+                            eyre::eyre!("Keyboard-interactive authentication was cancelled")
+                        })?
+                    };
+
+                    response = self
+                        .session
+                        .authenticate_keyboard_interactive_respond(answers)
+                        .await?;
+                }
+            }
+        }
+
+        Err(eyre::eyre!(
+            "Keyboard-interactive authentication did not finish after {MAX_KEYBOARD_INTERACTIVE_ROUNDS} challenges"
+        ))
+    }
+
+    /// Finish a login the server only partly accepted, such as a key that also needs an OTP
+    async fn complete_partial_auth(&mut self, username: &str) -> Result<AuthResult> {
+        tracing::info!("Server requires another factor, continuing with keyboard-interactive");
+        if self.keyboard_interactive_auth(username).await? {
+            return Ok(AuthResult::default());
+        }
+
+        Err(eyre::eyre!(
+            "The server accepted the key but requires another factor, and keyboard-interactive authentication failed"
+        ))
+    }
+
     /// Authenticate the session. If a username is provided, use it for authentication - otherwise we will use SSH config or the current user
     ///
     /// The authentication order matches the ssh command:
     /// 1. SSH Agent authentication
     /// 2. SSH config identity files
     /// 3. Default SSH keys (id_rsa, id_ecdsa, id_ecdsa_sk, id_ed25519, id_ed25519_sk, id_xmss, id_dsa)
-    /// 4. Provided authentication method (password or key)
+    /// 4. Provided authentication method (password or key), or keyboard-interactive if none
+    ///
+    /// If the server accepts a key but requires a second factor, keyboard-interactive
+    /// authentication is used straight away to finish logging in.
     ///
     /// Returns AuthResult containing any warnings from the authentication process
     pub async fn authenticate(
@@ -1024,6 +1466,9 @@ impl Session {
             tracing::info!("✓ SSH authentication successful with agent");
             return Ok(AuthResult::default());
         }
+        if self.partial_success {
+            return self.complete_partial_auth(username).await;
+        }
         tracing::info!("✗ SSH agent authentication failed or unavailable");
 
         // 2. Try SSH config identity files
@@ -1038,6 +1483,9 @@ impl Session {
             {
                 return Ok(auth_result);
             }
+            if self.partial_success {
+                return self.complete_partial_auth(username).await;
+            }
         }
 
         // 3. Try default SSH keys if not already tried via config
@@ -1063,6 +1511,9 @@ impl Session {
                     tracing::debug!("Default SSH key failed: {e}");
                 }
             }
+            if self.partial_success {
+                return self.complete_partial_auth(username).await;
+            }
         }
 
         // 4. whatever the user provided
@@ -1078,9 +1529,14 @@ impl Session {
                 return self.key_auth(username, &hostname, key_path).await;
             }
             None => {
+                if self.keyboard_interactive_auth(username).await? {
+                    tracing::info!("✓ SSH authentication successful with keyboard-interactive");
+                    return Ok(AuthResult::default());
+                }
+
                 tracing::warn!("All SSH authentication methods exhausted");
                 tracing::warn!(
-                    "Tried: SSH agent, {} config keys, {} default keys",
+                    "Tried: SSH agent, {} config keys, {} default keys, keyboard-interactive",
                     identity_files.len(),
                     default_keys.len()
                 );
@@ -1154,6 +1610,9 @@ impl Session {
                             tracing::info!("✓ SSH authentication successful with pasted key");
                             return Ok(auth_result);
                         }
+                        Err(_) if self.partial_success => {
+                            return self.complete_partial_auth(username).await;
+                        }
                         Err(e) => {
                             // Explicit key was configured but failed - do not fall back
                             return Err(eyre::eyre!(
@@ -1177,6 +1636,9 @@ impl Session {
                             tracing::info!("✓ SSH authentication successful with key: {}", path);
                             return Ok(auth_result);
                         }
+                        Err(_) if self.partial_success => {
+                            return self.complete_partial_auth(username).await;
+                        }
                         Err(e) => {
                             // Explicit key was configured but failed - do not fall back
                             return Err(eyre::eyre!(
@@ -1347,6 +1809,7 @@ impl Session {
         // For now, let's simplify this and just execute the command directly
         // without creating files on the remote
         let mut channel = self.session.channel_open_session().await?;
+        self.request_agent_forward(&channel).await;
 
         // Create the actual command to execute
         // Parse interpreter string into program and args
@@ -1397,12 +1860,13 @@ impl Session {
                     .send(OutputLine::Stderr(e.to_string()))
                     .await;
                 tracing::debug!("Sending exec finished for channel {channel_id_clone}");
-                let _ = handle.exec_finished(&channel_id_clone).await;
+                let _ = handle.exec_finished(&channel_id_clone, None).await;
                 return;
             }
 
             let mut line_buffer = String::new();
             let mut stderr_line_buffer = String::new();
+            let mut exit_status = None;
 
             loop {
                 tokio::select! {
@@ -1456,8 +1920,9 @@ impl Session {
                             // guarantee all Data messages have been delivered (RFC 4254
                             // §6.10). Only Eof guarantees no more data will follow.
                             // Continue reading until Eof or Close.
-                            ChannelMsg::ExitStatus { .. } => {
+                            ChannelMsg::ExitStatus { exit_status: status } => {
                                 tracing::trace!("Handling SSH ExitStatus message (continuing to read)");
+                                exit_status = Some(status);
                             }
                             ChannelMsg::Eof => {
                                 tracing::trace!("Handling SSH EOF message");
@@ -1486,7 +1951,7 @@ impl Session {
             }
 
             tracing::debug!("Sending exec finished for channel {channel_id_clone}");
-            let _ = handle.exec_finished(&channel_id_clone).await;
+            let _ = handle.exec_finished(&channel_id_clone, exit_status).await;
         });
 
         Ok(())
@@ -1509,6 +1974,7 @@ impl Session {
         let mut channel = timeout(SSH_OPERATION_TIMEOUT, self.session.channel_open_session())
             .await
             .map_err(|_| eyre::eyre!("Timeout opening SSH channel for PTY"))??;
+        self.request_agent_forward(&channel).await;
 
         // Request PTY
         timeout(
@@ -1761,6 +2227,92 @@ Host example.com
         assert_eq!(result, Some(expected));
     }
 
+    #[test]
+    fn test_parse_known_hosts_files() {
+        let config_content = r#"
+Host example.com
+    UserKnownHostsFile ~/.ssh/known_hosts_work /etc/ssh/work_known_hosts
+    StrictHostKeyChecking accept-new
+
+Host nokeys.com
+    UserKnownHostsFile none
+    StrictHostKeyChecking no
+"#;
+        let temp_dir = create_test_ssh_config(config_content);
+        let config_path = temp_dir.path().join(".ssh").join("config");
+        let home_dir = dirs::home_dir().unwrap();
+
+        assert_eq!(
+            Session::parse_known_hosts_files_from_path("example.com", &config_path),
+            vec![
+                home_dir.join(".ssh/known_hosts_work"),
+                PathBuf::from("/etc/ssh/work_known_hosts")
+            ]
+        );
+        assert_eq!(
+            Session::parse_strict_host_key_checking_from_path("example.com", &config_path),
+            StrictHostKeyChecking::AcceptNew
+        );
+
+        assert!(Session::parse_known_hosts_files_from_path("nokeys.com", &config_path).is_empty());
+        assert_eq!(
+            Session::parse_strict_host_key_checking_from_path("nokeys.com", &config_path),
+            StrictHostKeyChecking::No
+        );
+
+        assert_eq!(
+            Session::parse_known_hosts_files_from_path("other.com", &config_path),
+            vec![home_dir.join(".ssh/known_hosts")]
+        );
+        assert_eq!(
+            Session::parse_strict_host_key_checking_from_path("other.com", &config_path),
+            StrictHostKeyChecking::Ask
+        );
+    }
+
+    #[test]
+    fn test_parse_forward_agent() {
+        let config_content = r#"
+Host bastion
+    ForwardAgent yes
+
+Host build
+    ForwardAgent ~/.ssh/build-agent.sock
+
+Host quiet
+    ForwardAgent no
+"#;
+        let temp_dir = create_test_ssh_config(config_content);
+        let config_path = temp_dir.path().join(".ssh").join("config");
+        let home_dir = dirs::home_dir().unwrap();
+
+        assert_eq!(
+            Session::parse_forward_agent_from_path(
+                "bastion",
+                &config_path,
+                Some("/tmp/identity-agent.sock")
+            ),
+            Some("/tmp/identity-agent.sock".to_string())
+        );
+        assert_eq!(
+            Session::parse_forward_agent_from_path("build", &config_path, None),
+            Some(
+                home_dir
+                    .join(".ssh/build-agent.sock")
+                    .to_string_lossy()
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            Session::parse_forward_agent_from_path("quiet", &config_path, Some("/tmp/agent")),
+            None
+        );
+        assert_eq!(
+            Session::parse_forward_agent_from_path("other", &config_path, Some("/tmp/agent")),
+            None
+        );
+    }
+
     #[test]
     fn test_resolve_ssh_config_defaults() {
         // Test with a host that's unlikely to be in any real SSH config
@@ -0,0 +1,298 @@
+// File transfers over SFTP on a pooled SSH session.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use eyre::{Context, Result};
+use russh_sftp::protocol::{FileAttributes, OpenFlags};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::ssh::session::Session;
+
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Minimum time between progress reports, so large transfers don't flood the output stream
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What to upload to the remote system
+#[derive(Debug, Clone)]
+pub enum UploadSource {
+    /// A file on the local system; its mode is kept unless overridden
+    File(PathBuf),
+    /// In-memory content, such as a rendered template
+    Contents(Vec<u8>),
+}
+
+/// A file transfer in either direction
+#[derive(Debug, Clone)]
+pub enum SftpTransfer {
+    Upload {
+        source: UploadSource,
+        remote_path: String,
+        /// Overrides the mode the remote file is given
+        mode: Option<u32>,
+    },
+    Download {
+        remote_path: String,
+        local_path: PathBuf,
+        /// Overrides the mode the local file is given
+        mode: Option<u32>,
+    },
+}
+
+/// Bytes moved so far in a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub transferred: u64,
+    /// Size of the file, if known
+    pub total: Option<u64>,
+}
+
+/// Run `transfer` on a new SFTP session, returning the number of bytes copied
+pub(crate) async fn transfer(
+    session: &Session,
+    transfer: SftpTransfer,
+    progress: &mpsc::UnboundedSender<TransferProgress>,
+) -> Result<u64> {
+    match transfer {
+        SftpTransfer::Upload {
+            source,
+            remote_path,
+            mode,
+        } => upload(session, source, &remote_path, mode, progress).await,
+        SftpTransfer::Download {
+            remote_path,
+            local_path,
+            mode,
+        } => download(session, &remote_path, &local_path, mode, progress).await,
+    }
+}
+
+/// Upload `source` to `remote_path`, replacing any existing file
+///
+/// `mode` overrides the permissions the file is given; otherwise a local file's
+/// mode is copied, and in-memory content gets the server's default.
+async fn upload(
+    session: &Session,
+    source: UploadSource,
+    remote_path: &str,
+    mode: Option<u32>,
+    progress: &mpsc::UnboundedSender<TransferProgress>,
+) -> Result<u64> {
+    let (mut reader, total, local_mode): (Box<dyn AsyncRead + Unpin + Send>, _, _) = match source {
+        UploadSource::File(path) => {
+            let file = tokio::fs::File::open(&path)
+                .await
+                .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+            let metadata = file.metadata().await?;
+            (Box::new(file), Some(metadata.len()), file_mode(&metadata))
+        }
+        UploadSource::Contents(contents) => {
+            let len = contents.len() as u64;
+            (Box::new(std::io::Cursor::new(contents)), Some(len), None)
+        }
+    };
+
+    // Only the permissions are set; the empty attributes leave everything else alone
+    let attributes = mode.or(local_mode).map(|mode| FileAttributes {
+        permissions: Some(mode & 0o7777),
+        ..FileAttributes::empty()
+    });
+
+    let sftp = session.open_sftp().await?;
+    // A new file is created with the requested mode, so its contents are never readable
+    // with the server's default permissions
+    let mut remote = sftp
+        .open_with_flags_and_attributes(
+            remote_path,
+            OpenFlags::CREATE | OpenFlags::TRUNCATE | OpenFlags::WRITE,
+            attributes.clone().unwrap_or_else(FileAttributes::empty),
+        )
+        .await
+        .wrap_err_with(|| format!("Failed to create remote file {remote_path}"))?;
+
+    // An existing file keeps its mode, and the server's umask may have narrowed a new
+    // one, so set it before any content is written
+    if let Some(attributes) = attributes {
+        sftp.set_metadata(remote_path, attributes)
+            .await
+            .wrap_err_with(|| format!("Failed to set mode of {remote_path}"))?;
+    }
+
+    let transferred = copy_with_progress(&mut reader, &mut remote, total, progress).await?;
+    remote.shutdown().await?;
+
+    let _ = sftp.close().await;
+    Ok(transferred)
+}
+
+/// Download `remote_path` to `local_path`, replacing any existing file
+///
+/// The local file gets the remote file's mode unless `mode` overrides it.
+async fn download(
+    session: &Session,
+    remote_path: &str,
+    local_path: &Path,
+    mode: Option<u32>,
+    progress: &mpsc::UnboundedSender<TransferProgress>,
+) -> Result<u64> {
+    let sftp = session.open_sftp().await?;
+    let attributes = sftp
+        .metadata(remote_path)
+        .await
+        .wrap_err_with(|| format!("Failed to stat remote file {remote_path}"))?;
+
+    let mut remote = sftp
+        .open(remote_path)
+        .await
+        .wrap_err_with(|| format!("Failed to open remote file {remote_path}"))?;
+
+    // A new file is created with the final mode, and an existing one is narrowed to it,
+    // before any content is written
+    let mode = mode.or(attributes.permissions).map(|mode| mode & 0o7777);
+    let mut local = create_local_file(local_path, mode)
+        .await
+        .wrap_err_with(|| format!("Failed to create {}", local_path.display()))?;
+    if let Some(mode) = mode {
+        set_local_mode(local_path, mode)?;
+    }
+
+    let transferred =
+        copy_with_progress(&mut remote, &mut local, attributes.size, progress).await?;
+    local.flush().await?;
+    let _ = remote.shutdown().await;
+    let _ = sftp.close().await;
+
+    Ok(transferred)
+}
+
+async fn copy_with_progress<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    total: Option<u64>,
+    progress: &mpsc::UnboundedSender<TransferProgress>,
+) -> Result<u64>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut transferred = 0u64;
+    let mut last_report: Option<Instant> = None;
+
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        writer.write_all(&buf[..n]).await?;
+        transferred += n as u64;
+
+        if last_report.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL) {
+            last_report = Some(Instant::now());
+            let _ = progress.send(TransferProgress { transferred, total });
+        }
+    }
+
+    // Always finish on a report of the final count
+    let _ = progress.send(TransferProgress { transferred, total });
+
+    Ok(transferred)
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+async fn create_local_file(path: &Path, mode: Option<u32>) -> std::io::Result<tokio::fs::File> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+    options.open(path).await
+}
+
+#[cfg(not(unix))]
+async fn create_local_file(path: &Path, _mode: Option<u32>) -> std::io::Result<tokio::fs::File> {
+    tokio::fs::File::create(path).await
+}
+
+#[cfg(unix)]
+fn set_local_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .wrap_err_with(|| format!("Failed to set mode of {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_local_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_copy_with_progress() {
+        let data = vec![7u8; CHUNK_SIZE * 3 + 10];
+        let mut reader = std::io::Cursor::new(data.clone());
+        let mut writer = Vec::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let copied = copy_with_progress(&mut reader, &mut writer, Some(data.len() as u64), &tx)
+            .await
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(writer, data);
+
+        let mut reports = Vec::new();
+        while let Some(report) = rx.recv().await {
+            reports.push(report);
+        }
+        assert_eq!(
+            reports.last(),
+            Some(&TransferProgress {
+                transferred: data.len() as u64,
+                total: Some(data.len() as u64),
+            })
+        );
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].transferred <= w[1].transferred));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_create_local_file_with_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("id_ed25519");
+
+        create_local_file(&path, Some(0o600)).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        create_local_file(&path, Some(0o600)).await.unwrap();
+        set_local_mode(&path, 0o600).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -1,6 +1,7 @@
 // An actor for managing SSH connections
 
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -12,6 +13,9 @@ use crate::context::DocumentSshConfig;
 use crate::pty::PtyMetadata;
 use crate::ssh::pool::Pool;
 use crate::ssh::session::{Authentication, OutputLine, Session, SshWarning};
+use crate::ssh::sftp::{self, SftpTransfer, TransferProgress};
+use crate::ssh::tunnel;
+use crate::ssh::SshPrompt;
 use eyre::Result;
 use std::sync::Arc;
 
@@ -89,17 +93,22 @@ pub enum SshPoolMessage {
         // The actual result of the exec command
         reply_to: oneshot::Sender<Result<()>>,
 
-        // Stored internally and used for the corresponding exec_finished message
-        result_tx: oneshot::Sender<()>,
+        // Stored internally and used for the corresponding exec_finished message,
+        // receives the command's exit status if the server sent one
+        result_tx: oneshot::Sender<Option<u32>>,
 
         // Optional SSH config overrides from block settings
         ssh_config: Option<DocumentSshConfig>,
 
         // Channel to send authentication warnings (certificate issues, etc.)
         warnings_tx: Option<oneshot::Sender<Vec<SshWarning>>>,
+
+        // Asked whether to trust the host if its key is not in known_hosts
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     },
     ExecFinished {
         channel: String,
+        exit_status: Option<u32>,
         reply_to: oneshot::Sender<Result<()>>,
     },
     ExecCancel {
@@ -115,6 +124,8 @@ pub enum SshPoolMessage {
         output_stream: mpsc::Sender<String>,
         // SSH config with identity key overrides
         ssh_config: Option<DocumentSshConfig>,
+        // Asked whether to trust the host if its key is not in known_hosts
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
 
         // The actual result of the open_pty command
         // returns a channel to send input to the pty, plus any auth warnings
@@ -130,6 +141,45 @@ pub enum SshPoolMessage {
     ClosePty {
         channel: String,
     },
+    OpenTunnel {
+        host: String,
+        username: Option<String>,
+        channel: String,
+        // Local port to listen on; 0 picks a free one
+        local_port: u16,
+        // Target of the forward, resolved by the remote system
+        remote_host: String,
+        remote_port: u16,
+        ssh_config: Option<DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+
+        // The address the tunnel is listening on
+        reply_to: oneshot::Sender<Result<SocketAddr>>,
+    },
+    CloseTunnel {
+        channel: String,
+    },
+    // Sent by a tunnel's task when it stops, whether closed or failed
+    TunnelClosed {
+        channel: String,
+    },
+    Transfer {
+        host: String,
+        username: Option<String>,
+        channel: String,
+        transfer: SftpTransfer,
+        ssh_config: Option<DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+
+        // Progress of the copy, sent periodically while it runs
+        progress: mpsc::UnboundedSender<TransferProgress>,
+
+        // The number of bytes transferred
+        reply_to: oneshot::Sender<Result<u64>>,
+    },
+    CloseTransfer {
+        channel: String,
+    },
     PtyWrite {
         channel: String,
         input: Bytes,
@@ -142,6 +192,7 @@ pub enum SshPoolMessage {
         host: String,
         username: Option<String>,
         prefix: String,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
         reply_to: oneshot::Sender<Result<String>>,
     },
     ReadFile {
@@ -256,7 +307,7 @@ impl SshPoolHandle {
         command: &str,
         channel: &str,
         output_stream: mpsc::Sender<OutputLine>,
-        result_tx: oneshot::Sender<()>,
+        result_tx: oneshot::Sender<Option<u32>>,
     ) -> Result<()> {
         self.exec_with_config(
             host,
@@ -268,6 +319,7 @@ impl SshPoolHandle {
             result_tx,
             None,
             None,
+            None,
         )
         .await
     }
@@ -281,9 +333,10 @@ impl SshPoolHandle {
         command: &str,
         channel: &str,
         output_stream: mpsc::Sender<OutputLine>,
-        result_tx: oneshot::Sender<()>,
+        result_tx: oneshot::Sender<Option<u32>>,
         ssh_config: Option<DocumentSshConfig>,
         warnings_tx: Option<oneshot::Sender<Vec<SshWarning>>>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     ) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
         let msg = SshPoolMessage::Exec {
@@ -297,16 +350,18 @@ impl SshPoolHandle {
             result_tx,
             ssh_config,
             warnings_tx,
+            ssh_prompt,
         };
 
         let _ = self.sender.send(msg).await;
         receiver.await?
     }
 
-    pub async fn exec_finished(&self, channel: &str) -> Result<()> {
+    pub async fn exec_finished(&self, channel: &str, exit_status: Option<u32>) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
         let msg = SshPoolMessage::ExecFinished {
             channel: channel.to_string(),
+            exit_status,
             reply_to: sender,
         };
 
@@ -336,8 +391,17 @@ impl SshPoolHandle {
         mpsc::Sender<(u16, u16)>,
         Vec<SshWarning>,
     )> {
-        self.open_pty_with_config(host, username, channel, output_stream, width, height, None)
-            .await
+        self.open_pty_with_config(
+            host,
+            username,
+            channel,
+            output_stream,
+            width,
+            height,
+            None,
+            None,
+        )
+        .await
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -350,6 +414,7 @@ impl SshPoolHandle {
         width: u16,
         height: u16,
         ssh_config: Option<DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     ) -> Result<(
         mpsc::Sender<Bytes>,
         mpsc::Sender<(u16, u16)>,
@@ -366,6 +431,7 @@ impl SshPoolHandle {
             width,
             height,
             ssh_config,
+            ssh_prompt,
         };
 
         let _ = self.sender.send(msg).await;
@@ -392,18 +458,99 @@ impl SshPoolHandle {
         Ok(())
     }
 
+    /// Forward a local port to `remote_host:remote_port` through the SSH connection to `host`
+    ///
+    /// Returns the local address once the tunnel is listening. The tunnel stays open
+    /// until [`SshPoolHandle::close_tunnel`] is called with the same channel.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn open_tunnel(
+        &self,
+        host: &str,
+        username: Option<&str>,
+        channel: &str,
+        local_port: u16,
+        remote_host: &str,
+        remote_port: u16,
+        ssh_config: Option<DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+    ) -> Result<SocketAddr> {
+        let (sender, receiver) = oneshot::channel();
+        let msg = SshPoolMessage::OpenTunnel {
+            host: host.to_string(),
+            username: username.map(|u| u.to_string()),
+            channel: channel.to_string(),
+            local_port,
+            remote_host: remote_host.to_string(),
+            remote_port,
+            ssh_config,
+            ssh_prompt,
+            reply_to: sender,
+        };
+
+        let _ = self.sender.send(msg).await;
+        receiver.await?
+    }
+
+    pub async fn close_tunnel(&self, channel: &str) -> Result<()> {
+        let msg = SshPoolMessage::CloseTunnel {
+            channel: channel.to_string(),
+        };
+        let _ = self.sender.send(msg).await;
+        Ok(())
+    }
+
+    /// Copy a file to or from `host` over SFTP, returning the number of bytes transferred
+    ///
+    /// The transfer can be stopped with [`SshPoolHandle::close_transfer`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn transfer(
+        &self,
+        host: &str,
+        username: Option<&str>,
+        channel: &str,
+        transfer: SftpTransfer,
+        ssh_config: Option<DocumentSshConfig>,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
+        progress: mpsc::UnboundedSender<TransferProgress>,
+    ) -> Result<u64> {
+        let (sender, receiver) = oneshot::channel();
+        let msg = SshPoolMessage::Transfer {
+            host: host.to_string(),
+            username: username.map(|u| u.to_string()),
+            channel: channel.to_string(),
+            transfer,
+            ssh_config,
+            ssh_prompt,
+            progress,
+            reply_to: sender,
+        };
+
+        let _ = self.sender.send(msg).await;
+        receiver.await?
+    }
+
+    pub async fn close_transfer(&self, channel: &str) -> Result<()> {
+        let msg = SshPoolMessage::CloseTransfer {
+            channel: channel.to_string(),
+        };
+        let _ = self.sender.send(msg).await;
+        Ok(())
+    }
+
     /// Create a temporary file on the remote system
     pub async fn create_temp_file(
         &self,
         host: &str,
         username: Option<&str>,
         prefix: &str,
+        ssh_prompt: Option<Arc<dyn SshPrompt>>,
     ) -> Result<String> {
         let (sender, receiver) = oneshot::channel();
         let msg = SshPoolMessage::CreateTempFile {
             host: host.to_string(),
             username: username.map(|u| u.to_string()),
             prefix: prefix.to_string(),
+            ssh_prompt,
             reply_to: sender,
         };
 
@@ -456,7 +603,7 @@ pub struct ChannelMeta {
     pub host: String,
     pub username: String,
     pub cancel_tx: oneshot::Sender<()>,
-    pub result_tx: oneshot::Sender<()>,
+    pub result_tx: oneshot::Sender<Option<u32>>,
     pub pty_input_tx: Option<mpsc::Sender<Bytes>>,
 }
 
@@ -545,15 +692,22 @@ impl SshPool {
                 reply_to,
             } => {
                 tracing::trace!("Handling Connect message for {host} with username {username:?}");
-                let result = self
-                    .pool
-                    .write()
-                    .await
-                    .connect(&host, username.as_deref(), auth, None)
+                let pool = self.pool.clone();
+                tokio::spawn(async move {
+                    let result = Pool::connect_shared(
+                        &pool,
+                        &host,
+                        username.as_deref(),
+                        auth,
+                        None,
+                        None,
+                        None,
+                    )
                     .await
                     .map(|(session, _auth_result)| session);
 
-                let _ = reply_to.send(result);
+                    let _ = reply_to.send(result);
+                });
             }
             SshPoolMessage::Disconnect {
                 host,
@@ -586,6 +740,7 @@ impl SshPool {
                 result_tx,
                 ssh_config,
                 warnings_tx,
+                ssh_prompt,
             } => {
                 tracing::trace!(
                     "Executing command on {host} with {interpreter} with username {username:?}"
@@ -618,12 +773,11 @@ impl SshPool {
                 // Run the SSH connection in a task to avoid blocking the actor
                 tokio::spawn(async move {
                     tracing::trace!("Connecting to SSH host {host} with username {username}");
-                    let mut pool_guard = pool.write().await;
                     let (session, warnings): (
                         Result<Arc<Session>, SshPoolConnectionError>,
                         Vec<SshWarning>,
                     ) = tokio::select! {
-                        result = pool_guard.connect_with_config(&host, Some(username.as_str()), None, Some(connect_cancel_rx), ssh_config.as_ref()) => {
+                        result = Pool::connect_shared(&pool, &host, Some(username.as_str()), None, Some(connect_cancel_rx), ssh_config.as_ref(), ssh_prompt) => {
                             tracing::trace!("SSH connection to {host} with username {username} successful");
                             match result {
                                 Ok((session, auth_result)) => (Ok(session), auth_result.warnings),
@@ -633,11 +787,10 @@ impl SshPool {
                         _ = &mut cancel_rx => {
                             tracing::trace!("SSH connection to {host} with username {username} cancelled");
                             let _ = connect_cancel_tx.send(());
-                            let _ = pool_guard.disconnect(&host, &username).await;
+                            let _ = pool.write().await.disconnect(&host, &username).await;
                             (Err(SshPoolConnectionError::Cancelled), Vec::new())
                         }
                     };
-                    drop(pool_guard);
 
                     // Send warnings to caller if they requested them
                     if let Some(tx) = warnings_tx {
@@ -692,12 +845,16 @@ impl SshPool {
                                 "Removing SSH connection due to connection error: {key}"
                             );
                             pool.write().await.connections.remove(&key);
-                        } else if let Some(session) = pool.read().await.connections.get(&key) {
-                            if !session.send_keepalive().await {
-                                tracing::debug!(
-                                    "Removing dead SSH connection after exec failure: {key}"
-                                );
-                                pool.write().await.connections.remove(&key);
+                        } else {
+                            // Don't hold the read lock across the write below
+                            let session = pool.read().await.connections.get(&key).cloned();
+                            if let Some(session) = session {
+                                if !session.send_keepalive().await {
+                                    tracing::debug!(
+                                        "Removing dead SSH connection after exec failure: {key}"
+                                    );
+                                    pool.write().await.connections.remove(&key);
+                                }
                             }
                         }
                     }
@@ -707,12 +864,18 @@ impl SshPool {
                     }
                 });
             }
-            SshPoolMessage::ExecFinished { channel, reply_to } => {
+            SshPoolMessage::ExecFinished {
+                channel,
+                exit_status,
+                reply_to,
+            } => {
                 tracing::trace!("Handling ExecFinished message for channel {channel}");
-                tracing::debug!("ExecFinished for channel: {channel}");
+                tracing::debug!(
+                    "ExecFinished for channel: {channel} (exit status {exit_status:?})"
+                );
 
                 if let Some(meta) = self.channels.remove(&channel) {
-                    let _ = meta.result_tx.send(());
+                    let _ = meta.result_tx.send(exit_status);
                 }
 
                 let _ = reply_to.send(Ok(()));
@@ -735,6 +898,7 @@ impl SshPool {
                 width,
                 height,
                 ssh_config,
+                ssh_prompt,
             } => {
                 tracing::trace!("Handling OpenPty message for {host} with username {username:?} with channel {channel}");
                 // Resolve username: block override > provided > SSH config > current user
@@ -746,36 +910,12 @@ impl SshPool {
                     .or(resolved_ssh_config.username)
                     .unwrap_or_else(whoami::username);
 
-                let connect_result = self
-                    .pool
-                    .write()
-                    .await
-                    .connect_with_config(
-                        &host,
-                        Some(username.as_str()),
-                        None,
-                        None,
-                        ssh_config.as_ref(),
-                    )
-                    .await;
-
-                let (session, auth_result) = match connect_result {
-                    Ok((session, auth_result)) => (session, auth_result),
-                    Err(e) => {
-                        tracing::error!("Failed to connect to SSH host {host}: {e}");
-                        if let Err(e) = reply_to.send(Err(e)) {
-                            tracing::error!("Failed to send error to reply_to: {e:?}");
-                        }
-                        return;
-                    }
-                };
-
                 // Create a channel to send input to the pty
                 let (input_tx, input_rx) = mpsc::channel::<Bytes>(100);
                 let (resize_tx, resize_rx) = mpsc::channel::<(u16, u16)>(100);
 
                 // Store the input sender in the channels map
-                let (cancel_tx, cancel_rx) = oneshot::channel();
+                let (cancel_tx, mut cancel_rx) = oneshot::channel();
                 let (result_tx, _) = oneshot::channel();
 
                 self.channels.insert(
@@ -789,50 +929,81 @@ impl SshPool {
                     },
                 );
 
-                tracing::debug!("Opening PTY for {channel}");
-                let pty_result = session
-                    .open_pty(
-                        channel.clone(),
-                        width,
-                        height,
-                        resize_rx,
-                        input_rx,
-                        output_stream,
-                        cancel_rx,
-                    )
-                    .await;
+                let pool = self.pool.clone();
+                let handle = self.handle();
+                // Connect in a task so a host key prompt doesn't block the actor
+                tokio::spawn(async move {
+                    let (connect_cancel_tx, connect_cancel_rx) = oneshot::channel();
+                    let connect_result = tokio::select! {
+                        result = Pool::connect_shared(&pool, &host, Some(username.as_str()), None, Some(connect_cancel_rx), ssh_config.as_ref(), ssh_prompt) => result,
+                        _ = &mut cancel_rx => {
+                            let _ = connect_cancel_tx.send(());
+                            Err(SshPoolConnectionError::Cancelled.into())
+                        }
+                    };
 
-                match pty_result {
-                    Err(e) => {
-                        tracing::error!("Failed to open PTY: {e:?}");
-                        // Check if connection is dead and remove it
-                        let key = format!("{username}@{host}");
+                    let (session, auth_result) = match connect_result {
+                        Ok((session, auth_result)) => (session, auth_result),
+                        Err(e) => {
+                            tracing::error!("Failed to connect to SSH host {host}: {e}");
+                            let _ = handle.close_pty(&channel).await;
+                            if let Err(e) = reply_to.send(Err(e)) {
+                                tracing::error!("Failed to send error to reply_to: {e:?}");
+                            }
+                            return;
+                        }
+                    };
 
-                        // TODO: use a proper error enum
-                        let error_str = e.to_string().to_lowercase();
-                        if error_str.contains("timeout")
-                            || error_str.contains("connection")
-                            || error_str.contains("broken pipe")
-                        {
-                            tracing::debug!(
-                                "Removing SSH connection due to PTY connection error: {key}"
-                            );
-                            self.pool.write().await.connections.remove(&key);
-                        } else if let Some(session) = self.pool.read().await.connections.get(&key) {
-                            if !session.send_keepalive().await {
+                    tracing::debug!("Opening PTY for {channel}");
+                    let pty_result = session
+                        .open_pty(
+                            channel.clone(),
+                            width,
+                            height,
+                            resize_rx,
+                            input_rx,
+                            output_stream,
+                            cancel_rx,
+                        )
+                        .await;
+
+                    match pty_result {
+                        Err(e) => {
+                            tracing::error!("Failed to open PTY: {e:?}");
+                            let _ = handle.close_pty(&channel).await;
+                            // Check if connection is dead and remove it
+                            let key = format!("{username}@{host}");
+
+                            // TODO: use a proper error enum
+                            let error_str = e.to_string().to_lowercase();
+                            if error_str.contains("timeout")
+                                || error_str.contains("connection")
+                                || error_str.contains("broken pipe")
+                            {
                                 tracing::debug!(
-                                    "Removing dead SSH connection after PTY failure: {key}"
+                                    "Removing SSH connection due to PTY connection error: {key}"
                                 );
-                                self.pool.write().await.connections.remove(&key);
+                                pool.write().await.connections.remove(&key);
+                            } else {
+                                // Don't hold the read lock across the write below
+                                let session = pool.read().await.connections.get(&key).cloned();
+                                if let Some(session) = session {
+                                    if !session.send_keepalive().await {
+                                        tracing::debug!(
+                                            "Removing dead SSH connection after PTY failure: {key}"
+                                        );
+                                        pool.write().await.connections.remove(&key);
+                                    }
+                                }
                             }
-                        }
 
-                        let _ = reply_to.send(Err(e));
-                    }
-                    Ok(_) => {
-                        let _ = reply_to.send(Ok((input_tx, resize_tx, auth_result.warnings)));
+                            let _ = reply_to.send(Err(e));
+                        }
+                        Ok(_) => {
+                            let _ = reply_to.send(Ok((input_tx, resize_tx, auth_result.warnings)));
+                        }
                     }
-                }
+                });
             }
             SshPoolMessage::PtyWrite {
                 channel,
@@ -864,6 +1035,205 @@ impl SshPool {
                     let _ = meta.cancel_tx.send(());
                 }
             }
+            SshPoolMessage::OpenTunnel {
+                host,
+                username,
+                channel,
+                local_port,
+                remote_host,
+                remote_port,
+                ssh_config,
+                ssh_prompt,
+                reply_to,
+            } => {
+                tracing::trace!("Handling OpenTunnel message for {host} with channel {channel}: {local_port} -> {remote_host}:{remote_port}");
+                // Resolve username: block override > provided > SSH config > current user
+                let resolved_ssh_config = Session::resolve_ssh_config(&host);
+                let username = ssh_config
+                    .as_ref()
+                    .and_then(|cfg| cfg.user.clone())
+                    .or(username)
+                    .or(resolved_ssh_config.username)
+                    .unwrap_or_else(whoami::username);
+
+                let (cancel_tx, mut cancel_rx) = oneshot::channel();
+                let (result_tx, _) = oneshot::channel();
+
+                // Re-running a tunnel block replaces its previous tunnel
+                if let Some(previous) = self.channels.insert(
+                    channel.clone(),
+                    ChannelMeta {
+                        host: host.clone(),
+                        username: username.clone(),
+                        cancel_tx,
+                        result_tx,
+                        pty_input_tx: None,
+                    },
+                ) {
+                    let _ = previous.cancel_tx.send(());
+                }
+
+                let pool = self.pool.clone();
+                let sender = self.sender.clone();
+                // Connect in a task so a slow host doesn't block the actor
+                tokio::spawn(async move {
+                    let tunnel_channel = channel.clone();
+                    let tunnel = async move {
+                        let (connect_cancel_tx, connect_cancel_rx) = oneshot::channel();
+                        let session = tokio::select! {
+                            result = Pool::connect_shared(&pool, &host, Some(username.as_str()), None, Some(connect_cancel_rx), ssh_config.as_ref(), ssh_prompt) => {
+                                result.map(|(session, _auth_result)| session)
+                            }
+                            _ = &mut cancel_rx => {
+                                let _ = connect_cancel_tx.send(());
+                                Err(SshPoolConnectionError::Cancelled.into())
+                            }
+                        };
+
+                        let session = match session {
+                            Ok(session) => session,
+                            Err(e) => {
+                                tracing::error!(
+                                    "Failed to connect to SSH host {host} for tunnel: {e}"
+                                );
+                                let _ = reply_to.send(Err(e));
+                                return;
+                            }
+                        };
+
+                        let listener =
+                            match tokio::net::TcpListener::bind(("127.0.0.1", local_port)).await {
+                                Ok(listener) => listener,
+                                Err(e) => {
+                                    let _ = reply_to.send(Err(eyre::eyre!(
+                                        "Failed to listen on local port {local_port}: {e}"
+                                    )));
+                                    return;
+                                }
+                            };
+
+                        let addr = match listener.local_addr() {
+                            Ok(addr) => addr,
+                            Err(e) => {
+                                let _ = reply_to.send(Err(e.into()));
+                                return;
+                            }
+                        };
+
+                        tracing::debug!(
+                            "SSH tunnel {channel} listening on {addr}, forwarding to {remote_host}:{remote_port} via {host}"
+                        );
+                        if reply_to.send(Ok(addr)).is_err() {
+                            return;
+                        }
+
+                        tunnel::forward_connections(
+                            listener,
+                            session,
+                            remote_host,
+                            remote_port,
+                            cancel_rx,
+                        )
+                        .await;
+                    };
+                    tunnel.await;
+
+                    let _ = sender
+                        .send(SshPoolMessage::TunnelClosed {
+                            channel: tunnel_channel,
+                        })
+                        .await;
+                });
+            }
+            SshPoolMessage::CloseTunnel { channel } => {
+                tracing::trace!("Handling CloseTunnel message for channel {channel}");
+                if let Some(meta) = self.channels.remove(&channel) {
+                    let _ = meta.cancel_tx.send(());
+                }
+            }
+            SshPoolMessage::TunnelClosed { channel } => {
+                tracing::trace!("Handling TunnelClosed message for channel {channel}");
+                // Only forget the channel if it still belongs to the tunnel that stopped; a
+                // re-run may already have replaced it with a live one
+                if self
+                    .channels
+                    .get(&channel)
+                    .is_some_and(|meta| meta.cancel_tx.is_closed())
+                {
+                    self.channels.remove(&channel);
+                }
+            }
+            SshPoolMessage::Transfer {
+                host,
+                username,
+                channel,
+                transfer,
+                ssh_config,
+                ssh_prompt,
+                progress,
+                reply_to,
+            } => {
+                tracing::trace!("Handling Transfer message for {host} with channel {channel}");
+                // Resolve username: block override > provided > SSH config > current user
+                let resolved_ssh_config = Session::resolve_ssh_config(&host);
+                let username = ssh_config
+                    .as_ref()
+                    .and_then(|cfg| cfg.user.clone())
+                    .or(username)
+                    .or(resolved_ssh_config.username)
+                    .unwrap_or_else(whoami::username);
+
+                let (cancel_tx, mut cancel_rx) = oneshot::channel();
+                let (result_tx, _) = oneshot::channel();
+                self.channels.insert(
+                    channel.clone(),
+                    ChannelMeta {
+                        host: host.clone(),
+                        username: username.clone(),
+                        cancel_tx,
+                        result_tx,
+                        pty_input_tx: None,
+                    },
+                );
+
+                let pool = self.pool.clone();
+                let handle = self.handle();
+                tokio::spawn(async move {
+                    let run = async {
+                        let (session, _auth_result) = Pool::connect_shared(
+                            &pool,
+                            &host,
+                            Some(username.as_str()),
+                            None,
+                            None,
+                            ssh_config.as_ref(),
+                            ssh_prompt,
+                        )
+                        .await?;
+
+                        sftp::transfer(&session, transfer, &progress).await
+                    };
+
+                    // Dropping the transfer future closes the SFTP channel mid-copy
+                    let result = tokio::select! {
+                        result = run => result,
+                        _ = &mut cancel_rx => Err(SshPoolConnectionError::Cancelled.into()),
+                    };
+
+                    if let Err(ref e) = result {
+                        tracing::error!("SFTP transfer on {host} failed: {e}");
+                    }
+
+                    let _ = handle.close_transfer(&channel).await;
+                    let _ = reply_to.send(result);
+                });
+            }
+            SshPoolMessage::CloseTransfer { channel } => {
+                tracing::trace!("Handling CloseTransfer message for channel {channel}");
+                if let Some(meta) = self.channels.remove(&channel) {
+                    let _ = meta.cancel_tx.send(());
+                }
+            }
             SshPoolMessage::HealthCheck { reply_to } => {
                 tracing::trace!("Handling HealthCheck message");
                 let connection_count = self.pool.read().await.connections.len();
@@ -901,24 +1271,33 @@ impl SshPool {
                 host,
                 username,
                 prefix,
+                ssh_prompt,
                 reply_to,
             } => {
                 tracing::trace!("Handling CreateTempFile message for {host} with username {username:?} with prefix {prefix}");
-                let mut pool_guard = self.pool.write().await;
-                let session = match pool_guard
-                    .connect(&host, username.as_deref(), None, None)
+                let pool = self.pool.clone();
+                tokio::spawn(async move {
+                    let session = match Pool::connect_shared(
+                        &pool,
+                        &host,
+                        username.as_deref(),
+                        None,
+                        None,
+                        None,
+                        ssh_prompt,
+                    )
                     .await
-                {
-                    Ok((session, _auth_result)) => session,
-                    Err(e) => {
-                        let _ = reply_to.send(Err(e));
-                        return;
-                    }
-                };
-                drop(pool_guard);
+                    {
+                        Ok((session, _auth_result)) => session,
+                        Err(e) => {
+                            let _ = reply_to.send(Err(e));
+                            return;
+                        }
+                    };
 
-                let result = session.create_temp_file(&prefix).await;
-                let _ = reply_to.send(result);
+                    let result = session.create_temp_file(&prefix).await;
+                    let _ = reply_to.send(result);
+                });
             }
             SshPoolMessage::ReadFile {
                 host,
@@ -927,21 +1306,29 @@ impl SshPool {
                 reply_to,
             } => {
                 tracing::trace!("Handling ReadFile message for {host} with username {username:?} with path {path}");
-                let mut pool_guard = self.pool.write().await;
-                let session = match pool_guard
-                    .connect(&host, username.as_deref(), None, None)
+                let pool = self.pool.clone();
+                tokio::spawn(async move {
+                    let session = match Pool::connect_shared(
+                        &pool,
+                        &host,
+                        username.as_deref(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
                     .await
-                {
-                    Ok((session, _auth_result)) => session,
-                    Err(e) => {
-                        let _ = reply_to.send(Err(e));
-                        return;
-                    }
-                };
-                drop(pool_guard);
+                    {
+                        Ok((session, _auth_result)) => session,
+                        Err(e) => {
+                            let _ = reply_to.send(Err(e));
+                            return;
+                        }
+                    };
 
-                let result = session.read_file(&path).await;
-                let _ = reply_to.send(result);
+                    let result = session.read_file(&path).await;
+                    let _ = reply_to.send(result);
+                });
             }
             SshPoolMessage::DeleteFile {
                 host,
@@ -950,21 +1337,29 @@ impl SshPool {
                 reply_to,
             } => {
                 tracing::trace!("Handling DeleteFile message for {host} with username {username:?} with path {path}");
-                let mut pool_guard = self.pool.write().await;
-                let session = match pool_guard
-                    .connect(&host, username.as_deref(), None, None)
+                let pool = self.pool.clone();
+                tokio::spawn(async move {
+                    let session = match Pool::connect_shared(
+                        &pool,
+                        &host,
+                        username.as_deref(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
                     .await
-                {
-                    Ok((session, _auth_result)) => session,
-                    Err(e) => {
-                        let _ = reply_to.send(Err(e));
-                        return;
-                    }
-                };
-                drop(pool_guard);
+                    {
+                        Ok((session, _auth_result)) => session,
+                        Err(e) => {
+                            let _ = reply_to.send(Err(e));
+                            return;
+                        }
+                    };
 
-                let result = session.delete_file(&path).await;
-                let _ = reply_to.send(result);
+                    let result = session.delete_file(&path).await;
+                    let _ = reply_to.send(result);
+                });
             }
         }
     }
@@ -0,0 +1,70 @@
+// Local port forwarding over a pooled SSH session, equivalent to `ssh -L`.
+
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+
+use crate::ssh::session::Session;
+
+/// Accept connections on `listener` and forward each one to `remote_host:remote_port`
+/// through a `direct-tcpip` channel on `session`
+///
+/// Runs until `cancel_rx` fires or its sender is dropped; open connections are
+/// aborted when the tunnel closes.
+pub(crate) async fn forward_connections(
+    listener: TcpListener,
+    session: Arc<Session>,
+    remote_host: String,
+    remote_port: u16,
+    mut cancel_rx: oneshot::Receiver<()>,
+) {
+    let mut connections = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut cancel_rx => {
+                tracing::debug!("Closing SSH tunnel to {remote_host}:{remote_port}");
+                break;
+            }
+            accepted = listener.accept() => {
+                let (mut stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Failed to accept tunnel connection: {e}");
+                        continue;
+                    }
+                };
+
+                let session = session.clone();
+                let remote_host = remote_host.clone();
+                connections.spawn(async move {
+                    let mut channel = match session
+                        .open_direct_tcpip(&remote_host, remote_port, peer)
+                        .await
+                    {
+                        Ok(channel) => channel,
+                        Err(e) => {
+                            tracing::warn!(
+                                "Failed to open tunnel channel to {remote_host}:{remote_port}: {e}"
+                            );
+                            return;
+                        }
+                    };
+
+                    match tokio::io::copy_bidirectional(&mut stream, &mut channel).await {
+                        Ok((sent, received)) => tracing::trace!(
+                            "Tunnel connection from {peer} closed ({sent} bytes sent, {received} received)"
+                        ),
+                        Err(e) => tracing::debug!("Tunnel connection from {peer} failed: {e}"),
+                    }
+                });
+            }
+            // Reap finished connections so the set doesn't grow for the life of the tunnel
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+
+    connections.shutdown().await;
+}
@@ -0,0 +1,337 @@
+use std::{fmt::Write, sync::OnceLock};
+
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use minijinja::{value::Kwargs, Environment, Error, ErrorKind, State, UndefinedBehavior, Value};
+use regex::Regex;
+use serde_json_path::JsonPath;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::context::Secrets;
+
+/// Context key holding the resolver's secrets, read by `secret()`
+pub(crate) const SECRETS_KEY: &str = "__secrets";
+
+/// The environment every runbook template is rendered with
+///
+/// Filters and functions don't depend on the document, so the environment is built once and
+/// shared. Anything document-specific, like secrets, is read from the render context instead.
+/// `tojson` and `urlencode` come with minijinja's builtins.
+pub(crate) fn template_environment() -> &'static Environment<'static> {
+    static ENVIRONMENT: OnceLock<Environment<'static>> = OnceLock::new();
+
+    ENVIRONMENT.get_or_init(|| {
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+
+        env.add_filter("shellquote", shellquote);
+        env.add_filter("fromjson", fromjson);
+        env.add_filter("yaml", yaml);
+        env.add_filter("fromyaml", fromyaml);
+        env.add_filter("b64encode", b64encode);
+        env.add_filter("b64decode", b64decode);
+        env.add_filter("sha256", sha256);
+        env.add_filter("regex_replace", regex_replace);
+        env.add_filter("regex_search", regex_search);
+        env.add_filter("jsonpath", jsonpath);
+        env.add_filter("strftime", strftime);
+
+        env.add_function("now", now);
+        env.add_function("uuid", uuid);
+        env.add_function("env_or", env_or);
+        env.add_function("secret", secret);
+
+        env
+    })
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidOperation, message.into())
+}
+
+/// Escape a string for the shell
+fn shellquote(value: String) -> String {
+    // Use POSIX shell single-quote escaping:
+    // wrap in single quotes and escape any single quotes as '\''
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn fromjson(value: String) -> Result<Value, Error> {
+    let parsed: serde_json::Value =
+        serde_json::from_str(&value).map_err(|e| invalid(format!("invalid JSON: {e}")))?;
+    Ok(Value::from_serialize(parsed))
+}
+
+fn yaml(value: Value) -> Result<String, Error> {
+    let yaml =
+        serde_yaml::to_string(&value).map_err(|e| invalid(format!("can't write YAML: {e}")))?;
+    Ok(yaml.trim_end().to_string())
+}
+
+fn fromyaml(value: String) -> Result<Value, Error> {
+    let parsed: serde_json::Value =
+        serde_yaml::from_str(&value).map_err(|e| invalid(format!("invalid YAML: {e}")))?;
+    Ok(Value::from_serialize(parsed))
+}
+
+fn b64encode(value: String) -> String {
+    base64::engine::general_purpose::STANDARD.encode(value)
+}
+
+fn b64decode(value: String) -> Result<String, Error> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|e| invalid(format!("invalid base64: {e}")))?;
+    String::from_utf8(bytes).map_err(|_| invalid("decoded base64 is not valid UTF-8"))
+}
+
+/// Hex-encoded SHA-256 digest of a string
+fn sha256(value: String) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, Error> {
+    Regex::new(pattern).map_err(|e| invalid(format!("invalid regex '{pattern}': {e}")))
+}
+
+/// Replace every match of a regex; the replacement can use `$1` or `${name}` for groups
+fn regex_replace(value: String, pattern: String, replacement: String) -> Result<String, Error> {
+    Ok(compile_regex(&pattern)?
+        .replace_all(&value, replacement.as_str())
+        .into_owned())
+}
+
+/// The first match of a regex, or of one of its groups, or none if it doesn't match
+fn regex_search(value: String, pattern: String, group: Option<usize>) -> Result<Value, Error> {
+    let regex = compile_regex(&pattern)?;
+    let found = regex
+        .captures(&value)
+        .and_then(|captures| captures.get(group.unwrap_or(0)))
+        .map(|found| Value::from(found.as_str()));
+
+    Ok(found.unwrap_or_else(|| Value::from(())))
+}
+
+/// Query a value, or a JSON string, with a JSONPath expression
+///
+/// Returns the first match, or none if nothing matches. Pass `all=true` to get a list of
+/// every match instead.
+fn jsonpath(value: Value, path: String, kwargs: Kwargs) -> Result<Value, Error> {
+    let all = kwargs.get::<Option<bool>>("all")?.unwrap_or(false);
+    kwargs.assert_all_used()?;
+
+    let path =
+        JsonPath::parse(&path).map_err(|e| invalid(format!("invalid JSONPath '{path}': {e}")))?;
+    let json = match value.as_str() {
+        Some(text) => {
+            serde_json::from_str(text).map_err(|e| invalid(format!("invalid JSON: {e}")))?
+        }
+        None => serde_json::to_value(&value).map_err(|e| invalid(e.to_string()))?,
+    };
+
+    let nodes = path.query(&json).all();
+    if all {
+        Ok(Value::from_serialize(nodes))
+    } else {
+        Ok(nodes
+            .first()
+            .map(Value::from_serialize)
+            .unwrap_or_else(|| Value::from(())))
+    }
+}
+
+/// Format a timestamp, either seconds since the epoch or an RFC 3339 string like `now()` returns
+fn strftime(value: Value, format: String) -> Result<String, Error> {
+    let timestamp = match value.as_str() {
+        Some(text) => DateTime::parse_from_rfc3339(text)
+            .map_err(|e| invalid(format!("invalid timestamp '{text}': {e}")))?
+            .with_timezone(&Utc),
+        None => {
+            let seconds = f64::try_from(value.clone())
+                .map_err(|_| invalid(format!("expected a timestamp, got {value}")))?;
+            DateTime::from_timestamp_millis((seconds * 1000.0) as i64)
+                .ok_or_else(|| invalid(format!("timestamp {seconds} is out of range")))?
+        }
+    };
+
+    // chrono reports bad format strings as a formatting error rather than panicking here
+    let mut formatted = String::new();
+    write!(formatted, "{}", timestamp.format(&format))
+        .map_err(|_| invalid(format!("invalid time format '{format}'")))?;
+    Ok(formatted)
+}
+
+/// The current time in UTC, as an RFC 3339 string
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn uuid() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// An environment variable from the runbook, then the process, or a default
+fn env_or(state: &State, name: String, default: Value) -> Value {
+    let from_runbook = state
+        .lookup("env")
+        .and_then(|env| env.get_attr(&name).ok())
+        .filter(|value| !value.is_undefined() && !value.is_none());
+
+    from_runbook
+        .or_else(|| std::env::var(&name).ok().map(Value::from))
+        .unwrap_or(default)
+}
+
+fn secret(state: &State, name: String) -> Result<String, Error> {
+    let secrets = state.lookup(SECRETS_KEY);
+    match secrets
+        .as_ref()
+        .and_then(|secrets| secrets.downcast_object_ref::<Secrets>())
+    {
+        Some(secrets) => secrets.get(&name),
+        None => Err(invalid(format!(
+            "secret '{name}' requested, but secrets are not available here"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::context;
+
+    use super::*;
+
+    fn render(template: &str, ctx: Value) -> Result<String, Error> {
+        template_environment().render_str(template, ctx)
+    }
+
+    #[test]
+    fn test_json_and_yaml() {
+        let ctx = context! { body => r#"{"items": [{"id": 1}, {"id": 2}]}"# };
+
+        assert_eq!(
+            render("{{ (body | fromjson).items[1].id }}", ctx.clone()).unwrap(),
+            "2"
+        );
+        assert_eq!(
+            render(r#"{{ body | jsonpath("$.items[*].id") }}"#, ctx.clone()).unwrap(),
+            "1"
+        );
+        assert_eq!(
+            render(
+                r#"{{ body | jsonpath("$.items[*].id", all=true) | tojson }}"#,
+                ctx.clone()
+            )
+            .unwrap(),
+            "[1,2]"
+        );
+        assert_eq!(
+            render(r#"{{ body | jsonpath("$.missing") is none }}"#, ctx.clone()).unwrap(),
+            "true"
+        );
+        assert_eq!(
+            render("{{ (body | fromjson).items[0] | yaml }}", ctx.clone()).unwrap(),
+            "id: 1"
+        );
+        assert_eq!(
+            render(r#"{{ ("a: [1, 2]" | fromyaml).a | tojson }}"#, ctx).unwrap(),
+            "[1,2]"
+        );
+    }
+
+    #[test]
+    fn test_encoding_and_hashing() {
+        let ctx = context! {};
+
+        assert_eq!(
+            render(r#"{{ "user:pass" | b64encode }}"#, ctx.clone()).unwrap(),
+            "dXNlcjpwYXNz"
+        );
+        assert_eq!(
+            render(r#"{{ "dXNlcjpwYXNz" | b64decode }}"#, ctx.clone()).unwrap(),
+            "user:pass"
+        );
+        assert!(render(r#"{{ "not base64!" | b64decode }}"#, ctx.clone()).is_err());
+        assert_eq!(
+            render(r#"{{ "abc" | sha256 }}"#, ctx.clone()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            render(r#"{{ "a b&c" | urlencode }}"#, ctx).unwrap(),
+            "a%20b%26c"
+        );
+    }
+
+    #[test]
+    fn test_regex() {
+        let ctx = context! { out => "deployed version 1.4.2 to prod" };
+
+        assert_eq!(
+            render(r#"{{ out | regex_replace("\\d+", "N") }}"#, ctx.clone()).unwrap(),
+            "deployed version N.N.N to prod"
+        );
+        assert_eq!(
+            render(
+                r#"{{ out | regex_search("version (\\S+)", 1) }}"#,
+                ctx.clone()
+            )
+            .unwrap(),
+            "1.4.2"
+        );
+        assert_eq!(
+            render(
+                r#"{{ out | regex_search("staging") is none }}"#,
+                ctx.clone()
+            )
+            .unwrap(),
+            "true"
+        );
+        assert!(render(r#"{{ out | regex_search("(") }}"#, ctx).is_err());
+    }
+
+    #[test]
+    fn test_time_and_ids() {
+        let ctx = context! {};
+
+        assert_eq!(
+            render(r#"{{ 86400 | strftime("%Y-%m-%d") }}"#, ctx.clone()).unwrap(),
+            "1970-01-02"
+        );
+        assert_eq!(
+            render(
+                r#"{{ "2025-03-01T12:30:00Z" | strftime("%H:%M") }}"#,
+                ctx.clone()
+            )
+            .unwrap(),
+            "12:30"
+        );
+        let now = render("{{ now() }}", ctx.clone()).unwrap();
+        assert!(DateTime::parse_from_rfc3339(&now).is_ok());
+
+        let id = render("{{ uuid() }}", ctx).unwrap();
+        assert!(Uuid::parse_str(&id).is_ok());
+    }
+
+    #[test]
+    fn test_env_or() {
+        let ctx = context! { env => context! { REGION => "eu-west-1" } };
+
+        assert_eq!(
+            render(r#"{{ env_or("REGION", "us-east-1") }}"#, ctx.clone()).unwrap(),
+            "eu-west-1"
+        );
+        assert_eq!(
+            render(
+                r#"{{ env_or("ATUIN_TEST_SURELY_UNSET", "fallback") }}"#,
+                ctx
+            )
+            .unwrap(),
+            "fallback"
+        );
+    }
+}
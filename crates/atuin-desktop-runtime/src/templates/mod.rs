@@ -5,14 +5,27 @@ use minijinja::{
 use serde::Serialize;
 use std::{collections::HashMap, sync::Arc};
 
-use crate::context::BlockExecutionOutput;
+use crate::context::{BlockExecutionOutput, Redactor};
 
+mod filters;
+
+pub(crate) use filters::{template_environment, SECRETS_KEY};
+
+/// Exposes a block's output to templates, such as `doc.named.x.output`
 #[derive(Debug)]
-struct OutputWrapper(Arc<dyn BlockExecutionOutput>);
+pub(crate) struct OutputWrapper(Arc<dyn BlockExecutionOutput>, Redactor);
+
+impl OutputWrapper {
+    pub(crate) fn new(output: Arc<dyn BlockExecutionOutput>, redactor: Redactor) -> Self {
+        Self(output, redactor)
+    }
+}
 
 impl Object for OutputWrapper {
     fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
-        self.0.get_template_value(key.as_str()?)
+        self.0
+            .get_template_value(key.as_str()?)
+            .map(|value| self.1.redact_template_value(value))
     }
 
     fn enumerate(self: &Arc<Self>) -> Enumerator {
@@ -50,6 +63,8 @@ pub struct BlockTemplateState {
     pub content: String,
     pub props: HashMap<String, String>,
     pub output: Option<Arc<dyn BlockExecutionOutput>>,
+    /// Masks sensitive values in the output
+    pub redactor: Redactor,
 }
 
 impl Object for BlockTemplateState {
@@ -61,7 +76,7 @@ impl Object for BlockTemplateState {
             "output" => self
                 .output
                 .as_ref()
-                .map(|o| Value::from_object(OutputWrapper(o.clone()))),
+                .map(|o| Value::from_object(OutputWrapper(o.clone(), self.redactor.clone()))),
             _ => None,
         }
     }
@@ -127,11 +142,22 @@ impl DocumentTemplateState {
         flattened_doc: &[serde_json::Value],
         active_block_id: Option<&str>,
         block_outputs: HashMap<String, Option<Arc<dyn BlockExecutionOutput>>>,
+        redactor: Redactor,
     ) -> Option<Self> {
         if flattened_doc.is_empty() {
             return None;
         }
 
+        let block_state = |block: &serde_json::Value| {
+            let mut state = serialized_block_to_state(block);
+            state.output = block_outputs
+                .get(block.get("id").unwrap().as_str().unwrap())
+                .cloned()
+                .flatten();
+            state.redactor = redactor.clone();
+            state
+        };
+
         let named = flattened_doc
             .iter()
             .filter_map(|block| {
@@ -149,51 +175,15 @@ impl DocumentTemplateState {
                         }
                     });
 
-                let mut state = serialized_block_to_state(block);
-                state.output = block_outputs
-                    .get(block.get("id").unwrap().as_str().unwrap())
-                    .cloned()
-                    .flatten();
-                name.map(|name| (name, state))
+                name.map(|name| (name, block_state(block)))
             })
             .collect::<HashMap<String, BlockTemplateState>>();
 
-        let mut first = serialized_block_to_state(flattened_doc.first().unwrap());
-        first.output = block_outputs
-            .get(
-                flattened_doc
-                    .first()
-                    .unwrap()
-                    .get("id")
-                    .unwrap()
-                    .as_str()
-                    .unwrap(),
-            )
-            .cloned()
-            .flatten();
-        let mut last = serialized_block_to_state(flattened_doc.last().unwrap());
-        last.output = block_outputs
-            .get(
-                flattened_doc
-                    .last()
-                    .unwrap()
-                    .get("id")
-                    .unwrap()
-                    .as_str()
-                    .unwrap(),
-            )
-            .cloned()
-            .flatten();
+        let first = block_state(flattened_doc.first().unwrap());
+        let last = block_state(flattened_doc.last().unwrap());
         let content = flattened_doc
             .iter()
-            .map(|block| {
-                let mut state = serialized_block_to_state(block);
-                state.output = block_outputs
-                    .get(block.get("id").unwrap().as_str().unwrap())
-                    .cloned()
-                    .flatten();
-                state
-            })
+            .map(block_state)
             .collect::<Vec<BlockTemplateState>>();
 
         let previous = if let Some(active_block_id) = active_block_id {
@@ -202,12 +192,7 @@ impl DocumentTemplateState {
                 .position(|block| block.get("id").unwrap().as_str().unwrap() == active_block_id)
                 .and_then(|active_index| active_index.checked_sub(1))
                 .and_then(|prev_index| flattened_doc.get(prev_index))
-                .map(|prev_block| {
-                    let mut state = serialized_block_to_state(prev_block);
-                    let prev_block_id = prev_block.get("id").unwrap().as_str().unwrap();
-                    state.output = block_outputs.get(prev_block_id).cloned().flatten();
-                    state
-                })
+                .map(block_state)
         } else {
             None
         };
@@ -220,6 +205,17 @@ impl DocumentTemplateState {
             previous,
         })
     }
+
+    /// Make the named blocks of an enclosing document available, unless a block of this
+    /// document has the same name
+    pub fn inherit_named(&mut self, enclosing: &DocumentTemplateState) {
+        for (name, block) in &enclosing.named.0 {
+            self.named
+                .0
+                .entry(name.clone())
+                .or_insert_with(|| block.clone());
+        }
+    }
 }
 
 impl Object for DocumentTemplateState {
@@ -279,6 +275,7 @@ pub fn serialized_block_to_state(block: &serde_json::Value) -> BlockTemplateStat
                 content: String::new(),
                 props: HashMap::new(),
                 output: None,
+                redactor: Redactor::default(),
             };
         }
     };
@@ -355,5 +352,6 @@ pub fn serialized_block_to_state(block: &serde_json::Value) -> BlockTemplateStat
         props,
         content: content.to_string(),
         output: None,
+        redactor: Redactor::default(),
     }
 }
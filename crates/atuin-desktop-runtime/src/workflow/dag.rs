@@ -0,0 +1,407 @@
+//! Ordering constraints between the blocks of a workflow
+//!
+//! Blocks resolve their context from the blocks above them, so a block must wait for every
+//! earlier block that writes something it reads: a Directory block before the scripts that
+//! run in it, a script with an output variable before the blocks that template it, and so on.
+//! Explicit block dependencies add further edges. Blocks with no path between them can run
+//! at the same time.
+
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use super::{DependencyMode, DependencySpec};
+use crate::blocks::Block;
+use crate::document::flatten_document;
+
+/// A part of the context that blocks read or write
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ContextKey {
+    Cwd,
+    Env,
+    Ssh,
+    Var(String),
+    /// Variables whose names can't be known ahead of time, like those set via
+    /// `$ATUIN_OUTPUT_VARS`
+    AnyVar,
+    /// Everything, for blocks that act as a barrier
+    All,
+}
+
+impl ContextKey {
+    fn overlaps(&self, other: &ContextKey) -> bool {
+        match (self, other) {
+            (ContextKey::All, _) | (_, ContextKey::All) => true,
+            (ContextKey::AnyVar, ContextKey::Var(_) | ContextKey::AnyVar)
+            | (ContextKey::Var(_), ContextKey::AnyVar) => true,
+            (a, b) => a == b,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Access {
+    reads: HashSet<ContextKey>,
+    writes: HashSet<ContextKey>,
+}
+
+impl Access {
+    fn of(block: &Block) -> Self {
+        let mut access = Access::default();
+
+        // Every block resolves its templates against the directory, environment and host
+        access
+            .reads
+            .extend([ContextKey::Cwd, ContextKey::Env, ContextKey::Ssh]);
+
+        let mut value = serde_json::to_value(block).unwrap_or_default();
+        if let (Block::Foreach(_), Some(fields)) = (block, value.as_object_mut()) {
+            // Nested blocks are accounted for below, as what they write stays in the loop
+            fields.remove("children");
+        }
+        let mut strings = Vec::new();
+        collect_strings(&value, &mut strings);
+        for text in strings {
+            if text.contains("doc.") {
+                // Reads the output of other blocks, which any of them may produce
+                access.reads.insert(ContextKey::All);
+            }
+            access
+                .reads
+                .extend(template_var_names(text).into_iter().map(ContextKey::Var));
+            if text.contains("ATUIN_OUTPUT_VARS") {
+                access.writes.insert(ContextKey::AnyVar);
+            }
+        }
+
+        if let Some(name) = value
+            .get("outputVariable")
+            .and_then(|v| v.as_str())
+            .filter(|name| !name.is_empty())
+        {
+            access.writes.insert(ContextKey::Var(name.to_string()));
+        }
+
+        match block {
+            Block::Directory(_) | Block::LocalDirectory(_) => {
+                access.writes.insert(ContextKey::Cwd);
+            }
+            Block::Environment(_) => {
+                access.writes.insert(ContextKey::Env);
+            }
+            Block::SshConnect(_) | Block::Host(_) => {
+                access.writes.insert(ContextKey::Ssh);
+            }
+            Block::Var(var) => {
+                access.writes.insert(ContextKey::Var(var.name.clone()));
+            }
+            Block::LocalVar(var) => {
+                access.writes.insert(ContextKey::Var(var.name.clone()));
+            }
+            Block::Dropdown(dropdown) => {
+                access.writes.insert(ContextKey::Var(dropdown.name.clone()));
+            }
+            Block::SshTunnel(tunnel) => {
+                access
+                    .writes
+                    .insert(ContextKey::Var(tunnel.output_variable.clone()));
+            }
+            // Each iteration runs the nested blocks in a scope of its own, so the loop reads
+            // whatever they read but none of their writes reach the blocks after it
+            Block::Foreach(foreach) => {
+                for child in flatten_document(&foreach.children) {
+                    if let Ok(child) = Block::from_document(&child) {
+                        access.reads.extend(Access::of(&child).reads);
+                    }
+                }
+            }
+            // Pauses wait for everything above and hold back everything below; sub-runbooks
+            // can export any part of their context
+            Block::Pause(_) | Block::SubRunbook(_) => {
+                access.reads.insert(ContextKey::All);
+                access.writes.insert(ContextKey::All);
+            }
+            _ => {}
+        }
+
+        access
+    }
+
+    /// Whether a later block with access `later` has to wait for this one
+    fn must_precede(&self, later: &Access) -> bool {
+        later.reads.contains(&ContextKey::All)
+            || self.writes.contains(&ContextKey::All)
+            || self
+                .writes
+                .iter()
+                .any(|write| later.reads.iter().any(|read| write.overlaps(read)))
+    }
+}
+
+fn collect_strings<'a>(value: &'a serde_json::Value, strings: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(s) => strings.push(s),
+        serde_json::Value::Array(values) => {
+            values.iter().for_each(|v| collect_strings(v, strings));
+        }
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_strings(v, strings)),
+        _ => {}
+    }
+}
+
+/// Names of the variables referenced as `var.name`, `var["name"]` or `var['name']`
+fn template_var_names(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+
+    while let Some(pos) = rest.find("var") {
+        let preceded_by_ident = rest[..pos]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.');
+        rest = &rest[pos + 3..];
+        if preceded_by_ident {
+            continue;
+        }
+
+        let name = if let Some(after) = rest.strip_prefix('.') {
+            after
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default()
+        } else if let Some(after) = rest.strip_prefix("[\"").or(rest.strip_prefix("['")) {
+            after.split(['"', '\'']).next().unwrap_or_default()
+        } else {
+            ""
+        };
+
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
+
+/// What a block waits for before it can run
+#[derive(Debug, Default, Clone)]
+pub(crate) struct BlockParents {
+    /// Earlier blocks writing context this block reads; all of them must succeed
+    pub context: HashSet<usize>,
+    /// Explicit dependencies that are part of the workflow
+    pub dependencies: HashSet<usize>,
+    pub mode: DependencyMode,
+}
+
+/// The blocks of a workflow and what each one waits for
+#[derive(Debug)]
+pub struct WorkflowGraph {
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) parents: Vec<BlockParents>,
+}
+
+impl WorkflowGraph {
+    /// Work out the edges between blocks, which must be in document order
+    ///
+    /// Dependencies on blocks that are not part of the workflow are ignored here; they are
+    /// checked against the execution log when the block runs.
+    pub fn build(blocks: Vec<Block>, dependencies: &HashMap<Uuid, DependencySpec>) -> Self {
+        let accesses = blocks.iter().map(Access::of).collect::<Vec<_>>();
+        let index = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (block.id().to_string(), i))
+            .collect::<HashMap<_, _>>();
+
+        let parents = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let context = (0..i)
+                    .filter(|&earlier| accesses[earlier].must_precede(&accesses[i]))
+                    .collect();
+
+                let spec = dependencies.get(&block.id());
+                let dependencies = spec
+                    .map(|spec| {
+                        spec.parents
+                            .iter()
+                            .filter_map(|parent| index.get(parent).copied())
+                            .filter(|&parent| parent != i)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                BlockParents {
+                    context,
+                    dependencies,
+                    mode: spec.map(|spec| spec.mode).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Self { blocks, parents }
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// IDs of the blocks that must finish before `id` can run
+    pub fn parents_of(&self, id: Uuid) -> Vec<Uuid> {
+        let Some(i) = self.blocks.iter().position(|block| block.id() == id) else {
+            return Vec::new();
+        };
+
+        let parents = &self.parents[i];
+        let mut ids = parents
+            .context
+            .union(&parents.dependencies)
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter().map(|p| self.blocks[p].id()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{
+        directory::Directory, foreach::Foreach, pause::Pause, script::Script,
+        ssh_tunnel::SshTunnel, var::Var,
+    };
+
+    fn script(code: &str, output_variable: Option<&str>) -> Block {
+        Block::Script(
+            Script::builder()
+                .id(Uuid::new_v4())
+                .name("script")
+                .code(code)
+                .interpreter("bash")
+                .output_variable(output_variable.map(str::to_string))
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_template_var_names() {
+        assert_eq!(
+            template_var_names(r#"echo {{ var.host }} {{var["user name"]}} {{ var['port'] }}"#),
+            vec!["host", "user name", "port"]
+        );
+        assert!(template_var_names("{{ env.var }} {{ somevar.x }} variable").is_empty());
+    }
+
+    #[test]
+    fn test_independent_blocks_have_no_parents() {
+        let a = script("uptime", None);
+        let b = script("df -h", None);
+        let graph = WorkflowGraph::build(vec![a, b.clone()], &HashMap::new());
+
+        assert!(graph.parents_of(b.id()).is_empty());
+    }
+
+    #[test]
+    fn test_context_writes_order_blocks() {
+        let dir = Block::Directory(Directory::builder().id(Uuid::new_v4()).path("/tmp").build());
+        let var = Block::Var(
+            Var::builder()
+                .id(Uuid::new_v4())
+                .name("region")
+                .value("eu")
+                .build(),
+        );
+        let produce = script("echo hi", Some("greeting"));
+        let consume = script("echo {{ var.greeting }}", None);
+        let region = script("echo {{ var.region }}", None);
+        let pause = Block::Pause(Pause::builder().id(Uuid::new_v4()).build());
+        let after = script("true", None);
+
+        let graph = WorkflowGraph::build(
+            vec![
+                dir.clone(),
+                var.clone(),
+                produce.clone(),
+                consume.clone(),
+                region.clone(),
+                pause.clone(),
+                after.clone(),
+            ],
+            &HashMap::new(),
+        );
+
+        assert_eq!(graph.parents_of(produce.id()), vec![dir.id()]);
+        assert_eq!(graph.parents_of(consume.id()), vec![dir.id(), produce.id()]);
+        assert_eq!(graph.parents_of(region.id()), vec![dir.id(), var.id()]);
+        assert_eq!(graph.parents_of(pause.id()).len(), 5);
+        assert_eq!(graph.parents_of(after.id()), vec![dir.id(), pause.id()]);
+    }
+
+    #[test]
+    fn test_tunnels_and_loops() {
+        let tunnel = Block::SshTunnel(
+            SshTunnel::builder()
+                .id(Uuid::new_v4())
+                .remote_host("db.internal")
+                .remote_port(5432)
+                .output_variable("db_port")
+                .build(),
+        );
+        let hosts = Block::Var(
+            Var::builder()
+                .id(Uuid::new_v4())
+                .name("hosts")
+                .value("[]")
+                .build(),
+        );
+        let each = Block::Foreach(
+            Foreach::builder()
+                .id(Uuid::new_v4())
+                .name("each host")
+                .items("var.hosts")
+                .children(vec![serde_json::json!({
+                    "id": Uuid::new_v4().to_string(),
+                    "type": "script",
+                    "props": {
+                        "name": "migrate",
+                        "code": "psql -p {{ var.db_port }} < migrate.sql",
+                        "interpreter": "bash",
+                        "outputVariable": "migrated"
+                    },
+                    "children": []
+                })])
+                .build(),
+        );
+        let after = script("echo {{ var.migrated }}", None);
+
+        let graph = WorkflowGraph::build(
+            vec![tunnel.clone(), hosts.clone(), each.clone(), after.clone()],
+            &HashMap::new(),
+        );
+
+        // The loop waits for what its nested blocks read, as well as for its items
+        assert_eq!(graph.parents_of(each.id()), vec![tunnel.id(), hosts.id()]);
+        // ...but what they write stays inside the loop
+        assert!(graph.parents_of(after.id()).is_empty());
+    }
+
+    #[test]
+    fn test_dependencies_add_edges() {
+        let a = script("make build", None);
+        let b = script("make test", None);
+        let dependencies = HashMap::from([(
+            b.id(),
+            DependencySpec {
+                parents: vec![a.id().to_string(), Uuid::new_v4().to_string()],
+                within: 0,
+                auto_run_parents: true,
+                mode: DependencyMode::Any,
+            },
+        )]);
+
+        let graph = WorkflowGraph::build(vec![a.clone(), b.clone()], &dependencies);
+        assert_eq!(graph.parents_of(b.id()), vec![a.id()]);
+        assert_eq!(graph.parents[1].mode, DependencyMode::Any);
+    }
+}
@@ -1,80 +1,322 @@
+use std::fmt;
+
 use eyre::Result;
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
 
 use crate::{blocks::Block, exec_log::ExecLogHandle};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How the parents of a block combine
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub enum DependencyMode {
+    /// Every parent must be satisfied
+    #[default]
+    All,
+    /// At least one parent must be satisfied
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DependencySpec {
+    #[serde(default)]
     pub parents: Vec<String>,
+    /// Seconds within which a parent must have run; `0` means since this block last ran,
+    /// and `-1` means at any time
+    #[serde(default)]
     pub within: i64,
+    #[serde(default = "default_auto_run_parents")]
     pub auto_run_parents: bool,
+    #[serde(default)]
+    pub mode: DependencyMode,
 }
 
-impl DependencySpec {
-    pub async fn can_run(&self, block: &Block, exec_log: ExecLogHandle) -> Result<bool> {
-        if self.parents.is_empty() {
-            return Ok(true);
+fn default_auto_run_parents() -> bool {
+    true
+}
+
+/// Why a parent stops a block from running
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub enum BlockedReason {
+    /// The parent is not a valid block ID
+    InvalidParent,
+    /// The parent has never run
+    NeverRun,
+    /// The parent has not run since this block last ran
+    NotRunSince,
+    /// The parent last ran longer ago than the dependency allows
+    Expired { within: i64 },
+}
+
+impl fmt::Display for BlockedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockedReason::InvalidParent => write!(f, "is not a valid block ID"),
+            BlockedReason::NeverRun => write!(f, "has never run"),
+            BlockedReason::NotRunSince => write!(f, "has not run since this block last ran"),
+            BlockedReason::Expired { within } => {
+                write!(f, "has not run in the last {within} seconds")
+            }
         }
+    }
+}
 
-        let parent_id = self.parents.first().unwrap();
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BlockedParent {
+    pub parent: String,
+    pub reason: BlockedReason,
+}
 
-        match self.within {
-            -1 => {
-                let parent_uuid = match uuid::Uuid::parse_str(parent_id) {
-                    Ok(uuid) => uuid,
-                    Err(_) => return Ok(false), // Invalid parent UUID
-                };
+impl fmt::Display for BlockedParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parent {} {}", self.parent, self.reason)
+    }
+}
+
+/// The result of checking a block's dependencies
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DependencyCheck {
+    /// Parents that stop the block from running; empty if it can run
+    pub blocked_by: Vec<BlockedParent>,
+    /// Parents to run before the block, if `auto_run_parents` is set and it is blocked
+    pub run_first: Vec<Uuid>,
+}
 
-                // Parent must have been run at least once
-                let parent_last_run = exec_log.get_last_execution_time(parent_uuid).await?;
-                println!("got run once ever, parent_last_run: {parent_last_run:?}");
+impl DependencyCheck {
+    pub fn can_run(&self) -> bool {
+        self.blocked_by.is_empty()
+    }
+}
 
-                Ok(parent_last_run.is_some())
-            }
+impl DependencySpec {
+    /// Read the dependency stored in a block's `dependency` prop, if it has any parents
+    pub fn from_document(block_data: &serde_json::Value) -> Option<Self> {
+        let spec = match block_data.get("props")?.get("dependency")? {
+            serde_json::Value::String(json) => serde_json::from_str::<Self>(json).ok()?,
+            value => serde_json::from_value::<Self>(value.clone()).ok()?,
+        };
+
+        (!spec.parents.is_empty()).then_some(spec)
+    }
+
+    pub async fn can_run(&self, block: &Block, exec_log: ExecLogHandle) -> Result<DependencyCheck> {
+        self.check(block.id(), &exec_log).await
+    }
+
+    /// Check every parent against the execution log
+    pub async fn check(&self, block_id: Uuid, exec_log: &ExecLogHandle) -> Result<DependencyCheck> {
+        if self.parents.is_empty() {
+            return Ok(DependencyCheck::default());
+        }
+
+        // Only needed when the parent has to have run since this block did
+        let block_last_run = if self.within == 0 {
+            exec_log.get_last_execution_time(block_id).await?
+        } else {
+            None
+        };
+        let now = time::OffsetDateTime::now_utc().unix_timestamp_nanos();
 
-            0 => {
-                let parent_uuid = match uuid::Uuid::parse_str(parent_id) {
-                    Ok(uuid) => uuid,
-                    Err(_) => return Ok(false), // Invalid parent UUID
-                };
+        let mut statuses = Vec::with_capacity(self.parents.len());
+        for parent in &self.parents {
+            let status = match Uuid::parse_str(parent) {
+                Ok(parent_id) => {
+                    let parent_last_run = exec_log.get_last_execution_time(parent_id).await?;
+                    self.parent_status(parent_last_run, block_last_run, now)
+                }
+                Err(_) => Some(BlockedReason::InvalidParent),
+            };
+            tracing::trace!("Dependency {parent} of block {block_id}: {status:?}");
 
-                // We have when the parent last ran. Now check when this block last ran. So long as it was before the parent last ran, we're good.
-                let parent_last_run = exec_log.get_last_execution_time(parent_uuid).await?;
-                let block_last_run = exec_log.get_last_execution_time(block.id()).await?;
+            statuses.push((parent.clone(), status));
+        }
 
-                match (parent_last_run, block_last_run) {
-                    (Some(parent_last_run), Some(block_last_run)) => {
-                        Ok(block_last_run < parent_last_run)
-                    }
+        Ok(self.combine(statuses))
+    }
 
-                    (Some(_), None) => Ok(true),
+    /// Whether one parent is satisfied, given when it and the block last ran (in nanoseconds)
+    fn parent_status(
+        &self,
+        parent_last_run: Option<u64>,
+        block_last_run: Option<u64>,
+        now: i128,
+    ) -> Option<BlockedReason> {
+        let Some(parent_last_run) = parent_last_run else {
+            return Some(BlockedReason::NeverRun);
+        };
 
-                    _ => Ok(false),
+        match self.within {
+            within if within < 0 => None,
+            0 => match block_last_run {
+                Some(block_last_run) if block_last_run >= parent_last_run => {
+                    Some(BlockedReason::NotRunSince)
                 }
+                _ => None,
+            },
+            within => {
+                let window = within as i128 * 1_000_000_000;
+                (now - parent_last_run as i128 > window)
+                    .then_some(BlockedReason::Expired { within })
             }
+        }
+    }
 
-            num => {
-                let parent_uuid = match uuid::Uuid::parse_str(parent_id) {
-                    Ok(uuid) => uuid,
-                    Err(_) => return Ok(false), // Invalid parent UUID
-                };
+    /// Combine the status of each parent according to the dependency mode
+    fn combine(&self, statuses: Vec<(String, Option<BlockedReason>)>) -> DependencyCheck {
+        let satisfied = statuses
+            .iter()
+            .filter(|(_, status)| status.is_none())
+            .count();
+        let can_run = match self.mode {
+            DependencyMode::All => satisfied == statuses.len(),
+            DependencyMode::Any => satisfied > 0,
+        };
 
-                // Parent must have been run within the specified time window
-                let parent_last_run = exec_log.get_last_execution_time(parent_uuid).await?;
+        if can_run {
+            return DependencyCheck::default();
+        }
 
-                println!("got run within, parent_last_run: {parent_last_run:?}");
+        let blocked_by = statuses
+            .into_iter()
+            .filter_map(|(parent, status)| {
+                Some(BlockedParent {
+                    parent,
+                    reason: status?,
+                })
+            })
+            .collect::<Vec<_>>();
 
-                if let Some(parent_last_run) = parent_last_run {
-                    let now = time::OffsetDateTime::now_utc().unix_timestamp_nanos();
-                    let window = num * 1_000_000_000;
-                    let time_since_parent_run = now - parent_last_run as i128;
+        let runnable = blocked_by
+            .iter()
+            .filter_map(|blocked| Uuid::parse_str(&blocked.parent).ok());
+        let run_first = match (self.auto_run_parents, self.mode) {
+            (false, _) => Vec::new(),
+            (true, DependencyMode::All) => runnable.collect(),
+            (true, DependencyMode::Any) => runnable.take(1).collect(),
+        };
 
-                    return Ok(time_since_parent_run <= window as i128);
-                }
+        DependencyCheck {
+            blocked_by,
+            run_first,
+        }
+    }
+}
 
-                Ok(false)
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn spec(within: i64, mode: DependencyMode) -> DependencySpec {
+        DependencySpec {
+            parents: Vec::new(),
+            within,
+            auto_run_parents: true,
+            mode,
         }
     }
+
+    #[test]
+    fn test_parent_status() {
+        let now = 100 * SECOND as i128;
+
+        let ever = spec(-1, DependencyMode::All);
+        assert_eq!(
+            ever.parent_status(None, None, now),
+            Some(BlockedReason::NeverRun)
+        );
+        assert_eq!(ever.parent_status(Some(1), Some(50 * SECOND), now), None);
+
+        let since = spec(0, DependencyMode::All);
+        assert_eq!(since.parent_status(Some(10), None, now), None);
+        assert_eq!(since.parent_status(Some(10), Some(5), now), None);
+        assert_eq!(
+            since.parent_status(Some(10), Some(20), now),
+            Some(BlockedReason::NotRunSince)
+        );
+
+        let window = spec(30, DependencyMode::All);
+        assert_eq!(window.parent_status(Some(80 * SECOND), None, now), None);
+        assert_eq!(
+            window.parent_status(Some(60 * SECOND), None, now),
+            Some(BlockedReason::Expired { within: 30 })
+        );
+    }
+
+    #[test]
+    fn test_combine_all_and_any() {
+        let a = Uuid::new_v4().to_string();
+        let b = Uuid::new_v4().to_string();
+        let statuses = vec![
+            (a.clone(), None),
+            (b.clone(), Some(BlockedReason::NeverRun)),
+            ("not-a-uuid".to_string(), Some(BlockedReason::InvalidParent)),
+        ];
+
+        let all = spec(0, DependencyMode::All).combine(statuses.clone());
+        assert!(!all.can_run());
+        assert_eq!(
+            all.blocked_by,
+            vec![
+                BlockedParent {
+                    parent: b.clone(),
+                    reason: BlockedReason::NeverRun,
+                },
+                BlockedParent {
+                    parent: "not-a-uuid".to_string(),
+                    reason: BlockedReason::InvalidParent,
+                },
+            ]
+        );
+        assert_eq!(all.run_first, vec![Uuid::parse_str(&b).unwrap()]);
+
+        let any = spec(0, DependencyMode::Any).combine(statuses.clone());
+        assert!(any.can_run());
+        assert!(any.run_first.is_empty());
+
+        let none_satisfied = statuses[1..].to_vec();
+        let any = spec(0, DependencyMode::Any).combine(none_satisfied);
+        assert!(!any.can_run());
+        assert_eq!(any.blocked_by.len(), 2);
+        assert_eq!(any.run_first, vec![Uuid::parse_str(&b).unwrap()]);
+
+        let mut manual = spec(0, DependencyMode::All);
+        manual.auto_run_parents = false;
+        assert!(manual.combine(statuses).run_first.is_empty());
+    }
+
+    #[test]
+    fn test_from_document() {
+        let parent = Uuid::new_v4().to_string();
+        let dependency = serde_json::json!({
+            "parents": [parent],
+            "within": -1,
+            "autoRunParents": false,
+        });
+        let block = serde_json::json!({
+            "id": Uuid::new_v4().to_string(),
+            "props": { "dependency": dependency.to_string() },
+        });
+
+        let spec = DependencySpec::from_document(&block).unwrap();
+        assert_eq!(spec.parents, vec![parent]);
+        assert_eq!(spec.within, -1);
+        assert!(!spec.auto_run_parents);
+        assert_eq!(spec.mode, DependencyMode::All);
+
+        let no_parents = serde_json::json!({ "props": { "dependency": "{}" } });
+        assert!(DependencySpec::from_document(&no_parents).is_none());
+        let no_dependency = serde_json::json!({ "props": {} });
+        assert!(DependencySpec::from_document(&no_dependency).is_none());
+    }
 }
@@ -9,13 +9,36 @@ pub enum WorkflowCommand {
     StopBlock { id: Uuid },
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 pub enum WorkflowEvent {
-    BlockStarted { id: Uuid },
-    BlockFinished { id: Uuid },
+    BlockStarted {
+        id: Uuid,
+    },
+    BlockFinished {
+        id: Uuid,
+    },
+    BlockFailed {
+        id: Uuid,
+        error: String,
+    },
+
+    /// A workflow asked for a block to run, as everything it depends on has finished
+    BlockScheduled {
+        workflow: Uuid,
+        id: Uuid,
+    },
+    /// A workflow will not run a block, as something it depends on failed
+    BlockSkipped {
+        workflow: Uuid,
+        id: Uuid,
+    },
 
-    WorkflowStarted { id: Uuid },
-    WorkflowFinished { id: Uuid },
+    WorkflowStarted {
+        id: Uuid,
+    },
+    WorkflowFinished {
+        id: Uuid,
+    },
 }
@@ -1,7 +1,8 @@
-/// Currently we only support a single, serial workflow
-/// Suporting multiple won't be much of a change here, we will just need to
-/// switch to broadcast channels in a few places.
-/// I've done ~half the work for multiple workflows here :)
+/// Runs workflows, serial or parallel, several at a time
+///
+/// Commands go out and events come back over broadcast channels, so any number of
+/// workflows (and listeners) can share them; each workflow only reacts to events for its
+/// own blocks.
 use std::collections::HashMap;
 
 use crate::blocks::Block;
@@ -9,13 +10,29 @@ use tokio::sync::{broadcast, mpsc, oneshot};
 use uuid::Uuid;
 
 use super::{
+    dag::WorkflowGraph,
     event::{WorkflowCommand, WorkflowEvent},
+    parallel::parallel_execute,
     serial::serial_execute,
+    DependencySpec,
 };
 
 pub enum ExecutorMessage {
-    RunWorkflow { id: Uuid, workflow: Vec<Block> },
-    StopWorkflow { id: Uuid },
+    RunWorkflow {
+        id: Uuid,
+        workflow: Vec<Block>,
+    },
+    /// Run blocks concurrently, in an order worked out from their dependencies and the
+    /// context they read and write
+    RunParallelWorkflow {
+        id: Uuid,
+        workflow: Vec<Block>,
+        dependencies: HashMap<Uuid, DependencySpec>,
+        max_concurrency: usize,
+    },
+    StopWorkflow {
+        id: Uuid,
+    },
 }
 
 #[derive(Clone)]
@@ -26,7 +43,7 @@ pub struct ExecutorHandle {
 impl ExecutorHandle {
     pub fn new(
         event_sender: broadcast::Sender<WorkflowEvent>,
-        cmd_sender: mpsc::Sender<WorkflowCommand>,
+        cmd_sender: broadcast::Sender<WorkflowCommand>,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
         let mut actor = Executor::new(receiver, event_sender, cmd_sender);
@@ -37,13 +54,34 @@ impl ExecutorHandle {
     }
 
     pub async fn run_workflow(&self, id: Uuid, workflow: Vec<Block>) {
-        println!("running workflow: {workflow:?}");
+        tracing::debug!("Running serial workflow {id}");
         self.sender
             .send(ExecutorMessage::RunWorkflow { id, workflow })
             .await
             .expect("Failed to send run workflow message");
     }
 
+    pub async fn run_parallel_workflow(
+        &self,
+        id: Uuid,
+        workflow: Vec<Block>,
+        dependencies: HashMap<Uuid, DependencySpec>,
+        max_concurrency: usize,
+    ) {
+        tracing::debug!(
+            "Running parallel workflow {id} with up to {max_concurrency} blocks at once"
+        );
+        self.sender
+            .send(ExecutorMessage::RunParallelWorkflow {
+                id,
+                workflow,
+                dependencies,
+                max_concurrency,
+            })
+            .await
+            .expect("Failed to send run workflow message");
+    }
+
     pub async fn stop_workflow(&self, id: Uuid) {
         self.sender
             .send(ExecutorMessage::StopWorkflow { id })
@@ -65,14 +103,14 @@ pub struct Executor {
     // for passing messages back to tauri/eventually other things
     // this can be global! it doesn't really matter
     event_sender: broadcast::Sender<WorkflowEvent>,
-    cmd_sender: mpsc::Sender<WorkflowCommand>,
+    cmd_sender: broadcast::Sender<WorkflowCommand>,
 }
 
 impl Executor {
     pub fn new(
         receiver: mpsc::Receiver<ExecutorMessage>,
         event_sender: broadcast::Sender<WorkflowEvent>,
-        cmd_sender: mpsc::Sender<WorkflowCommand>,
+        cmd_sender: broadcast::Sender<WorkflowCommand>,
     ) -> Self {
         Self {
             receiver,
@@ -84,52 +122,103 @@ impl Executor {
 
     pub async fn run(&mut self) {
         while let Some(message) = self.receiver.recv().await {
+            // A workflow's task drops its cancel receiver when it finishes
+            self.workflow_store
+                .retain(|_, workflow| !workflow.cancel_channel.is_closed());
+
             match message {
                 ExecutorMessage::RunWorkflow { id, workflow } => {
-                    // if the workflow is already running, we don't want to run it again
-                    if self.workflow_store.contains_key(&id) {
-                        println!("workflow already running, skipping");
-                        continue;
-                    }
-
-                    let (cancel_channel, cancel_receiver) = oneshot::channel();
-
-                    // we don't want to block here, so we spawn a new task
-                    // Currently we assume all workflows are serial, as that is the only type we support
-                    let event_receiver = self.event_sender.subscribe();
-                    let cmd_sender = self.cmd_sender.clone();
-
-                    let event_sender = self.event_sender.clone();
-
-                    tokio::spawn(async move {
-                        serial_execute(workflow, cancel_receiver, cmd_sender, event_receiver).await;
-
-                        event_sender
-                            .send(WorkflowEvent::WorkflowFinished { id })
-                            .expect("Failed to send finished event");
-                    });
-
-                    println!("inserting workflow into store: {id:?}");
-                    self.workflow_store
-                        .insert(id, WorkflowData { cancel_channel });
+                    self.spawn_workflow(
+                        id,
+                        move |cancel_receiver, cmd_sender, event_receiver, _| {
+                            serial_execute(workflow, cancel_receiver, cmd_sender, event_receiver)
+                        },
+                    );
+                }
+                ExecutorMessage::RunParallelWorkflow {
+                    id,
+                    workflow,
+                    dependencies,
+                    max_concurrency,
+                } => {
+                    let graph = WorkflowGraph::build(workflow, &dependencies);
+                    self.spawn_workflow(
+                        id,
+                        move |cancel_receiver, cmd_sender, event_receiver, event_sender| {
+                            parallel_execute(
+                                id,
+                                graph,
+                                max_concurrency,
+                                cancel_receiver,
+                                cmd_sender,
+                                event_receiver,
+                                event_sender,
+                            )
+                        },
+                    );
                 }
                 ExecutorMessage::StopWorkflow { id } => {
-                    println!("stopping workflow: {id:?}");
+                    tracing::debug!("Stopping workflow {id}");
                     match self.workflow_store.remove(&id) {
                         Some(handle) => {
                             // If the cancel channel is still open, cancel the workflow. Otherwise, it has already finished
                             if !handle.cancel_channel.is_closed() {
                                 if let Err(e) = handle.cancel_channel.send(()) {
-                                    println!("error sending cancel signal: {e:?}");
+                                    tracing::warn!("Error sending cancel signal: {e:?}");
                                 }
                             }
                         }
                         _ => {
-                            println!("workflow not found, skipping");
+                            tracing::debug!("Workflow {id} not found, skipping");
                         }
                     }
                 }
             }
         }
     }
+
+    /// Run a workflow in its own task, announcing when it starts and finishes
+    fn spawn_workflow<F, Fut>(&mut self, id: Uuid, execute: F)
+    where
+        F: FnOnce(
+                oneshot::Receiver<()>,
+                broadcast::Sender<WorkflowCommand>,
+                broadcast::Receiver<WorkflowEvent>,
+                broadcast::Sender<WorkflowEvent>,
+            ) -> Fut
+            + Send
+            + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        // if the workflow is already running, we don't want to run it again
+        if self.workflow_store.contains_key(&id) {
+            tracing::debug!("Workflow {id} already running, skipping");
+            return;
+        }
+
+        let (cancel_channel, cancel_receiver) = oneshot::channel();
+
+        // Subscribe before starting, so no event for the workflow's blocks is missed
+        let event_receiver = self.event_sender.subscribe();
+        let cmd_sender = self.cmd_sender.clone();
+        let event_sender = self.event_sender.clone();
+
+        // we don't want to block here, so we spawn a new task
+        tokio::spawn(async move {
+            let _ = event_sender.send(WorkflowEvent::WorkflowStarted { id });
+
+            execute(
+                cancel_receiver,
+                cmd_sender,
+                event_receiver,
+                event_sender.clone(),
+            )
+            .await;
+
+            let _ = event_sender.send(WorkflowEvent::WorkflowFinished { id });
+        });
+
+        self.workflow_store
+            .insert(id, WorkflowData { cancel_channel });
+    }
 }
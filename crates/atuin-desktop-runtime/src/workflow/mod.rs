@@ -2,16 +2,23 @@
 //!
 //! This module provides workflow execution capabilities including:
 //! - Serial execution of blocks in order
+//! - Parallel execution of independent blocks, ordered by a dependency graph
 //! - Dependency-based execution ordering
 //! - Workflow event broadcasting
 //! - Execution orchestration
 
+mod dag;
 mod dependency;
 mod event;
 mod executor;
+mod parallel;
 mod serial;
 
-pub use dependency::DependencySpec;
+pub use dag::WorkflowGraph;
+pub use dependency::{
+    BlockedParent, BlockedReason, DependencyCheck, DependencyMode, DependencySpec,
+};
 pub use event::{WorkflowCommand, WorkflowEvent};
 pub use executor::ExecutorHandle;
+pub use parallel::parallel_execute;
 pub use serial::serial_execute;
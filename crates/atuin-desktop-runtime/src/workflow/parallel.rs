@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use tokio::sync::{broadcast, oneshot};
+use uuid::Uuid;
+
+use super::dag::{BlockParents, WorkflowGraph};
+use super::event::{WorkflowCommand, WorkflowEvent};
+use super::DependencyMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeState {
+    Waiting,
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+impl NodeState {
+    fn is_done(self) -> bool {
+        matches!(
+            self,
+            NodeState::Succeeded | NodeState::Failed | NodeState::Skipped
+        )
+    }
+}
+
+/// Whether a block can start, must wait, or can never run because a parent failed
+#[derive(Debug, PartialEq, Eq)]
+enum Readiness {
+    Ready,
+    Waiting,
+    Blocked,
+}
+
+fn readiness(parents: &BlockParents, states: &[NodeState]) -> Readiness {
+    let context_failed = parents
+        .context
+        .iter()
+        .any(|&p| matches!(states[p], NodeState::Failed | NodeState::Skipped));
+    let context_done = parents
+        .context
+        .iter()
+        .all(|&p| states[p] == NodeState::Succeeded);
+
+    let dependencies = parents.dependencies.iter().map(|&p| states[p]);
+    let (dependencies_failed, dependencies_done) = if parents.dependencies.is_empty() {
+        (false, true)
+    } else {
+        match parents.mode {
+            DependencyMode::All => (
+                dependencies
+                    .clone()
+                    .any(|s| matches!(s, NodeState::Failed | NodeState::Skipped)),
+                dependencies.clone().all(|s| s == NodeState::Succeeded),
+            ),
+            DependencyMode::Any => (
+                dependencies
+                    .clone()
+                    .all(|s| matches!(s, NodeState::Failed | NodeState::Skipped)),
+                dependencies.clone().any(|s| s == NodeState::Succeeded),
+            ),
+        }
+    };
+
+    if context_failed || dependencies_failed {
+        Readiness::Blocked
+    } else if context_done && dependencies_done {
+        Readiness::Ready
+    } else {
+        Readiness::Waiting
+    }
+}
+
+/// Run the blocks of a workflow as soon as what they depend on has finished
+///
+/// At most `max_concurrency` blocks run at once. Blocks are started with
+/// [`WorkflowCommand::RunBlock`], and are considered done when a `BlockFinished` or
+/// `BlockFailed` event arrives for them. Blocks that can no longer run because a parent
+/// failed are reported with `BlockSkipped`.
+pub async fn parallel_execute(
+    id: Uuid,
+    graph: WorkflowGraph,
+    max_concurrency: usize,
+    mut cancel_channel: oneshot::Receiver<()>,
+    send_command: broadcast::Sender<WorkflowCommand>,
+    mut recv_event: broadcast::Receiver<WorkflowEvent>,
+    send_event: broadcast::Sender<WorkflowEvent>,
+) {
+    let max_concurrency = max_concurrency.max(1);
+    let index = graph
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.id(), i))
+        .collect::<HashMap<_, _>>();
+    let mut states = vec![NodeState::Waiting; graph.blocks.len()];
+
+    // The first waiting block, in document order, with the given readiness
+    let next_waiting = |states: &[NodeState], wanted: Readiness| {
+        (0..states.len()).find(|&i| {
+            states[i] == NodeState::Waiting && readiness(&graph.parents[i], states) == wanted
+        })
+    };
+
+    loop {
+        // Skip blocks whose parents failed, then start whatever is ready
+        while let Some(i) = next_waiting(&states, Readiness::Blocked) {
+            states[i] = NodeState::Skipped;
+
+            let block = graph.blocks[i].id();
+            tracing::debug!("Workflow {id}: skipping block {block}, as a parent failed");
+            let _ = send_event.send(WorkflowEvent::BlockSkipped {
+                workflow: id,
+                id: block,
+            });
+        }
+
+        while states.iter().filter(|&&s| s == NodeState::Running).count() < max_concurrency {
+            let Some(i) = next_waiting(&states, Readiness::Ready) else {
+                break;
+            };
+            states[i] = NodeState::Running;
+
+            let block = graph.blocks[i].id();
+            tracing::debug!("Workflow {id}: starting block {block}");
+            let _ = send_event.send(WorkflowEvent::BlockScheduled {
+                workflow: id,
+                id: block,
+            });
+            if send_command
+                .send(WorkflowCommand::RunBlock { id: block })
+                .is_err()
+            {
+                tracing::warn!("Workflow {id}: nothing is listening for workflow commands");
+                return;
+            }
+        }
+
+        if states.iter().all(|s| s.is_done()) {
+            return;
+        }
+
+        tokio::select! {
+            Ok(()) = &mut cancel_channel => {
+                tracing::debug!("Workflow {id} cancelled");
+                for (block, state) in graph.blocks.iter().zip(&states) {
+                    if *state == NodeState::Running {
+                        let _ = send_command.send(WorkflowCommand::StopBlock { id: block.id() });
+                    }
+                }
+                return;
+            }
+
+            event = recv_event.recv() => {
+                let (block, state) = match event {
+                    Ok(WorkflowEvent::BlockFinished { id }) => (id, NodeState::Succeeded),
+                    Ok(WorkflowEvent::BlockFailed { id, .. }) => (id, NodeState::Failed),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("Workflow {id} missed {missed} events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+
+                if let Some(&i) = index.get(&block) {
+                    if states[i] == NodeState::Running {
+                        states[i] = state;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{script::Script, Block};
+    use std::time::Duration;
+
+    fn script(code: &str, output_variable: Option<&str>) -> Block {
+        Block::Script(
+            Script::builder()
+                .id(Uuid::new_v4())
+                .name("script")
+                .code(code)
+                .interpreter("bash")
+                .output_variable(output_variable.map(str::to_string))
+                .build(),
+        )
+    }
+
+    /// Stands in for the app: runs each block it is asked to, failing those in `failing`
+    fn spawn_runner(
+        mut commands: broadcast::Receiver<WorkflowCommand>,
+        events: broadcast::Sender<WorkflowEvent>,
+        failing: Vec<Uuid>,
+    ) {
+        tokio::spawn(async move {
+            while let Ok(command) = commands.recv().await {
+                let WorkflowCommand::RunBlock { id } = command else {
+                    continue;
+                };
+
+                let events = events.clone();
+                let fail = failing.contains(&id);
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    let _ = events.send(if fail {
+                        WorkflowEvent::BlockFailed {
+                            id,
+                            error: "failed".to_string(),
+                        }
+                    } else {
+                        WorkflowEvent::BlockFinished { id }
+                    });
+                });
+            }
+        });
+    }
+
+    async fn run(
+        blocks: Vec<Block>,
+        max_concurrency: usize,
+        failing: Vec<Uuid>,
+    ) -> Vec<WorkflowEvent> {
+        let (command_tx, command_rx) = broadcast::channel(64);
+        let (event_tx, event_rx) = broadcast::channel(64);
+        let mut observer = event_tx.subscribe();
+        spawn_runner(command_rx, event_tx.clone(), failing);
+
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        let graph = WorkflowGraph::build(blocks, &HashMap::new());
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            parallel_execute(
+                Uuid::new_v4(),
+                graph,
+                max_concurrency,
+                cancel_rx,
+                command_tx,
+                event_rx,
+                event_tx,
+            ),
+        )
+        .await
+        .expect("workflow did not finish");
+
+        let mut events = Vec::new();
+        while let Ok(event) = observer.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    /// Most blocks that were scheduled without any of them finishing in between
+    fn peak_concurrency(events: &[WorkflowEvent]) -> usize {
+        let mut running = 0usize;
+        let mut peak = 0;
+        for event in events {
+            match event {
+                WorkflowEvent::BlockScheduled { .. } => running += 1,
+                WorkflowEvent::BlockFinished { .. } | WorkflowEvent::BlockFailed { .. } => {
+                    running -= 1
+                }
+                _ => {}
+            }
+            peak = peak.max(running);
+        }
+        peak
+    }
+
+    #[tokio::test]
+    async fn test_independent_blocks_run_concurrently_up_to_limit() {
+        let blocks = (0..5).map(|_| script("true", None)).collect::<Vec<_>>();
+
+        let events = run(blocks.clone(), 2, Vec::new()).await;
+        assert_eq!(peak_concurrency(&events), 2);
+
+        let events = run(blocks, 10, Vec::new()).await;
+        assert_eq!(peak_concurrency(&events), 5);
+    }
+
+    #[tokio::test]
+    async fn test_dependents_wait_and_are_skipped_on_failure() {
+        let produce = script("echo hi", Some("greeting"));
+        let consume = script("echo {{ var.greeting }}", None);
+        let other = script("uptime", None);
+
+        let events = run(
+            vec![produce.clone(), consume.clone(), other.clone()],
+            4,
+            Vec::new(),
+        )
+        .await;
+        let produced = events
+            .iter()
+            .position(|e| *e == WorkflowEvent::BlockFinished { id: produce.id() })
+            .unwrap();
+        let consumed = events
+            .iter()
+            .position(
+                |e| matches!(e, WorkflowEvent::BlockScheduled { id, .. } if *id == consume.id()),
+            )
+            .unwrap();
+        assert!(produced < consumed);
+
+        let events = run(
+            vec![produce.clone(), consume.clone(), other.clone()],
+            4,
+            vec![produce.id()],
+        )
+        .await;
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, WorkflowEvent::BlockSkipped { id, .. } if *id == consume.id())));
+        assert!(events
+            .iter()
+            .any(|e| *e == WorkflowEvent::BlockFinished { id: other.id() }));
+    }
+}
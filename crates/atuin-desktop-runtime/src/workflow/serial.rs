@@ -1,28 +1,31 @@
 use crate::blocks::Block;
 use crate::workflow::event::{WorkflowCommand, WorkflowEvent};
 use tokio::sync::broadcast;
-use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
 
 pub async fn serial_execute(
     workflow: Vec<Block>,
     cancel_channel: oneshot::Receiver<()>,
-    send_command: Sender<WorkflowCommand>,
+    send_command: broadcast::Sender<WorkflowCommand>,
     mut recv_event: broadcast::Receiver<WorkflowEvent>,
 ) {
     // 1. Kick off the first block in the workflow
     // 2. When we receive the finish event for it, kick off the next block
-    // 3. Repeat until the workflow is complete
+    // 3. Repeat until the workflow is complete, or a block fails
     // 4. Listen on the cancel channel for a stop event. Terminate the current block and exit.
     let mut iter = workflow.iter();
-    println!("serial workflow: {workflow:?}");
+    tracing::trace!("serial workflow: {workflow:?}");
 
-    if let Some(block) = iter.next() {
-        println!("running block: {block:?}");
-        send_command
-            .send(WorkflowCommand::RunBlock { id: block.id() })
-            .await
-            .expect("Failed to send run block event");
+    // Events for other workflows' blocks arrive on the same channel, so track our own
+    let Some(mut current) = iter.next().map(|block| block.id()) else {
+        return;
+    };
+    if send_command
+        .send(WorkflowCommand::RunBlock { id: current })
+        .is_err()
+    {
+        tracing::warn!("Nothing is listening for workflow commands");
+        return;
     }
 
     let mut cancel_fut = cancel_channel;
@@ -30,12 +33,11 @@ pub async fn serial_execute(
     loop {
         tokio::select! {
             Ok(()) = &mut cancel_fut => {
-                println!("Workflow cancelled");
+                tracing::debug!("Workflow cancelled");
 
                 // Send stop command to all blocks
                 for block in workflow.iter() {
-                    send_command.send(WorkflowCommand::StopBlock { id: block.id() }).await
-                        .expect("Failed to send stop block event");
+                    let _ = send_command.send(WorkflowCommand::StopBlock { id: block.id() });
                 }
 
                 // Terminate the workflow
@@ -45,20 +47,24 @@ pub async fn serial_execute(
             Ok(event) = recv_event.recv() => {
                 match event {
                     WorkflowEvent::BlockStarted { id } => {
-                        println!("block {id} started");
+                        tracing::trace!("block {id} started");
                     }
-                    WorkflowEvent::BlockFinished { id } => {
-                        println!("block {id} finished");
+                    WorkflowEvent::BlockFinished { id } if id == current => {
+                        tracing::trace!("block {id} finished");
 
                         // Get the next block in the workflow
                         if let Some(block) = iter.next() {
-                            send_command.send(WorkflowCommand::RunBlock { id: block.id() }).await
-                                .expect("Failed to send run block event");
+                            current = block.id();
+                            let _ = send_command.send(WorkflowCommand::RunBlock { id: current });
                         } else {
                             // The workflow is complete
                             break;
                         }
                     }
+                    WorkflowEvent::BlockFailed { id, error } if id == current => {
+                        tracing::debug!("block {id} failed, stopping workflow: {error}");
+                        break;
+                    }
 
                     // Not relevant in this function
                     _ => {}
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS local_values;
+
+DROP TABLE IF EXISTS context;
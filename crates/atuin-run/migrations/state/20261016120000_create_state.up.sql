@@ -0,0 +1,11 @@
+CREATE TABLE
+  context (document_id TEXT, block_id TEXT, context TEXT);
+
+CREATE INDEX idx_context_document_id ON context (document_id);
+
+CREATE UNIQUE INDEX idx_context_document_id_block_id ON context (document_id, block_id);
+
+CREATE TABLE
+  local_values (block_id TEXT, property TEXT, value TEXT);
+
+CREATE UNIQUE INDEX idx_local_values_block_id_property ON local_values (block_id, property);
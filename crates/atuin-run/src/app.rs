@@ -1,15 +1,139 @@
-use std::io::IsTerminal;
+use std::{io::IsTerminal, path::PathBuf, time::Duration};
 
-use clap::Parser;
+use atuin_desktop_runtime::client::ClientPromptResult;
+use clap::{Parser, Subcommand};
+use uuid::Uuid;
+
+use crate::{
+    history::parse_since, prompt::parse_answer, report::ReportFormat, selection::BlockSelector,
+};
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Run the runbook non-interactively (auto-detected from TTY if not specified)
     #[arg(short, long)]
     pub non_interactive: bool,
 
+    /// Set a runbook variable, overriding the value in the runbook (can be repeated)
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+
+    /// Load runbook variables from a YAML file of name: value pairs
+    #[arg(long, value_name = "PATH")]
+    pub vars_file: Option<PathBuf>,
+
+    /// Set an environment variable for every block (can be repeated)
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub env: Vec<(String, String)>,
+
+    /// Print each block with its templates rendered, without executing anything
+    #[arg(long)]
+    pub plan: bool,
+
+    /// Restore the context (directories, variables, etc.) left by the previous run of
+    /// this runbook instead of starting fresh
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Continue past Pause blocks instead of stopping (for non-interactive runs)
+    #[arg(long)]
+    pub auto_continue: bool,
+
+    /// Answer a prompt ahead of time, matched by its title (can be repeated). Quote a title
+    /// that has an `=` followed by a `:`, as in '"x=1: sure?"=yes'
+    #[arg(long = "answer", value_name = "TITLE=BUTTON[:VALUE]", value_parser = parse_answer)]
+    pub answers: Vec<(String, ClientPromptResult)>,
+
+    /// Start execution at this block (ID or name); earlier blocks still provide context
+    #[arg(long, value_name = "BLOCK")]
+    pub from: Option<BlockSelector>,
+
+    /// Stop execution after this block (ID or name)
+    #[arg(long, value_name = "BLOCK")]
+    pub until: Option<BlockSelector>,
+
+    /// Only execute these blocks (ID or name, can be repeated)
+    #[arg(long, value_name = "BLOCK")]
+    pub only: Vec<BlockSelector>,
+
+    /// Don't execute these blocks (ID or name, can be repeated)
+    #[arg(long, value_name = "BLOCK")]
+    pub skip: Vec<BlockSelector>,
+
+    /// Write a machine-readable report of the run in the given format
+    #[arg(long, value_enum, requires = "report_file")]
+    pub report: Option<ReportFormat>,
+
+    /// File to write the report to
+    #[arg(long, value_name = "PATH", requires = "report")]
+    pub report_file: Option<PathBuf>,
+
+    /// Append runtime events to this file as JSON Lines
+    #[arg(long, value_name = "PATH", conflicts_with = "events_fd")]
+    pub events_file: Option<PathBuf>,
+
+    /// Write runtime events as JSON Lines to an inherited file descriptor (e.g. 3)
+    #[arg(long, value_name = "FD")]
+    pub events_fd: Option<i32>,
+
     /// Path to an .atrb file, or a @user/name identifier
-    pub runbook: String,
+    #[arg(required = true)]
+    pub runbook: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check runbooks for problems without running them
+    Validate(ValidateArgs),
+    /// Browse the log of block executions, most recent first
+    Log(LogArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ValidateArgs {
+    /// Treat a variable as defined, e.g. one supplied with --var at run time (can be repeated)
+    #[arg(long = "var", value_name = "NAME")]
+    pub vars: Vec<String>,
+
+    /// .atrb files, or directories to search for them
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LogArgs {
+    /// Only show executions of this block
+    #[arg(long, value_name = "BLOCK_ID")]
+    pub block: Option<Uuid>,
+
+    /// Only show executions of blocks in this runbook
+    #[arg(long, value_name = "RUNBOOK_ID")]
+    pub runbook: Option<Uuid>,
+
+    /// Only show failed executions
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only show executions that ended within this long ago, e.g. 30m, 12h or 7d
+    #[arg(long, value_name = "DURATION", value_parser = parse_since)]
+    pub since: Option<Duration>,
+
+    /// Show at most this many executions
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: u32,
+
+    /// Print executions as JSON Lines
+    #[arg(long)]
+    pub json: bool,
+
+    /// Remove executions older than 90 days, keeping at most 1000 per block, instead of
+    /// listing them
+    #[arg(long, conflicts_with_all = ["block", "runbook", "failed", "since", "json"])]
+    pub prune: bool,
 }
 
 impl Args {
@@ -38,3 +162,11 @@ impl Args {
         std::io::stdout().is_terminal()
     }
 }
+
+/// Parse a `KEY=VALUE` command line argument
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{arg}'")),
+    }
+}
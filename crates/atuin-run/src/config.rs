@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use atuin_desktop_runtime::client::HubClient;
+use serde::Deserialize;
+
+/// Environment variable holding an Atuin Hub API token
+const HUB_TOKEN_ENV: &str = "ATUIN_HUB_TOKEN";
+
+/// Environment variable overriding the Atuin Hub API base URL
+const HUB_URL_ENV: &str = "ATUIN_HUB_URL";
+
+/// Environment variable overriding the config file location
+const CONFIG_PATH_ENV: &str = "ATUIN_RUN_CONFIG";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")]
+    IoError(PathBuf, std::io::Error),
+
+    #[error("Failed to parse config file {0}: {1}")]
+    ParseError(PathBuf, serde_yaml::Error),
+}
+
+/// Settings for the CLI, read from `~/.config/atuin-run/config.yaml`
+///
+/// Environment variables take precedence over values in the file, so CI jobs
+/// can inject credentials without writing a config file.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Config {
+    /// Token used to authenticate against Atuin Hub (needed for private runbooks)
+    #[serde(default)]
+    pub hub_token: Option<String>,
+
+    /// Base URL of the Atuin Hub API
+    #[serde(default)]
+    pub hub_url: Option<String>,
+}
+
+impl Config {
+    /// Load the config file (if present) and apply environment overrides
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match Self::config_path() {
+            Some(path) if path.is_file() => Self::from_file(path)?,
+            _ => Self::default(),
+        };
+
+        if let Ok(token) = std::env::var(HUB_TOKEN_ENV) {
+            if !token.is_empty() {
+                config.hub_token = Some(token);
+            }
+        }
+
+        if let Ok(url) = std::env::var(HUB_URL_ENV) {
+            if !url.is_empty() {
+                config.hub_url = Some(url);
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self, ConfigError> {
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| ConfigError::IoError(path.clone(), e))?;
+        serde_yaml::from_str(&content).map_err(|e| ConfigError::ParseError(path, e))
+    }
+
+    fn config_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var(CONFIG_PATH_ENV) {
+            return Some(PathBuf::from(path));
+        }
+
+        dirs::config_dir().map(|dir| dir.join("atuin-run").join("config.yaml"))
+    }
+
+    /// Build a hub client using the configured endpoint and credentials
+    pub fn hub_client(&self) -> HubClient {
+        let client = HubClient::with_auth(self.hub_token.clone());
+        match &self.hub_url {
+            Some(url) => client.with_base_url(url),
+            None => client,
+        }
+    }
+}
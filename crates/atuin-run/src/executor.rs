@@ -1,24 +1,37 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use atuin_desktop_runtime::{
     blocks::Block,
-    client::DocumentBridgeMessage,
-    context::ContextResolver,
-    document::{DocumentError, DocumentHandle},
-    execution::BlockLifecycleEvent,
+    client::{ClientPrompt, DocumentBridgeMessage, LocalValueProvider},
+    context::{BlockContextStorage, ContextResolver},
+    document::{flatten_document, DocumentError, DocumentHandle},
+    events::{EventBus, GCEvent},
+    exec_log::{self, ExecLogHandle, ExecRecord, ExecTrigger},
+    execution::{BlockLifecycleEvent, ExecutionHandle, ExecutionResult},
     pty::PtyStoreHandle,
     ssh::SshPoolHandle,
+    workflow::{DependencyCheck, DependencySpec},
 };
+use chrono::Utc;
 use crossterm::terminal;
+use sqlx::SqlitePool;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::{
+    config::Config,
+    inputs::{InputError, Inputs},
+    plan::PlannedBlock,
+    prompt::PromptResponder,
+    report::{BlockReport, RunReport},
     runbooks::Runbook,
-    runtime::{
-        ChannelDocumentBridge, FileRunbookLoader, NullDocumentBridge, NullEventBus,
-        TempNullContextStorage, TempNullLocalValueProvider,
-    },
+    runtime::{ChannelDocumentBridge, EnvSecretProvider, FileRunbookLoader, NullDocumentBridge},
+    selection::{BlockSelection, SelectionError},
+    state::{SqliteContextStorage, SqliteLocalValueProvider},
     ui::{Renderer, StreamingRenderer, TerminalViewport, ViewportManager},
 };
 
@@ -35,6 +48,15 @@ pub enum ExecutorError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("{0}")]
+    InputError(#[from] InputError),
+
+    #[error("{0}")]
+    SelectionError(#[from] SelectionError),
+
+    #[error("Failed to access run state: {0}")]
+    StateError(String),
+
     #[error("Block {0} failed: {0} (exited with code {2:?})")]
     BlockFailed(Uuid, String, Option<i32>),
 
@@ -44,40 +66,86 @@ pub enum ExecutorError {
     #[error("Block {0} error: {1}")]
     BlockError(Uuid, String),
 
-    #[error("Workflow paused at block {0} - cannot pause in non-interactive mode")]
+    #[error("Workflow paused at block {0}")]
     BlockPaused(Uuid),
+
+    #[error("Block {0} cannot run yet: {1}")]
+    DependencyBlocked(Uuid, String),
+}
+
+/// How a runbook should be run, as chosen on the command line
+pub struct RunOptions {
+    pub inputs: Inputs,
+    pub selection: BlockSelection,
+    pub prompts: PromptResponder,
+    pub interactive: bool,
+    /// Keep the block context stored by the previous run instead of starting fresh
+    pub resume: bool,
+    /// Receives runtime events (block lifecycle, SSH connections, PTYs, etc.)
+    pub event_bus: Arc<dyn EventBus>,
+    /// Records when each block ran, for checking block dependencies
+    pub exec_log: ExecLogHandle,
 }
 
 pub struct Executor {
     runbook: Runbook,
     document: Arc<DocumentHandle>,
+    event_bus: Arc<dyn EventBus>,
+    inputs: Inputs,
+    selection: BlockSelection,
+    prompts: Arc<PromptResponder>,
+    /// Held by whoever reads from stdin, so prompts and terminal key forwarding take turns
+    stdin: Arc<tokio::sync::Mutex<()>>,
+    local_values: Arc<SqliteLocalValueProvider>,
+    context_storage: SqliteContextStorage,
     interactive: bool,
+    resume: bool,
+    /// Exit code reported by the most recently finished block, if any
+    last_exit_code: Option<i32>,
+    /// Fingerprint of the inputs the most recently started block resolved
+    last_fingerprint: Option<String>,
+    exec_log: ExecLogHandle,
+    /// Dependencies of each block that has any, read from the document on load
+    dependencies: HashMap<Uuid, DependencySpec>,
     pty_store: PtyStoreHandle,
     ssh_pool: SshPoolHandle,
     renderer: Box<dyn Renderer>,
 }
 
 impl Executor {
-    pub fn new(runbook: Runbook, interactive: bool) -> Self {
-        // Create runbook loader based on source path
-        let runbook_loader = runbook.source_path.as_ref().map(|path| {
-            Arc::new(FileRunbookLoader::from_runbook_path(path))
-                as Arc<dyn atuin_desktop_runtime::client::RunbookContentLoader>
+    pub fn new(runbook: Runbook, options: RunOptions, state: SqlitePool, config: &Config) -> Self {
+        // Sub-runbook paths resolve relative to the runbook file, or the current
+        // directory for runbooks fetched from the hub
+        let runbook_loader = match runbook.source_path.as_ref() {
+            Some(path) => FileRunbookLoader::from_runbook_path(path, config.hub_client()),
+            None => FileRunbookLoader::new(PathBuf::from("."), config.hub_client()),
+        }
+        .with_workspace(runbook.workspace.clone());
+
+        let workspace_root = runbook.workspace.as_ref().and_then(|workspace| {
+            tracing::debug!(
+                "Running in workspace {} ({}) at {}",
+                workspace.name,
+                workspace.id,
+                workspace.root.display()
+            );
+            workspace.template_state().root
         });
 
-        // TODO: Load workspace root from atuin.toml if present
+        let local_values = Arc::new(SqliteLocalValueProvider::new(state.clone()));
+        let context_storage = SqliteContextStorage::new(state, options.resume);
         let document = DocumentHandle::new(
             runbook.id.to_string(),
-            Arc::new(NullEventBus),
+            options.event_bus.clone(),
             Arc::new(NullDocumentBridge),
-            Some(Arc::new(TempNullLocalValueProvider)),
-            Some(Box::new(TempNullContextStorage)),
-            runbook_loader,
-            None, // workspace_root - not yet supported in CLI
+            Some(local_values.clone()),
+            Some(Box::new(context_storage.clone())),
+            Some(Arc::new(runbook_loader)),
+            workspace_root,
         );
 
         // Choose renderer based on interactive mode
-        let renderer: Box<dyn Renderer> = if interactive {
+        let renderer: Box<dyn Renderer> = if options.interactive {
             Box::new(ViewportManager::new())
         } else {
             Box::new(StreamingRenderer::new())
@@ -86,34 +154,377 @@ impl Executor {
         Self {
             runbook,
             document,
-            interactive,
+            event_bus: options.event_bus,
+            inputs: options.inputs,
+            selection: options.selection,
+            prompts: Arc::new(options.prompts),
+            stdin: Arc::new(tokio::sync::Mutex::new(())),
+            local_values,
+            context_storage,
+            interactive: options.interactive,
+            resume: options.resume,
+            last_exit_code: None,
+            last_fingerprint: None,
+            exec_log: options.exec_log,
+            dependencies: HashMap::new(),
             pty_store: PtyStoreHandle::new(),
             ssh_pool: SshPoolHandle::new(),
             renderer,
         }
     }
 
-    // TODO: find variables / inputs that need setting
-    pub async fn execute(&mut self) -> Result<()> {
-        self.document
-            .put_document(self.runbook.content.clone())
-            .await?;
+    /// Run every block in order, stopping at the first failure
+    ///
+    /// Block failures are printed and recorded in the returned report rather than
+    /// returned as errors; errors are reserved for problems setting up the run.
+    pub async fn execute(&mut self) -> Result<RunReport> {
+        // Unless resuming, forget the context stored by the previous run, so a later
+        // --resume only sees what this run produced
+        if !self.resume {
+            self.context_storage
+                .delete_for_document(&self.runbook.id.to_string())
+                .await
+                .map_err(|e| ExecutorError::StateError(e.to_string()))?;
+        }
+
+        self.load_document(true).await?;
+
+        let mut report = RunReport::new(self.runbook.id);
+        // Error of the block that stopped the run, if any
+        let mut failure: Option<String> = None;
+
+        self.emit(GCEvent::RunbookStarted {
+            runbook_id: self.runbook.id,
+        })
+        .await;
 
         let blocks = self.document.blocks().await?;
-        for block in blocks {
-            let (sender, receiver) = mpsc::channel(16);
-            let document_bridge = Arc::new(ChannelDocumentBridge::new(sender));
-            self.document.update_bridge_channel(document_bridge).await?;
+        let selected = self.selection.select(&blocks)?;
+
+        for (block, selected) in blocks.iter().zip(selected) {
+            let mut block_report =
+                BlockReport::not_run(block.id(), block.name(), self.get_block_type(block));
+
+            if !selected {
+                tracing::debug!("Skipping unselected block {}", block.id());
+                block_report.skipped = true;
+                report.blocks.push(block_report);
+                continue;
+            }
+
+            if failure.is_some() {
+                report.blocks.push(block_report);
+                continue;
+            }
+
+            let result = match self.run_dependencies(block, &blocks, &mut report, 0).await {
+                Ok(()) => {
+                    self.run_block(block, &mut block_report, ExecTrigger::Cli)
+                        .await
+                }
+                Err(e @ ExecutorError::DependencyBlocked(..)) => {
+                    block_report.result = Some(ExecutionResult::Failure);
+                    block_report.error = Some(e.to_string());
+                    Err(e)
+                }
+                // A parent failed; its report already records why
+                Err(e) => Err(e),
+            };
 
-            if let Err(e) = self.execute_block(block, receiver).await {
+            if let Err(e) = result {
                 println!("{e}");
-                std::process::exit(1);
+                failure = Some(e.to_string());
+            }
+            report.blocks.push(block_report);
+        }
+
+        report.finished_at = Some(Utc::now());
+
+        let runbook_id = self.runbook.id;
+        self.emit(match failure {
+            Some(error) => GCEvent::RunbookFailed { runbook_id, error },
+            None => GCEvent::RunbookCompleted { runbook_id },
+        })
+        .await;
+
+        Ok(report)
+    }
+
+    /// Run a single block, recording the outcome in its report and in the execution log
+    async fn run_block(
+        &mut self,
+        block: &Block,
+        block_report: &mut BlockReport,
+        trigger: ExecTrigger,
+    ) -> Result<()> {
+        let (sender, receiver) = mpsc::channel(16);
+        let document_bridge = Arc::new(ChannelDocumentBridge::new(sender));
+        self.document.update_bridge_channel(document_bridge).await?;
+
+        self.last_exit_code = None;
+        self.last_fingerprint = None;
+        let started_at = Utc::now();
+        block_report.started_at = Some(started_at);
+        let result = self.execute_block(block.clone(), receiver).await;
+        let finished_at = Utc::now();
+        block_report.finished_at = Some(finished_at);
+        block_report.exit_code = self.last_exit_code;
+
+        block_report.result = Some(match &result {
+            Ok(()) => ExecutionResult::Success,
+            Err(ExecutorError::BlockCancelled(_)) => ExecutionResult::Cancelled,
+            Err(ExecutorError::BlockPaused(_)) => ExecutionResult::Paused,
+            Err(_) => ExecutionResult::Failure,
+        });
+
+        match &result {
+            Ok(()) => {}
+            Err(ExecutorError::BlockFailed(_, _, exit_code)) => {
+                block_report.exit_code = *exit_code;
+            }
+            Err(e) => {
+                block_report.error = Some(e.to_string());
+            }
+        }
+
+        block_report.output = self
+            .document
+            .get_block_execution_output_serialized(block.id())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to serialize output of block {}: {e}", block.id());
+                None
+            });
+
+        // Timestamps are in nanoseconds, as logged by the app
+        let nanos =
+            |time: chrono::DateTime<Utc>| time.timestamp_nanos_opt().unwrap_or_default() as u64;
+        let record = ExecRecord {
+            block_id: block.id(),
+            runbook_id: Some(self.runbook.id),
+            start_time: nanos(started_at),
+            end_time: nanos(finished_at),
+            output: block_report
+                .output
+                .as_ref()
+                .map(|output| output.to_string())
+                .unwrap_or_default(),
+            exit_code: block_report.exit_code,
+            result: block_report.result.unwrap_or(ExecutionResult::Failure),
+            fingerprint: self.last_fingerprint.take(),
+            trigger,
+        };
+        if let Err(e) = self.exec_log.log_record(record).await {
+            tracing::warn!("Failed to log execution of block {}: {e}", block.id());
+        }
+
+        result
+    }
+
+    /// Make sure the parents of a block are satisfied, running them first if the
+    /// dependency allows it
+    ///
+    /// Parents that run here replace their entry in the report, as they are usually
+    /// blocks left out by a selector.
+    async fn run_dependencies(
+        &mut self,
+        block: &Block,
+        blocks: &[Block],
+        report: &mut RunReport,
+        depth: usize,
+    ) -> Result<()> {
+        let Some(spec) = self.dependencies.get(&block.id()).cloned() else {
+            return Ok(());
+        };
+
+        let check = self.check_dependencies(block, &spec).await?;
+        if check.can_run() {
+            return Ok(());
+        }
+
+        if check.run_first.is_empty() || depth > blocks.len() {
+            return Err(self.dependency_blocked(block, &check, blocks));
+        }
+
+        for parent_id in &check.run_first {
+            let Some(parent) = blocks.iter().find(|b| b.id() == *parent_id) else {
+                return Err(self.dependency_blocked(block, &check, blocks));
+            };
+
+            Box::pin(self.run_dependencies(parent, blocks, report, depth + 1)).await?;
+
+            println!(
+                "Running {} first, as {} depends on it",
+                parent.name(),
+                block.name()
+            );
+            let mut parent_report =
+                BlockReport::not_run(parent.id(), parent.name(), self.get_block_type(parent));
+            let trigger = ExecTrigger::Dependency {
+                dependent: block.id(),
+            };
+            let result = self.run_block(parent, &mut parent_report, trigger).await;
+
+            match report.blocks.iter_mut().find(|b| b.id == parent.id()) {
+                Some(existing) => *existing = parent_report,
+                None => report.blocks.push(parent_report),
             }
+            result?;
+        }
+
+        let check = self.check_dependencies(block, &spec).await?;
+        if !check.can_run() {
+            return Err(self.dependency_blocked(block, &check, blocks));
         }
 
         Ok(())
     }
 
+    async fn check_dependencies(
+        &self,
+        block: &Block,
+        spec: &DependencySpec,
+    ) -> Result<DependencyCheck> {
+        spec.check(block.id(), &self.exec_log)
+            .await
+            .map_err(|e| ExecutorError::StateError(e.to_string()))
+    }
+
+    /// Describe which parents stop a block from running, by name where possible
+    fn dependency_blocked(
+        &self,
+        block: &Block,
+        check: &DependencyCheck,
+        blocks: &[Block],
+    ) -> ExecutorError {
+        let reasons = check
+            .blocked_by
+            .iter()
+            .map(|blocked| {
+                let name = blocks
+                    .iter()
+                    .find(|b| b.id().to_string() == blocked.parent)
+                    .map(|b| b.name())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| blocked.parent.clone());
+                format!("parent '{name}' {}", blocked.reason)
+            })
+            .collect::<Vec<_>>();
+
+        ExecutorError::DependencyBlocked(block.id(), reasons.join("; "))
+    }
+
+    /// Render every selected executable block against the context it would run in,
+    /// without executing anything
+    pub async fn plan(&mut self) -> Result<Vec<PlannedBlock>> {
+        self.load_document(false).await?;
+
+        let blocks = self.document.blocks().await?;
+        let selected = self.selection.select(&blocks)?;
+
+        let mut plan = Vec::new();
+        for (block, selected) in blocks.into_iter().zip(selected) {
+            if !selected {
+                continue;
+            }
+
+            let context = self
+                .document
+                .create_execution_context(block.id(), None, None, None)
+                .await?;
+            let title = self.get_viewport_title(&block);
+
+            if let Some(planned) = PlannedBlock::new(&block, title, &context.context_resolver) {
+                plan.push(planned);
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Apply the run's inputs and load the runbook content into the document
+    ///
+    /// With `save_values`, supplied local values are saved for later runs, except those of
+    /// obscured local-var blocks; otherwise they are only used for this run.
+    async fn load_document(&mut self, save_values: bool) -> Result<()> {
+        let mut content = self.runbook.content.clone();
+        let stored_local_values = self.stored_local_values(&content).await?;
+        let local_values =
+            self.inputs
+                .apply(&mut content, &stored_local_values, self.interactive)?;
+
+        let obscured: HashSet<Uuid> = flatten_document(&content)
+            .iter()
+            .filter_map(|block_data| match Block::from_document(block_data) {
+                Ok(Block::LocalVar(local_var)) if local_var.obscured => Some(local_var.id),
+                _ => None,
+            })
+            .collect();
+        for (block_id, value) in local_values {
+            if !save_values || obscured.contains(&block_id) {
+                self.local_values.set_run_value(block_id, "value", &value);
+                continue;
+            }
+
+            self.local_values
+                .set_value(block_id, "value", &value)
+                .await
+                .map_err(|e| ExecutorError::StateError(e.to_string()))?;
+        }
+
+        self.dependencies = flatten_document(&content)
+            .iter()
+            .filter_map(|block_data| {
+                let id = block_data.get("id")?.as_str()?.parse().ok()?;
+                Some((id, DependencySpec::from_document(block_data)?))
+            })
+            .collect();
+
+        // The parent context must be in place before the document is loaded, so the
+        // passive contexts built on load can see the supplied values
+        self.document
+            .set_parent_context(Arc::new(self.inputs.context_resolver()))
+            .await?;
+        self.document
+            .set_secret_provider(Arc::new(EnvSecretProvider))
+            .await?;
+        self.document.put_document(content).await?;
+
+        Ok(())
+    }
+
+    /// Emit a runtime event; a broken event stream should not stop the run
+    async fn emit(&self, event: GCEvent) {
+        if let Err(e) = self.event_bus.emit(event).await {
+            tracing::warn!("Failed to emit event: {e}");
+        }
+    }
+
+    /// Values already saved for the local-var blocks of a document, by block ID
+    async fn stored_local_values(
+        &self,
+        content: &[serde_json::Value],
+    ) -> Result<HashMap<Uuid, String>> {
+        let mut values = HashMap::new();
+
+        for block_data in flatten_document(content) {
+            let Ok(Block::LocalVar(local_var)) = Block::from_document(&block_data) else {
+                continue;
+            };
+
+            let value = self
+                .local_values
+                .get_block_local_value(local_var.id, "value")
+                .await
+                .map_err(|e| ExecutorError::StateError(e.to_string()))?;
+            if let Some(value) = value {
+                values.insert(local_var.id, value);
+            }
+        }
+
+        Ok(values)
+    }
+
     async fn execute_block(
         &mut self,
         block: Block,
@@ -129,7 +540,7 @@ impl Executor {
 
         let viewport_height = match block {
             Block::Terminal(_) => 15,
-            Block::Script(_) => 8,
+            Block::Script(_) | Block::Foreach(_) => 8,
             _ => 2,
         };
 
@@ -170,6 +581,7 @@ impl Executor {
             .await?;
 
         let resolver = context.context_resolver.clone();
+        self.last_fingerprint = Some(exec_log::fingerprint(&block, &resolver));
 
         let execution_handle = block
             .execute(context)
@@ -204,6 +616,7 @@ impl Executor {
         // Create keyboard input channel for terminal blocks (only in interactive mode)
         let (mut key_rx, keyboard_task_handle) = if is_terminal && self.interactive {
             let (tx, rx) = tokio::sync::mpsc::channel::<crossterm::event::Event>(32);
+            let stdin = self.stdin.clone();
 
             // Spawn task to read keyboard events
             let handle = tokio::spawn(async move {
@@ -213,13 +626,21 @@ impl Executor {
                         break;
                     }
 
-                    if crossterm::event::poll(std::time::Duration::from_millis(100))
-                        .unwrap_or(false)
-                    {
-                        if let Ok(event) = crossterm::event::read() {
-                            if tx.send(event).await.is_err() {
-                                break; // Channel closed, exit
-                            }
+                    // Stay off stdin while a prompt is reading from it
+                    let event = {
+                        let _stdin = stdin.lock().await;
+                        if crossterm::event::poll(std::time::Duration::from_millis(100))
+                            .unwrap_or(false)
+                        {
+                            crossterm::event::read().ok()
+                        } else {
+                            None
+                        }
+                    };
+
+                    if let Some(event) = event {
+                        if tx.send(event).await.is_err() {
+                            break; // Channel closed, exit
                         }
                     }
                 }
@@ -230,8 +651,9 @@ impl Executor {
             (None, None)
         };
 
-        let result = if let Some(_handle) = execution_handle {
+        let result = if let Some(handle) = execution_handle {
             self.execute_block_with_io(
+                &handle,
                 block_id,
                 viewport,
                 receiver,
@@ -268,6 +690,7 @@ impl Executor {
     #[allow(clippy::too_many_arguments)]
     async fn execute_block_with_io(
         &mut self,
+        handle: &ExecutionHandle,
         block_id: uuid::Uuid,
         viewport: usize,
         receiver: &mut mpsc::Receiver<DocumentBridgeMessage>,
@@ -328,7 +751,9 @@ impl Executor {
 
                 // Handle PTY output messages
                 Some(message) = receiver.recv() => {
-                if let DocumentBridgeMessage::BlockOutput { output, .. } = message {
+                if let DocumentBridgeMessage::ClientPrompt { prompt_id, prompt, .. } = message {
+                    self.answer_prompt(handle, prompt_id, &prompt).await?;
+                } else if let DocumentBridgeMessage::BlockOutput { block_id: output_block_id, output } = message {
                     // Handle PTY metadata message - resize PTY when it's created
                     if is_terminal {
                         if let Some(ref obj) = output.object {
@@ -374,11 +799,14 @@ impl Executor {
                         }
                     }
 
-                    // Handle lifecycle events - only break on these
-                    if let Some(lifecycle) = output.lifecycle {
+                    // Handle lifecycle events - only break on these, and only on the block's own;
+                    // blocks nested in a loop report theirs through the same channel
+                    if let Some(lifecycle) = output.lifecycle.filter(|_| output_block_id == block_id) {
                         match lifecycle {
                             BlockLifecycleEvent::Started(_) => {}
                             BlockLifecycleEvent::Finished(data) => {
+                                self.last_exit_code = data.exit_code;
+
                                 // Flush any pending viewport updates before completing
                                 if pending_viewport_update && is_terminal {
                                     if let Some(ref term_viewport) = terminal_viewport {
@@ -442,18 +870,16 @@ impl Executor {
                                     }
                                 }
 
-                                if self.interactive {
-                                    // In interactive mode, prompt the user to continue
-                                    self.renderer.mark_complete(viewport)?;
-                                    println!("\n⏸  Workflow paused. Press Enter to continue...");
-                                    let mut input = String::new();
-                                    std::io::stdin().read_line(&mut input)?;
+                                self.renderer.mark_complete(viewport)?;
+                                if self.ask_user(|prompts| prompts.continue_after_pause()).await? {
                                     // Continue to the next block
                                     return Ok(());
-                                } else {
-                                    // Non-interactive mode cannot pause
-                                    return Err(ExecutorError::BlockPaused(block_id));
                                 }
+
+                                if !self.interactive {
+                                    println!("Pass --auto-continue to continue past pause blocks in non-interactive mode");
+                                }
+                                return Err(ExecutorError::BlockPaused(block_id));
                             }
                         }
                     }
@@ -546,6 +972,7 @@ impl Executor {
                             match lifecycle {
                                 BlockLifecycleEvent::Started(_) => {}
                                 BlockLifecycleEvent::Finished(data) => {
+                                    self.last_exit_code = data.exit_code;
                                     let _ = self.renderer.mark_complete(viewport);
                                     if let Some(exit_code) = data.exit_code {
                                         if exit_code != 0 {
@@ -585,6 +1012,60 @@ impl Executor {
         Ok(())
     }
 
+    /// Reply to a prompt raised by a block; if it can't be answered, the block's
+    /// request fails instead of waiting forever
+    async fn answer_prompt(
+        &self,
+        handle: &ExecutionHandle,
+        prompt_id: Uuid,
+        prompt: &ClientPrompt,
+    ) -> Result<()> {
+        let Some(sender) = handle.prompt_callbacks.lock().await.remove(&prompt_id) else {
+            tracing::warn!("Received prompt {prompt_id} with no pending callback");
+            return Ok(());
+        };
+
+        let question = prompt.clone();
+        match self
+            .ask_user(move |prompts| prompts.respond(&question))
+            .await?
+        {
+            Some(answer) => {
+                let _ = sender.send(answer);
+            }
+            None => {
+                // A quoted title is always read whole, whatever separators it has
+                let title = if prompt.title.contains('=') {
+                    format!("\"{}\"", prompt.title)
+                } else {
+                    prompt.title.clone()
+                };
+                println!(
+                    "Block asked '{}' but no answer was given; pass --answer '{title}=<button>' to answer it",
+                    prompt.title
+                );
+                // Dropping the sender fails the block's pending prompt
+                drop(sender);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ask the user something, off the async runtime and with terminal key forwarding paused
+    async fn ask_user<T, F>(&self, ask: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&PromptResponder) -> std::io::Result<T> + Send + 'static,
+    {
+        let _stdin = self.stdin.lock().await;
+        let prompts = self.prompts.clone();
+        tokio::task::spawn_blocking(move || ask(&prompts))
+            .await
+            .map_err(|e| ExecutorError::GenericError(format!("Prompt failed: {e}")))?
+            .map_err(ExecutorError::from)
+    }
+
     fn get_output_lines(&self, block: Block, resolver: &ContextResolver) -> Vec<String> {
         match block {
             Block::Directory(dir) => {
@@ -631,6 +1112,8 @@ impl Executor {
             Block::Directory(_) => "Directory".to_string(),
             Block::LocalDirectory(_) => "Local directory".to_string(),
             Block::SshConnect(_) => "SSH connect".to_string(),
+            Block::SshTunnel(_) => "SSH tunnel".to_string(),
+            Block::Sftp(_) => "SFTP".to_string(),
             Block::Host(_) => "Host".to_string(),
             Block::VarDisplay(_) => "Variable display".to_string(),
             Block::MarkdownRender(_) => "Markdown render".to_string(),
@@ -638,6 +1121,9 @@ impl Executor {
             Block::Dropdown(_) => "Dropdown".to_string(),
             Block::Pause(_) => "Pause".to_string(),
             Block::SubRunbook(_) => "Sub-Runbook".to_string(),
+            Block::Assert(_) => "Assert".to_string(),
+            Block::WaitUntil(_) => "Wait until".to_string(),
+            Block::Foreach(_) => "For each".to_string(),
         }
     }
 }
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use atuin_desktop_runtime::{
+    exec_log::{ExecLogEntry, ExecLogQuery, ExecTrigger, RetentionPolicy},
+    execution::ExecutionResult,
+};
+use chrono::{DateTime, Local};
+
+use crate::{app::LogArgs, state};
+
+/// Print the execution log, or prune it with `--prune`
+pub async fn run(args: &LogArgs) -> eyre::Result<()> {
+    let exec_log = state::open_exec_log()?;
+
+    if args.prune {
+        let removed = exec_log.prune(RetentionPolicy::default()).await?;
+        println!("✓ Removed {removed} execution(s)");
+        return Ok(());
+    }
+
+    let since = args.since.map(|since| {
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        now.saturating_sub(since.as_nanos() as u64)
+    });
+    let entries = exec_log
+        .query(ExecLogQuery {
+            block_id: args.block,
+            runbook_id: args.runbook,
+            result: args.failed.then_some(ExecutionResult::Failure),
+            since,
+            limit: Some(args.limit),
+        })
+        .await?;
+
+    for entry in &entries {
+        if args.json {
+            println!("{}", serde_json::to_string(entry)?);
+        } else {
+            println!("{}", format_entry(entry));
+        }
+    }
+
+    if entries.is_empty() && !args.json {
+        println!("No executions found");
+    }
+
+    Ok(())
+}
+
+fn format_entry(entry: &ExecLogEntry) -> String {
+    let ended = DateTime::from_timestamp_nanos(entry.end_time as i64).with_timezone(&Local);
+    let result = match entry.result {
+        Some(ExecutionResult::Success) | None => "✓",
+        Some(ExecutionResult::Failure) => "✗",
+        Some(ExecutionResult::Cancelled) => "⊘",
+        Some(ExecutionResult::Paused) => "⏸",
+    };
+
+    let mut line = format!(
+        "{} {result} {} {:>8}",
+        ended.format("%Y-%m-%d %H:%M:%S"),
+        entry.block_id,
+        format_duration(entry.duration()),
+    );
+    if let Some(exit_code) = entry.exit_code {
+        line.push_str(&format!("  exit {exit_code}"));
+    }
+    if let Some(runbook_id) = entry.runbook_id {
+        line.push_str(&format!("  runbook {runbook_id}"));
+    }
+    if let Some(trigger) = &entry.trigger {
+        line.push_str(&format!("  via {}", format_trigger(trigger)));
+    }
+
+    line
+}
+
+fn format_duration(duration: Duration) -> String {
+    match duration.as_millis() {
+        millis if millis < 1000 => format!("{millis}ms"),
+        millis if millis < 60_000 => format!("{:.1}s", millis as f64 / 1000.0),
+        _ => {
+            let secs = duration.as_secs();
+            format!("{}m{:02}s", secs / 60, secs % 60)
+        }
+    }
+}
+
+fn format_trigger(trigger: &ExecTrigger) -> String {
+    match trigger {
+        ExecTrigger::User => "app".to_string(),
+        ExecTrigger::Workflow { id } => format!("workflow {id}"),
+        ExecTrigger::Dependency { dependent } => format!("dependency of {dependent}"),
+        ExecTrigger::Cli => "atuin-run".to_string(),
+    }
+}
+
+/// Parse a duration like `90s`, `30m`, `12h` or `7d`
+pub fn parse_since(arg: &str) -> Result<Duration, String> {
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (count, unit) = arg.split_at(split);
+    let count = count
+        .parse::<u64>()
+        .map_err(|_| format!("expected a duration like 30m, 12h or 7d, got '{arg}'"))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{unit}', expected s, m, h, d or w"
+            ))
+        }
+    };
+
+    Ok(Duration::from_secs(count * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_since("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_since("1d"), Ok(Duration::from_secs(24 * 60 * 60)));
+        assert!(parse_since("d").is_err());
+        assert!(parse_since("5y").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m05s");
+    }
+}
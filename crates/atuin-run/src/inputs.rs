@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use atuin_desktop_runtime::{
+    blocks::{Block, DropdownOption},
+    context::ContextResolver,
+};
+use uuid::Uuid;
+
+use crate::app::Args;
+
+type Result<T> = std::result::Result<T, InputError>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InputError {
+    #[error("Failed to read vars file {0}: {1}")]
+    VarsFileReadError(PathBuf, std::io::Error),
+
+    #[error("Failed to parse vars file {0}: {1}")]
+    VarsFileParseError(PathBuf, serde_yaml::Error),
+
+    #[error("Invalid value for variable '{0}' in vars file: expected a string, number or boolean")]
+    InvalidVarsFileValue(String),
+
+    #[error("Failed to read input: {0}")]
+    PromptError(#[from] std::io::Error),
+}
+
+/// Variables and environment supplied for a run, from flags, a vars file or prompts
+#[derive(Debug, Default)]
+pub struct Inputs {
+    vars: HashMap<String, String>,
+    env: HashMap<String, String>,
+}
+
+impl Inputs {
+    /// Collect inputs from the command line; `--var` takes precedence over `--vars-file`
+    pub fn from_args(args: &Args) -> Result<Self> {
+        let mut vars = match &args.vars_file {
+            Some(path) => read_vars_file(path)?,
+            None => HashMap::new(),
+        };
+        vars.extend(args.vars.iter().cloned());
+
+        Ok(Self {
+            vars,
+            env: args.env.iter().cloned().collect(),
+        })
+    }
+
+    /// Build the context the runbook starts from, so templates can use any supplied
+    /// variable even if no block defines it
+    pub fn context_resolver(&self) -> ContextResolver {
+        let mut resolver = ContextResolver::new();
+        for (name, value) in &self.vars {
+            resolver.set_var(name.clone(), value.clone());
+        }
+        for (name, value) in &self.env {
+            resolver.set_env_var(name.clone(), value.clone());
+        }
+        resolver
+    }
+
+    /// Write supplied values into the `var`, `local-var` and `dropdown` blocks of a document
+    ///
+    /// In interactive mode, the user is prompted for any of those blocks that still has no value;
+    /// `stored_local_values` holds the values local variables already have on this machine.
+    /// Local variables are never stored in the document, so their values are returned by block ID.
+    pub fn apply(
+        &mut self,
+        content: &mut [serde_json::Value],
+        stored_local_values: &HashMap<Uuid, String>,
+        interactive: bool,
+    ) -> Result<HashMap<Uuid, String>> {
+        let mut local_values = HashMap::new();
+
+        for block_data in content.iter_mut() {
+            if let Some(children) = block_data
+                .get_mut("children")
+                .and_then(|c| c.as_array_mut())
+            {
+                local_values.extend(self.apply(children, stored_local_values, interactive)?);
+            }
+
+            let Ok(block) = Block::from_document(block_data) else {
+                continue;
+            };
+
+            let (name, current, options) = match &block {
+                Block::Var(var) => (var.name.clone(), Some(var.value.clone()), None),
+                Block::Dropdown(dropdown) => (
+                    dropdown.name.clone(),
+                    Some(dropdown.value.clone()),
+                    dropdown.fixed_option_list().and_then(|o| o.ok()),
+                ),
+                Block::LocalVar(local_var) => (
+                    local_var.name.clone(),
+                    stored_local_values.get(&local_var.id).cloned(),
+                    None,
+                ),
+                _ => continue,
+            };
+
+            let value = match self.vars.get(&name) {
+                Some(value) => value.clone(),
+                None if current.as_ref().is_some_and(|v| !v.is_empty()) => continue,
+                None if interactive => {
+                    let value = prompt_for_value(&name, options.as_deref())?;
+                    // Later blocks with the same name reuse the answer
+                    self.vars.insert(name.clone(), value.clone());
+                    value
+                }
+                None => continue,
+            };
+
+            if matches!(block, Block::LocalVar(_)) {
+                local_values.insert(block.id(), value);
+            } else if let Some(props) = block_data.get_mut("props").and_then(|p| p.as_object_mut())
+            {
+                props.insert("value".to_string(), serde_json::Value::String(value));
+            }
+        }
+
+        Ok(local_values)
+    }
+}
+
+fn read_vars_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| InputError::VarsFileReadError(path.to_path_buf(), e))?;
+    let values: HashMap<String, serde_yaml::Value> = serde_yaml::from_str(&content)
+        .map_err(|e| InputError::VarsFileParseError(path.to_path_buf(), e))?;
+
+    values
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_yaml::Value::String(s) => s,
+                serde_yaml::Value::Number(n) => n.to_string(),
+                serde_yaml::Value::Bool(b) => b.to_string(),
+                _ => return Err(InputError::InvalidVarsFileValue(name)),
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn prompt_for_value(name: &str, options: Option<&[DropdownOption]>) -> Result<String> {
+    let mut stdout = std::io::stdout();
+
+    match options {
+        Some(options) if !options.is_empty() => {
+            println!("Choose a value for '{name}':");
+            for (i, option) in options.iter().enumerate() {
+                println!("  {}) {}", i + 1, option.label);
+            }
+
+            loop {
+                print!("> ");
+                stdout.flush()?;
+                let input = read_line()?;
+
+                if let Some(option) = input
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| options.get(i))
+                {
+                    return Ok(option.value.clone());
+                }
+
+                if let Some(option) = options
+                    .iter()
+                    .find(|o| o.value == input || o.label == input)
+                {
+                    return Ok(option.value.clone());
+                }
+
+                println!("Enter a number between 1 and {}", options.len());
+            }
+        }
+        _ => {
+            print!("Enter a value for '{name}': ");
+            stdout.flush()?;
+            read_line()
+        }
+    }
+}
+
+fn read_line() -> Result<String> {
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_overrides_block_values() {
+        let local_var_id = Uuid::new_v4();
+        let mut content = vec![
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "env_name", "value": "staging" },
+                "children": []
+            }),
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "untouched", "value": "keep" },
+                "children": []
+            }),
+            json!({
+                "id": local_var_id.to_string(),
+                "type": "local-var",
+                "props": { "name": "token" },
+                "children": []
+            }),
+        ];
+
+        let mut inputs = Inputs {
+            vars: HashMap::from([
+                ("env_name".to_string(), "prod".to_string()),
+                ("token".to_string(), "secret".to_string()),
+            ]),
+            env: HashMap::new(),
+        };
+
+        let local_values = inputs.apply(&mut content, &HashMap::new(), false).unwrap();
+
+        assert_eq!(content[0]["props"]["value"], "prod");
+        assert_eq!(content[1]["props"]["value"], "keep");
+        assert_eq!(local_values.get(&local_var_id).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_read_vars_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vars.yaml");
+        std::fs::write(&path, "region: eu-west-1\nreplicas: 3\ndry_run: true\n").unwrap();
+
+        let vars = read_vars_file(&path).unwrap();
+        assert_eq!(vars.get("region").unwrap(), "eu-west-1");
+        assert_eq!(vars.get("replicas").unwrap(), "3");
+        assert_eq!(vars.get("dry_run").unwrap(), "true");
+
+        std::fs::write(&path, "nested:\n  key: value\n").unwrap();
+        assert!(matches!(
+            read_vars_file(&path),
+            Err(InputError::InvalidVarsFileValue(name)) if name == "nested"
+        ));
+    }
+}
@@ -1,25 +1,107 @@
+use std::sync::Arc;
+
+use atuin_desktop_runtime::events::EventBus;
 use clap::Parser;
 use eyre::Result;
 
-use crate::{app::Args, executor::Executor};
+use crate::{
+    app::{Args, Command},
+    config::Config,
+    executor::{Executor, RunOptions},
+    inputs::Inputs,
+    prompt::PromptResponder,
+    runtime::{JsonLinesEventBus, NullEventBus},
+    selection::BlockSelection,
+};
 
 mod app;
+mod config;
 mod executor;
+mod history;
+mod inputs;
+mod plan;
+mod prompt;
+mod report;
 mod runbooks;
 mod runtime;
+mod selection;
+mod state;
 mod ui;
+mod validate;
+mod workspace;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     atuin_desktop_runtime::init_tracing();
 
     let args = Args::parse();
-    let runbook = runbooks::load_runbook(&args.runbook).await?;
+    let config = Config::load()?;
+
+    if let Some(Command::Validate(validate_args)) = &args.command {
+        if validate::run(validate_args, &config).await? > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Log(log_args)) = &args.command {
+        return history::run(log_args).await;
+    }
+
+    let runbook = args
+        .runbook
+        .as_deref()
+        .expect("clap requires a runbook unless a subcommand is given");
+    let runbook = runbooks::load_runbook(runbook, &config).await?;
+
+    let inputs = Inputs::from_args(&args)?;
+    let selection = BlockSelection::from_args(&args);
 
     let interactive = args.is_interactive();
-    let mut executor = Executor::new(runbook, interactive);
-    match executor.execute().await {
-        Err(e) => Err(eyre::eyre!(e)),
-        Ok(()) => Ok(()),
+    let prompts = PromptResponder::new(args.answers.clone(), interactive, args.auto_continue);
+    let state = state::open_state_db().await?;
+    let exec_log = state::open_exec_log()?;
+
+    let event_bus: Arc<dyn EventBus> = match (&args.events_file, args.events_fd) {
+        (Some(path), _) => Arc::new(JsonLinesEventBus::from_path(path)?),
+        (None, Some(fd)) => Arc::new(JsonLinesEventBus::from_fd(fd)?),
+        (None, None) => Arc::new(NullEventBus),
+    };
+
+    let options = RunOptions {
+        inputs,
+        selection,
+        prompts,
+        interactive,
+        resume: args.resume,
+        event_bus,
+        exec_log,
+    };
+    let mut executor = Executor::new(runbook, options, state, &config);
+
+    if args.plan {
+        let plan = executor.plan().await.map_err(|e| eyre::eyre!(e))?;
+        for (i, block) in plan.iter().enumerate() {
+            block.print(i + 1);
+        }
+
+        let errors = plan.iter().map(|block| block.error_count()).sum::<usize>();
+        if errors > 0 {
+            println!("✗ {errors} template(s) failed to render");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+    let report = executor.execute().await.map_err(|e| eyre::eyre!(e))?;
+
+    if let (Some(format), Some(path)) = (args.report, args.report_file.as_ref()) {
+        report.write(format, path)?;
+    }
+
+    if !report.succeeded() {
+        std::process::exit(1);
     }
+
+    Ok(())
 }
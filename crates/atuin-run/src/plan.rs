@@ -0,0 +1,343 @@
+use atuin_desktop_runtime::{
+    blocks::{Block, LoopMode, WaitCheck},
+    context::ContextResolver,
+};
+use uuid::Uuid;
+
+/// A template field of a block, rendered against the context the block would run in
+#[derive(Debug)]
+pub struct PlannedField {
+    pub label: &'static str,
+    pub template: String,
+    pub rendered: Result<String, String>,
+}
+
+/// What a single block would do if the runbook were executed
+///
+/// Secrets and other sensitive values in the rendered fields and environment are masked.
+#[derive(Debug)]
+pub struct PlannedBlock {
+    pub id: Uuid,
+    pub title: String,
+    pub cwd: String,
+    pub ssh_host: Option<String>,
+    pub interpreter: Option<String>,
+    pub env: Vec<(String, String)>,
+    pub fields: Vec<PlannedField>,
+}
+
+impl PlannedBlock {
+    /// Render the executable parts of a block; returns `None` for blocks that only
+    /// contribute context (variables, directories, etc.) and run nothing themselves
+    pub fn new(block: &Block, title: String, resolver: &ContextResolver) -> Option<Self> {
+        let render = |label: &'static str, template: &str| PlannedField {
+            label,
+            template: template.to_string(),
+            rendered: resolver
+                .resolve_template(template)
+                .map_err(|e| e.to_string()),
+        };
+
+        let (interpreter, fields) = match block {
+            Block::Script(script) => (
+                Some(script.interpreter.clone()),
+                vec![render("code", &script.code)],
+            ),
+            Block::Terminal(terminal) => (None, vec![render("code", &terminal.code)]),
+            Block::Kubernetes(kubernetes) => {
+                let mut fields = vec![render("command", &kubernetes.command)];
+                if !kubernetes.namespace.is_empty() {
+                    fields.push(render("namespace", &kubernetes.namespace));
+                }
+                if !kubernetes.context.is_empty() {
+                    fields.push(render("context", &kubernetes.context));
+                }
+                (Some(kubernetes.interpreter.clone()), fields)
+            }
+            Block::Http(http) => {
+                let verb = serde_json::to_value(&http.verb)
+                    .ok()
+                    .and_then(|v| v.as_str().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                let mut fields = vec![PlannedField {
+                    label: "method",
+                    template: verb.clone(),
+                    rendered: Ok(verb),
+                }];
+                fields.push(render("url", &http.url));
+
+                let mut headers = http.headers.iter().collect::<Vec<_>>();
+                headers.sort();
+                for (key, value) in headers {
+                    fields.push(render("header", &format!("{key}: {value}")));
+                }
+
+                if !http.body.is_empty() && http.verb.is_body_allowed() {
+                    fields.push(render("body", &http.body));
+                }
+                (None, fields)
+            }
+            Block::Postgres(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+            Block::SQLite(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+            Block::Mysql(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+            Block::Clickhouse(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+            Block::Prometheus(prometheus) => (
+                None,
+                vec![
+                    render("endpoint", &prometheus.endpoint),
+                    render("query", &prometheus.query),
+                ],
+            ),
+            Block::SubRunbook(sub_runbook) => {
+                let runbook = sub_runbook.runbook_ref.display_id();
+                (
+                    None,
+                    vec![PlannedField {
+                        label: "runbook",
+                        template: runbook.clone(),
+                        rendered: Ok(runbook),
+                    }],
+                )
+            }
+            // Expressions read the outputs of earlier blocks, so they can't be evaluated yet
+            Block::Assert(assert) => (
+                None,
+                assert
+                    .expressions()
+                    .into_iter()
+                    .map(|expression| PlannedField {
+                        label: "assert",
+                        template: expression.to_string(),
+                        rendered: Ok(expression.to_string()),
+                    })
+                    .collect(),
+            ),
+            Block::WaitUntil(wait_until) => {
+                let (interpreter, mut fields) = match &wait_until.check {
+                    WaitCheck::Http(http) => (None, vec![render("url", &http.url)]),
+                    WaitCheck::Postgres(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+                    WaitCheck::SQLite(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+                    WaitCheck::Mysql(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+                    WaitCheck::Clickhouse(sql) => (None, sql_fields(render, &sql.uri, &sql.query)),
+                    WaitCheck::Prometheus(prometheus) => (
+                        None,
+                        vec![
+                            render("endpoint", &prometheus.endpoint),
+                            render("query", &prometheus.query),
+                        ],
+                    ),
+                    WaitCheck::Command { interpreter, code } => {
+                        (Some(interpreter.clone()), vec![render("code", code)])
+                    }
+                };
+
+                // The condition reads the check's output, so like assertions it stays unrendered
+                if !wait_until.condition.trim().is_empty() {
+                    fields.push(PlannedField {
+                        label: "until",
+                        template: wait_until.condition.clone(),
+                        rendered: Ok(wait_until.condition.clone()),
+                    });
+                }
+                (interpreter, fields)
+            }
+            // Items often come from the output of an earlier block, so they stay unrendered too
+            Block::Foreach(foreach) => {
+                let mode = match foreach.mode {
+                    LoopMode::Serial => "serial".to_string(),
+                    LoopMode::Parallel => format!("parallel, {} at a time", foreach.concurrency),
+                };
+                (
+                    None,
+                    vec![
+                        PlannedField {
+                            label: "items",
+                            template: foreach.items.clone(),
+                            rendered: Ok(foreach.items.clone()),
+                        },
+                        PlannedField {
+                            label: "mode",
+                            template: mode.clone(),
+                            rendered: Ok(mode),
+                        },
+                    ],
+                )
+            }
+            _ => return None,
+        };
+
+        // Built after rendering, so it also masks the secrets the fields just used
+        let redactor = resolver.redactor();
+        let fields = fields
+            .into_iter()
+            .map(|field| PlannedField {
+                rendered: field
+                    .rendered
+                    .map(|rendered| redactor.redact(&rendered).into_owned()),
+                ..field
+            })
+            .collect();
+
+        let mut env = resolver
+            .env_vars()
+            .iter()
+            .map(|(k, v)| (k.clone(), redactor.redact(v).into_owned()))
+            .collect::<Vec<_>>();
+        env.sort();
+
+        Some(Self {
+            id: block.id(),
+            title,
+            cwd: resolver.cwd().to_string(),
+            ssh_host: resolver.ssh_host().cloned(),
+            interpreter,
+            env,
+            fields,
+        })
+    }
+
+    /// Number of fields whose templates failed to render
+    pub fn error_count(&self) -> usize {
+        self.fields.iter().filter(|f| f.rendered.is_err()).count()
+    }
+
+    pub fn print(&self, index: usize) {
+        println!("[{index}] {} ({})", self.title, self.id);
+        println!("    cwd:         {}", self.cwd);
+        println!(
+            "    host:        {}",
+            self.ssh_host.as_deref().unwrap_or("local")
+        );
+        if let Some(interpreter) = &self.interpreter {
+            println!("    interpreter: {interpreter}");
+        }
+        if !self.env.is_empty() {
+            println!("    env:");
+            for (key, value) in &self.env {
+                println!("      {key}={value}");
+            }
+        }
+
+        for field in &self.fields {
+            match &field.rendered {
+                Ok(rendered) if rendered.contains('\n') => {
+                    println!("    {}:", field.label);
+                    for line in rendered.lines() {
+                        println!("      | {line}");
+                    }
+                }
+                Ok(rendered) => println!("    {}: {rendered}", field.label),
+                Err(error) => {
+                    println!("    {}: ✗ template error: {error}", field.label);
+                    for line in field.template.lines() {
+                        println!("      | {line}");
+                    }
+                }
+            }
+        }
+
+        println!();
+    }
+}
+
+fn sql_fields(
+    render: impl Fn(&'static str, &str) -> PlannedField,
+    uri: &str,
+    query: &str,
+) -> Vec<PlannedField> {
+    vec![render("uri", uri), render("query", query)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atuin_desktop_runtime::client::SecretProvider;
+    use atuin_desktop_runtime::context::{Secrets, REDACTED};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_plan_renders_templates_and_flags_undefined() {
+        let block = Block::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "script",
+            "props": {
+                "name": "deploy",
+                "code": "kubectl apply -n {{ var.namespace }}\necho {{ var.missing }}",
+                "interpreter": "zsh"
+            }
+        }))
+        .unwrap();
+
+        let mut resolver = ContextResolver::new();
+        resolver.set_var("namespace".to_string(), "prod".to_string());
+        resolver.set_env_var("KUBECONFIG".to_string(), "/tmp/kube".to_string());
+
+        let planned = PlannedBlock::new(&block, "Script: deploy".to_string(), &resolver).unwrap();
+        assert_eq!(planned.interpreter.as_deref(), Some("zsh"));
+        assert_eq!(
+            planned.env,
+            vec![("KUBECONFIG".to_string(), "/tmp/kube".to_string())]
+        );
+        assert_eq!(planned.error_count(), 1);
+
+        resolver.set_var("missing".to_string(), "ok".to_string());
+        let planned = PlannedBlock::new(&block, "Script: deploy".to_string(), &resolver).unwrap();
+        assert_eq!(planned.error_count(), 0);
+        assert_eq!(
+            planned.fields[0].rendered.as_deref().unwrap(),
+            "kubectl apply -n prod\necho ok"
+        );
+
+        let var = Block::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "var",
+            "props": { "name": "x", "value": "1" }
+        }))
+        .unwrap();
+        assert!(PlannedBlock::new(&var, String::new(), &ContextResolver::new()).is_none());
+    }
+
+    struct TestSecrets;
+
+    impl SecretProvider for TestSecrets {
+        fn get_secret(
+            &self,
+            name: &str,
+        ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok((name == "db-password").then(|| "hunter2".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_plan_masks_secrets_and_sensitive_env() {
+        let block = Block::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "script",
+            "props": {
+                "name": "migrate",
+                "code": "psql -W {{ secret(\"db-password\") }}\ncurl -H \"Bearer {{ env.API_TOKEN }}\"",
+                "interpreter": "bash"
+            }
+        }))
+        .unwrap();
+
+        let mut resolver = ContextResolver::new();
+        resolver.set_secrets(Secrets::new(Arc::new(TestSecrets)));
+        resolver.set_env_var("API_TOKEN".to_string(), "tok-123456".to_string());
+        resolver.set_env_var("REGION".to_string(), "eu-west-1".to_string());
+
+        let planned = PlannedBlock::new(&block, "Script: migrate".to_string(), &resolver).unwrap();
+        assert_eq!(
+            planned.fields[0].rendered.as_deref().unwrap(),
+            format!("psql -W {REDACTED}\ncurl -H \"Bearer {REDACTED}\"")
+        );
+        assert_eq!(
+            planned.env,
+            vec![
+                ("API_TOKEN".to_string(), REDACTED.to_string()),
+                ("REGION".to_string(), "eu-west-1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    io::{IsTerminal, Write},
+};
+
+use atuin_desktop_runtime::client::{ClientPrompt, ClientPromptResult, PromptInput, PromptOption};
+
+/// Title of the prompt shown when a Pause block stops the workflow
+const PAUSE_PROMPT_TITLE: &str = "Workflow paused";
+
+/// Answers to client prompts, either given ahead of time or asked for in the terminal
+#[derive(Debug, Default)]
+pub struct PromptResponder {
+    /// Prompt title -> answer
+    answers: HashMap<String, ClientPromptResult>,
+    interactive: bool,
+    /// Continue past Pause blocks without asking
+    auto_continue: bool,
+}
+
+impl PromptResponder {
+    pub fn new(
+        answers: Vec<(String, ClientPromptResult)>,
+        interactive: bool,
+        auto_continue: bool,
+    ) -> Self {
+        Self {
+            answers: answers.into_iter().collect(),
+            interactive,
+            auto_continue,
+        }
+    }
+
+    /// Decide whether to carry on after a Pause block
+    ///
+    /// Returns `false` if the user chose to stop, or if nobody can be asked.
+    pub fn continue_after_pause(&self) -> std::io::Result<bool> {
+        if self.auto_continue {
+            return Ok(true);
+        }
+
+        let prompt =
+            ClientPrompt::new(PAUSE_PROMPT_TITLE, "Continue with the rest of the runbook?")
+                .option(PromptOption::new("Continue", "continue"))
+                .option(PromptOption::new("Stop", "stop"));
+
+        Ok(self
+            .respond(&prompt)?
+            .is_some_and(|answer| answer.button == "continue"))
+    }
+
+    /// Answer a prompt, preferring a preset answer over asking the user
+    ///
+    /// Returns `None` if there is no preset answer and the run is non-interactive.
+    pub fn respond(&self, prompt: &ClientPrompt) -> std::io::Result<Option<ClientPromptResult>> {
+        if let Some(answer) = self.answers.get(&prompt.title) {
+            tracing::debug!("Answering prompt '{}' from --answer", prompt.title);
+            return Ok(Some(answer.clone()));
+        }
+
+        if !self.interactive {
+            return Ok(None);
+        }
+
+        // Terminal blocks put the terminal in raw mode, where lines can't be read
+        let _cooked = CookedMode::enter()?;
+        ask(prompt).map(Some)
+    }
+}
+
+/// Takes the terminal out of raw mode until dropped, if it was in it
+struct CookedMode {
+    was_raw: bool,
+}
+
+impl CookedMode {
+    fn enter() -> std::io::Result<Self> {
+        let was_raw = crossterm::terminal::is_raw_mode_enabled()?;
+        if was_raw {
+            crossterm::terminal::disable_raw_mode()?;
+        }
+        Ok(Self { was_raw })
+    }
+}
+
+impl Drop for CookedMode {
+    fn drop(&mut self) {
+        if self.was_raw {
+            let _ = crossterm::terminal::enable_raw_mode();
+        }
+    }
+}
+
+/// Parse an `--answer TITLE=BUTTON[:VALUE]` argument
+///
+/// The title runs up to the first `=` that is followed by a button without an `=`, so
+/// `x=1=ok` answers the prompt titled `x=1`. A title can also be quoted, as in
+/// `"Step 1: x=y"=ok`, which is needed when an `=` in it is followed by a `:`.
+pub fn parse_answer(arg: &str) -> Result<(String, ClientPromptResult), String> {
+    let invalid = || format!("expected TITLE=BUTTON[:VALUE], got '{arg}'");
+
+    let (title, answer) = match arg.strip_prefix('"') {
+        Some(quoted) => quoted.split_once("\"=").ok_or_else(invalid)?,
+        None => arg
+            .match_indices('=')
+            .map(|(at, _)| (&arg[..at], &arg[at + 1..]))
+            .find(|(_, answer)| {
+                let button = answer.split_once(':').map_or(*answer, |(button, _)| button);
+                !button.contains('=')
+            })
+            .ok_or_else(invalid)?,
+    };
+    if title.is_empty() {
+        return Err(invalid());
+    }
+
+    let (button, value) = match answer.split_once(':') {
+        Some((button, value)) => (button, Some(value.to_string())),
+        None => (answer, None),
+    };
+
+    Ok((
+        title.to_string(),
+        ClientPromptResult {
+            button: button.to_string(),
+            value,
+        },
+    ))
+}
+
+fn ask(prompt: &ClientPrompt) -> std::io::Result<ClientPromptResult> {
+    println!();
+    println!("? {}", prompt.title);
+    if !prompt.prompt.is_empty() {
+        println!("  {}", prompt.prompt);
+    }
+
+    let value = match &prompt.input {
+        Some(PromptInput::String) => Some(read_prompted("  > ")?),
+        Some(PromptInput::Secret) => Some(read_secret("  > ")?),
+        Some(PromptInput::Text) => {
+            println!("  (finish with an empty line)");
+            let mut lines = Vec::new();
+            loop {
+                let line = read_prompted("  | ")?;
+                if line.is_empty() {
+                    break;
+                }
+                lines.push(line);
+            }
+            Some(lines.join("\n"))
+        }
+        Some(PromptInput::Dropdown(options)) => {
+            let options = options
+                .iter()
+                .map(|(label, value)| (label.as_str(), value.as_str()))
+                .collect::<Vec<_>>();
+            Some(choose(&options)?.to_string())
+        }
+        None => None,
+    };
+
+    let button = match prompt.options.as_slice() {
+        [] => String::new(),
+        [option] => option.value.clone(),
+        options => {
+            let options = options
+                .iter()
+                .map(|o| (o.label.as_str(), o.value.as_str()))
+                .collect::<Vec<_>>();
+            choose(&options)?.to_string()
+        }
+    };
+
+    Ok(ClientPromptResult { button, value })
+}
+
+/// Ask the user to pick one of `(label, value)`, by number, label or value
+fn choose<'a>(options: &[(&str, &'a str)]) -> std::io::Result<&'a str> {
+    for (i, (label, _)) in options.iter().enumerate() {
+        println!("  {}) {label}", i + 1);
+    }
+
+    loop {
+        let input = read_prompted("  > ")?;
+
+        if let Some((_, value)) = input
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| options.get(i))
+        {
+            return Ok(*value);
+        }
+
+        if let Some((_, value)) = options
+            .iter()
+            .find(|(label, value)| input.eq_ignore_ascii_case(label) || input == *value)
+        {
+            return Ok(*value);
+        }
+
+        println!("  Enter a number between 1 and {}", options.len());
+    }
+}
+
+fn read_prompted(prefix: &str) -> std::io::Result<String> {
+    print!("{prefix}");
+    std::io::stdout().flush()?;
+
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input)? == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "stdin closed while waiting for an answer",
+        ));
+    }
+
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Read a line without echoing it, for passwords and one-time codes
+fn read_secret(prefix: &str) -> std::io::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    if !std::io::stdin().is_terminal() {
+        return read_prompted(prefix);
+    }
+
+    print!("{prefix}");
+    std::io::stdout().flush()?;
+
+    let was_raw = crossterm::terminal::is_raw_mode_enabled()?;
+    crossterm::terminal::enable_raw_mode()?;
+    let mut input = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "interrupted while waiting for an answer",
+                ))
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    };
+    if !was_raw {
+        crossterm::terminal::disable_raw_mode()?;
+    }
+    println!();
+
+    result.map(|_| input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_answer() {
+        let (title, answer) = parse_answer("Confirm deploy=yes").unwrap();
+        assert_eq!(title, "Confirm deploy");
+        assert_eq!(answer.button, "yes");
+        assert_eq!(answer.value, None);
+
+        let (_, answer) = parse_answer("Release notes=submit:v1.2: bug fixes").unwrap();
+        assert_eq!(answer.button, "submit");
+        assert_eq!(answer.value.as_deref(), Some("v1.2: bug fixes"));
+
+        assert!(parse_answer("no-equals").is_err());
+        assert!(parse_answer("=yes").is_err());
+    }
+
+    #[test]
+    fn test_parse_answer_title_with_separators() {
+        let (title, answer) = parse_answer("Set replicas=3=yes").unwrap();
+        assert_eq!(title, "Set replicas=3");
+        assert_eq!(answer.button, "yes");
+
+        let (title, answer) = parse_answer("Step 1: confirm=ok:a=b").unwrap();
+        assert_eq!(title, "Step 1: confirm");
+        assert_eq!(answer.button, "ok");
+        assert_eq!(answer.value.as_deref(), Some("a=b"));
+
+        let (title, answer) = parse_answer("\"mode=fast: sure?\"=yes:x").unwrap();
+        assert_eq!(title, "mode=fast: sure?");
+        assert_eq!(answer.button, "yes");
+        assert_eq!(answer.value.as_deref(), Some("x"));
+
+        assert!(parse_answer("\"unterminated=yes").is_err());
+    }
+
+    #[test]
+    fn test_non_interactive_uses_preset_answers_only() {
+        let responder = PromptResponder::new(
+            vec![parse_answer("Confirm deploy=yes").unwrap()],
+            false,
+            false,
+        );
+
+        let known = ClientPrompt::new("Confirm deploy", "Deploy to production?")
+            .option(PromptOption::new("Yes", "yes"))
+            .option(PromptOption::new("No", "no"));
+        let answer = responder.respond(&known).unwrap().unwrap();
+        assert_eq!(answer.button, "yes");
+
+        let unknown = ClientPrompt::new("Something else", "?");
+        assert!(responder.respond(&unknown).unwrap().is_none());
+        assert!(!responder.continue_after_pause().unwrap());
+
+        let responder = PromptResponder::new(vec![], false, true);
+        assert!(responder.continue_after_pause().unwrap());
+    }
+}
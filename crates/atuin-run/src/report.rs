@@ -0,0 +1,264 @@
+use std::{fmt::Write as _, path::Path};
+
+use atuin_desktop_runtime::execution::ExecutionResult;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReportError {
+    #[error("Failed to serialize report: {0}")]
+    SerializeError(#[from] serde_json::Error),
+
+    #[error("Failed to write report: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Outcome of a single block in a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReport {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exit_code: Option<i32>,
+    /// `None` if the block never ran (e.g. an earlier block failed)
+    pub result: Option<ExecutionResult>,
+    /// Whether the block was left out by a block selector (`--from`, `--skip`, etc.)
+    pub skipped: bool,
+    pub error: Option<String>,
+    /// The block's execution output, as serialized by the runtime
+    pub output: Option<serde_json::Value>,
+}
+
+impl BlockReport {
+    pub fn not_run(id: Uuid, name: String, block_type: String) -> Self {
+        Self {
+            id,
+            name,
+            block_type,
+            started_at: None,
+            finished_at: None,
+            exit_code: None,
+            result: None,
+            skipped: false,
+            error: None,
+            output: None,
+        }
+    }
+
+    fn duration_secs(&self) -> f64 {
+        match (self.started_at, self.finished_at) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds() as f64 / 1000.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Machine-readable summary of a runbook run, for CI systems and dashboards
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    pub runbook_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub blocks: Vec<BlockReport>,
+}
+
+impl RunReport {
+    pub fn new(runbook_id: Uuid) -> Self {
+        Self {
+            runbook_id,
+            started_at: Utc::now(),
+            finished_at: None,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Whether every selected block ran and succeeded
+    pub fn succeeded(&self) -> bool {
+        self.blocks
+            .iter()
+            .all(|b| b.skipped || b.result == Some(ExecutionResult::Success))
+    }
+
+    pub fn write(&self, format: ReportFormat, path: &Path) -> Result<(), ReportError> {
+        let content = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+            ReportFormat::Junit => self.to_junit()?,
+        };
+
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Render the report as JUnit XML, with one test case per block
+    pub fn to_junit(&self) -> Result<String, ReportError> {
+        let count = |result: Option<ExecutionResult>| {
+            self.blocks.iter().filter(|b| b.result == result).count()
+        };
+        let failures = count(Some(ExecutionResult::Failure));
+        let skipped = self.blocks.len() - count(Some(ExecutionResult::Success)) - failures;
+        let time = self
+            .finished_at
+            .map(|end| (end - self.started_at).num_milliseconds() as f64 / 1000.0)
+            .unwrap_or_default();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"atuin-run\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+            self.blocks.len()
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\" timestamp=\"{}\">",
+            self.runbook_id,
+            self.blocks.len(),
+            self.started_at.to_rfc3339()
+        );
+
+        for block in &self.blocks {
+            let name = if block.name.is_empty() {
+                block.block_type.clone()
+            } else {
+                format!("{}: {}", block.block_type, block.name)
+            };
+
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+                escape_xml(&name),
+                self.runbook_id,
+                block.duration_secs()
+            );
+
+            match block.result {
+                Some(ExecutionResult::Success) => {}
+                Some(ExecutionResult::Failure) => {
+                    let message = match (&block.error, block.exit_code) {
+                        (Some(error), _) => error.clone(),
+                        (None, Some(code)) => format!("Exited with code {code}"),
+                        (None, None) => "Block failed".to_string(),
+                    };
+                    let _ = writeln!(xml, "      <failure message=\"{}\"/>", escape_xml(&message));
+                }
+                Some(ExecutionResult::Cancelled) => {
+                    xml.push_str("      <skipped message=\"Cancelled\"/>\n");
+                }
+                Some(ExecutionResult::Paused) => {
+                    xml.push_str("      <skipped message=\"Paused\"/>\n");
+                }
+                None if block.skipped => {
+                    xml.push_str("      <skipped message=\"Skipped\"/>\n");
+                }
+                None => {
+                    xml.push_str("      <skipped message=\"Not run\"/>\n");
+                }
+            }
+
+            if let Some(output) = &block.output {
+                let output = serde_json::to_string_pretty(output)?;
+                let _ = writeln!(
+                    xml,
+                    "      <system-out>{}</system-out>",
+                    escape_xml(&output)
+                );
+            }
+
+            xml.push_str("    </testcase>\n");
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        Ok(xml)
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0
+            c if c.is_control() && c != '\n' && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_junit_report() {
+        let mut report = RunReport::new(Uuid::new_v4());
+        let now = Utc::now();
+
+        report.blocks.push(BlockReport {
+            started_at: Some(now),
+            finished_at: Some(now),
+            exit_code: Some(0),
+            result: Some(ExecutionResult::Success),
+            output: Some(serde_json::json!({ "stdout": "ok" })),
+            ..BlockReport::not_run(Uuid::new_v4(), "build".to_string(), "Script".to_string())
+        });
+        report.blocks.push(BlockReport {
+            started_at: Some(now),
+            finished_at: Some(now),
+            exit_code: Some(2),
+            result: Some(ExecutionResult::Failure),
+            ..BlockReport::not_run(
+                Uuid::new_v4(),
+                "test <all>".to_string(),
+                "Script".to_string(),
+            )
+        });
+        report.blocks.push(BlockReport::not_run(
+            Uuid::new_v4(),
+            "deploy".to_string(),
+            "Script".to_string(),
+        ));
+        report.finished_at = Some(now);
+
+        assert!(!report.succeeded());
+
+        let xml = report.to_junit().unwrap();
+        assert!(xml.contains("tests=\"3\" failures=\"1\" skipped=\"1\""));
+        assert!(xml.contains("name=\"Script: test &lt;all&gt;\""));
+        assert!(xml.contains("<failure message=\"Exited with code 2\"/>"));
+        assert!(xml.contains("<skipped message=\"Not run\"/>"));
+        assert!(xml.contains("&quot;stdout&quot;: &quot;ok&quot;"));
+    }
+
+    #[test]
+    fn test_json_report_fields() {
+        let mut report = RunReport::new(Uuid::new_v4());
+        report.blocks.push(BlockReport {
+            exit_code: Some(0),
+            result: Some(ExecutionResult::Success),
+            ..BlockReport::not_run(Uuid::new_v4(), "build".to_string(), "Script".to_string())
+        });
+
+        let json = serde_json::to_value(&report).unwrap();
+        let block = &json["blocks"][0];
+        assert_eq!(block["type"], "Script");
+        assert_eq!(block["exitCode"], 0);
+        assert_eq!(block["result"], "success");
+        assert!(json["runbookId"].is_string());
+    }
+}
@@ -1,7 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use atuin_desktop_runtime::client::{
+    load_runbook_from_uri, HubClient, ParsedUri, RunbookLoadError,
+};
 use uuid::Uuid;
 
+use crate::{
+    config::Config,
+    workspace::{Workspace, WorkspaceError},
+};
+
 type Result<T> = std::result::Result<T, RunbookError>;
 
 #[derive(thiserror::Error, Debug)]
@@ -17,6 +25,15 @@ pub enum RunbookError {
 
     #[error("Invalid runbook file {0}: {1}")]
     InvalidRunbookFile(PathBuf, String),
+
+    #[error("Invalid runbook reference '{0}': expected @user/name or @user/name:tag")]
+    InvalidRunbookReference(String),
+
+    #[error("{0}")]
+    HubLoadError(#[from] RunbookLoadError),
+
+    #[error("Failed to load workspace: {0}")]
+    WorkspaceError(#[from] WorkspaceError),
 }
 
 pub struct Runbook {
@@ -24,6 +41,8 @@ pub struct Runbook {
     pub content: Vec<serde_json::Value>,
     /// Source path of the runbook file (if loaded from a file)
     pub source_path: Option<PathBuf>,
+    /// Workspace containing the runbook file (if it lives under an `atuin.toml`)
+    pub workspace: Option<Workspace>,
 }
 
 impl Runbook {
@@ -32,30 +51,43 @@ impl Runbook {
             id,
             content,
             source_path,
+            workspace: None,
         }
     }
 }
 
-pub async fn load_runbook(path_or_id: &str) -> Result<Runbook> {
+pub async fn load_runbook(path_or_id: &str, config: &Config) -> Result<Runbook> {
     let path = PathBuf::from(path_or_id);
     if path.is_file() {
-        load_runbook_from_file(&path).await
-    } else {
-        load_runbook_from_id(path_or_id).await
+        return load_runbook_from_file(&path).await;
+    }
+
+    match path_or_id.strip_prefix('@') {
+        Some(uri) => load_runbook_from_id(uri, &config.hub_client()).await,
+        None => load_runbook_from_file(&path).await,
     }
 }
 
-// TODO: Find `atuin.toml` and load workspace information
 async fn load_runbook_from_file(path: impl AsRef<Path>) -> Result<Runbook> {
     let content = tokio::fs::read_to_string(path.as_ref()).await?;
     let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content)?;
     let json_value: serde_json::Value = serde_yaml::from_value(yaml_value)?;
-    load_runbook_from_json_value(json_value, path)
+    let mut runbook = load_runbook_from_json_value(json_value, path.as_ref())?;
+    runbook.workspace = Workspace::discover(path.as_ref()).await?;
+    Ok(runbook)
 }
 
-// TODO: handle API credentials
-async fn load_runbook_from_id(_id: &str) -> Result<Runbook> {
-    todo!()
+/// Fetch a published runbook from Atuin Hub by `user/name` or `user/name:tag`
+///
+/// Without a tag, the hub's `latest` snapshot is used.
+async fn load_runbook_from_id(uri: &str, hub_client: &HubClient) -> Result<Runbook> {
+    let display_id = format!("@{uri}");
+    if ParsedUri::parse(uri).is_none() {
+        return Err(RunbookError::InvalidRunbookReference(display_id));
+    }
+
+    let loaded = load_runbook_from_uri(hub_client, uri, &display_id).await?;
+    Ok(Runbook::new(loaded.id, loaded.content, None))
 }
 
 fn load_runbook_from_json_value(
@@ -86,3 +118,112 @@ fn load_runbook_from_json_value(
 
     Ok(Runbook::new(id, content, Some(path.as_ref().to_path_buf())))
 }
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn test_config(server: &MockServer, token: Option<&str>) -> Config {
+        Config {
+            hub_token: token.map(|t| t.to_string()),
+            hub_url: Some(server.url("/api")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_runbook_from_hub_with_tag_and_token() {
+        let server = MockServer::start_async().await;
+        let runbook_id = Uuid::new_v4();
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/resolve/runbook")
+                    .query_param("nwo", "team/deploy")
+                    .query_param("tag", "v2")
+                    .header("Authorization", "Bearer secret");
+                then.status(200).json_body(serde_json::json!({
+                    "runbook": {
+                        "id": runbook_id.to_string(),
+                        "name": "Deploy",
+                        "slug": "deploy",
+                        "nwo": "team/deploy",
+                        "visibility": "private",
+                    },
+                    "snapshot": {
+                        "id": "snap",
+                        "tag": "v2",
+                        "content": [{ "id": Uuid::new_v4().to_string(), "type": "paragraph" }],
+                    },
+                }));
+            })
+            .await;
+
+        let config = test_config(&server, Some("secret"));
+        let runbook = load_runbook("@team/deploy:v2", &config).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(runbook.id, runbook_id);
+        assert_eq!(runbook.content.len(), 1);
+        assert!(runbook.source_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_runbook_from_hub_defaults_to_latest_tag() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/resolve/runbook")
+                    .query_param("nwo", "team/deploy")
+                    .query_param("tag", "latest");
+                then.status(200).json_body(serde_json::json!({
+                    "runbook": {
+                        "id": Uuid::new_v4().to_string(),
+                        "name": "Deploy",
+                        "slug": "deploy",
+                        "nwo": "team/deploy",
+                        "visibility": "public",
+                    },
+                    "snapshot": { "id": "snap", "tag": "latest", "content": [] },
+                }));
+            })
+            .await;
+
+        let config = test_config(&server, None);
+        load_runbook("@team/deploy", &config).await.unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_load_runbook_from_hub_not_found() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/resolve/runbook");
+                then.status(404);
+            })
+            .await;
+
+        let config = test_config(&server, None);
+        let result = load_runbook("@team/missing", &config).await;
+
+        assert!(matches!(
+            result,
+            Err(RunbookError::HubLoadError(
+                RunbookLoadError::NotFound { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_runbook_rejects_invalid_reference() {
+        let result = load_runbook("@not-a-runbook", &Config::default()).await;
+        assert!(matches!(
+            result,
+            Err(RunbookError::InvalidRunbookReference(_))
+        ));
+    }
+}
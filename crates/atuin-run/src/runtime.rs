@@ -1,14 +1,22 @@
-use std::path::PathBuf;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 
 use atuin_desktop_runtime::client::{
     load_runbook_from_id, load_runbook_from_uri, DocumentBridgeMessage, HubClient, LoadedRunbook,
-    LocalValueProvider, MessageChannel, RunbookContentLoader, RunbookLoadError, SubRunbookRef,
+    MessageChannel, RunbookContentLoader, RunbookLoadError, SecretProvider, SubRunbookRef,
 };
-use atuin_desktop_runtime::context::{BlockContext, BlockContextStorage};
 use atuin_desktop_runtime::events::{EventBus, GCEvent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::workspace::Workspace;
+
 pub struct NullEventBus;
 
 #[async_trait::async_trait]
@@ -21,6 +29,75 @@ impl EventBus for NullEventBus {
     }
 }
 
+/// Writes every runtime event as a line of JSON, so other tools can follow a run live
+///
+/// Each line is the serialized `GCEvent` (`{"type": ..., "data": ...}`) plus a `timestamp`.
+pub struct JsonLinesEventBus {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a GCEvent,
+}
+
+impl JsonLinesEventBus {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// Append events to a file, creating it if needed
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+
+    /// Write events to a file descriptor inherited from the parent process (e.g. `3>events.jsonl`)
+    ///
+    /// The descriptor is duplicated, so it is checked to be open and is not closed by us.
+    #[cfg(unix)]
+    pub fn from_fd(fd: std::os::fd::RawFd) -> std::io::Result<Self> {
+        use std::os::fd::BorrowedFd;
+
+        // SAFETY: the descriptor is only borrowed long enough to duplicate it; if it is
+        // not open, duplicating fails with EBADF rather than touching another file.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        Ok(Self::new(std::fs::File::from(fd)))
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_fd(_fd: i32) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "writing events to a file descriptor is only supported on Unix",
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl EventBus for JsonLinesEventBus {
+    async fn emit(
+        &self,
+        event: GCEvent,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut line = serde_json::to_vec(&EventLine {
+            timestamp: Utc::now(),
+            event: &event,
+        })?;
+        line.push(b'\n');
+
+        // Flush every line so readers see events as they happen
+        let mut writer = self.writer.lock().map_err(|e| e.to_string())?;
+        writer.write_all(&line)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
 pub struct NullDocumentBridge;
 
 #[async_trait::async_trait]
@@ -32,6 +109,39 @@ impl MessageChannel<DocumentBridgeMessage> for NullDocumentBridge {
         Ok(())
     }
 }
+
+/// Secrets for `{{ secret("name") }}` templates, read from `ATUIN_SECRET_<NAME>` environment
+/// variables
+///
+/// The name is upper-cased, with anything other than letters and digits replaced by `_`, so
+/// `secret("prod-db-password")` reads `ATUIN_SECRET_PROD_DB_PASSWORD`.
+pub struct EnvSecretProvider;
+
+impl EnvSecretProvider {
+    pub fn variable_name(name: &str) -> String {
+        let name = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        format!("ATUIN_SECRET_{name}")
+    }
+}
+
+impl SecretProvider for EnvSecretProvider {
+    fn get_secret(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(std::env::var(Self::variable_name(name)).ok())
+    }
+}
+
 pub struct ChannelDocumentBridge {
     sender: mpsc::Sender<DocumentBridgeMessage>,
 }
@@ -52,81 +162,45 @@ impl MessageChannel<DocumentBridgeMessage> for ChannelDocumentBridge {
     }
 }
 
-pub struct TempNullLocalValueProvider;
-
-#[async_trait::async_trait]
-impl LocalValueProvider for TempNullLocalValueProvider {
-    async fn get_block_local_value(
-        &self,
-        _block_id: Uuid,
-        _property_name: &str,
-    ) -> std::result::Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(None)
-    }
-}
-
-pub struct TempNullContextStorage;
-
-#[async_trait::async_trait]
-impl BlockContextStorage for TempNullContextStorage {
-    async fn save(
-        &self,
-        _document_id: &str,
-        _block_id: &Uuid,
-        _context: &BlockContext,
-    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        Ok(())
-    }
-
-    async fn load(
-        &self,
-        _document_id: &str,
-        _block_id: &Uuid,
-    ) -> std::result::Result<Option<BlockContext>, Box<dyn std::error::Error + Send + Sync>> {
-        Ok(None)
-    }
-
-    async fn delete(
-        &self,
-        _document_id: &str,
-        _block_id: &Uuid,
-    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        Ok(())
-    }
-
-    async fn delete_for_document(
-        &self,
-        _runbook_id: &str,
-    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        Ok(())
-    }
-}
-
 /// Runbook loader that resolves references as file paths relative to a base directory,
 /// or fetches from Atuin Hub for remote references.
 ///
 /// Resolution order:
 /// 1. If `path` is set: Try as relative path, then absolute path
 /// 2. If `uri` is set: Fetch from hub by NWO (user/runbook:tag)
-/// 3. If `id` is set: Fetch from hub by ID
+/// 3. If `id` is set: Look up the runbook in the workspace, then fetch from hub by ID
 pub struct FileRunbookLoader {
     /// Base directory for resolving relative paths (typically the directory containing the parent runbook)
     base_dir: PathBuf,
     /// Hub API client for fetching remote runbooks
     hub_client: HubClient,
+    /// Workspace containing the parent runbook, used to resolve runbooks by ID
+    workspace: Option<Workspace>,
 }
 
 impl FileRunbookLoader {
+    /// Create a loader that resolves relative paths against `base_dir`
+    pub fn new(base_dir: PathBuf, hub_client: HubClient) -> Self {
+        Self {
+            base_dir,
+            hub_client,
+            workspace: None,
+        }
+    }
+
+    /// Resolve runbook IDs against the given workspace before falling back to the hub
+    pub fn with_workspace(mut self, workspace: Option<Workspace>) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
     /// Create a loader from a runbook file path (uses the parent directory as base)
-    pub fn from_runbook_path(runbook_path: &std::path::Path) -> Self {
+    pub fn from_runbook_path(runbook_path: &std::path::Path, hub_client: HubClient) -> Self {
         let base_dir = runbook_path
             .parent()
             .map(|p| p.to_path_buf())
             .unwrap_or_else(|| PathBuf::from("."));
-        Self {
-            base_dir,
-            hub_client: HubClient::new(),
-        }
+        Self::new(base_dir, hub_client)
     }
 
     /// Try to resolve a path (relative to base_dir or absolute)
@@ -240,8 +314,15 @@ impl RunbookContentLoader for FileRunbookLoader {
             return self.load_from_uri(uri, &display_id).await;
         }
 
-        // 3. Try ID (hub fetch by ID)
+        // 3. Try ID (workspace lookup, then hub fetch by ID)
         if let Some(id) = &runbook_ref.id {
+            if let Some(path) = self
+                .workspace
+                .as_ref()
+                .and_then(|workspace| workspace.runbook_path(id))
+            {
+                return self.load_from_path(&path.to_path_buf(), &display_id).await;
+            }
             return self.load_from_hub_id(id, &display_id).await;
         }
 
@@ -251,3 +332,51 @@ impl RunbookContentLoader for FileRunbookLoader {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_json_lines_event_bus() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let runbook_id = Uuid::new_v4();
+
+        let bus = JsonLinesEventBus::from_path(&path).unwrap();
+        bus.emit(GCEvent::RunbookStarted { runbook_id })
+            .await
+            .unwrap();
+        bus.emit(GCEvent::SshConnectionFailed {
+            host: "example.com".to_string(),
+            error: "refused".to_string(),
+        })
+        .await
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "runbookStarted");
+        assert_eq!(lines[0]["data"]["runbook_id"], runbook_id.to_string());
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[1]["type"], "sshConnectionFailed");
+        assert_eq!(lines[1]["data"]["host"], "example.com");
+    }
+
+    #[test]
+    fn test_env_secret_variable_name() {
+        assert_eq!(
+            EnvSecretProvider::variable_name("prod-db-password"),
+            "ATUIN_SECRET_PROD_DB_PASSWORD"
+        );
+        assert_eq!(
+            EnvSecretProvider::variable_name("github.token"),
+            "ATUIN_SECRET_GITHUB_TOKEN"
+        );
+    }
+}
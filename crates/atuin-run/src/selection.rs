@@ -0,0 +1,210 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use atuin_desktop_runtime::blocks::Block;
+use uuid::Uuid;
+
+use crate::app::Args;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SelectionError {
+    #[error("No block matches {0}")]
+    NoMatch(BlockSelector),
+
+    #[error("{0} matches {1} blocks; use the block ID instead")]
+    Ambiguous(BlockSelector, usize),
+
+    #[error("--from block {0} comes after --until block {1}")]
+    EmptyRange(BlockSelector, BlockSelector),
+}
+
+/// Identifies a block on the command line, by ID or by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockSelector {
+    Id(Uuid),
+    Name(String),
+}
+
+impl BlockSelector {
+    fn matches(&self, block: &Block) -> bool {
+        match self {
+            BlockSelector::Id(id) => block.id() == *id,
+            BlockSelector::Name(name) => block.name() == *name,
+        }
+    }
+
+    /// Find the single block this selector refers to
+    fn position(&self, blocks: &[Block]) -> Result<usize, SelectionError> {
+        let matches = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| self.matches(block))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        match matches.as_slice() {
+            [] => Err(SelectionError::NoMatch(self.clone())),
+            [index] => Ok(*index),
+            _ => Err(SelectionError::Ambiguous(self.clone(), matches.len())),
+        }
+    }
+}
+
+impl FromStr for BlockSelector {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match Uuid::parse_str(s) {
+            Ok(id) => BlockSelector::Id(id),
+            Err(_) => BlockSelector::Name(s.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for BlockSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockSelector::Id(id) => write!(f, "block ID {id}"),
+            BlockSelector::Name(name) => write!(f, "block name '{name}'"),
+        }
+    }
+}
+
+/// Which blocks of a runbook to execute
+///
+/// Blocks that are not selected are still part of the document, so their passive
+/// context (variables, directories, environment) is available to the blocks that run.
+#[derive(Debug, Default, Clone)]
+pub struct BlockSelection {
+    from: Option<BlockSelector>,
+    until: Option<BlockSelector>,
+    only: Vec<BlockSelector>,
+    skip: Vec<BlockSelector>,
+}
+
+impl BlockSelection {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            from: args.from.clone(),
+            until: args.until.clone(),
+            only: args.only.clone(),
+            skip: args.skip.clone(),
+        }
+    }
+
+    /// Work out which blocks should run, returning one flag per block
+    pub fn select(&self, blocks: &[Block]) -> Result<Vec<bool>, SelectionError> {
+        let start = match &self.from {
+            Some(selector) => selector.position(blocks)?,
+            None => 0,
+        };
+        let end = match &self.until {
+            Some(selector) => selector.position(blocks)?,
+            None => blocks.len().saturating_sub(1),
+        };
+
+        if let (Some(from), Some(until)) = (&self.from, &self.until) {
+            if start > end {
+                return Err(SelectionError::EmptyRange(from.clone(), until.clone()));
+            }
+        }
+
+        for selector in self.only.iter().chain(self.skip.iter()) {
+            if !blocks.iter().any(|block| selector.matches(block)) {
+                return Err(SelectionError::NoMatch(selector.clone()));
+            }
+        }
+
+        Ok(blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let in_range = i >= start && i <= end;
+                let included = self.only.is_empty() || self.only.iter().any(|s| s.matches(block));
+                let skipped = self.skip.iter().any(|s| s.matches(block));
+                in_range && included && !skipped
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn block(name: &str) -> Block {
+        Block::from_document(&json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "script",
+            "props": { "name": name, "code": "true", "interpreter": "bash" }
+        }))
+        .unwrap()
+    }
+
+    fn selector(s: &str) -> BlockSelector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_from_until_and_skip() {
+        let blocks = vec![block("a"), block("b"), block("c"), block("d")];
+
+        let selection = BlockSelection {
+            from: Some(selector("b")),
+            until: Some(BlockSelector::Id(blocks[3].id())),
+            skip: vec![selector("c")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            selection.select(&blocks).unwrap(),
+            vec![false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn test_only() {
+        let blocks = vec![block("a"), block("b"), block("c")];
+
+        let selection = BlockSelection {
+            only: vec![selector("a"), selector("c")],
+            ..Default::default()
+        };
+
+        assert_eq!(selection.select(&blocks).unwrap(), vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        let blocks = vec![block("a"), block("b"), block("b")];
+
+        let missing = BlockSelection {
+            skip: vec![selector("nope")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            missing.select(&blocks),
+            Err(SelectionError::NoMatch(_))
+        ));
+
+        let ambiguous = BlockSelection {
+            from: Some(selector("b")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ambiguous.select(&blocks),
+            Err(SelectionError::Ambiguous(_, 2))
+        ));
+
+        let backwards = BlockSelection {
+            from: Some(selector("b")),
+            until: Some(selector("a")),
+            ..Default::default()
+        };
+        let blocks = vec![block("a"), block("b")];
+        assert!(matches!(
+            backwards.select(&blocks),
+            Err(SelectionError::EmptyRange(_, _))
+        ));
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    str::FromStr,
+    sync::{PoisonError, RwLock},
+    time::Duration,
+};
+
+use atuin_desktop_runtime::client::LocalValueProvider;
+use atuin_desktop_runtime::context::{BlockContext, BlockContextStorage};
+use atuin_desktop_runtime::exec_log::ExecLogHandle;
+use sqlx::{
+    sqlite::{self, SqliteRow},
+    FromRow, Row, SqlitePool,
+};
+use uuid::Uuid;
+
+/// Environment variable overriding the state database location
+const STATE_PATH_ENV: &str = "ATUIN_RUN_STATE";
+
+#[derive(thiserror::Error, Debug)]
+pub enum StateError {
+    #[error("Could not determine a directory for the state database; set {STATE_PATH_ENV}")]
+    NoStateDir,
+
+    #[error("Failed to create state directory {0}: {1}")]
+    IoError(PathBuf, std::io::Error),
+
+    #[error("Failed to open state database: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Failed to migrate state database: {0}")]
+    MigrateError(#[from] sqlx::migrate::MigrateError),
+
+    #[error("Failed to open execution log: {0}")]
+    ExecLogError(String),
+}
+
+/// Open the database that persists block context and local-var values between runs
+///
+/// Lives at `$XDG_STATE_HOME/atuin-run/state.db` by default.
+pub async fn open_state_db() -> Result<SqlitePool, StateError> {
+    open_state_db_at(state_db_path()?).await
+}
+
+/// Open the log of when each block last ran, used to check block dependencies
+///
+/// Lives next to the state database, as `exec_log.db`.
+pub fn open_exec_log() -> Result<ExecLogHandle, StateError> {
+    let path = state_db_path()?.with_file_name("exec_log.db");
+    ExecLogHandle::new(path).map_err(|e| StateError::ExecLogError(e.to_string()))
+}
+
+fn state_db_path() -> Result<PathBuf, StateError> {
+    match std::env::var(STATE_PATH_ENV) {
+        Ok(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Ok(dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .ok_or(StateError::NoStateDir)?
+            .join("atuin-run")
+            .join("state.db")),
+    }
+}
+
+async fn open_state_db_at(path: PathBuf) -> Result<SqlitePool, StateError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| StateError::IoError(parent.to_path_buf(), e))?;
+    }
+
+    let opts = sqlite::SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
+        .journal_mode(sqlite::SqliteJournalMode::Wal)
+        .synchronous(sqlite::SqliteSynchronous::Normal)
+        .create_if_missing(true);
+
+    let pool = sqlite::SqlitePoolOptions::new()
+        .acquire_timeout(Duration::from_secs_f64(3.0))
+        .connect_with(opts)
+        .await?;
+
+    sqlx::migrate!("./migrations/state").run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Stores local-var values on disk, so they are only asked for once per machine
+///
+/// Values that shouldn't outlive the run, such as obscured ones, are kept in memory instead.
+pub struct SqliteLocalValueProvider {
+    pool: SqlitePool,
+    /// Values that only last for this run, looked up before the stored ones
+    run_values: RwLock<HashMap<(Uuid, String), String>>,
+}
+
+impl SqliteLocalValueProvider {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            run_values: RwLock::default(),
+        }
+    }
+
+    /// Use a value for the rest of this run without saving it to disk
+    pub fn set_run_value(&self, block_id: Uuid, property_name: &str, value: &str) {
+        self.run_values
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((block_id, property_name.to_string()), value.to_string());
+    }
+
+    pub async fn set_value(
+        &self,
+        block_id: Uuid,
+        property_name: &str,
+        value: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO local_values (block_id, property, value) VALUES (?, ?, ?) \
+                ON CONFLICT(block_id, property) DO UPDATE SET value = excluded.value",
+        )
+        .bind(block_id.to_string())
+        .bind(property_name)
+        .bind(value)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl LocalValueProvider for SqliteLocalValueProvider {
+    async fn get_block_local_value(
+        &self,
+        block_id: Uuid,
+        property_name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let run_value = self
+            .run_values
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(block_id, property_name.to_string()))
+            .cloned();
+        if run_value.is_some() {
+            return Ok(run_value);
+        }
+
+        let value = sqlx::query_scalar(
+            "SELECT value FROM local_values WHERE block_id = ? AND property = ?",
+        )
+        .bind(block_id.to_string())
+        .bind(property_name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(value)
+    }
+}
+
+struct BlockContextWrapper(BlockContext);
+
+/// Stores the active context of executed blocks, so a later run can `--resume` from it
+#[derive(Clone)]
+pub struct SqliteContextStorage {
+    pool: SqlitePool,
+    /// Whether stored context is handed back to the document; when `false`, context is
+    /// still saved but every block starts without one
+    restore: bool,
+}
+
+impl SqliteContextStorage {
+    pub fn new(pool: SqlitePool, restore: bool) -> Self {
+        Self { pool, restore }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockContextStorage for SqliteContextStorage {
+    async fn save(
+        &self,
+        document_id: &str,
+        block_id: &Uuid,
+        context: &BlockContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(
+            "INSERT INTO context (document_id, block_id, context) VALUES (?, ?, ?) \
+                ON CONFLICT(document_id, block_id) DO UPDATE SET context = excluded.context",
+        )
+        .bind(document_id)
+        .bind(block_id.to_string())
+        .bind(serde_json::to_string(context)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load(
+        &self,
+        document_id: &str,
+        block_id: &Uuid,
+    ) -> Result<Option<BlockContext>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.restore {
+            return Ok(None);
+        }
+
+        let context: Option<BlockContextWrapper> =
+            sqlx::query_as("SELECT context FROM context WHERE document_id = ? AND block_id = ?")
+                .bind(document_id)
+                .bind(block_id.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(context.map(|c| c.0))
+    }
+
+    async fn delete(
+        &self,
+        document_id: &str,
+        block_id: &Uuid,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM context WHERE document_id = ? AND block_id = ?")
+            .bind(document_id)
+            .bind(block_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_for_document(
+        &self,
+        document_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM context WHERE document_id = ?")
+            .bind(document_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'a> FromRow<'a, SqliteRow> for BlockContextWrapper {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        let json_context = row.get::<String, _>("context");
+        let context: BlockContext =
+            serde_json::from_str(&json_context).map_err(|e| sqlx::Error::ColumnDecode {
+                index: "context".to_string(),
+                source: Box::new(e),
+            })?;
+        Ok(BlockContextWrapper(context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_values_persist_across_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.db");
+        let block_id = Uuid::new_v4();
+
+        let pool = open_state_db_at(path.clone()).await.unwrap();
+        let local_values = SqliteLocalValueProvider::new(pool.clone());
+        local_values
+            .set_value(block_id, "value", "first")
+            .await
+            .unwrap();
+        local_values
+            .set_value(block_id, "value", "second")
+            .await
+            .unwrap();
+
+        let storage = SqliteContextStorage::new(pool.clone(), true);
+        storage
+            .save("doc", &block_id, &BlockContext::new())
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = open_state_db_at(path).await.unwrap();
+        let local_values = SqliteLocalValueProvider::new(pool.clone());
+        assert_eq!(
+            local_values
+                .get_block_local_value(block_id, "value")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("second")
+        );
+        assert!(local_values
+            .get_block_local_value(block_id, "other")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(SqliteContextStorage::new(pool.clone(), false)
+            .load("doc", &block_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let storage = SqliteContextStorage::new(pool, true);
+        assert!(storage.load("doc", &block_id).await.unwrap().is_some());
+        storage.delete_for_document("doc").await.unwrap();
+        assert!(storage.load("doc", &block_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_values_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let block_id = Uuid::new_v4();
+
+        let pool = open_state_db_at(path.clone()).await.unwrap();
+        let local_values = SqliteLocalValueProvider::new(pool.clone());
+        local_values
+            .set_value(block_id, "value", "stored")
+            .await
+            .unwrap();
+        local_values.set_run_value(block_id, "value", "secret");
+        assert_eq!(
+            local_values
+                .get_block_local_value(block_id, "value")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("secret")
+        );
+        pool.close().await;
+
+        let pool = open_state_db_at(path).await.unwrap();
+        let local_values = SqliteLocalValueProvider::new(pool);
+        assert_eq!(
+            local_values
+                .get_block_local_value(block_id, "value")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("stored")
+        );
+    }
+}
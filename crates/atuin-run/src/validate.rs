@@ -0,0 +1,640 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use atuin_desktop_runtime::{
+    blocks::{Block, KNOWN_UNSUPPORTED_BLOCKS},
+    client::RunbookContentLoader,
+    document::flatten_document,
+};
+use minijinja::Environment;
+use uuid::Uuid;
+
+use crate::{
+    app::ValidateArgs,
+    config::Config,
+    runtime::FileRunbookLoader,
+    workspace::{find_runbook_files, Workspace},
+};
+
+/// Something wrong with a runbook that would stop it from running as intended
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Problem {
+    #[error("Could not read runbook: {0}")]
+    Unreadable(String),
+
+    #[error("Runbook id is missing or not a valid UUID")]
+    InvalidRunbookId,
+
+    #[error("Runbook has no content array")]
+    MissingContent,
+
+    #[error("Block id is missing or not a valid UUID")]
+    InvalidBlockId,
+
+    #[error("Unknown block type '{0}'")]
+    UnknownBlockType(String),
+
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
+
+    #[error("Block name '{0}' is also used by block {1}")]
+    DuplicateName(String, Uuid),
+
+    #[error("Template syntax error in '{0}': {1}")]
+    TemplateSyntax(String, String),
+
+    #[error("'{0}' uses var.{1}, which no earlier block defines")]
+    UndefinedVar(String, String),
+
+    #[error("'{0}' uses var.{1}, which no earlier block defines unless a script sets it through $ATUIN_OUTPUT_VARS")]
+    PossiblyUndefinedVar(String, String),
+
+    #[error("Sub-runbook {0} could not be loaded: {1}")]
+    UnresolvedSubRunbook(String, String),
+}
+
+impl Problem {
+    /// Whether the problem might not be one, and shouldn't fail validation
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Problem::PossiblyUndefinedVar(..))
+    }
+}
+
+/// A problem, located at the block it was found in
+#[derive(Debug)]
+pub struct Finding {
+    /// ID (or position, if the ID is invalid) and type of the block, if the problem is in one
+    pub block: Option<String>,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.problem.is_warning() {
+            write!(f, "warning: ")?;
+        }
+        match &self.block {
+            Some(block) => write!(f, "{block}: {}", self.problem),
+            None => write!(f, "{}", self.problem),
+        }
+    }
+}
+
+/// Validate every runbook named on the command line, printing what was found
+///
+/// Returns the total number of problems, not counting warnings.
+pub async fn run(args: &ValidateArgs, config: &Config) -> eyre::Result<usize> {
+    let mut files = Vec::new();
+    for path in &args.paths {
+        if path.is_dir() {
+            files.extend(find_runbook_files(path).await?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let defined = args.vars.iter().cloned().collect::<HashSet<_>>();
+    let mut total = 0;
+
+    for file in &files {
+        let findings = validate_file(file, &defined, config).await;
+        for finding in &findings {
+            println!("{}: {finding}", file.display());
+        }
+        total += findings.iter().filter(|f| !f.problem.is_warning()).count();
+    }
+
+    if total == 0 {
+        println!("✓ {} runbook(s) OK", files.len());
+    } else {
+        println!("✗ {total} problem(s) in {} runbook(s)", files.len());
+    }
+
+    Ok(total)
+}
+
+async fn validate_file(path: &Path, defined: &HashSet<String>, config: &Config) -> Vec<Finding> {
+    let json = match read_runbook_json(path).await {
+        Ok(json) => json,
+        Err(e) => {
+            return vec![Finding {
+                block: None,
+                problem: Problem::Unreadable(e),
+            }]
+        }
+    };
+
+    let workspace = Workspace::discover(path).await.unwrap_or_else(|e| {
+        tracing::warn!("Ignoring workspace of {}: {e}", path.display());
+        None
+    });
+    let loader =
+        FileRunbookLoader::from_runbook_path(path, config.hub_client()).with_workspace(workspace);
+
+    Validator::new(defined.clone())
+        .validate(&json, &loader)
+        .await
+}
+
+async fn read_runbook_json(path: &Path) -> Result<serde_json::Value, String> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let yaml_value: serde_yaml::Value =
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())?;
+    serde_yaml::from_value(yaml_value).map_err(|e| e.to_string())
+}
+
+struct Validator {
+    /// Variables defined so far, in document order
+    defined: HashSet<String>,
+    /// Whether an earlier block writes to `$ATUIN_OUTPUT_VARS`, and so may define variables
+    /// that can't be found by reading it
+    writes_output_vars: bool,
+    /// Block name -> first block using it
+    names: HashMap<String, Uuid>,
+    findings: Vec<Finding>,
+}
+
+impl Validator {
+    fn new(defined: HashSet<String>) -> Self {
+        Self {
+            defined,
+            writes_output_vars: false,
+            names: HashMap::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    async fn validate(
+        mut self,
+        runbook: &serde_json::Value,
+        loader: &dyn RunbookContentLoader,
+    ) -> Vec<Finding> {
+        if runbook
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|v| Uuid::parse_str(v).ok())
+            .is_none()
+        {
+            self.report(None, Problem::InvalidRunbookId);
+        }
+
+        let Some(content) = runbook.get("content").and_then(|v| v.as_array()) else {
+            self.report(None, Problem::MissingContent);
+            return self.findings;
+        };
+
+        self.validate_blocks(content, loader).await;
+        self.findings
+    }
+
+    async fn validate_blocks(
+        &mut self,
+        content: &[serde_json::Value],
+        loader: &dyn RunbookContentLoader,
+    ) {
+        for (index, block_data) in flatten_document(content).iter().enumerate() {
+            self.validate_block(index, block_data, loader).await;
+        }
+    }
+
+    async fn validate_block(
+        &mut self,
+        index: usize,
+        block_data: &serde_json::Value,
+        loader: &dyn RunbookContentLoader,
+    ) {
+        let block_type = block_data
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("<no type>");
+        let id = block_data
+            .get("id")
+            .and_then(|v| v.as_str())
+            .and_then(|v| Uuid::parse_str(v).ok());
+        let location = match id {
+            Some(id) => format!("block {id} ({block_type})"),
+            None => format!("block #{} ({block_type})", index + 1),
+        };
+
+        if id.is_none() {
+            self.report(Some(&location), Problem::InvalidBlockId);
+        }
+
+        if KNOWN_UNSUPPORTED_BLOCKS.contains(&block_type) {
+            return;
+        }
+
+        let block = match Block::from_document(block_data) {
+            Ok(block) => block,
+            // An invalid id has already been reported
+            Err(_) if id.is_none() => return,
+            Err(e) if e.starts_with("Unknown block type") => {
+                self.report(
+                    Some(&location),
+                    Problem::UnknownBlockType(block_type.to_string()),
+                );
+                return;
+            }
+            Err(e) => {
+                self.report(Some(&location), Problem::InvalidBlock(e));
+                return;
+            }
+        };
+
+        // Only names given in the document count; variable blocks share names on purpose,
+        // and some blocks fall back to a default name
+        let name = block_data
+            .get("props")
+            .and_then(|p| p.get("name"))
+            .and_then(|v| v.as_str())
+            .filter(|name| !name.is_empty() && *name == block.name());
+        if let Some(name) = name {
+            match self.names.get(name) {
+                Some(first) => {
+                    let problem = Problem::DuplicateName(name.to_string(), *first);
+                    self.report(Some(&location), problem);
+                }
+                None => {
+                    self.names.insert(name.to_string(), block.id());
+                }
+            }
+        }
+
+        if let Some(props) = block_data.get("props") {
+            for template in templates_in(props) {
+                self.check_template(&location, template);
+            }
+        }
+
+        match &block {
+            Block::Var(var) => {
+                self.defined.insert(var.name.clone());
+            }
+            Block::LocalVar(local_var) => {
+                self.defined.insert(local_var.name.clone());
+            }
+            Block::Dropdown(dropdown) => {
+                self.defined.insert(dropdown.name.clone());
+            }
+            Block::SshTunnel(ssh_tunnel) => {
+                self.defined.insert(ssh_tunnel.output_variable.clone());
+            }
+            Block::Assert(assert) => {
+                // Expressions written without `{{ }}` aren't among the templates checked
+                // above, so check them as the templates they stand for
+                for line in assert.assertions.lines().map(str::trim) {
+                    if !line.is_empty() && !line.starts_with('#') && !line.contains("{{") {
+                        self.check_template(&location, &format!("{{{{ {line} }}}}"));
+                    }
+                }
+            }
+            Block::WaitUntil(wait_until) => {
+                let condition = wait_until.condition.trim();
+                if !condition.is_empty() && !condition.contains("{{") {
+                    self.check_template(&location, &format!("{{{{ {condition} }}}}"));
+                }
+            }
+            Block::Script(script) => self.output_vars_written(&script.code),
+            Block::Terminal(terminal) => self.output_vars_written(&terminal.code),
+            Block::Foreach(foreach) => {
+                // Nested blocks run in a scope of their own, so the variables they define
+                // aren't visible after the loop
+                let defined = self.defined.clone();
+                let writes_output_vars = self.writes_output_vars;
+                Box::pin(self.validate_blocks(&foreach.children, loader)).await;
+                self.defined = defined;
+                self.writes_output_vars = writes_output_vars;
+            }
+            Block::SubRunbook(sub_runbook) => {
+                let reference = sub_runbook.runbook_ref.display_id();
+                match loader.load_runbook(&sub_runbook.runbook_ref).await {
+                    Ok(loaded) if sub_runbook.export_vars => {
+                        self.defined.extend(defined_vars(&loaded.content));
+                    }
+                    Ok(_) => {}
+                    Err(e) => self.report(
+                        Some(&location),
+                        Problem::UnresolvedSubRunbook(reference, e.to_string()),
+                    ),
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(output) = output_variable(block_data) {
+            self.defined.insert(output.to_string());
+        }
+    }
+
+    fn check_template(&mut self, location: &str, template: &str) {
+        let env = Environment::new();
+        let compiled = match env.template_from_str(template) {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                let problem = Problem::TemplateSyntax(summarize(template), e.to_string());
+                self.report(Some(location), problem);
+                return;
+            }
+        };
+
+        let mut undefined = compiled
+            .undeclared_variables(true)
+            .into_iter()
+            .filter_map(|name| {
+                name.strip_prefix("var.")
+                    .map(|var| var.split('.').next().unwrap_or(var).to_string())
+            })
+            .filter(|var| !self.defined.contains(var))
+            .collect::<Vec<_>>();
+        undefined.sort();
+        undefined.dedup();
+
+        for var in undefined {
+            let problem = if self.writes_output_vars {
+                Problem::PossiblyUndefinedVar(summarize(template), var)
+            } else {
+                Problem::UndefinedVar(summarize(template), var)
+            };
+            self.report(Some(location), problem);
+        }
+    }
+
+    /// Define the variables a script writes to `$ATUIN_OUTPUT_VARS`
+    fn output_vars_written(&mut self, code: &str) {
+        if !code.contains(OUTPUT_VARS) {
+            return;
+        }
+
+        self.writes_output_vars = true;
+        self.defined.extend(output_var_names(code));
+    }
+
+    fn report(&mut self, block: Option<&str>, problem: Problem) {
+        self.findings.push(Finding {
+            block: block.map(|b| b.to_string()),
+            problem,
+        });
+    }
+}
+
+/// Name of the file scripts write variables to
+const OUTPUT_VARS: &str = "ATUIN_OUTPUT_VARS";
+
+/// Names of the variables a script writes on lines that mention `$ATUIN_OUTPUT_VARS`, such as
+/// `echo "name=value" >> $ATUIN_OUTPUT_VARS`, `name<<EOF` or `name:json=[...]`
+///
+/// Variables written some other way, like from the body of a heredoc, aren't found.
+fn output_var_names(code: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for line in code.lines().filter(|line| line.contains(OUTPUT_VARS)) {
+        let mut rest = line;
+        let mut previous = None;
+        while let Some(c) = rest.chars().next() {
+            let starts_word =
+                previous.is_none_or(|p: char| p.is_whitespace() || p == '"' || p == '\'');
+            let name_len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+
+            if starts_word && (c.is_ascii_alphabetic() || c == '_') {
+                let (name, after) = rest.split_at(name_len);
+                let assigns = ["=", "<<", ":json="].iter().any(|op| after.starts_with(op));
+                if assigns && name != OUTPUT_VARS {
+                    names.push(name.to_string());
+                }
+            }
+
+            let step = if name_len > 0 { name_len } else { c.len_utf8() };
+            previous = rest[..step].chars().last();
+            rest = &rest[step..];
+        }
+    }
+    names
+}
+
+/// Every string in a block's props that contains template markers
+fn templates_in(value: &serde_json::Value) -> Vec<&str> {
+    match value {
+        serde_json::Value::String(s) if s.contains("{{") || s.contains("{%") => vec![s.as_str()],
+        serde_json::Value::Array(items) => items.iter().flat_map(templates_in).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(templates_in).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Name of the variable a block stores its output in, if any
+fn output_variable(block_data: &serde_json::Value) -> Option<&str> {
+    block_data
+        .get("props")
+        .and_then(|p| p.get("outputVariable"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+}
+
+/// Variables a runbook defines, for sub-runbooks that export them to their parent
+fn defined_vars(content: &[serde_json::Value]) -> Vec<String> {
+    flatten_document(content)
+        .iter()
+        .filter_map(|block_data| {
+            let name = match Block::from_document(block_data).ok()? {
+                Block::Var(var) => var.name,
+                Block::LocalVar(local_var) => local_var.name,
+                Block::Dropdown(dropdown) => dropdown.name,
+                _ => return output_variable(block_data).map(|s| s.to_string()),
+            };
+            Some(name)
+        })
+        .collect()
+}
+
+/// First line of a template, shortened for display
+fn summarize(template: &str) -> String {
+    let line = template.lines().next().unwrap_or_default();
+    if line.chars().count() > 40 {
+        format!("{}…", line.chars().take(40).collect::<String>())
+    } else if template.contains('\n') {
+        format!("{line}…")
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atuin_desktop_runtime::client::{LoadedRunbook, RunbookLoadError, SubRunbookRef};
+    use serde_json::json;
+
+    struct NoRunbooks;
+
+    #[async_trait::async_trait]
+    impl RunbookContentLoader for NoRunbooks {
+        async fn load_runbook(
+            &self,
+            runbook_ref: &SubRunbookRef,
+        ) -> Result<LoadedRunbook, RunbookLoadError> {
+            Err(RunbookLoadError::NotFound {
+                runbook_id: runbook_ref.display_id(),
+            })
+        }
+    }
+
+    fn script(name: &str, code: &str) -> serde_json::Value {
+        json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "script",
+            "props": { "name": name, "code": code, "interpreter": "bash" }
+        })
+    }
+
+    async fn validate(content: Vec<serde_json::Value>) -> Vec<Problem> {
+        let runbook = json!({ "id": Uuid::new_v4().to_string(), "content": content });
+        Validator::new(HashSet::new())
+            .validate(&runbook, &NoRunbooks)
+            .await
+            .into_iter()
+            .map(|finding| finding.problem)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_valid_runbook() {
+        let problems = validate(vec![
+            json!({ "id": Uuid::new_v4().to_string(), "type": "paragraph", "props": {} }),
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "region", "value": "eu-west-1" }
+            }),
+            script("deploy", "deploy --region {{ var.region }} {{ env.HOME }}"),
+        ])
+        .await;
+
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[tokio::test]
+    async fn test_checks_assert_expressions() {
+        let problems = validate(vec![json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "assert",
+            "props": {
+                "name": "smoke",
+                "assertions": "{{ env.HOME != '' }}\nvar.region == 'eu-west-1'\nvar.count >"
+            }
+        })])
+        .await;
+
+        assert!(matches!(
+            &problems[0],
+            Problem::UndefinedVar(_, var) if var == "region"
+        ));
+        assert!(matches!(&problems[1], Problem::TemplateSyntax(_, _)));
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_checks_blocks_nested_in_loops() {
+        let mut foreach = json!({
+            "id": Uuid::new_v4().to_string(),
+            "type": "foreach",
+            "props": { "name": "each host", "items": "{{ var.hosts }}" }
+        });
+        foreach["children"] = json!([
+            {
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "host", "value": "{{ item }}" }
+            },
+            script("ping", "ping -c1 {{ var.host }} {{ var.port }}"),
+        ]);
+
+        let problems = validate(vec![
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "hosts", "value": "web-1" }
+            }),
+            foreach,
+            script("after", "echo {{ var.host }}"),
+        ])
+        .await;
+
+        assert!(matches!(
+            &problems[0],
+            Problem::UndefinedVar(_, var) if var == "port"
+        ));
+        assert!(matches!(
+            &problems[1],
+            Problem::UndefinedVar(_, var) if var == "host"
+        ));
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_output_vars_define_variables() {
+        let problems = validate(vec![
+            script(
+                "release",
+                "echo \"version=1.2\" >> $ATUIN_OUTPUT_VARS\n\
+                 echo 'hosts:json=[\"web-1\"]' >> \"$ATUIN_OUTPUT_VARS\"\n\
+                 echo \"notes<<EOF\" >> $ATUIN_OUTPUT_VARS\n\
+                 cat notes.txt >> $ATUIN_OUTPUT_VARS\n\
+                 echo \"EOF\" >> $ATUIN_OUTPUT_VARS",
+            ),
+            script(
+                "deploy",
+                "deploy {{ var.version }} {{ var.hosts }} {{ var.notes }} {{ var.region }}",
+            ),
+        ])
+        .await;
+
+        assert!(matches!(
+            &problems[0],
+            Problem::PossiblyUndefinedVar(_, var) if var == "region"
+        ));
+        assert!(problems[0].is_warning());
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reports_problems() {
+        let problems = validate(vec![
+            script("uses-later", "echo {{ var.region }}"),
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "var",
+                "props": { "name": "region", "value": "eu-west-1" }
+            }),
+            json!({ "id": "not-a-uuid", "type": "script", "props": {} }),
+            json!({ "id": Uuid::new_v4().to_string(), "type": "mystery", "props": {} }),
+            script("build", "make"),
+            script("build", "echo {{ var.region "),
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "type": "sub-runbook",
+                "props": { "name": "child", "runbookPath": "child.atrb" }
+            }),
+        ])
+        .await;
+
+        assert!(matches!(
+            &problems[0],
+            Problem::UndefinedVar(_, var) if var == "region"
+        ));
+        assert_eq!(problems[1], Problem::InvalidBlockId);
+        assert_eq!(
+            problems[2],
+            Problem::UnknownBlockType("mystery".to_string())
+        );
+        assert!(matches!(&problems[3], Problem::DuplicateName(name, _) if name == "build"));
+        assert!(matches!(&problems[4], Problem::TemplateSyntax(_, _)));
+        assert!(matches!(&problems[5], Problem::UnresolvedSubRunbook(_, _)));
+        assert_eq!(problems.len(), 6);
+    }
+}
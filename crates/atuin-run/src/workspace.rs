@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use atuin_desktop_runtime::templates::WorkspaceTemplateState;
+use serde::Deserialize;
+
+/// Name of the manifest file that marks the root of an offline workspace
+const WORKSPACE_MANIFEST: &str = "atuin.toml";
+
+type Result<T> = std::result::Result<T, WorkspaceError>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WorkspaceError {
+    #[error("Failed to read workspace: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Failed to parse workspace manifest {0}: {1}")]
+    ManifestParseError(PathBuf, toml::de::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceConfig {
+    workspace: WorkspaceConfigDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceConfigDetails {
+    id: String,
+    name: String,
+}
+
+/// An offline workspace, rooted at the directory containing `atuin.toml`
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub root: PathBuf,
+    /// Runbook ID -> path of the `.atrb` file that defines it
+    runbooks: HashMap<String, PathBuf>,
+}
+
+impl Workspace {
+    /// Find the workspace containing `path` by walking up to the nearest `atuin.toml`
+    ///
+    /// Returns `None` if no ancestor directory contains a workspace manifest.
+    pub async fn discover(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = std::path::absolute(path.as_ref())?;
+
+        for dir in path.ancestors() {
+            let manifest = dir.join(WORKSPACE_MANIFEST);
+            if manifest.is_file() {
+                return Self::load(dir).await.map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Load the workspace rooted at `root`, indexing every runbook inside it
+    pub async fn load(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let manifest_path = root.join(WORKSPACE_MANIFEST);
+        let manifest = tokio::fs::read_to_string(&manifest_path).await?;
+        let config: WorkspaceConfig = toml::from_str(&manifest)
+            .map_err(|e| WorkspaceError::ManifestParseError(manifest_path, e))?;
+
+        let runbooks = index_runbooks(&root).await?;
+        tracing::debug!(
+            "Loaded workspace {} ({}) with {} runbooks",
+            config.workspace.name,
+            root.display(),
+            runbooks.len()
+        );
+
+        Ok(Self {
+            id: config.workspace.id,
+            name: config.workspace.name,
+            root,
+            runbooks,
+        })
+    }
+
+    /// Get the path of the runbook with the given ID, if it belongs to this workspace
+    pub fn runbook_path(&self, id: &str) -> Option<&Path> {
+        self.runbooks.get(id).map(|p| p.as_path())
+    }
+
+    pub fn template_state(&self) -> WorkspaceTemplateState {
+        WorkspaceTemplateState {
+            root: Some(self.root.to_string_lossy().to_string()),
+        }
+    }
+}
+
+/// Walk the workspace and map runbook IDs to file paths
+async fn index_runbooks(root: &Path) -> Result<HashMap<String, PathBuf>> {
+    let mut runbooks: HashMap<String, PathBuf> = HashMap::new();
+
+    for path in find_runbook_files(root).await? {
+        let Some(id) = read_runbook_id(&path).await else {
+            tracing::warn!("Skipping invalid runbook file {}", path.display());
+            continue;
+        };
+
+        if let Some(existing) = runbooks.get(&id) {
+            tracing::warn!(
+                "Runbook ID {id} is defined by both {} and {}; using the former",
+                existing.display(),
+                path.display()
+            );
+            continue;
+        }
+
+        runbooks.insert(id, path);
+    }
+
+    Ok(runbooks)
+}
+
+/// Find every `.atrb` file under `root`, skipping hidden directories
+pub async fn find_runbook_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let file_type = entry.file_type().await?;
+
+            if file_type.is_dir() {
+                if !file_name.starts_with('.') {
+                    pending.push(path);
+                }
+                continue;
+            }
+
+            if file_name.ends_with(".atrb") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+async fn read_runbook_id(path: &Path) -> Option<String> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content).ok()?;
+    yaml_value
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn write_runbook(path: &Path, id: &str) {
+        tokio::fs::create_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+        let content = format!("id: {id}\nname: Test\nversion: 1\ncontent: []\n");
+        tokio::fs::write(path, content).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_discover_walks_up_to_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        tokio::fs::write(
+            root.path().join("atuin.toml"),
+            "[workspace]\nid = \"ws-id\"\nname = \"Ops\"\n",
+        )
+        .await
+        .unwrap();
+
+        let nested = root.path().join("deploy").join("prod.atrb");
+        write_runbook(&nested, "runbook-1").await;
+        write_runbook(&root.path().join("shared").join("util.atrb"), "runbook-2").await;
+        write_runbook(&root.path().join(".git").join("ignored.atrb"), "runbook-3").await;
+
+        let workspace = Workspace::discover(&nested).await.unwrap().unwrap();
+
+        assert_eq!(workspace.id, "ws-id");
+        assert_eq!(workspace.name, "Ops");
+        assert_eq!(workspace.root, root.path());
+        assert_eq!(workspace.runbook_path("runbook-1"), Some(nested.as_path()));
+        assert_eq!(
+            workspace.runbook_path("runbook-2"),
+            Some(root.path().join("shared").join("util.atrb").as_path())
+        );
+        assert_eq!(workspace.runbook_path("runbook-3"), None);
+        assert_eq!(
+            workspace.template_state().root,
+            Some(root.path().to_string_lossy().to_string())
+        );
+    }
+}
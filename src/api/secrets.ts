@@ -0,0 +1,21 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Runbook secrets are stored in the keychain under this service, with the secret name
+// as the user; templates read them with {{ secret("name") }}
+const RUNBOOK_SECRETS_SERVICE = "sh.atuin.runbooks.secrets";
+
+export async function saveRunbookSecret(name: string, value: string): Promise<void> {
+  await invoke("save_password", { service: RUNBOOK_SECRETS_SERVICE, user: name, value });
+}
+
+export async function hasRunbookSecret(name: string): Promise<boolean> {
+  const value = await invoke<string | null>("load_password", {
+    service: RUNBOOK_SECRETS_SERVICE,
+    user: name,
+  });
+  return value !== null;
+}
+
+export async function deleteRunbookSecret(name: string): Promise<void> {
+  await invoke("delete_password", { service: RUNBOOK_SECRETS_SERVICE, user: name });
+}
@@ -621,6 +621,12 @@ const RunbookSettings = () => {
     Settings.scriptShell,
     Settings.scriptShell,
   );
+  const [runConcurrency, setRunConcurrency, runConcurrencyLoading] = useSettingsState(
+    "run_concurrency",
+    1,
+    Settings.runConcurrency,
+    Settings.runConcurrency,
+  );
   const [prometheusUrl, setPrometheusUrl, urlLoading] = useSettingsState(
     "prometheus_url",
     "http://localhost:9090",
@@ -636,12 +642,27 @@ const RunbookSettings = () => {
     fontSizeLoading ||
     shellLoading ||
     scriptShellLoading ||
+    runConcurrencyLoading ||
     !fonts
   )
     return <Spinner />;
 
   return (
     <>
+      <Card shadow="sm">
+        <CardBody className="flex flex-col gap-4">
+          <h2 className="text-xl font-semibold">Running runbooks</h2>
+          <SettingInput
+            type="number"
+            label="Blocks at once"
+            value={String(runConcurrency)}
+            onChange={(value) => setRunConcurrency(Math.max(1, parseInt(value) || 1))}
+            placeholder="1"
+            description="When running a whole runbook, blocks that don't depend on each other run at the same time, up to this many. 1 runs them one after another"
+          />
+        </CardBody>
+      </Card>
+
       <Card shadow="sm">
         <CardBody className="flex flex-col gap-4">
           <h2 className="text-xl font-semibold">Terminal</h2>
@@ -41,6 +41,8 @@ import { insertScript } from "@/components/runbooks/editor/blocks/Script/Script"
 import { insertPrometheus } from "@/components/runbooks/editor/blocks/Prometheus/Prometheus";
 import { insertEditor } from "@/components/runbooks/editor/blocks/Editor/Editor";
 import { insertSshConnect } from "@/components/runbooks/editor/blocks/ssh/SshConnect";
+import { insertSshTunnel } from "@/components/runbooks/editor/blocks/SshTunnel";
+import { insertSftp } from "@/components/runbooks/editor/blocks/Sftp";
 import { insertHostSelect } from "@/components/runbooks/editor/blocks/Host";
 import { insertLocalVar } from "@/components/runbooks/editor/blocks/LocalVar";
 import { insertMarkdownRender } from "@/components/runbooks/editor/blocks/MarkdownRender";
@@ -66,6 +68,9 @@ import {
 } from "@/utils/scroll-position";
 import { insertDropdown } from "./blocks/Dropdown/Dropdown";
 import { insertPause } from "./blocks/Pause";
+import { insertAssert } from "./blocks/Assert";
+import { insertWaitUntil } from "./blocks/WaitUntil";
+import { insertForeach } from "./blocks/Foreach";
 import { insertSubRunbook } from "./blocks/SubRunbook";
 import { insertTerminal } from "@/lib/blocks/terminal";
 import { insertKubernetes } from "@/lib/blocks/kubernetes";
@@ -665,6 +670,9 @@ export default function Editor({
                     insertLocalDirectory(editor as any),
                     insertDropdown(schema)(editor),
                     insertPause(schema)(editor),
+                    insertAssert(schema)(editor),
+                    insertWaitUntil(schema)(editor),
+                    insertForeach(schema)(editor),
                     insertSubRunbook(editor as any),
 
                     // Content group
@@ -689,6 +697,8 @@ export default function Editor({
                     // Network group
                     insertHttp(schema)(editor),
                     insertSshConnect(schema)(editor),
+                    insertSshTunnel(schema)(editor),
+                    insertSftp(schema)(editor),
                     insertHostSelect(schema)(editor),
 
                     // Misc group
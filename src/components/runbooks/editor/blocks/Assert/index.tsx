@@ -0,0 +1,190 @@
+import { useCallback, useState } from "react";
+import { CircleCheckIcon, CircleXIcon, ListChecksIcon } from "lucide-react";
+import { Input, Textarea } from "@heroui/react";
+import { createReactBlockSpec } from "@blocknote/react";
+import undent from "undent";
+import AIBlockRegistry from "@/lib/ai/block_registry";
+import { exportPropMatter } from "@/lib/utils";
+import PlayButton from "@/lib/blocks/common/PlayButton";
+import {
+  GenericBlockOutput,
+  useBlockExecution,
+  useBlockOutput,
+} from "@/lib/hooks/useDocumentBridge";
+import { AssertExecutionOutput } from "@/rs-bindings/AssertExecutionOutput";
+import { AssertionResult } from "@/rs-bindings/AssertionResult";
+import track_event from "@/tracking";
+
+interface AssertProps {
+  id: string;
+  name: string;
+  assertions: string;
+  isEditable: boolean;
+  onChange: (props: Partial<Pick<AssertProps, "name" | "assertions">>) => void;
+}
+
+const Assert = ({ id, name, assertions, isEditable, onChange }: AssertProps) => {
+  const execution = useBlockExecution(id);
+  const [results, setResults] = useState<AssertionResult[] | null>(null);
+
+  const handleOutput = useCallback((output: GenericBlockOutput<AssertExecutionOutput>) => {
+    if (output.lifecycle?.type === "started") {
+      setResults(null);
+    }
+    if (output.object?.results) {
+      setResults(output.object.results);
+    }
+  }, []);
+  useBlockOutput<AssertExecutionOutput>(id, handleOutput);
+
+  return (
+    <div className="flex flex-col w-full bg-gradient-to-r from-emerald-50 to-teal-50 dark:from-slate-800 dark:to-teal-950 rounded-lg p-3 gap-2 border border-emerald-200 dark:border-emerald-900 shadow-sm hover:shadow-md transition-all duration-200">
+      <div className="flex flex-row items-center gap-2">
+        <PlayButton
+          eventName="runbooks.block.execute"
+          eventProps={{ type: "assert" }}
+          isRunning={execution.isRunning}
+          cancellable={false}
+          onPlay={() => execution.execute()}
+          tooltip="Check assertions"
+        />
+
+        <span className="text-xs font-medium text-emerald-700 dark:text-emerald-300">Assert</span>
+
+        <Input
+          placeholder="Name"
+          value={name}
+          onValueChange={(value) => onChange({ name: value })}
+          size="sm"
+          className="flex-1"
+          isDisabled={!isEditable}
+          classNames={{ inputWrapper: "h-8 min-h-8" }}
+        />
+      </div>
+
+      <Textarea
+        placeholder={"One expression per line, e.g.\n{{ doc.named.health.output.status == 200 }}"}
+        value={assertions}
+        onValueChange={(value) => onChange({ assertions: value })}
+        autoComplete="off"
+        autoCapitalize="off"
+        autoCorrect="off"
+        spellCheck="false"
+        minRows={2}
+        size="sm"
+        className="font-mono text-sm"
+        isDisabled={!isEditable}
+      />
+
+      {results && (
+        <div className="flex flex-col gap-1">
+          {results.map((result, index) => (
+            <div key={index} className="flex flex-row items-start gap-2 text-xs">
+              {result.passed ? (
+                <CircleCheckIcon size={14} className="text-success shrink-0 mt-0.5" />
+              ) : (
+                <CircleXIcon size={14} className="text-danger shrink-0 mt-0.5" />
+              )}
+              {result.message ? (
+                <pre className="text-danger-600 whitespace-pre-wrap select-text">
+                  {result.message}
+                </pre>
+              ) : (
+                <code>{result.expression}</code>
+              )}
+            </div>
+          ))}
+        </div>
+      )}
+      {execution.isError && !results && (
+        <span className="text-xs text-danger-600">{execution.error}</span>
+      )}
+    </div>
+  );
+};
+
+export default createReactBlockSpec(
+  {
+    type: "assert",
+    propSchema: {
+      name: { default: "Assert" },
+      assertions: { default: "" },
+    },
+    content: "none",
+  },
+  {
+    toExternalHTML: ({ block }) => {
+      const propMatter = exportPropMatter("assert", block.props, ["name", "assertions"]);
+      return (
+        <pre lang="assert">
+          <code>{propMatter}</code>
+        </pre>
+      );
+    },
+    // @ts-ignore
+    render: ({ block, editor }) => {
+      const onChange = (props: Record<string, string>): void => {
+        editor.updateBlock(block, {
+          // @ts-ignore
+          props: { ...block.props, ...props },
+        });
+      };
+
+      return (
+        <Assert
+          id={block.id}
+          name={block.props.name}
+          assertions={block.props.assertions}
+          isEditable={editor.isEditable}
+          onChange={onChange}
+        />
+      );
+    },
+  },
+);
+
+// Component to insert this block from the editor menu
+export const insertAssert = (schema: any) => (editor: typeof schema.BlockNoteEditor) => ({
+  title: "Assert",
+  subtext: "Check that expressions about earlier blocks are true",
+  onItemClick: async () => {
+    track_event("runbooks.block.create", { type: "assert" });
+
+    editor.insertBlocks(
+      [
+        {
+          type: "assert",
+          props: {},
+        },
+      ],
+      editor.getTextCursorPosition().block.id,
+      "before",
+    );
+  },
+  icon: <ListChecksIcon size={18} />,
+  aliases: ["assertion", "check", "test", "expect"],
+  group: "Execute",
+});
+
+AIBlockRegistry.getInstance().addBlock({
+  typeName: "assert",
+  friendlyName: "Assert",
+  shortDescription: "Fails unless every one of its expressions is true.",
+  description: undent`
+    Assert blocks check MiniJinja expressions against variables, environment and the outputs of earlier blocks, and fail if any of them is false. When a comparison fails, the values on both sides are shown. Use them to turn a runbook into a smoke test.
+
+    The available props are:
+    - name (string): Name of the block
+    - assertions (string): Expressions to check, one per line. Each may be wrapped in {{ }} or not. Blank lines and lines starting with # are ignored
+
+    Earlier blocks are referenced by name through doc.named, for example doc.named.health.output.status for an HTTP block named "health".
+
+    Example: {
+      "type": "assert",
+      "props": {
+        "name": "API is healthy",
+        "assertions": "{{ doc.named.health.output.status == 200 }}\\n{{ doc.named.health.output.body_json.ready }}"
+      }
+    }
+  `,
+});
@@ -0,0 +1,279 @@
+import {
+  CircleCheckIcon,
+  CircleDashedIcon,
+  CircleMinusIcon,
+  CircleXIcon,
+  LoaderCircleIcon,
+  RepeatIcon,
+} from "lucide-react";
+import { useCallback, useState } from "react";
+import { Input, Select, SelectItem, Switch } from "@heroui/react";
+import { createReactBlockSpec } from "@blocknote/react";
+import undent from "undent";
+import AIBlockRegistry from "@/lib/ai/block_registry";
+import { exportPropMatter } from "@/lib/utils";
+import PlayButton from "@/lib/blocks/common/PlayButton";
+import {
+  GenericBlockOutput,
+  useBlockExecution,
+  useBlockOutput,
+  useBlockState,
+} from "@/lib/hooks/useDocumentBridge";
+import { ForeachState } from "@/rs-bindings/ForeachState";
+import { IterationStatus } from "@/rs-bindings/IterationStatus";
+import track_event from "@/tracking";
+
+// Lines of loop output kept on the block
+const MAX_OUTPUT_LINES = 500;
+
+const EDITABLE_PROPS = ["name", "items", "mode", "concurrency", "failFast"] as const;
+
+type EditableProp = (typeof EDITABLE_PROPS)[number];
+
+interface ForeachProps {
+  id: string;
+  props: Record<EditableProp, string>;
+  hasChildren: boolean;
+  isEditable: boolean;
+  onChange: (props: Partial<Record<EditableProp, string>>) => void;
+}
+
+const StatusIcon = ({ status }: { status: IterationStatus }) => {
+  switch (status) {
+    case "running":
+      return <LoaderCircleIcon size={14} className="text-primary shrink-0 animate-spin" />;
+    case "success":
+      return <CircleCheckIcon size={14} className="text-success shrink-0" />;
+    case "failed":
+      return <CircleXIcon size={14} className="text-danger shrink-0" />;
+    case "cancelled":
+    case "skipped":
+      return <CircleMinusIcon size={14} className="text-default-400 shrink-0" />;
+    default:
+      return <CircleDashedIcon size={14} className="text-default-400 shrink-0" />;
+  }
+};
+
+const Foreach = ({ id, props, hasChildren, isEditable, onChange }: ForeachProps) => {
+  const execution = useBlockExecution(id);
+  const state = useBlockState<ForeachState>(id);
+  const parallel = props.mode === "parallel";
+  const [output, setOutput] = useState<string[]>([]);
+
+  // A line per finished item and, in parallel mode, the nested blocks' output labelled by item
+  const onOutput = useCallback((out: GenericBlockOutput) => {
+    if (out.lifecycle?.type === "started") {
+      setOutput([]);
+      return;
+    }
+    const lines = `${out.stdout ?? ""}${out.stderr ?? ""}`
+      .split("\n")
+      .filter((line) => line.length > 0);
+    if (lines.length > 0) {
+      setOutput((current) => [...current, ...lines].slice(-MAX_OUTPUT_LINES));
+    }
+  }, []);
+  useBlockOutput(id, onOutput);
+
+  return (
+    <div className="flex flex-col w-full bg-gradient-to-r from-violet-50 to-indigo-50 dark:from-slate-800 dark:to-indigo-950 rounded-lg p-3 gap-2 border border-violet-200 dark:border-violet-900 shadow-sm hover:shadow-md transition-all duration-200">
+      <div className="flex flex-row items-center gap-2">
+        <PlayButton
+          eventName="runbooks.block.execute"
+          eventProps={{ type: "foreach" }}
+          isRunning={execution.isRunning}
+          cancellable={true}
+          onPlay={() => execution.execute()}
+          onStop={() => execution.cancel()}
+          tooltip="Run for each item"
+        />
+
+        <span className="text-xs font-medium text-violet-700 dark:text-violet-300">For each</span>
+
+        <Input
+          placeholder="Name"
+          value={props.name}
+          onValueChange={(value) => onChange({ name: value })}
+          size="sm"
+          className="flex-1"
+          isDisabled={!isEditable}
+          classNames={{ inputWrapper: "h-8 min-h-8" }}
+        />
+      </div>
+
+      <Input
+        placeholder="Items, e.g. var.hosts or doc.named.users.output.rows"
+        value={props.items}
+        onValueChange={(value) => onChange({ items: value })}
+        autoComplete="off"
+        autoCorrect="off"
+        spellCheck="false"
+        size="sm"
+        className="font-mono"
+        isDisabled={!isEditable}
+        classNames={{ inputWrapper: "h-8 min-h-8" }}
+      />
+
+      <div className="flex flex-row items-center gap-2">
+        <Select
+          size="sm"
+          className="w-36"
+          selectedKeys={[parallel ? "parallel" : "serial"]}
+          onSelectionChange={(keys: any) => onChange({ mode: keys.currentKey })}
+          isDisabled={!isEditable}
+          aria-label="Mode"
+          classNames={{ trigger: "h-8 min-h-8" }}
+        >
+          <SelectItem key="serial">One at a time</SelectItem>
+          <SelectItem key="parallel">In parallel</SelectItem>
+        </Select>
+        {parallel && (
+          <Input
+            label="At once"
+            labelPlacement="outside-left"
+            value={props.concurrency}
+            onValueChange={(value) => onChange({ concurrency: value })}
+            size="sm"
+            className="w-32"
+            isDisabled={!isEditable}
+          />
+        )}
+        <Switch
+          size="sm"
+          isSelected={props.failFast !== "false"}
+          onValueChange={(value) => onChange({ failFast: value ? "true" : "false" })}
+          isDisabled={!isEditable}
+        >
+          <span className="text-xs">Stop on first failure</span>
+        </Switch>
+      </div>
+
+      {!hasChildren && (
+        <span className="text-xs text-default-500">
+          Indent blocks under this one to run them for each item, with {"{{ item }}"} and{" "}
+          {"{{ loop.index }}"} set.
+        </span>
+      )}
+
+      {state && state.iterations.length > 0 && (
+        <div className="flex flex-col gap-1 max-h-40 overflow-y-auto">
+          {state.iterations.map((iteration, index) => (
+            <div key={index} className="flex flex-row items-start gap-2 text-xs">
+              <StatusIcon status={iteration.status} />
+              <code className="truncate">{iteration.label}</code>
+              {iteration.error && (
+                <span className="text-danger-600 truncate">{iteration.error}</span>
+              )}
+            </div>
+          ))}
+        </div>
+      )}
+      {output.length > 0 && (
+        <pre className="text-xs font-mono max-h-60 overflow-y-auto whitespace-pre-wrap bg-default-100 dark:bg-default-50 rounded p-2">
+          {output.join("\n")}
+        </pre>
+      )}
+      {execution.isError && <span className="text-xs text-danger-600">{execution.error}</span>}
+    </div>
+  );
+};
+
+export default createReactBlockSpec(
+  {
+    type: "foreach",
+    propSchema: {
+      name: { default: "For each" },
+      items: { default: "" },
+      mode: { default: "serial" },
+      concurrency: { default: "4" },
+      failFast: { default: "true" },
+    },
+    content: "none",
+  },
+  {
+    toExternalHTML: ({ block }) => {
+      const propMatter = exportPropMatter("foreach", block.props, [...EDITABLE_PROPS]);
+      return (
+        <pre lang="foreach">
+          <code>{propMatter}</code>
+        </pre>
+      );
+    },
+    // @ts-ignore
+    render: ({ block, editor }) => {
+      const onChange = (props: Record<string, string>): void => {
+        editor.updateBlock(block, {
+          // @ts-ignore
+          props: { ...block.props, ...props },
+        });
+      };
+
+      return (
+        <Foreach
+          id={block.id}
+          props={block.props}
+          hasChildren={block.children.length > 0}
+          isEditable={editor.isEditable}
+          onChange={onChange}
+        />
+      );
+    },
+  },
+);
+
+// Component to insert this block from the editor menu
+export const insertForeach = (schema: any) => (editor: typeof schema.BlockNoteEditor) => ({
+  title: "For Each",
+  subtext: "Run the blocks nested under it once per item of a list",
+  onItemClick: async () => {
+    track_event("runbooks.block.create", { type: "foreach" });
+
+    editor.insertBlocks(
+      [
+        {
+          type: "foreach",
+          props: {},
+        },
+      ],
+      editor.getTextCursorPosition().block.id,
+      "before",
+    );
+  },
+  icon: <RepeatIcon size={18} />,
+  aliases: ["loop", "for", "each", "iterate", "repeat"],
+  group: "Execute",
+});
+
+AIBlockRegistry.getInstance().addBlock({
+  typeName: "foreach",
+  friendlyName: "For Each",
+  shortDescription: "Runs the blocks nested under it once for every item of a list.",
+  description: undent`
+    For Each blocks run their child blocks once per item of a list, such as every host in a variable or every row of a query. Each iteration runs the children in a scope of its own: variables they set don't leak into other iterations or the rest of the runbook. The children only run as part of the loop.
+
+    The available props are:
+    - name (string): Name of the block
+    - items (string): MiniJinja expression for the list, such as var.hosts, doc.named.users.output.rows, doc.named.pods.output.data or doc.named.list.output.stdout. Text is split into its non-empty lines
+    - mode (string): "serial" to run one item at a time, or "parallel"
+    - concurrency (string): How many items run at once in parallel mode, default 4
+    - failFast (string): "true" to stop the remaining items after the first failure, or "false"
+
+    In the children, {{ item }} is the current item and {{ loop.index }} its position, starting at 1; loop.index0, loop.length, loop.first and loop.last are also set. After the loop, doc.named.<name>.output.iterations lists every item with its success, error and the outputs of the named children, as in iterations[0].outputs.deploy.stdout.
+
+    Example: {
+      "type": "foreach",
+      "props": {
+        "name": "Each host",
+        "items": "var.hosts",
+        "mode": "parallel",
+        "concurrency": "4"
+      },
+      "children": [
+        {
+          "type": "script",
+          "props": { "name": "uptime", "code": "ssh {{ item }} uptime" }
+        }
+      ]
+    }
+  `,
+});
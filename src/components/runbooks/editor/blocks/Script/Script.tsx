@@ -6,7 +6,16 @@ import AIBlockRegistry from "@/lib/ai/block_registry";
 import { useMemo, useState, useEffect, useRef, useCallback, useContext } from "react";
 
 import { useStore } from "@/state/store.ts";
-import { Button, Input, Tooltip, addToast } from "@heroui/react";
+import {
+  Button,
+  Chip,
+  Input,
+  Select,
+  SelectItem,
+  Switch,
+  Tooltip,
+  addToast,
+} from "@heroui/react";
 import {
   FileTerminalIcon,
   Eye,
@@ -14,6 +23,7 @@ import {
   TriangleAlertIcon,
   ArrowDownToLineIcon,
   ArrowUpToLineIcon,
+  ServerIcon,
 } from "lucide-react";
 import EditableHeading from "@/components/EditableHeading/index.tsx";
 
@@ -46,10 +56,39 @@ import Xterm, {
 } from "@/components/runbooks/editor/components/Xterm";
 import ResizeHandle from "@/components/common/ResizeHandle";
 import { TabsContext } from "@/routes/root/Tabs";
+import { FanOutUpdate } from "@/rs-bindings/FanOutUpdate";
+import { HostStatus } from "@/rs-bindings/HostStatus";
 
 const MIN_SCRIPT_TERMINAL_ROWS = 5;
 const MAX_SCRIPT_TERMINAL_ROWS = 40;
 
+// Where a fan-out script gets its hosts from; empty runs on the current host
+type HostsSource = "" | "variable" | "inventory" | "sshConfig";
+
+interface FanOutSettings {
+  hostsSource: HostsSource;
+  hosts: string;
+  concurrency: number;
+  failFast: boolean;
+}
+
+const hostsPlaceholders: Record<HostsSource, string> = {
+  "": "",
+  variable: "Variable name, e.g. web_hosts",
+  inventory: "Inventory file, e.g. ~/hosts.ini",
+  sshConfig: "Host pattern, e.g. web-*",
+};
+
+type ChipColor = "default" | "primary" | "success" | "danger" | "warning";
+
+const hostStatusColors: Record<HostStatus, ChipColor> = {
+  running: "primary",
+  success: "success",
+  failed: "danger",
+  cancelled: "warning",
+  skipped: "default",
+};
+
 interface ScriptBlockProps {
   onChange: (val: string) => void;
   setName: (name: string) => void;
@@ -68,6 +107,9 @@ interface ScriptBlockProps {
   terminalRows: number;
   setTerminalRows: (rows: number) => void;
 
+  fanOut: FanOutSettings;
+  setFanOut: (settings: Partial<FanOutSettings>) => void;
+
   script: ScriptBlockType;
 }
 
@@ -88,8 +130,12 @@ const ScriptBlock = ({
   setCollapseCode,
   terminalRows,
   setTerminalRows,
+  fanOut,
+  setFanOut,
 }: ScriptBlockProps) => {
   const [hasRun, setHasRun] = useState<boolean>(false);
+  const [showFanOut, setShowFanOut] = useState<boolean>(!!fanOut.hostsSource);
+  const [hostStatuses, setHostStatuses] = useState<Record<string, HostStatus>>({});
   const xtermRef = useRef<XtermHandle>(null);
   // Track available shells
   const [availableShells, setAvailableShells] = useState<Record<string, boolean>>({});
@@ -147,7 +193,11 @@ const ScriptBlock = ({
 
   const showSpinner = (blockExecution.isStarting || blockExecution.isStopping) && !!sshParent;
 
-  const onBlockOutput = useCallback(async (output: GenericBlockOutput<void>) => {
+  const onBlockOutput = useCallback(async (output: GenericBlockOutput<FanOutUpdate>) => {
+    if (output.object) {
+      const { host, status } = output.object;
+      setHostStatuses((statuses) => ({ ...statuses, [host]: status }));
+    }
     if (output.stdout) {
       xtermRef.current?.write(output.stdout);
     }
@@ -159,9 +209,10 @@ const ScriptBlock = ({
     }
   }, []);
 
-  useBlockOutput<void>(script.id, onBlockOutput);
+  useBlockOutput<FanOutUpdate>(script.id, onBlockOutput);
   useBlockStart(script.id, () => {
     setHasRun(true);
+    setHostStatuses({});
     xtermRef.current?.clear();
     incrementBadge(1);
   });
@@ -346,6 +397,18 @@ const ScriptBlock = ({
                 </Button>
               </Tooltip>
 
+              <Tooltip content={showFanOut ? "Hide host fan-out" : "Run on multiple hosts"}>
+                <Button
+                  onPress={() => setShowFanOut(!showFanOut)}
+                  size="sm"
+                  variant="flat"
+                  color={fanOut.hostsSource ? "primary" : "default"}
+                  isIconOnly
+                >
+                  <ServerIcon size={20} />
+                </Button>
+              </Tooltip>
+
               <Tooltip content={collapseCode ? "Expand code" : "Collapse code"}>
                 <Button
                   onPress={() => setCollapseCode(!collapseCode)}
@@ -363,6 +426,84 @@ const ScriptBlock = ({
             </div>
           </div>
 
+          {showFanOut && (
+            <div className="flex flex-row items-center gap-2 w-full">
+              <Select
+                size="sm"
+                variant="flat"
+                className="w-44"
+                aria-label="Hosts source"
+                selectedKeys={[fanOut.hostsSource || "current"]}
+                onSelectionChange={(keys: any) =>
+                  setFanOut({
+                    hostsSource: keys.currentKey === "current" ? "" : keys.currentKey,
+                  })
+                }
+                isDisabled={!isEditable}
+              >
+                <SelectItem key="current">Current host</SelectItem>
+                <SelectItem key="variable">Variable</SelectItem>
+                <SelectItem key="inventory">Inventory file</SelectItem>
+                <SelectItem key="sshConfig">SSH config</SelectItem>
+              </Select>
+
+              {fanOut.hostsSource && (
+                <>
+                  <Input
+                    size="sm"
+                    variant="flat"
+                    className="flex-1 font-mono"
+                    aria-label="Hosts"
+                    placeholder={hostsPlaceholders[fanOut.hostsSource]}
+                    autoComplete="off"
+                    autoCapitalize="off"
+                    autoCorrect="off"
+                    spellCheck="false"
+                    value={fanOut.hosts}
+                    onValueChange={(val) => setFanOut({ hosts: val })}
+                    isDisabled={!isEditable}
+                  />
+                  <Input
+                    size="sm"
+                    variant="flat"
+                    type="number"
+                    min={1}
+                    className="w-28"
+                    aria-label="Concurrency"
+                    startContent={<span className="text-xs text-default-500">Parallel</span>}
+                    value={String(fanOut.concurrency)}
+                    onValueChange={(val) =>
+                      setFanOut({ concurrency: Math.max(1, Number(val) || 1) })
+                    }
+                    isDisabled={!isEditable}
+                  />
+                  <Tooltip content="Stop all hosts after the first failure">
+                    <div>
+                      <Switch
+                        size="sm"
+                        isSelected={fanOut.failFast}
+                        onValueChange={(value) => setFanOut({ failFast: value })}
+                        isDisabled={!isEditable}
+                      >
+                        <span className="text-xs">Fail fast</span>
+                      </Switch>
+                    </div>
+                  </Tooltip>
+                </>
+              )}
+            </div>
+          )}
+
+          {Object.keys(hostStatuses).length > 0 && (
+            <div className="flex flex-row flex-wrap gap-1 w-full">
+              {Object.entries(hostStatuses).map(([host, status]) => (
+                <Chip key={host} size="sm" variant="flat" color={hostStatusColors[status]}>
+                  {host}
+                </Chip>
+              ))}
+            </div>
+          )}
+
           <div className="flex flex-row gap-2 flex-grow w-full overflow-x-auto">
             <Tooltip
               content={
@@ -476,6 +617,18 @@ export default createReactBlockSpec(
       terminalRows: {
         default: DEFAULT_SCRIPT_TERMINAL_ROWS,
       },
+      hostsSource: {
+        default: "",
+      },
+      hosts: {
+        default: "",
+      },
+      concurrency: {
+        default: 10,
+      },
+      failFast: {
+        default: true,
+      },
     },
     content: "none",
   },
@@ -571,6 +724,12 @@ export default createReactBlockSpec(
         });
       };
 
+      const setFanOut = (settings: Partial<FanOutSettings>) => {
+        editor.updateBlock(block, {
+          props: { ...block.props, ...settings },
+        });
+      };
+
       let dependency = DependencySpec.deserialize(block.props.dependency);
       let script = new ScriptBlockType(
         block.id,
@@ -598,6 +757,13 @@ export default createReactBlockSpec(
           setCollapseCode={setCollapseCode}
           terminalRows={block.props.terminalRows}
           setTerminalRows={setTerminalRows}
+          fanOut={{
+            hostsSource: block.props.hostsSource as HostsSource,
+            hosts: block.props.hosts,
+            concurrency: block.props.concurrency,
+            failFast: block.props.failFast,
+          }}
+          setFanOut={setFanOut}
         />
       );
     },
@@ -649,6 +815,10 @@ AIBlockRegistry.getInstance().addBlock({
     - interpreter (string): The shell interpreter to use (bash, zsh, fish, python3, node, sh)
     - outputVariable (string): Optional variable name to store the script's stdout
     - outputVisible (boolean): Whether to show terminal output. Defaults to true.
+    - hostsSource (string): Set to run the script on several SSH hosts at once: "variable", "inventory" or "sshConfig". Empty runs on the current host
+    - hosts (string): The variable name holding hosts (whitespace/comma separated, or a JSON array), the inventory file path, or the ssh_config Host glob, depending on hostsSource
+    - concurrency (number): Maximum hosts running at once when fanning out. Defaults to 10.
+    - failFast (boolean): Stop the remaining hosts after the first failure. Defaults to true.
 
     NOTE that Script blocks use 'interpreter' instead of 'type' to specify the shell interpreter to use.
 
@@ -662,6 +832,8 @@ AIBlockRegistry.getInstance().addBlock({
 
     Note: outputVariable captures stdout only. Use doc.named['name'].output for stderr or combined.
 
+    When fanning out across hosts, output is keyed by host: output['web-1'].stdout, output['web-1'].exit_code. The outputVariable then holds a JSON object mapping each host to its stdout.
+
     A common issue users run into when using script blocks is that they try to use aliases, environment variables, and other shell features that are only available in interactive shells.
     If a user runs into these issues, you have the following options:
 
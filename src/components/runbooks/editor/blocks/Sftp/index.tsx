@@ -0,0 +1,298 @@
+import { useCallback, useState } from "react";
+import { ArrowDownToLineIcon, ArrowUpFromLineIcon, FileUpIcon } from "lucide-react";
+import { Input, Progress, Select, SelectItem, Textarea } from "@heroui/react";
+import { createReactBlockSpec } from "@blocknote/react";
+import undent from "undent";
+import AIBlockRegistry from "@/lib/ai/block_registry";
+import { exportPropMatter } from "@/lib/utils";
+import PlayButton from "@/lib/blocks/common/PlayButton";
+import ErrorCard from "@/lib/blocks/common/ErrorCard";
+import {
+  GenericBlockOutput,
+  useBlockExecution,
+  useBlockOutput,
+} from "@/lib/hooks/useDocumentBridge";
+import track_event from "@/tracking";
+
+type Direction = "upload" | "download";
+type Source = "file" | "template";
+
+interface TransferProgress {
+  transferred: number;
+  total: number | null;
+}
+
+interface SftpProps {
+  id: string;
+  direction: Direction;
+  source: Source;
+  localPath: string;
+  remotePath: string;
+  content: string;
+  mode: string;
+  isEditable: boolean;
+  onChange: (props: Record<string, string>) => void;
+}
+
+const formatBytes = (bytes: number): string => {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KiB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MiB`;
+};
+
+const Sftp = ({
+  id,
+  direction,
+  source,
+  localPath,
+  remotePath,
+  content,
+  mode,
+  isEditable,
+  onChange,
+}: SftpProps) => {
+  const execution = useBlockExecution(id);
+  const [progress, setProgress] = useState<TransferProgress | null>(null);
+  const [summary, setSummary] = useState<string | null>(null);
+
+  const handleOutput = useCallback((output: GenericBlockOutput<TransferProgress>) => {
+    if (output.lifecycle?.type === "started") {
+      setProgress(null);
+      setSummary(null);
+    }
+    if (output.object) {
+      setProgress(output.object);
+    }
+    if (output.stdout) {
+      setSummary(output.stdout);
+    }
+  }, []);
+  useBlockOutput<TransferProgress>(id, handleOutput);
+
+  const isTemplateUpload = direction === "upload" && source === "template";
+  const inputClassNames = { inputWrapper: "h-8 min-h-8" };
+  const percent =
+    progress && progress.total ? Math.round((progress.transferred / progress.total) * 100) : null;
+
+  return (
+    <div className="flex flex-col w-full bg-gradient-to-r from-emerald-50 to-teal-50 dark:from-slate-800 dark:to-teal-950 rounded-lg p-3 gap-2 border border-emerald-200 dark:border-emerald-900 shadow-sm hover:shadow-md transition-all duration-200">
+      <div className="flex flex-row items-center gap-2">
+        <PlayButton
+          eventName="runbooks.block.execute"
+          eventProps={{ type: "sftp" }}
+          isRunning={execution.isRunning}
+          cancellable={true}
+          onPlay={() => execution.execute()}
+          onStop={() => execution.cancel()}
+        />
+
+        <Select
+          size="sm"
+          className="w-36"
+          selectedKeys={[direction]}
+          onSelectionChange={(keys: any) => onChange({ direction: keys.currentKey })}
+          isDisabled={!isEditable}
+          aria-label="Transfer direction"
+          classNames={{ trigger: "h-8 min-h-8" }}
+        >
+          <SelectItem key="upload" startContent={<ArrowUpFromLineIcon size={14} />}>
+            Upload
+          </SelectItem>
+          <SelectItem key="download" startContent={<ArrowDownToLineIcon size={14} />}>
+            Download
+          </SelectItem>
+        </Select>
+
+        {direction === "upload" && (
+          <Select
+            size="sm"
+            className="w-32"
+            selectedKeys={[source]}
+            onSelectionChange={(keys: any) => onChange({ source: keys.currentKey })}
+            isDisabled={!isEditable}
+            aria-label="Upload source"
+            classNames={{ trigger: "h-8 min-h-8" }}
+          >
+            <SelectItem key="file">File</SelectItem>
+            <SelectItem key="template">Template</SelectItem>
+          </Select>
+        )}
+
+        {!isTemplateUpload && (
+          <Input
+            placeholder={direction === "upload" ? "Local file" : "Local path (default: cwd)"}
+            value={localPath}
+            onValueChange={(value) => onChange({ localPath: value })}
+            autoComplete="off"
+            autoCorrect="off"
+            spellCheck="false"
+            size="sm"
+            className="flex-1 font-mono"
+            isDisabled={!isEditable}
+            classNames={inputClassNames}
+          />
+        )}
+        <span className="text-xs text-default-500">{direction === "upload" ? "→" : "←"}</span>
+        <Input
+          placeholder="Remote path"
+          value={remotePath}
+          onValueChange={(value) => onChange({ remotePath: value })}
+          autoComplete="off"
+          autoCorrect="off"
+          spellCheck="false"
+          size="sm"
+          className="flex-1 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+        />
+        <Input
+          placeholder="Mode"
+          value={mode}
+          onValueChange={(value) => onChange({ mode: value })}
+          size="sm"
+          className="w-20 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+        />
+      </div>
+
+      {isTemplateUpload && (
+        <Textarea
+          placeholder="File content; supports templates like {{ var.name }}"
+          value={content}
+          onValueChange={(value) => onChange({ content: value })}
+          minRows={3}
+          className="font-mono text-sm"
+          isDisabled={!isEditable}
+        />
+      )}
+
+      {execution.isRunning && progress && (
+        <Progress
+          size="sm"
+          aria-label="Transfer progress"
+          isIndeterminate={percent === null}
+          value={percent ?? undefined}
+          label={`${formatBytes(progress.transferred)}${
+            progress.total ? ` of ${formatBytes(progress.total)}` : ""
+          }`}
+          showValueLabel={percent !== null}
+        />
+      )}
+      {execution.isSuccess && summary && (
+        <span className="text-xs text-emerald-700 dark:text-emerald-300">{summary}</span>
+      )}
+      {execution.isError && <ErrorCard error={execution.error} />}
+    </div>
+  );
+};
+
+export default createReactBlockSpec(
+  {
+    type: "sftp",
+    propSchema: {
+      name: { default: "SFTP" },
+      direction: { default: "upload" },
+      source: { default: "file" },
+      localPath: { default: "" },
+      remotePath: { default: "" },
+      content: { default: "" },
+      mode: { default: "" },
+    },
+    content: "none",
+  },
+  {
+    toExternalHTML: ({ block }) => {
+      const propMatter = exportPropMatter("sftp", block.props, [
+        "name",
+        "direction",
+        "source",
+        "localPath",
+        "remotePath",
+        "mode",
+      ]);
+      return (
+        <pre lang="sftp">
+          <code>
+            {propMatter}
+            {block.props.content}
+          </code>
+        </pre>
+      );
+    },
+    // @ts-ignore
+    render: ({ block, editor }) => {
+      const onChange = (props: Record<string, string>): void => {
+        editor.updateBlock(block, {
+          // @ts-ignore
+          props: { ...block.props, ...props },
+        });
+      };
+
+      return (
+        <Sftp
+          id={block.id}
+          direction={block.props.direction as Direction}
+          source={block.props.source as Source}
+          localPath={block.props.localPath}
+          remotePath={block.props.remotePath}
+          content={block.props.content}
+          mode={block.props.mode}
+          isEditable={editor.isEditable}
+          onChange={onChange}
+        />
+      );
+    },
+  },
+);
+
+// Component to insert this block from the editor menu
+export const insertSftp = (schema: any) => (editor: typeof schema.BlockNoteEditor) => ({
+  title: "SFTP",
+  subtext: "Upload or download a file over the current SSH connection",
+  onItemClick: async () => {
+    track_event("runbooks.block.create", { type: "sftp" });
+
+    editor.insertBlocks(
+      [
+        {
+          type: "sftp",
+          props: {},
+        },
+      ],
+      editor.getTextCursorPosition().block.id,
+      "before",
+    );
+  },
+  icon: <FileUpIcon size={18} />,
+  aliases: ["scp", "upload", "download", "copy"],
+  group: "Network",
+});
+
+AIBlockRegistry.getInstance().addBlock({
+  typeName: "sftp",
+  friendlyName: "SFTP",
+  shortDescription: "Uploads or downloads a file over the current SSH connection.",
+  description: undent`
+    SFTP blocks copy a single file between the local machine and the current SSH host, reusing the connection and auth settings of the SSH Connect or Host block above them. Use them instead of running scp in a Script block.
+
+    The available props are:
+    - direction (string): "upload" or "download"
+    - source (string): For uploads, "file" to send localPath or "template" to send the rendered content prop
+    - localPath (string): Local file to upload, or where to save a download. Relative paths use the current directory; downloads default to the remote file name
+    - remotePath (string): Path on the SSH host
+    - content (string): File content for template uploads. Supports templates
+    - mode (string): Octal mode such as "0755"; when empty the source file's mode is kept
+
+    Example: {
+      "type": "sftp",
+      "props": {
+        "direction": "upload",
+        "source": "template",
+        "remotePath": "/etc/myapp/config.toml",
+        "content": "port = {{ var.port }}",
+        "mode": "0644"
+      }
+    }
+  `,
+});
@@ -0,0 +1,232 @@
+import { useCallback, useState } from "react";
+import { CableIcon } from "lucide-react";
+import { Input } from "@heroui/react";
+import { createReactBlockSpec } from "@blocknote/react";
+import { invoke } from "@tauri-apps/api/core";
+import undent from "undent";
+import AIBlockRegistry from "@/lib/ai/block_registry";
+import { exportPropMatter } from "@/lib/utils";
+import PlayButton from "@/lib/blocks/common/PlayButton";
+import ErrorCard from "@/lib/blocks/common/ErrorCard";
+import {
+  GenericBlockOutput,
+  useBlockExecution,
+  useBlockOutput,
+} from "@/lib/hooks/useDocumentBridge";
+import track_event from "@/tracking";
+
+interface TunnelOutput {
+  localAddress: string;
+  localPort: number;
+}
+
+interface SshTunnelProps {
+  id: string;
+  remoteHost: string;
+  remotePort: string;
+  localPort: string;
+  outputVariable: string;
+  isEditable: boolean;
+  onChange: (props: Partial<Omit<SshTunnelProps, "id" | "isEditable" | "onChange">>) => void;
+}
+
+const SshTunnel = ({
+  id,
+  remoteHost,
+  remotePort,
+  localPort,
+  outputVariable,
+  isEditable,
+  onChange,
+}: SshTunnelProps) => {
+  const execution = useBlockExecution(id);
+  const [openAddress, setOpenAddress] = useState<string | null>(null);
+
+  const handleOutput = useCallback((output: GenericBlockOutput<TunnelOutput>) => {
+    if (output.object?.localAddress) {
+      setOpenAddress(output.object.localAddress);
+    }
+    if (output.lifecycle?.type === "cancelled" || output.lifecycle?.type === "error") {
+      setOpenAddress(null);
+    }
+  }, []);
+  useBlockOutput<TunnelOutput>(id, handleOutput);
+
+  const handleClose = async () => {
+    await invoke("close_ssh_tunnel", { blockId: id });
+    setOpenAddress(null);
+    execution.reset();
+    track_event("runbooks.block.ssh-tunnel.close", { block_id: id });
+  };
+
+  const inputClassNames = { inputWrapper: "h-8 min-h-8" };
+
+  return (
+    <div className="flex flex-col w-full bg-gradient-to-r from-sky-50 to-cyan-50 dark:from-slate-800 dark:to-cyan-950 rounded-lg p-3 gap-2 border border-sky-200 dark:border-sky-900 shadow-sm hover:shadow-md transition-all duration-200">
+      <div className="flex flex-row items-center gap-2">
+        <PlayButton
+          eventName="runbooks.block.execute"
+          eventProps={{ type: "ssh-tunnel" }}
+          isRunning={execution.isRunning || openAddress !== null}
+          cancellable={true}
+          onPlay={() => execution.execute()}
+          onStop={handleClose}
+          tooltip={openAddress ? "Close tunnel" : "Open tunnel"}
+        />
+
+        <span className="text-xs font-medium text-sky-700 dark:text-sky-300">SSH Tunnel</span>
+
+        <Input
+          placeholder="Local port (auto)"
+          value={localPort}
+          onValueChange={(value) => onChange({ localPort: value })}
+          size="sm"
+          className="w-32 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+        />
+        <span className="text-xs text-default-500">→</span>
+        <Input
+          placeholder="Remote host, e.g. db.internal"
+          value={remoteHost}
+          onValueChange={(value) => onChange({ remoteHost: value })}
+          autoComplete="off"
+          autoCapitalize="off"
+          autoCorrect="off"
+          spellCheck="false"
+          size="sm"
+          className="flex-1 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+        />
+        <Input
+          placeholder="Port"
+          value={remotePort}
+          onValueChange={(value) => onChange({ remotePort: value })}
+          size="sm"
+          className="w-24 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+        />
+        <Input
+          placeholder="tunnel_port"
+          value={outputVariable}
+          onValueChange={(value) => onChange({ outputVariable: value })}
+          size="sm"
+          className="w-40 font-mono"
+          isDisabled={!isEditable}
+          classNames={inputClassNames}
+          startContent={<span className="text-xs text-default-400">var</span>}
+        />
+      </div>
+
+      {openAddress && (
+        <span className="text-xs text-sky-700 dark:text-sky-300">
+          Listening on <code>{openAddress}</code>; use{" "}
+          <code>{`{{ var.${outputVariable || "tunnel_port"} }}`}</code> as the port
+        </span>
+      )}
+      {execution.isError && <ErrorCard error={execution.error} />}
+    </div>
+  );
+};
+
+export default createReactBlockSpec(
+  {
+    type: "ssh-tunnel",
+    propSchema: {
+      name: { default: "SSH Tunnel" },
+      remoteHost: { default: "localhost" },
+      remotePort: { default: "" },
+      localPort: { default: "" },
+      outputVariable: { default: "tunnel_port" },
+    },
+    content: "none",
+  },
+  {
+    toExternalHTML: ({ block }) => {
+      const propMatter = exportPropMatter("ssh-tunnel", block.props, [
+        "name",
+        "remoteHost",
+        "remotePort",
+        "localPort",
+        "outputVariable",
+      ]);
+      return (
+        <pre lang="ssh-tunnel">
+          <code>{propMatter}</code>
+        </pre>
+      );
+    },
+    // @ts-ignore
+    render: ({ block, editor }) => {
+      const onChange = (props: Record<string, string>): void => {
+        editor.updateBlock(block, {
+          // @ts-ignore
+          props: { ...block.props, ...props },
+        });
+      };
+
+      return (
+        <SshTunnel
+          id={block.id}
+          remoteHost={block.props.remoteHost}
+          remotePort={block.props.remotePort}
+          localPort={block.props.localPort}
+          outputVariable={block.props.outputVariable}
+          isEditable={editor.isEditable}
+          onChange={onChange}
+        />
+      );
+    },
+  },
+);
+
+// Component to insert this block from the editor menu
+export const insertSshTunnel = (schema: any) => (editor: typeof schema.BlockNoteEditor) => ({
+  title: "SSH Tunnel",
+  subtext: "Forward a local port through the current SSH host",
+  onItemClick: async () => {
+    track_event("runbooks.block.create", { type: "ssh-tunnel" });
+
+    editor.insertBlocks(
+      [
+        {
+          type: "ssh-tunnel",
+          props: {},
+        },
+      ],
+      editor.getTextCursorPosition().block.id,
+      "before",
+    );
+  },
+  icon: <CableIcon size={18} />,
+  aliases: ["tunnel", "port forward", "bastion"],
+  group: "Network",
+});
+
+AIBlockRegistry.getInstance().addBlock({
+  typeName: "ssh-tunnel",
+  friendlyName: "SSH Tunnel",
+  shortDescription: "Forwards a local port to a host reachable from the SSH host.",
+  description: undent`
+    SSH Tunnel blocks forward a local port to a host that is only reachable from the current SSH host (like \`ssh -L\`), for example a database behind a bastion. They must come after an SSH Connect or Host block.
+
+    The local port is stored in a template variable, so later blocks can connect to 127.0.0.1 on that port. The tunnel stays open until it is closed, the block is re-run, or the runbook is closed.
+
+    The available props are:
+    - remoteHost (string): Host to forward to, as resolved by the SSH host. Supports templates
+    - remotePort (string): Port on the remote host
+    - localPort (string): Local port to listen on; leave empty to pick a free port
+    - outputVariable (string): Variable that receives the local port (default "tunnel_port")
+
+    Example: {
+      "type": "ssh-tunnel",
+      "props": {
+        "remoteHost": "db.internal",
+        "remotePort": "5432",
+        "outputVariable": "db_port"
+      }
+    }
+  `,
+});
@@ -0,0 +1,306 @@
+import { CircleCheckIcon, HourglassIcon } from "lucide-react";
+import { Input, Select, SelectItem, Textarea } from "@heroui/react";
+import { createReactBlockSpec } from "@blocknote/react";
+import undent from "undent";
+import AIBlockRegistry from "@/lib/ai/block_registry";
+import { exportPropMatter } from "@/lib/utils";
+import PlayButton from "@/lib/blocks/common/PlayButton";
+import { useBlockExecution, useBlockState } from "@/lib/hooks/useDocumentBridge";
+import { WaitUntilState } from "@/rs-bindings/WaitUntilState";
+import track_event from "@/tracking";
+
+type CheckType = "http" | "postgres" | "mysql" | "sqlite" | "clickhouse" | "prometheus" | "command";
+
+const CHECKS: { key: CheckType; label: string }[] = [
+  { key: "http", label: "HTTP" },
+  { key: "postgres", label: "Postgres" },
+  { key: "mysql", label: "MySQL" },
+  { key: "sqlite", label: "SQLite" },
+  { key: "clickhouse", label: "Clickhouse" },
+  { key: "prometheus", label: "Prometheus" },
+  { key: "command", label: "Command" },
+];
+
+const EDITABLE_PROPS = [
+  "name",
+  "check",
+  "url",
+  "uri",
+  "endpoint",
+  "query",
+  "code",
+  "condition",
+  "intervalSeconds",
+  "timeoutSeconds",
+  "backoff",
+] as const;
+
+type EditableProp = (typeof EDITABLE_PROPS)[number];
+
+interface WaitUntilProps {
+  id: string;
+  props: Record<EditableProp, string>;
+  isEditable: boolean;
+  onChange: (props: Partial<Record<EditableProp, string>>) => void;
+}
+
+// The prop holding what to connect to, for each kind of check
+const targetProp = (check: CheckType): { prop: EditableProp; placeholder: string } | null => {
+  switch (check) {
+    case "http":
+      return { prop: "url", placeholder: "https://api.example.com/health" };
+    case "prometheus":
+      return { prop: "endpoint", placeholder: "http://prometheus:9090" };
+    case "command":
+      return null;
+    default:
+      return { prop: "uri", placeholder: `${check}://user:password@host/database` };
+  }
+};
+
+const WaitUntil = ({ id, props, isEditable, onChange }: WaitUntilProps) => {
+  const execution = useBlockExecution(id);
+  const state = useBlockState<WaitUntilState>(id);
+  const check = (props.check || "http") as CheckType;
+  const target = targetProp(check);
+  const bodyProp: EditableProp | null =
+    check === "command" ? "code" : check === "http" ? null : "query";
+
+  return (
+    <div className="flex flex-col w-full bg-gradient-to-r from-amber-50 to-orange-50 dark:from-slate-800 dark:to-amber-950 rounded-lg p-3 gap-2 border border-amber-200 dark:border-amber-900 shadow-sm hover:shadow-md transition-all duration-200">
+      <div className="flex flex-row items-center gap-2">
+        <PlayButton
+          eventName="runbooks.block.execute"
+          eventProps={{ type: "wait-until" }}
+          isRunning={execution.isRunning}
+          cancellable={true}
+          onPlay={() => execution.execute()}
+          onStop={() => execution.cancel()}
+          tooltip="Start waiting"
+        />
+
+        <span className="text-xs font-medium text-amber-700 dark:text-amber-300">Wait until</span>
+
+        <Input
+          placeholder="Name"
+          value={props.name}
+          onValueChange={(value) => onChange({ name: value })}
+          size="sm"
+          className="flex-1"
+          isDisabled={!isEditable}
+          classNames={{ inputWrapper: "h-8 min-h-8" }}
+        />
+
+        <Select
+          size="sm"
+          className="w-36"
+          selectedKeys={[check]}
+          onSelectionChange={(keys: any) => onChange({ check: keys.currentKey })}
+          isDisabled={!isEditable}
+          aria-label="Check"
+          classNames={{ trigger: "h-8 min-h-8" }}
+        >
+          {CHECKS.map(({ key, label }) => (
+            <SelectItem key={key}>{label}</SelectItem>
+          ))}
+        </Select>
+      </div>
+
+      {target && (
+        <Input
+          placeholder={target.placeholder}
+          value={props[target.prop]}
+          onValueChange={(value) => onChange({ [target.prop]: value })}
+          autoComplete="off"
+          autoCorrect="off"
+          spellCheck="false"
+          size="sm"
+          className="font-mono"
+          isDisabled={!isEditable}
+          classNames={{ inputWrapper: "h-8 min-h-8" }}
+        />
+      )}
+
+      {bodyProp && (
+        <Textarea
+          placeholder={bodyProp === "code" ? "kubectl get deploy/api -o json" : "Query"}
+          value={props[bodyProp]}
+          onValueChange={(value) => onChange({ [bodyProp]: value })}
+          autoComplete="off"
+          autoCapitalize="off"
+          autoCorrect="off"
+          spellCheck="false"
+          minRows={1}
+          size="sm"
+          className="font-mono text-sm"
+          isDisabled={!isEditable}
+        />
+      )}
+
+      <Input
+        placeholder="Condition, e.g. output.status == 200 (empty: the check succeeds)"
+        value={props.condition}
+        onValueChange={(value) => onChange({ condition: value })}
+        autoComplete="off"
+        autoCorrect="off"
+        spellCheck="false"
+        size="sm"
+        className="font-mono"
+        isDisabled={!isEditable}
+        classNames={{ inputWrapper: "h-8 min-h-8" }}
+      />
+
+      <div className="flex flex-row items-center gap-2">
+        <Input
+          label="Every (s)"
+          labelPlacement="outside-left"
+          value={props.intervalSeconds}
+          onValueChange={(value) => onChange({ intervalSeconds: value })}
+          size="sm"
+          className="w-36"
+          isDisabled={!isEditable}
+        />
+        <Input
+          label="Timeout (s)"
+          labelPlacement="outside-left"
+          value={props.timeoutSeconds}
+          onValueChange={(value) => onChange({ timeoutSeconds: value })}
+          size="sm"
+          className="w-40"
+          isDisabled={!isEditable}
+        />
+        <Select
+          size="sm"
+          className="w-40"
+          selectedKeys={[props.backoff || "constant"]}
+          onSelectionChange={(keys: any) => onChange({ backoff: keys.currentKey })}
+          isDisabled={!isEditable}
+          aria-label="Backoff"
+          classNames={{ trigger: "h-8 min-h-8" }}
+        >
+          <SelectItem key="constant">Constant interval</SelectItem>
+          <SelectItem key="exponential">Exponential backoff</SelectItem>
+        </Select>
+      </div>
+
+      {state && state.attempts > 0 && (
+        <div className="flex flex-row items-center gap-2 text-xs text-default-600">
+          {state.satisfied && <CircleCheckIcon size={14} className="text-success shrink-0" />}
+          <span>
+            {state.attempts} attempt{state.attempts === 1 ? "" : "s"}, {state.elapsedSeconds.toFixed(1)}s
+            {state.lastResult && ` — ${state.lastResult}`}
+            {execution.isRunning &&
+              state.nextAttemptInSeconds !== null &&
+              `, next in ${state.nextAttemptInSeconds}s`}
+          </span>
+        </div>
+      )}
+      {execution.isError && <span className="text-xs text-danger-600">{execution.error}</span>}
+    </div>
+  );
+};
+
+export default createReactBlockSpec(
+  {
+    type: "wait-until",
+    propSchema: {
+      name: { default: "Wait until" },
+      check: { default: "http" },
+      url: { default: "" },
+      uri: { default: "" },
+      endpoint: { default: "" },
+      query: { default: "" },
+      code: { default: "" },
+      condition: { default: "" },
+      intervalSeconds: { default: "5" },
+      timeoutSeconds: { default: "300" },
+      backoff: { default: "constant" },
+    },
+    content: "none",
+  },
+  {
+    toExternalHTML: ({ block }) => {
+      const propMatter = exportPropMatter("wait-until", block.props, [...EDITABLE_PROPS]);
+      return (
+        <pre lang="wait-until">
+          <code>{propMatter}</code>
+        </pre>
+      );
+    },
+    // @ts-ignore
+    render: ({ block, editor }) => {
+      const onChange = (props: Record<string, string>): void => {
+        editor.updateBlock(block, {
+          // @ts-ignore
+          props: { ...block.props, ...props },
+        });
+      };
+
+      return (
+        <WaitUntil
+          id={block.id}
+          props={block.props}
+          isEditable={editor.isEditable}
+          onChange={onChange}
+        />
+      );
+    },
+  },
+);
+
+// Component to insert this block from the editor menu
+export const insertWaitUntil = (schema: any) => (editor: typeof schema.BlockNoteEditor) => ({
+  title: "Wait Until",
+  subtext: "Repeat a check until a condition is true",
+  onItemClick: async () => {
+    track_event("runbooks.block.create", { type: "wait-until" });
+
+    editor.insertBlocks(
+      [
+        {
+          type: "wait-until",
+          props: {},
+        },
+      ],
+      editor.getTextCursorPosition().block.id,
+      "before",
+    );
+  },
+  icon: <HourglassIcon size={18} />,
+  aliases: ["wait", "poll", "retry", "until", "healthy"],
+  group: "Execute",
+});
+
+AIBlockRegistry.getInstance().addBlock({
+  typeName: "wait-until",
+  friendlyName: "Wait Until",
+  shortDescription: "Repeats an HTTP, SQL, Prometheus or command check until a condition is true.",
+  description: undent`
+    Wait Until blocks run a check repeatedly until a condition holds or a timeout passes, such as waiting for a rollout to become healthy. They fail on timeout and can be cancelled.
+
+    The available props are:
+    - name (string): Name of the block
+    - check (string): "http", "postgres", "mysql", "sqlite", "clickhouse", "prometheus" or "command"
+    - url (string): URL to request, for "http"
+    - uri (string): Connection URI, for the SQL checks
+    - endpoint (string): Prometheus server, for "prometheus"
+    - query (string): Query to run, for the SQL and Prometheus checks
+    - code (string): Bash command to run, for "command"
+    - condition (string): MiniJinja expression over output, the check's result, with the same fields as the output of the equivalent block (output.status and output.body_json for HTTP, output.first and output.total_rows for SQL, output.stdout and output.exit_code for commands). If empty, the check has to succeed: a 2xx response, a query without errors or exit code 0
+    - intervalSeconds (string): Seconds between attempts, default 5
+    - timeoutSeconds (string): Seconds to wait in total, default 300
+    - backoff (string): "constant" or "exponential", which doubles the interval up to a minute
+
+    Example: {
+      "type": "wait-until",
+      "props": {
+        "name": "Rollout healthy",
+        "check": "http",
+        "url": "https://{{ var.host }}/health",
+        "condition": "output.status == 200 and output.body_json.ready",
+        "intervalSeconds": "10",
+        "timeoutSeconds": "600"
+      }
+    }
+  `,
+});
@@ -30,8 +30,13 @@ import "@/lib/blocks/codeberg-preview/paste-handler";
 import "@/lib/blocks/gitlab-preview/paste-handler";
 import Script from "./blocks/Script/Script";
 import SshConnect from "./blocks/ssh/SshConnect";
+import SshTunnel from "./blocks/SshTunnel";
+import Sftp from "./blocks/Sftp";
 import HostSelect from "./blocks/Host";
 import Pause from "./blocks/Pause";
+import Assert from "./blocks/Assert";
+import WaitUntil from "./blocks/WaitUntil";
+import Foreach from "./blocks/Foreach";
 import SubRunbook from "./blocks/SubRunbook";
 import TableOfContents from "./blocks/TableOfContents";
 
@@ -80,6 +85,8 @@ export const schema = BlockNoteSchema.create({
     // Network
     http: HttpBlockSpec(),
     "ssh-connect": SshConnect(),
+    "ssh-tunnel": SshTunnel(),
+    sftp: Sftp(),
     "host-select": HostSelect(),
 
     // Misc
@@ -89,6 +96,9 @@ export const schema = BlockNoteSchema.create({
 
     // Workflow control
     pause: Pause(),
+    assert: Assert(),
+    "wait-until": WaitUntil(),
+    foreach: Foreach(),
 
     // Link Previews
     githubPreview: GitHubPreviewBlockSpec(),
@@ -39,6 +39,7 @@ import {
   useBlockOutput,
 } from "@/lib/hooks/useDocumentBridge.ts";
 import { PtyMetadata } from "@/rs-bindings/PtyMetadata.ts";
+import { ExecutionResult } from "@/rs-bindings/ExecutionResult.ts";
 import { Settings } from "@/state/settings.ts";
 import { calculateRowHeight } from "@/components/runbooks/editor/components/Xterm.tsx";
 
@@ -163,7 +164,16 @@ export const RunBlock = ({
     }
 
     if (!pty && commandStart) {
-      logExecution(terminal, terminal.typeName, commandStart, Date.now() * 1000000, "");
+      const result: ExecutionResult = execution.isCancelled
+        ? "cancelled"
+        : exitCode === null || exitCode === 0
+          ? "success"
+          : "failure";
+      logExecution(terminal, terminal.typeName, commandStart, Date.now() * 1000000, "", {
+        runbookId: currentRunbookId ?? undefined,
+        exitCode: exitCode ?? undefined,
+        result,
+      });
       setCommandStart(null);
     }
   }, [pty]);
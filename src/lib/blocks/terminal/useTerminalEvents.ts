@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
 import { addToast } from "@heroui/react";
 import { logExecution } from "@/lib/exec_log.ts";
+import { useCurrentRunbookId } from "@/context/runbook_id_context.ts";
 import { TerminalBlock } from "./schema.ts";
 
 export const useTerminalEvents = (terminalData: any, terminal: TerminalBlock) => {
@@ -8,6 +9,7 @@ export const useTerminalEvents = (terminalData: any, terminal: TerminalBlock) =>
   const [isRunning, setIsRunning] = useState<boolean>(false);
   const [exitCode, setExitCode] = useState<number | null>(null);
   const [commandDuration, setCommandDuration] = useState<number | null>(null);
+  const runbookId = useCurrentRunbookId();
 
   useEffect(() => {
     if (!terminalData) return;
@@ -32,7 +34,11 @@ export const useTerminalEvents = (terminalData: any, terminal: TerminalBlock) =>
       // Log execution for history
       if (duration) {
         const startTime = Date.now() * 1000000 - duration * 1000;
-        logExecution(terminal, terminal.typeName, startTime, Date.now() * 1000000, "");
+        logExecution(terminal, terminal.typeName, startTime, Date.now() * 1000000, "", {
+          runbookId: runbookId ?? undefined,
+          exitCode: exitCode ?? undefined,
+          result: exitCode == null || exitCode === 0 ? "success" : "failure",
+        });
       }
     };
 
@@ -64,7 +70,7 @@ export const useTerminalEvents = (terminalData: any, terminal: TerminalBlock) =>
       terminalData.off("execution_cancelled", handleExecutionCancelled);
       terminalData.off("execution_error", handleExecutionError);
     };
-  }, [terminalData, terminal]);
+  }, [terminalData, terminal, runbookId]);
 
   return { isLoading, setIsLoading, isRunning, exitCode, commandDuration };
 };
@@ -18,6 +18,12 @@ export interface GrandCentralEvents {
   "ssh-certificate-load-failed": { host: string; cert_path: string; error: string };
   "ssh-certificate-expired": { host: string; cert_path: string; valid_until: string };
   "ssh-certificate-not-yet-valid": { host: string; cert_path: string; valid_from: string };
+  "ssh-host-key-mismatch": {
+    host: string;
+    fingerprint: string;
+    known_hosts_path: string;
+    line: number;
+  };
 }
 
 /**
@@ -195,6 +201,15 @@ export class GrandCentral extends Emittery<GrandCentralEvents> {
           });
           break;
 
+        case "sshHostKeyMismatch":
+          this.emit("ssh-host-key-mismatch", {
+            host: event.data.host,
+            fingerprint: event.data.fingerprint,
+            known_hosts_path: event.data.known_hosts_path,
+            line: event.data.line,
+          });
+          break;
+
         default:
           console.warn("Grand Central: Unhandled event type:", event);
       }
@@ -268,3 +283,7 @@ export const onSshCertificateExpired = (
 export const onSshCertificateNotYetValid = (
   handler: (data: GrandCentralEvents["ssh-certificate-not-yet-valid"]) => void,
 ) => grandCentral.on("ssh-certificate-not-yet-valid", handler);
+
+export const onSshHostKeyMismatch = (
+  handler: (data: GrandCentralEvents["ssh-host-key-mismatch"]) => void,
+) => grandCentral.on("ssh-host-key-mismatch", handler);
@@ -1,4 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { ExecLogEntry } from "@/rs-bindings/ExecLogEntry";
+import type { ExecLogQuery } from "@/rs-bindings/ExecLogQuery";
+import type { ExecTrigger } from "@/rs-bindings/ExecTrigger";
+import type { ExecutionResult } from "@/rs-bindings/ExecutionResult";
+
+export interface ExecutionDetails {
+  runbookId?: string;
+  exitCode?: number;
+  result?: ExecutionResult;
+  trigger?: ExecTrigger;
+}
 
 export async function logExecution(
   block: any,
@@ -6,11 +17,29 @@ export async function logExecution(
   startTime: number,
   endTime: number,
   output: string,
+  details: ExecutionDetails = {},
 ) {
   await invoke("log_execution", {
     block: {type: block_type, ...block.object()},
     startTime,
     endTime,
     output,
+    runbookId: details.runbookId ?? null,
+    exitCode: details.exitCode ?? null,
+    result: details.result ?? null,
+    trigger: details.trigger ?? null,
+  });
+}
+
+export async function queryExecLog(query: Partial<ExecLogQuery>): Promise<ExecLogEntry[]> {
+  return await invoke("exec_log_query", {
+    query: {
+      blockId: null,
+      runbookId: null,
+      result: null,
+      since: null,
+      limit: null,
+      ...query,
+    },
   });
 }
@@ -8,6 +8,7 @@ import {
 } from "../events/grand_central";
 import { invoke } from "@tauri-apps/api/core";
 import { UnsubscribeFunction } from "emittery";
+import { Settings } from "@/state/settings";
 
 export interface SerialExecutionHandle {
   isRunning: boolean;
@@ -31,9 +32,10 @@ export function useSerialExecution(runbookId: string | undefined | null) {
   const [pausedAtBlockId, setPausedAtBlockId] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
 
-  const start = useCallback(() => {
+  const start = useCallback(async () => {
     if (!runbookId) return;
-    invoke("start_serial_execution", { documentId: runbookId });
+    const maxConcurrency = await Settings.runConcurrency();
+    invoke("start_serial_execution", { documentId: runbookId, maxConcurrency });
   }, [runbookId]);
 
   const stop = useCallback(() => {
@@ -42,11 +44,13 @@ export function useSerialExecution(runbookId: string | undefined | null) {
   }, [runbookId]);
 
   const resumeFrom = useCallback(
-    (blockId: string) => {
+    async (blockId: string) => {
       if (!runbookId) return;
+      const maxConcurrency = await Settings.runConcurrency();
       invoke("start_serial_execution", {
         documentId: runbookId,
         fromBlock: blockId,
+        maxConcurrency,
       });
     },
     [runbookId],
@@ -16,6 +16,7 @@ import {
   onSshCertificateLoadFailed,
   onSshCertificateExpired,
   onSshCertificateNotYetValid,
+  onSshHostKeyMismatch,
 } from "@/lib/events/grand_central";
 import { Settings } from "@/state/settings";
 import Runbook from "@/state/runbooks/runbook";
@@ -582,6 +583,20 @@ export default function NotificationManager() {
     [],
   );
 
+  // Handler for SSH host key mismatch - always show toast, the connection was refused
+  const handleSshHostKeyMismatch = useCallback(
+    (data: GrandCentralEvents["ssh-host-key-mismatch"]) => {
+      logger.warn("SSH host key mismatch", data);
+      addToast({
+        title: "SSH Host Key Changed",
+        description: `The host key for ${data.host} does not match ${data.known_hosts_path}:${data.line}. The connection was refused.`,
+        color: "danger",
+        timeout: 15000,
+      });
+    },
+    [],
+  );
+
   useEffect(() => {
     // Load initial settings
     refreshSettings();
@@ -605,6 +620,7 @@ export default function NotificationManager() {
     const unsubSshCertFailed = onSshCertificateLoadFailed(handleSshCertificateLoadFailed);
     const unsubSshCertExpired = onSshCertificateExpired(handleSshCertificateExpired);
     const unsubSshCertNotYetValid = onSshCertificateNotYetValid(handleSshCertificateNotYetValid);
+    const unsubSshHostKeyMismatch = onSshHostKeyMismatch(handleSshHostKeyMismatch);
 
     return () => {
       clearInterval(settingsInterval);
@@ -619,6 +635,7 @@ export default function NotificationManager() {
       unsubSshCertFailed();
       unsubSshCertExpired();
       unsubSshCertNotYetValid();
+      unsubSshHostKeyMismatch();
     };
   }, [
     refreshSettings,
@@ -633,6 +650,7 @@ export default function NotificationManager() {
     handleSshCertificateLoadFailed,
     handleSshCertificateExpired,
     handleSshCertificateNotYetValid,
+    handleSshHostKeyMismatch,
   ]);
 
   // This component doesn't render anything
@@ -8,11 +8,12 @@ export async function handleClientPrompt(prompt: ClientPrompt): Promise<ClientPr
   let value: any = null;
   let message: any = prompt.prompt;
 
-  if (prompt.input?.type === "string") {
+  if (prompt.input?.type === "string" || prompt.input?.type === "secret") {
     message = (
       <div className="flex flex-col gap-2 w-full">
-        <p>{prompt.prompt}</p>
+        <p className="whitespace-pre-wrap">{prompt.prompt}</p>
         <StringInput
+          secret={prompt.input.type === "secret"}
           onValueChange={(v) => {
             value = v;
           }}
@@ -66,6 +67,7 @@ export async function handleClientPrompt(prompt: ClientPrompt): Promise<ClientPr
 }
 
 interface StringInputProps {
+  secret?: boolean;
   onValueChange: (value: string) => void;
 }
 
@@ -76,7 +78,7 @@ function StringInput(props: StringInputProps) {
 
   return (
     <div className="flex flex-col gap-2 w-full">
-      <Input onValueChange={handleValueChange} />
+      <Input type={props.secret ? "password" : "text"} onValueChange={handleValueChange} />
     </div>
   );
 }
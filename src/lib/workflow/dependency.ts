@@ -2,16 +2,19 @@ import { useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import Block from "./blocks/block";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { DependencyCheck } from "@/rs-bindings/DependencyCheck";
+import { DependencyMode } from "@/rs-bindings/DependencyMode";
 
 export function useDependencyState(block: Block, isRunning: boolean | null | undefined) {
-  const [canRun, setCanRun] = useState<boolean>(true);
-  const unlistenExecLogCompleted = useRef<UnlistenFn | null>(null);
+  const [check, setCheck] = useState<DependencyCheck>({ blockedBy: [], runFirst: [] });
+  const unlistenExecLogCompleted = useRef<UnlistenFn[]>([]);
+  const canRun = check.blockedBy.length === 0;
 
   const updateCanRun = async () => {
-    let cr = await block.dependency.canRun(block);
-    setCanRun(cr);
+    let result = await block.dependency.canRun(block);
+    setCheck(result);
 
-    if (cr && block.dependency.within) {
+    if (result.blockedBy.length === 0 && block.dependency.within) {
       setTimeout(() => {
         updateCanRun();
       }, block.dependency.within * 1000);
@@ -20,24 +23,19 @@ export function useDependencyState(block: Block, isRunning: boolean | null | und
 
   useEffect(() => {
     (async () => {
-      if (unlistenExecLogCompleted.current) {
-        unlistenExecLogCompleted.current();
-      }
-
-      const unlisten = await listen(
-        `exec_log_completed:${block.dependency.parent}`,
-        async (event: any) => {
-          console.log("exec_log_completed", event);
-          updateCanRun();
-        },
+      unlistenExecLogCompleted.current.forEach((unlisten) => unlisten());
+
+      unlistenExecLogCompleted.current = await Promise.all(
+        block.dependency.parents.map((parent) =>
+          listen(`exec_log_completed:${parent}`, () => {
+            updateCanRun();
+          }),
+        ),
       );
-
-      unlistenExecLogCompleted.current = unlisten;
     })();
     return () => {
-      if (unlistenExecLogCompleted.current) {
-        unlistenExecLogCompleted.current();
-      }
+      unlistenExecLogCompleted.current.forEach((unlisten) => unlisten());
+      unlistenExecLogCompleted.current = [];
     };
   }, [block.dependency]);
 
@@ -48,14 +46,14 @@ export function useDependencyState(block: Block, isRunning: boolean | null | und
   }, [isRunning]);
 
   useEffect(() => {
-    block.dependency.canRun(block).then(setCanRun);
+    block.dependency.canRun(block).then(setCheck);
   }, [block.dependency]);
 
-  return { canRun };
+  return { canRun, blockedBy: check.blockedBy, runFirst: check.runFirst };
 }
 
 export class DependencySpec {
-  // For now we will only expose setting a single parent, but in the future we will support multiple parents
+  // The UI only sets a single parent for now, but the runtime honours all of them
   parents: string[] = [];
 
   // The time within which the parent must be run
@@ -71,6 +69,9 @@ export class DependencySpec {
 
   autoRunParents: boolean = true;
 
+  // Whether all parents must be satisfied, or any one of them
+  mode: DependencyMode = "all";
+
   static empty(): DependencySpec {
     return new DependencySpec([]);
   }
@@ -83,21 +84,33 @@ export class DependencySpec {
     return this.parents[0];
   }
 
-  constructor(parents: string[] = [], within: number = 0) {
+  constructor(
+    parents: string[] = [],
+    within: number = 0,
+    autoRunParents: boolean = true,
+    mode: DependencyMode = "all",
+  ) {
     this.parents = parents;
     this.within = within;
+    this.autoRunParents = autoRunParents;
+    this.mode = mode;
   }
 
   static deserialize(json: string): DependencySpec {
     let obj = JSON.parse(json);
-    return new DependencySpec(obj.parents, obj.within || 0);
+    return new DependencySpec(
+      obj.parents || [],
+      obj.within || 0,
+      obj.autoRunParents ?? true,
+      obj.mode || "all",
+    );
   }
 
   serialize(): string {
     return JSON.stringify(this);
   }
 
-  async canRun(block: Block): Promise<boolean> {
+  async canRun(block: Block): Promise<DependencyCheck> {
     return await invoke("can_run", {
       spec: this,
       block: { type: block.typeName, ...block.object() },
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The outcome of checking a single expression
+ */
+export type AssertionResult = { expression: string, passed: boolean, 
+/**
+ * Why the expression failed, including the values that were compared
+ */
+message: string | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Why a parent stops a block from running
+ */
+export type BlockedReason = { "type": "invalidParent" } | { "type": "neverRun" } | { "type": "notRunSince" } | { "type": "expired", within: bigint, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BlockedParent } from "./BlockedParent";
+
+/**
+ * The result of checking a block's dependencies
+ */
+export type DependencyCheck = { 
+/**
+ * Parents that stop the block from running; empty if it can run
+ */
+blockedBy: Array<BlockedParent>, 
+/**
+ * Parents to run before the block, if `auto_run_parents` is set and it is blocked
+ */
+runFirst: Array<string>, };
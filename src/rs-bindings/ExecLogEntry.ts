@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ExecTrigger } from "./ExecTrigger";
+import type { ExecutionResult } from "./ExecutionResult";
+
+/**
+ * Database record for a single block execution
+ */
+export type ExecLogEntry = { 
+/**
+ * Database-assigned ID
+ */
+id: number, blockId: string, runbookId: string | null, 
+/**
+ * Start time, in nanoseconds since the epoch
+ */
+startTime: number, 
+/**
+ * End time, in nanoseconds since the epoch
+ */
+endTime: number, 
+/**
+ * Execution output
+ */
+output: string, exitCode: number | null, 
+/**
+ * `None` for executions logged before results were recorded
+ */
+result: ExecutionResult | null, fingerprint: string | null, trigger: ExecTrigger | null, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ExecutionResult } from "./ExecutionResult";
+
+/**
+ * Filters for browsing the execution log; unset fields match everything
+ */
+export type ExecLogQuery = { blockId: string | null, runbookId: string | null, result: ExecutionResult | null, 
+/**
+ * Only executions that ended at or after this time, in nanoseconds since the epoch
+ */
+since: number | null, 
+/**
+ * Return at most this many executions, most recent first
+ */
+limit: number | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Who or what started a block execution
+ */
+export type ExecTrigger = { "type": "user" } | { "type": "workflow", id: string, } | { "type": "dependency", dependent: string, } | { "type": "cli" };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HostStatus } from "./HostStatus";
+import type { OutputLine } from "./OutputLine";
+
+/**
+ * Progress of one host, sent as the `object` of the block's streamed output
+ *
+ * Output lines are also sent as plain stdout/stderr, prefixed with `[host]`.
+ */
+export type FanOutUpdate = { host: string, status: HostStatus, 
+/**
+ * Set once the host has finished
+ */
+exitCode: number | null, error: string | null, 
+/**
+ * A line of output, if this update carries one
+ */
+line: OutputLine | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { IterationState } from "./IterationState";
+
+/**
+ * Progress of a For Each block, one entry per item
+ */
+export type ForeachState = { iterations: Array<IterationState>, };
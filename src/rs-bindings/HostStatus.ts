@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * State of one host in a fan-out run
+ */
+export type HostStatus = "running" | "success" | "failed" | "cancelled" | "skipped";
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { IterationStatus } from "./IterationStatus";
+
+export type IterationState = { 
+/**
+ * The item, as text
+ */
+label: string, status: IterationStatus, error: string | null, };
@@ -3,4 +3,4 @@
 /**
  * Input types for client prompts
  */
-export type PromptInput = { "type": "string" } | { "type": "text" } | { "type": "dropdown", "data": Array<[string, string]> };
+export type PromptInput = { "type": "string" } | { "type": "secret" } | { "type": "text" } | { "type": "dropdown", "data": Array<[string, string]> };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ScriptExecutionOutput } from "./ScriptExecutionOutput";
+
+/**
+ * Output of a fan-out run: the output of each host, by host
+ *
+ * In templates, `output["web-1"].stdout` reads like the output of a single-host script.
+ */
+export type ScriptFanOutOutput = { hosts: { [key in string]?: ScriptExecutionOutput }, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Progress of a Wait Until block, updated after every attempt
+ */
+export type WaitUntilState = { attempts: number, elapsedSeconds: number, 
+/**
+ * When the next attempt will be made, if the block is still waiting
+ */
+nextAttemptInSeconds: number | null, 
+/**
+ * Why the last attempt didn't satisfy the condition
+ */
+lastResult: string | null, satisfied: boolean, };
@@ -10,6 +10,7 @@ const TERMINAL_GHOSTTY = "settings.runbooks.terminal.ghostty";
 const TERMINAL_SHELL = "settings.runbooks.terminal.shell";
 const SCRIPT_SHELL = "settings.runbooks.script.shell";
 const SCRIPT_INTERPRETERS = "settings.runbooks.script.interpreters";
+const RUN_CONCURRENCY = "settings.runbooks.run.concurrency";
 const EDITOR_VIM_MODE = "settings.editor.vim_mode";
 const SHELLCHECK_ENABLED = "settings.editor.shellcheck.enabled";
 const SHELLCHECK_PATH = "settings.editor.shellcheck.path";
@@ -123,6 +124,18 @@ export class Settings {
     return await store.get(SCRIPT_SHELL);
   }
 
+  // How many independent blocks run at once when running a whole runbook
+  public static async runConcurrency(val: number | null = null): Promise<number> {
+    let store = await KVStore.open_default();
+
+    if (val !== null) {
+      await store.set(RUN_CONCURRENCY, val);
+      return val;
+    }
+
+    return (await store.get(RUN_CONCURRENCY)) || 1;
+  }
+
   public static async scriptInterpreters(): Promise<Array<{ command: string; name: string }>> {
     let store = await KVStore.open_default();
     const interpreters = await store.get<Array<{ command: string; name: string }>>(